@@ -0,0 +1,152 @@
+use crate::error::{BackupError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+/// Audit log kept in the host backup directory, one JSON record per line.
+/// Records are only ever appended.
+pub const AUDIT_FILE: &str = ".odoo-backup-audit.jsonl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOperation {
+    Backup,
+    Restore,
+    /// A backup removed from the backup directory or the dedupe store
+    DeleteBackup,
+    DropDatabase,
+    ConfigChange,
+}
+
+/// One destructive or administrative operation, who ran it and how it went.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub timestamp: DateTime<Utc>,
+    pub user: String,
+    pub operation: AuditOperation,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, String>,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AuditRecord {
+    /// A successful `operation` by the current user, happening now.
+    pub fn new(operation: AuditOperation, client: Option<&str>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            user: current_user(),
+            operation,
+            client: client.map(str::to_string),
+            parameters: BTreeMap::new(),
+            success: true,
+            error: None,
+        }
+    }
+
+    pub fn with(mut self, key: &str, value: impl ToString) -> Self {
+        self.parameters.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Marks the record as failed when `result` is an error.
+    pub fn outcome<T>(mut self, result: &Result<T>) -> Self {
+        if let Err(e) = result {
+            self.success = false;
+            self.error = Some(e.to_string());
+        }
+        self
+    }
+}
+
+/// The user behind the operation: the one who ran `sudo` if any, otherwise
+/// the login user.
+pub fn current_user() -> String {
+    ["SUDO_USER", "USER", "LOGNAME"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|user| !user.is_empty()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Appends `record` to the audit log in `backup_dir`.
+pub fn append(backup_dir: &str, record: &AuditRecord) -> Result<()> {
+    let path = Path::new(backup_dir).join(AUDIT_FILE);
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| BackupError::from_fs(format!("Failed to open {}", path.display()), e))?;
+    // One write per record, so concurrent runs never interleave lines
+    file.write_all(line.as_bytes())
+        .map_err(|e| BackupError::from_fs(format!("Failed to write {}", path.display()), e))
+}
+
+/// Appends `record` to the audit log, only logging a failure so the
+/// operation itself is not affected.
+pub fn record(backup_dir: &str, record: AuditRecord) {
+    if let Err(e) = append(backup_dir, &record) {
+        log::warn!("Failed to write audit record: {}", e);
+    }
+}
+
+/// Every record of the audit log in `backup_dir`, oldest first.
+pub fn load(backup_dir: &str) -> Result<Vec<AuditRecord>> {
+    let path = Path::new(backup_dir).join(AUDIT_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| BackupError::from_fs(format!("Failed to read {}", path.display()), e))?;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Ok(serde_json::from_str(line)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_append_and_load() {
+        let dir = tempdir().unwrap();
+        let backup_dir = dir.path().to_str().unwrap();
+        assert!(load(backup_dir).unwrap().is_empty());
+
+        record(
+            backup_dir,
+            AuditRecord::new(AuditOperation::DropDatabase, Some("Acme"))
+                .with("database", "acme_copy"),
+        );
+        let failed: Result<()> = Err(BackupError::Config("boom".to_string()));
+        record(
+            backup_dir,
+            AuditRecord::new(AuditOperation::ConfigChange, None)
+                .with("action", "remove")
+                .outcome(&failed),
+        );
+
+        let records = load(backup_dir).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].operation, AuditOperation::DropDatabase);
+        assert_eq!(records[0].client.as_deref(), Some("Acme"));
+        assert_eq!(records[0].parameters["database"], "acme_copy");
+        assert!(records[0].success);
+        assert!(!records[1].success);
+        assert!(records[1].error.as_deref().unwrap().contains("boom"));
+
+        let content = std::fs::read_to_string(dir.path().join(AUDIT_FILE)).unwrap();
+        assert!(content.starts_with("{\"timestamp\":"));
+        assert_eq!(content.lines().count(), 2);
+    }
+}
@@ -0,0 +1,390 @@
+use crate::error::{BackupError, Result};
+use crate::manifest::{self, DUMP_ENTRY, FILESTORE_PREFIX};
+use crate::size;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Instant;
+use zip::ZipArchive;
+
+/// Compressors and levels `compress` tries, fastest first within each.
+/// gzip is the deflate that `zip_compression_level` uses.
+const CANDIDATES: [(&str, &[u32]); 3] = [
+    ("gzip", &[1, 6, 9]),
+    ("zstd", &[1, 3, 9, 19]),
+    ("xz", &[1, 6]),
+];
+
+/// Below this share of the input saved, compressing is not worth the time.
+const MIN_SAVING: f64 = 0.05;
+
+/// How one compressor at one level did on the sample.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CompressResult {
+    pub tool: String,
+    pub level: u32,
+    pub output_size: u64,
+    pub seconds: f64,
+    /// Configuration setting that compresses the backup this way; `None`
+    /// for results only shown for comparison
+    pub setting: Option<String>,
+}
+
+impl CompressResult {
+    /// Output size as a share of `input`.
+    fn ratio(&self, input: u64) -> f64 {
+        self.output_size as f64 / input.max(1) as f64
+    }
+
+    /// Bytes of input compressed per second.
+    fn throughput(&self, input: u64) -> f64 {
+        input as f64 / self.seconds.max(1e-6)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CompressBench {
+    pub file: String,
+    /// Bytes there are to compress: the uncompressed `dump.sql` and
+    /// filestore of a zip backup, or the whole file otherwise
+    pub size: u64,
+    /// Bytes from the start of those that were compressed
+    pub sampled: u64,
+    /// Zip backups are the only ones with a compression setting
+    pub zip: bool,
+    pub results: Vec<CompressResult>,
+    /// Compressors not installed on this host
+    pub missing: Vec<String>,
+    /// Index in `results` of the recommended setting; `None` when
+    /// compressing saves too little to be worth it, or nothing can be set
+    pub recommended: Option<usize>,
+}
+
+impl CompressBench {
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "{} ({}{}), {} compressed\n",
+            self.file,
+            size::format_size(self.size),
+            if self.zip { " uncompressed" } else { "" },
+            size::format_size(self.sampled)
+        );
+        for result in &self.results {
+            out.push_str(&format!(
+                "  {:<9} {:>10} {:>6.1}% {:>7.2}s {:>10}/s{}\n",
+                format!("{} -{}", result.tool, result.level),
+                size::format_size(result.output_size),
+                result.ratio(self.sampled) * 100.0,
+                result.seconds,
+                size::format_size(result.throughput(self.sampled) as u64),
+                if result.setting.is_none() {
+                    "  (informational)"
+                } else {
+                    ""
+                }
+            ));
+        }
+        if !self.missing.is_empty() {
+            out.push_str(&format!("  Not installed: {}\n", self.missing.join(", ")));
+        }
+        match self.recommended.map(|i| &self.results[i]) {
+            Some(result) => {
+                out.push_str(&format!(
+                    "Recommended: {} -{}, {:.1}% of the size at {}/s\n",
+                    result.tool,
+                    result.level,
+                    result.ratio(self.sampled) * 100.0,
+                    size::format_size(result.throughput(self.sampled) as u64)
+                ));
+                if let Some(setting) = &result.setting {
+                    out.push_str(&format!("Set {}\n", setting));
+                }
+            }
+            None if !self.zip => out.push_str(
+                "Only zip backups have a compression setting, so nothing is recommended\n",
+            ),
+            None if !self.results.iter().any(|r| r.setting.is_some()) => {
+                out.push_str("gzip could not be run, so nothing is recommended\n");
+            }
+            None => out.push_str(&format!(
+                "Recommended: no compression, set \"zip_compression_level\": 0, \
+                 no level saves {:.0}% or more\n",
+                MIN_SAVING * 100.0
+            )),
+        }
+        out
+    }
+}
+
+/// The smallest result with a `setting` compressing at least `min_speed`
+/// bytes per second, or the fastest of those when none is that fast.
+/// `None` when even the smallest saves less than `MIN_SAVING`.
+pub fn recommend(results: &[CompressResult], input: u64, min_speed: u64) -> Option<usize> {
+    let usable = || {
+        results
+            .iter()
+            .enumerate()
+            .filter(|(_, result)| result.setting.is_some())
+    };
+    let smallest = usable()
+        .map(|(_, result)| result.ratio(input))
+        .fold(f64::INFINITY, f64::min);
+    if smallest > 1.0 - MIN_SAVING {
+        return None;
+    }
+    let by_size = |a: &(usize, &CompressResult), b: &(usize, &CompressResult)| {
+        a.1.output_size.cmp(&b.1.output_size)
+    };
+    usable()
+        .filter(|(_, result)| result.throughput(input) >= min_speed as f64)
+        .min_by(by_size)
+        .or_else(|| usable().max_by(|a, b| a.1.throughput(input).total_cmp(&b.1.throughput(input))))
+        .map(|(i, _)| i)
+}
+
+/// Compresses the first `sample` bytes, or all, of what the backup at
+/// `path` holds with every candidate installed on this host, and recommends
+/// the setting to use. A zip backup is measured on its uncompressed
+/// `dump.sql` and filestore, unpacked to a dotfile next to it for the run,
+/// since those are what `zip_compression_level` compresses. Only gzip maps
+/// to that setting; the other compressors are shown for comparison.
+pub fn compress(path: &Path, sample: Option<u64>, min_speed: u64) -> Result<CompressBench> {
+    let zip = path.extension().is_some_and(|ext| ext == "zip");
+    if !zip {
+        let size = path
+            .metadata()
+            .map_err(|e| BackupError::from_fs(format!("Failed to read {}", path.display()), e))?
+            .len();
+        let sampled = sample.map_or(size, |sample| sample.min(size));
+        return compress_file(path, path, size, sampled, false, min_speed);
+    }
+
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let unpacked = path.with_file_name(format!(".bench-{}", file_name));
+    let result = unpack(path, &unpacked, sample)
+        .and_then(|(size, sampled)| compress_file(path, &unpacked, size, sampled, true, min_speed));
+    let _ = fs::remove_file(&unpacked);
+    result
+}
+
+/// Benchmarks `input`, which holds the first `sampled` of `size` bytes of
+/// the backup at `path`.
+fn compress_file(
+    path: &Path,
+    input: &Path,
+    size: u64,
+    sampled: u64,
+    zip: bool,
+    min_speed: u64,
+) -> Result<CompressBench> {
+    let mut results = Vec::new();
+    let mut missing = Vec::new();
+    for (tool, levels) in CANDIDATES {
+        for &level in levels {
+            match run(tool, level, input, sampled) {
+                Ok(mut result) => {
+                    if zip && tool == "gzip" {
+                        result.setting = Some(format!("\"zip_compression_level\": {}", level));
+                    }
+                    results.push(result);
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    missing.push(tool.to_string());
+                    break;
+                }
+                Err(e) => {
+                    return Err(BackupError::from_fs(
+                        format!("Failed to run {} -{}", tool, level),
+                        e,
+                    ))
+                }
+            }
+        }
+    }
+    let recommended = recommend(&results, sampled, min_speed);
+    Ok(CompressBench {
+        file: path.display().to_string(),
+        size,
+        sampled,
+        zip,
+        results,
+        missing,
+        recommended,
+    })
+}
+
+/// Writes the uncompressed `dump.sql` and filestore entries of the zip
+/// backup at `path` to `out`, up to `limit` bytes. Returns their total size
+/// and how much was written.
+fn unpack(path: &Path, out: &Path, limit: Option<u64>) -> Result<(u64, u64)> {
+    let mut archive =
+        ZipArchive::new(File::open(path)?).map_err(|e| manifest::zip_error(path, e))?;
+    let mut writer = BufWriter::new(File::create(out)?);
+    let mut size = 0;
+    let mut written = 0;
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| manifest::zip_error(path, e))?;
+        if entry.is_dir()
+            || !(entry.name() == DUMP_ENTRY || entry.name().starts_with(FILESTORE_PREFIX))
+        {
+            continue;
+        }
+        size += entry.size();
+        let left = limit.map_or(u64::MAX, |limit| limit - written);
+        written += io::copy(&mut entry.by_ref().take(left), &mut writer)?;
+    }
+    writer.flush()?;
+    Ok((size, written))
+}
+
+/// Pipes the first `len` bytes of `path` through `tool` at `level`,
+/// single-threaded so the compressors compare fairly, and counts what
+/// comes out.
+fn run(tool: &str, level: u32, path: &Path, len: u64) -> io::Result<CompressResult> {
+    let mut command = Command::new(tool);
+    command.arg(format!("-{}", level)).arg("-c");
+    if tool != "gzip" {
+        command.arg("-T1");
+    }
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let mut input = File::open(path)?.take(len);
+
+    let started = Instant::now();
+    let feeder = std::thread::spawn(move || io::copy(&mut input, &mut stdin).map(|_| ()));
+    let output_size = io::copy(&mut stdout, &mut io::sink())?;
+    feeder.join().expect("feeding the compressor panicked")?;
+    let status = child.wait()?;
+    let seconds = started.elapsed().as_secs_f64();
+    if !status.success() {
+        return Err(io::Error::other(format!("{} exited with {}", tool, status)));
+    }
+    Ok(CompressResult {
+        tool: tool.to_string(),
+        level,
+        output_size,
+        seconds,
+        setting: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn result(tool: &str, level: u32, output_size: u64, seconds: f64) -> CompressResult {
+        CompressResult {
+            tool: tool.to_string(),
+            level,
+            output_size,
+            seconds,
+            setting: Some(format!("level {}", level)),
+        }
+    }
+
+    #[test]
+    fn test_recommend() {
+        let mb = 1024 * 1024;
+        let results = [
+            result("gzip", 1, 40 * mb, 1.0),
+            result("gzip", 6, 35 * mb, 0.5),
+            result("gzip", 9, 30 * mb, 20.0),
+        ];
+        // -9 is smallest but compresses 5 MB/s
+        assert_eq!(recommend(&results, 100 * mb, 10 * mb), Some(1));
+        assert_eq!(recommend(&results, 100 * mb, 0), Some(2));
+        // Nothing is fast enough, so the fastest
+        assert_eq!(recommend(&results, 100 * mb, 1000 * mb), Some(1));
+
+        // Results without a setting are never recommended
+        let mut informational = result("xz", 6, 10 * mb, 0.1);
+        informational.setting = None;
+        let mut results = results.to_vec();
+        results.push(informational.clone());
+        assert_eq!(recommend(&results, 100 * mb, 0), Some(2));
+        assert_eq!(recommend(&[informational], 100 * mb, 0), None);
+
+        // Already compressed data
+        let results = [result("gzip", 6, 99 * mb, 1.0)];
+        assert_eq!(recommend(&results, 100 * mb, 0), None);
+        assert_eq!(recommend(&[], 100 * mb, 0), None);
+    }
+
+    #[test]
+    fn test_compress_bench() {
+        let temp_dir = tempdir().unwrap();
+        let backup = temp_dir.path().join("backup_acme_20240101_120000.dump");
+        std::fs::write(
+            &backup,
+            "INSERT INTO res_partner VALUES (1);\n".repeat(2000),
+        )
+        .unwrap();
+
+        let bench = compress(&backup, Some(36_000), 0).unwrap();
+        assert_eq!(bench.sampled, 36_000);
+        assert!(bench.results.iter().any(|result| result.tool == "gzip"));
+        assert!(bench
+            .results
+            .iter()
+            .all(|result| result.output_size < 36_000 / 10));
+        // No setting compresses dump backups
+        assert!(bench.results.iter().all(|result| result.setting.is_none()));
+        assert_eq!(bench.recommended, None);
+        assert!(bench.render().contains("Only zip backups"));
+    }
+
+    #[test]
+    fn test_compress_bench_unpacks_zip() {
+        let temp_dir = tempdir().unwrap();
+        let backup = temp_dir.path().join("backup_acme_20240101_120000.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&backup).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, content) in [
+            ("manifest.json", "{}".to_string()),
+            (
+                "dump.sql",
+                "INSERT INTO res_partner VALUES (1);\n".repeat(2000),
+            ),
+            ("filestore/ab/abcdef", "attachment ".repeat(1000)),
+        ] {
+            writer.start_file(name, options).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let bench = compress(&backup, None, 0).unwrap();
+        // The uncompressed dump and filestore, not the zip file itself
+        assert!(bench.zip);
+        assert_eq!(bench.size, 72_000 + 11_000);
+        assert_eq!(bench.sampled, bench.size);
+        assert!(bench.size > backup.metadata().unwrap().len());
+        let recommended = &bench.results[bench.recommended.unwrap()];
+        assert_eq!(recommended.tool, "gzip");
+        assert!(bench
+            .results
+            .iter()
+            .all(|result| result.setting.is_some() == (result.tool == "gzip")));
+        assert!(bench.render().contains(&format!(
+            "Set \"zip_compression_level\": {}",
+            recommended.level
+        )));
+        // The unpacked copy is gone again
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+
+        let bench = compress(&backup, Some(1000), 0).unwrap();
+        assert_eq!(bench.sampled, 1000);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::cron::CronSchedule;
+use chrono::{DateTime, Duration, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+
+/// Chained windows are followed at most this far, in case they cover
+/// every hour of the day.
+const MAX_CHAINED: usize = 64;
+
+/// A recurring period during which a database must not be backed up, in the
+/// host's local time. A window whose `end` is before its `start` runs past
+/// midnight. `days`, `months` and `weekdays` take the matching cron fields
+/// and restrict the days the window starts on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlackoutWindow {
+    /// Start time, e.g. "08:00"
+    pub start: String,
+    /// End time, e.g. "20:00"
+    pub end: String,
+    /// Days of the month, e.g. "28-31"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub days: Option<String>,
+    /// Months, e.g. "mar,jun,sep,dec"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub months: Option<String>,
+    /// Days of the week, e.g. "mon-fri"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weekdays: Option<String>,
+}
+
+impl BlackoutWindow {
+    pub fn validate(&self) -> Result<(), String> {
+        let (start, end, _) = self.parse()?;
+        if start == end {
+            return Err("start and end must differ".to_string());
+        }
+        Ok(())
+    }
+
+    fn parse(&self) -> Result<(NaiveTime, NaiveTime, CronSchedule), String> {
+        let time = |field: &str, value: &str| {
+            NaiveTime::parse_from_str(value, "%H:%M")
+                .map_err(|_| format!("{} '{}' is not a time like 08:00", field, value))
+        };
+        let days = CronSchedule::parse(&format!(
+            "0 0 {} {} {}",
+            self.days.as_deref().unwrap_or("*"),
+            self.months.as_deref().unwrap_or("*"),
+            self.weekdays.as_deref().unwrap_or("*")
+        ))?;
+        Ok((time("start", &self.start)?, time("end", &self.end)?, days))
+    }
+
+    /// When the window `now` falls in ends, if it falls in one.
+    pub fn end_after<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let (start, end, days) = self.parse().ok()?;
+        let local = now.naive_local();
+        // A window that runs past midnight may have started yesterday
+        for days_ago in [0, 1] {
+            let day = local.date() - Duration::days(days_ago);
+            if !days.fires_on(day) {
+                continue;
+            }
+            let window_start = day.and_time(start);
+            let mut window_end = day.and_time(end);
+            if end < start {
+                window_end += Duration::days(1);
+            }
+            if window_start <= local && local < window_end {
+                let timezone = now.timezone();
+                // An end skipped by a daylight saving change is an hour later
+                return timezone
+                    .from_local_datetime(&window_end)
+                    .earliest()
+                    .or_else(|| {
+                        timezone
+                            .from_local_datetime(&(window_end + Duration::hours(1)))
+                            .earliest()
+                    });
+            }
+        }
+        None
+    }
+}
+
+/// When backups may run again, if `now` falls in one of `windows`. Windows
+/// that overlap or follow each other without a gap count as one.
+pub fn blackout_end<Tz: TimeZone>(
+    windows: &[BlackoutWindow],
+    now: &DateTime<Tz>,
+) -> Option<DateTime<Tz>> {
+    let mut end: Option<DateTime<Tz>> = None;
+    for _ in 0..MAX_CHAINED {
+        let at = end.clone().unwrap_or_else(|| now.clone());
+        match windows.iter().filter_map(|w| w.end_after(&at)).max() {
+            Some(next) => end = Some(next),
+            None => break,
+        }
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn window(start: &str, end: &str) -> BlackoutWindow {
+        BlackoutWindow {
+            start: start.to_string(),
+            end: end.to_string(),
+            days: None,
+            months: None,
+            weekdays: None,
+        }
+    }
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn end(windows: &[BlackoutWindow], now: &str) -> Option<String> {
+        blackout_end(windows, &at(now)).map(|end| end.to_rfc3339())
+    }
+
+    #[test]
+    fn test_month_end_window() {
+        let month_end = BlackoutWindow {
+            days: Some("28-31".to_string()),
+            ..window("08:00", "20:00")
+        };
+        let windows = [month_end];
+        assert_eq!(
+            end(&windows, "2024-03-29T09:15:00Z").as_deref(),
+            Some("2024-03-29T20:00:00+00:00")
+        );
+        assert_eq!(end(&windows, "2024-03-29T20:00:00Z"), None);
+        assert_eq!(end(&windows, "2024-03-29T07:59:00Z"), None);
+        assert_eq!(end(&windows, "2024-03-15T12:00:00Z"), None);
+    }
+
+    #[test]
+    fn test_window_past_midnight() {
+        let nights = BlackoutWindow {
+            weekdays: Some("fri".to_string()),
+            ..window("22:00", "02:00")
+        };
+        let windows = [nights];
+        // Friday night continues into Saturday morning
+        assert_eq!(
+            end(&windows, "2024-03-08T23:00:00Z").as_deref(),
+            Some("2024-03-09T02:00:00+00:00")
+        );
+        assert_eq!(
+            end(&windows, "2024-03-09T01:00:00Z").as_deref(),
+            Some("2024-03-09T02:00:00+00:00")
+        );
+        // Saturday night is not blacked out
+        assert_eq!(end(&windows, "2024-03-09T23:00:00Z"), None);
+    }
+
+    #[test]
+    fn test_adjacent_windows_chain() {
+        let windows = [window("08:00", "12:00"), window("11:00", "14:00")];
+        assert_eq!(
+            end(&windows, "2024-03-08T09:00:00Z").as_deref(),
+            Some("2024-03-08T14:00:00+00:00")
+        );
+        // Covering the whole day still gives an answer
+        let always = [window("00:00", "12:00"), window("12:00", "00:00")];
+        assert!(end(&always, "2024-03-08T09:00:00Z").is_some());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(window("08:00", "20:00").validate().is_ok());
+        assert!(window("8am", "20:00").validate().is_err());
+        assert!(window("08:00", "08:00").validate().is_err());
+        let bad_days = BlackoutWindow {
+            days: Some("32".to_string()),
+            ..window("08:00", "20:00")
+        };
+        assert!(bad_days.validate().is_err());
+    }
+}
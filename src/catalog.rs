@@ -0,0 +1,648 @@
+use crate::config::DatabaseConfig;
+use crate::error::{BackupError, Result};
+use crate::manifest::BackupManifest;
+use crate::retention::RetentionTier;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+/// Catalog file kept in the host backup directory. The leading dot keeps it
+/// out of backup listings.
+pub const CATALOG_FILE: &str = ".odoo-backup-catalog.json";
+
+/// Lock file that writers of the catalog hold an advisory lock on.
+pub const LOCK_FILE: &str = ".odoo-backup-catalog.lock";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupStatus {
+    Success,
+    Failed,
+    /// Stopped by a shutdown request
+    Aborted,
+    /// Not taken because the database did not change since the last backup
+    Skipped,
+    /// Taken, but its file was later removed outside the tool
+    Missing,
+}
+
+impl std::fmt::Display for BackupStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackupStatus::Success => write!(f, "success"),
+            BackupStatus::Failed => write!(f, "failed"),
+            BackupStatus::Aborted => write!(f, "aborted"),
+            BackupStatus::Skipped => write!(f, "skipped (unchanged)"),
+            BackupStatus::Missing => write!(f, "missing (file removed)"),
+        }
+    }
+}
+
+/// Picks one stored backup of a client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackupSelector {
+    /// The backup with this file name
+    File(String),
+    /// The newest backup started at or before this time
+    At(DateTime<Utc>),
+    /// The n-th newest backup, 1 being the newest
+    NthLatest(usize),
+}
+
+impl Default for BackupSelector {
+    fn default() -> Self {
+        BackupSelector::NthLatest(1)
+    }
+}
+
+/// One backup attempt.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    pub id: String,
+    pub client: String,
+    pub database_name: String,
+    pub backup_format: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub status: BackupStatus,
+    /// Backup file name, relative to the host backup directory
+    pub file_name: Option<String>,
+    pub size: Option<u64>,
+    /// Hex SHA-256 of the backup file as written
+    pub sha256: Option<String>,
+    /// Odoo server version reported by the instance at backup time
+    pub odoo_version: Option<String>,
+    /// Environment the backup was taken in: installed modules, database
+    /// size, tool version, and what a restore target is checked against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<BackupManifest>,
+    pub error: Option<String>,
+    /// Where the backup was moved when it left the local backup directory
+    pub archive_location: Option<String>,
+    /// Kept in the dedupe store rather than as a plain file
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deduplicated: bool,
+    /// Last time the stored backup was re-checked against `sha256`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified_at: Option<DateTime<Utc>>,
+    /// Last time the backup was restored successfully in a restore drill
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restore_tested_at: Option<DateTime<Utc>>,
+    /// Write activity counters of the database when the backup started,
+    /// compared by `skip_unchanged`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub db_fingerprint: Option<String>,
+    /// Label given with `backup --label`, e.g. before a migration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Long-term copy of the backup kept in the archive by `archive_copies`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_copy: Option<String>,
+    /// Retention tier the backup was assigned, when tiers are configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tier: Option<RetentionTier>,
+}
+
+impl CatalogEntry {
+    /// Starts a new entry for a backup attempt, initially marked as failed
+    /// until the attempt completes.
+    pub fn start(config: &DatabaseConfig) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            client: config.name.clone(),
+            database_name: config.database_name.clone(),
+            backup_format: config.backup_format.clone(),
+            started_at: Utc::now(),
+            finished_at: None,
+            status: BackupStatus::Failed,
+            file_name: None,
+            size: None,
+            sha256: None,
+            odoo_version: None,
+            manifest: None,
+            error: None,
+            archive_location: None,
+            deduplicated: false,
+            verified_at: None,
+            restore_tested_at: None,
+            db_fingerprint: None,
+            label: None,
+            archive_copy: None,
+            tier: None,
+        }
+    }
+
+    pub fn succeed(&mut self, backup_path: &str) {
+        let path = Path::new(backup_path);
+        self.finished_at = Some(Utc::now());
+        self.status = BackupStatus::Success;
+        self.file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| n.to_string());
+        if let Ok(metadata) = fs::metadata(path) {
+            self.size = Some(metadata.len());
+        }
+    }
+
+    pub fn fail(&mut self, error: &BackupError) {
+        self.finished_at = Some(Utc::now());
+        self.status = match error.root() {
+            BackupError::Cancelled => BackupStatus::Aborted,
+            _ => BackupStatus::Failed,
+        };
+        self.error = Some(error.to_string());
+    }
+
+    /// Marks the attempt as skipped because the database is unchanged.
+    pub fn skip(&mut self) {
+        self.finished_at = Some(Utc::now());
+        self.status = BackupStatus::Skipped;
+    }
+
+    pub fn duration_secs(&self) -> Option<i64> {
+        self.finished_at
+            .map(|finished| (finished - self.started_at).num_seconds())
+    }
+
+    /// Whether the backup was last checked, or written, before `cutoff`.
+    pub fn verified_before(&self, cutoff: DateTime<Utc>) -> bool {
+        self.verified_at
+            .or(self.finished_at)
+            .is_none_or(|checked| checked < cutoff)
+    }
+}
+
+/// How many recent successful backups the typical duration is taken from.
+const DURATION_SAMPLES: usize = 10;
+/// Fewer successful backups than this are not enough to call one slow.
+const MIN_DURATION_SAMPLES: usize = 3;
+
+/// History of backup attempts, stored as JSON next to the backups.
+pub struct Catalog {
+    path: PathBuf,
+    pub entries: Vec<CatalogEntry>,
+    /// Held by a catalog loaded with `load_for_update` until it is dropped
+    lock: Option<File>,
+}
+
+impl Catalog {
+    pub fn load(backup_dir: &str) -> Result<Self> {
+        let path = Path::new(backup_dir).join(CATALOG_FILE);
+        let entries = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|e| {
+                BackupError::from_fs(format!("Failed to read catalog {}", path.display()), e)
+            })?;
+            serde_json::from_str(&content)?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            path,
+            entries,
+            lock: None,
+        })
+    }
+
+    /// Loads the catalog to change and save it. An exclusive advisory lock
+    /// on `LOCK_FILE` is held until the catalog is dropped, so concurrent
+    /// writers, in this process or another, cannot lose each other's changes.
+    pub fn load_for_update(backup_dir: &str) -> Result<Self> {
+        let lock_path = Path::new(backup_dir).join(LOCK_FILE);
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .and_then(|file| file.lock().map(|()| file))
+            .map_err(|e| {
+                BackupError::from_fs(format!("Failed to lock catalog {}", lock_path.display()), e)
+            })?;
+        let mut catalog = Self::load(backup_dir)?;
+        catalog.lock = Some(lock);
+        Ok(catalog)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        // A name of its own, so concurrent saves never write the same file
+        let tmp_path = self
+            .path
+            .with_extension(format!("json.{}.tmp", uuid::Uuid::new_v4()));
+        fs::write(&tmp_path, serde_json::to_string_pretty(&self.entries)?).map_err(|e| {
+            BackupError::from_fs(format!("Failed to write catalog {}", tmp_path.display()), e)
+        })?;
+        fs::rename(&tmp_path, &self.path).map_err(|e| {
+            BackupError::from_fs(
+                format!("Failed to replace catalog {}", self.path.display()),
+                e,
+            )
+        })
+    }
+
+    /// Appends an entry and persists the catalog.
+    pub fn record(backup_dir: &str, entry: CatalogEntry) -> Result<()> {
+        let mut catalog = Self::load_for_update(backup_dir)?;
+        catalog.entries.push(entry);
+        catalog.save()
+    }
+
+    /// Entries newest first, optionally restricted to one client.
+    pub fn history(&self, client: Option<&str>) -> Vec<&CatalogEntry> {
+        let mut entries: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|e| client.is_none_or(|c| e.client == c))
+            .collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.started_at));
+        entries
+    }
+
+    /// The backup `client`'s data is in when the database still has
+    /// `fingerprint`: the last successful backup, provided the latest
+    /// backup or skip recorded that fingerprint.
+    pub fn unchanged_backup(&self, client: &str, fingerprint: &str) -> Option<&CatalogEntry> {
+        let mut history = self
+            .history(Some(client))
+            .into_iter()
+            .filter(|e| matches!(e.status, BackupStatus::Success | BackupStatus::Skipped));
+        if history.next()?.db_fingerprint.as_deref() != Some(fingerprint) {
+            return None;
+        }
+        self.history(Some(client))
+            .into_iter()
+            .find(|e| e.status == BackupStatus::Success && e.file_name.is_some())
+    }
+
+    /// Average duration of `client`'s recent successful backups, once there
+    /// are enough of them to go by.
+    pub fn average_duration(&self, client: &str) -> Option<chrono::Duration> {
+        let durations: Vec<i64> = self
+            .history(Some(client))
+            .into_iter()
+            .filter(|e| e.status == BackupStatus::Success)
+            .filter_map(|e| Some((e.finished_at? - e.started_at).num_milliseconds()))
+            .take(DURATION_SAMPLES)
+            .collect();
+        if durations.len() < MIN_DURATION_SAMPLES {
+            return None;
+        }
+        let average = durations.iter().sum::<i64>() / durations.len() as i64;
+        Some(chrono::Duration::milliseconds(average))
+    }
+
+    /// The stored backup of `client` that `selector` picks, among its
+    /// successful backups that were not archived away.
+    pub fn select(&self, client: &str, selector: &BackupSelector) -> Option<&CatalogEntry> {
+        let mut stored = self.history(Some(client)).into_iter().filter(|e| {
+            e.status == BackupStatus::Success
+                && e.archive_location.is_none()
+                && e.file_name.is_some()
+        });
+        match selector {
+            BackupSelector::File(name) => stored.find(|e| e.file_name.as_ref() == Some(name)),
+            BackupSelector::At(time) => stored.find(|e| e.started_at <= *time),
+            BackupSelector::NthLatest(n) => stored.nth(n.checked_sub(1)?),
+        }
+    }
+
+    /// The newest successful backup of `client`.
+    pub fn last_success(&self, client: &str) -> Option<&CatalogEntry> {
+        self.history(Some(client))
+            .into_iter()
+            .find(|e| e.status == BackupStatus::Success)
+    }
+
+    /// Tier and local day of every successful backup of `config` that was
+    /// assigned a tier.
+    pub fn tiered_backups(&self, config: &DatabaseConfig) -> Vec<(RetentionTier, NaiveDate)> {
+        self.history(Some(&config.name))
+            .into_iter()
+            .filter(|e| e.status == BackupStatus::Success)
+            .filter_map(|e| Some((e.tier?, config.local_date(e.started_at))))
+            .collect()
+    }
+
+    /// Local days of the backups of `config` that got an archive copy.
+    pub fn archive_copy_dates(&self, config: &DatabaseConfig) -> Vec<NaiveDate> {
+        self.history(Some(&config.name))
+            .into_iter()
+            .filter(|e| e.archive_copy.is_some())
+            .map(|e| config.local_date(e.started_at))
+            .collect()
+    }
+
+    /// The newest backup attempt of `client`, whatever its outcome.
+    pub fn last_attempt(&self, client: &str) -> Option<&CatalogEntry> {
+        self.history(Some(client)).into_iter().next()
+    }
+
+    /// Start of the last successful, or skipped as unchanged, backup of
+    /// each client.
+    pub fn last_backups(&self) -> BTreeMap<String, DateTime<Utc>> {
+        let mut last = BTreeMap::new();
+        for entry in self.history(None) {
+            if matches!(entry.status, BackupStatus::Success | BackupStatus::Skipped) {
+                // History is newest first, so the first entry per client wins
+                last.entry(entry.client.clone()).or_insert(entry.started_at);
+            }
+        }
+        last
+    }
+
+    pub fn find_by_file_mut(&mut self, file_name: &str) -> Option<&mut CatalogEntry> {
+        self.entries
+            .iter_mut()
+            .rev()
+            .find(|e| e.file_name.as_deref() == Some(file_name))
+    }
+
+    pub fn find_by_file(&self, file_name: &str) -> Option<&CatalogEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|e| e.file_name.as_deref() == Some(file_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn create_test_database_config() -> DatabaseConfig {
+        DatabaseConfig {
+            name: "Test Client".to_string(),
+            database_name: "test_database".to_string(),
+            container_name: "test_container".to_string(),
+            master_password: "admin".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_catalog_load_missing_file() {
+        let temp_dir = tempdir().unwrap();
+        let catalog = Catalog::load(temp_dir.path().to_str().unwrap()).unwrap();
+        assert!(catalog.entries.is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_records_are_all_kept() {
+        let temp_dir = tempdir().unwrap();
+        let backup_dir = temp_dir.path().to_str().unwrap().to_string();
+
+        let writers: Vec<_> = (0..8)
+            .map(|_| {
+                let backup_dir = backup_dir.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        let entry = CatalogEntry::start(&create_test_database_config());
+                        Catalog::record(&backup_dir, entry).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        assert_eq!(Catalog::load(&backup_dir).unwrap().entries.len(), 80);
+        // No temporary files are left behind
+        let mut files: Vec<_> = fs::read_dir(&backup_dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        files.sort();
+        assert_eq!(files, vec![CATALOG_FILE, LOCK_FILE]);
+    }
+
+    #[test]
+    fn test_catalog_record_and_reload() {
+        let temp_dir = tempdir().unwrap();
+        let backup_dir = temp_dir.path().to_str().unwrap();
+        let backup_path = temp_dir
+            .path()
+            .join("backup_test_database_20240101_120000.zip");
+        fs::write(&backup_path, b"backup data").unwrap();
+
+        let mut entry = CatalogEntry::start(&create_test_database_config());
+        entry.odoo_version = Some("17.0".to_string());
+        entry.succeed(backup_path.to_str().unwrap());
+        Catalog::record(backup_dir, entry.clone()).unwrap();
+
+        let catalog = Catalog::load(backup_dir).unwrap();
+        assert_eq!(catalog.entries, vec![entry]);
+        let found = catalog
+            .find_by_file("backup_test_database_20240101_120000.zip")
+            .unwrap();
+        assert_eq!(found.status, BackupStatus::Success);
+        assert_eq!(found.size, Some(11));
+        assert_eq!(found.odoo_version.as_deref(), Some("17.0"));
+    }
+
+    #[test]
+    fn test_catalog_entry_fail() {
+        let mut entry = CatalogEntry::start(&create_test_database_config());
+        entry.fail(&BackupError::Docker(
+            "Container 'x' is not running".to_string(),
+        ));
+        assert_eq!(entry.status, BackupStatus::Failed);
+        assert!(entry.finished_at.is_some());
+        assert!(entry.error.unwrap().contains("not running"));
+        assert!(entry.file_name.is_none());
+    }
+
+    #[test]
+    fn test_catalog_entry_fail_cancelled_is_aborted() {
+        let mut entry = CatalogEntry::start(&create_test_database_config());
+        entry.fail(&BackupError::Cancelled.context("Backup failed for Test"));
+        assert_eq!(entry.status, BackupStatus::Aborted);
+    }
+
+    #[test]
+    fn test_catalog_history_newest_first() {
+        let temp_dir = tempdir().unwrap();
+        let backup_dir = temp_dir.path().to_str().unwrap();
+        let config = create_test_database_config();
+        let mut other = config.clone();
+        other.name = "Other Client".to_string();
+
+        let mut first = CatalogEntry::start(&config);
+        first.started_at -= chrono::Duration::hours(2);
+        let second = CatalogEntry::start(&other);
+        let third = CatalogEntry::start(&config);
+        for entry in [first.clone(), second, third.clone()] {
+            Catalog::record(backup_dir, entry).unwrap();
+        }
+
+        let catalog = Catalog::load(backup_dir).unwrap();
+        assert_eq!(catalog.history(None).len(), 3);
+        let history = catalog.history(Some("Test Client"));
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].id, third.id);
+        assert_eq!(history[1].id, first.id);
+    }
+
+    #[test]
+    fn test_unchanged_backup() {
+        let temp_dir = tempdir().unwrap();
+        let mut catalog = Catalog::load(temp_dir.path().to_str().unwrap()).unwrap();
+        let config = create_test_database_config();
+
+        let mut backup = CatalogEntry::start(&config);
+        backup.started_at -= chrono::Duration::hours(2);
+        backup.status = BackupStatus::Success;
+        backup.file_name = Some("backup_test_database_20240101_120000.zip".to_string());
+        backup.db_fingerprint = Some("10|4|0|".to_string());
+        catalog.entries.push(backup.clone());
+        let mut skipped = CatalogEntry::start(&config);
+        skipped.started_at -= chrono::Duration::hours(1);
+        skipped.db_fingerprint = Some("10|4|0|".to_string());
+        skipped.skip();
+        catalog.entries.push(skipped);
+
+        assert_eq!(
+            catalog
+                .unchanged_backup("Test Client", "10|4|0|")
+                .map(|e| &e.id),
+            Some(&backup.id)
+        );
+        assert!(catalog.unchanged_backup("Test Client", "11|4|0|").is_none());
+
+        // Failed attempts do not count, a newer backup without one does
+        let mut failed = CatalogEntry::start(&config);
+        failed.fail(&BackupError::Timeout("slow".to_string()));
+        catalog.entries.push(failed);
+        assert!(catalog.unchanged_backup("Test Client", "10|4|0|").is_some());
+        let mut newer = CatalogEntry::start(&config);
+        newer.succeed("backup_test_database_20240101_140000.zip");
+        catalog.entries.push(newer);
+        assert!(catalog.unchanged_backup("Test Client", "10|4|0|").is_none());
+    }
+
+    #[test]
+    fn test_average_duration() {
+        let temp_dir = tempdir().unwrap();
+        let mut catalog = Catalog::load(temp_dir.path().to_str().unwrap()).unwrap();
+        let config = create_test_database_config();
+        let entry = |minutes: i64, status: BackupStatus| {
+            let mut entry = CatalogEntry::start(&config);
+            entry.finished_at = Some(entry.started_at + chrono::Duration::minutes(minutes));
+            entry.status = status;
+            entry
+        };
+        catalog.entries.push(entry(10, BackupStatus::Success));
+        catalog.entries.push(entry(20, BackupStatus::Success));
+        catalog.entries.push(entry(90, BackupStatus::Failed));
+        assert_eq!(catalog.average_duration("Test Client"), None);
+
+        catalog.entries.push(entry(30, BackupStatus::Success));
+        assert_eq!(
+            catalog.average_duration("Test Client"),
+            Some(chrono::Duration::minutes(20))
+        );
+        assert_eq!(catalog.average_duration("Other Client"), None);
+    }
+
+    #[test]
+    fn test_last_success_and_attempt() {
+        let temp_dir = tempdir().unwrap();
+        let mut catalog = Catalog::load(temp_dir.path().to_str().unwrap()).unwrap();
+        let config = create_test_database_config();
+        assert!(catalog.last_attempt("Test Client").is_none());
+
+        let mut success = CatalogEntry::start(&config);
+        success.started_at -= chrono::Duration::hours(1);
+        success.status = BackupStatus::Success;
+        let mut failure = CatalogEntry::start(&config);
+        failure.fail(&BackupError::Timeout("slow".to_string()));
+        catalog.entries.push(success.clone());
+        catalog.entries.push(failure.clone());
+
+        assert_eq!(catalog.last_success("Test Client"), Some(&success));
+        assert_eq!(catalog.last_attempt("Test Client"), Some(&failure));
+        assert!(catalog.last_success("Other Client").is_none());
+    }
+
+    #[test]
+    fn test_select() {
+        let temp_dir = tempdir().unwrap();
+        let mut catalog = Catalog::load(temp_dir.path().to_str().unwrap()).unwrap();
+        let config = create_test_database_config();
+        let backup = |started_at: &str, file_name: &str| {
+            let mut entry = CatalogEntry::start(&config);
+            entry.started_at = started_at.parse().unwrap();
+            entry.status = BackupStatus::Success;
+            entry.file_name = Some(file_name.to_string());
+            entry
+        };
+        catalog
+            .entries
+            .push(backup("2024-05-31T02:00:00Z", "may.zip"));
+        catalog
+            .entries
+            .push(backup("2024-06-01T02:00:00Z", "june_1.zip"));
+        catalog
+            .entries
+            .push(backup("2024-06-02T02:00:00Z", "june_2.zip"));
+        let mut archived = backup("2024-06-03T02:00:00Z", "june_3.zip");
+        archived.archive_location = Some("s3://cold/june_3.zip".to_string());
+        catalog.entries.push(archived);
+        let mut failed = CatalogEntry::start(&config);
+        failed.fail(&BackupError::Timeout("slow".to_string()));
+        catalog.entries.push(failed);
+
+        let file = |selector: BackupSelector| {
+            catalog
+                .select("Test Client", &selector)
+                .and_then(|e| e.file_name.clone())
+        };
+        assert_eq!(
+            file(BackupSelector::default()).as_deref(),
+            Some("june_2.zip")
+        );
+        assert_eq!(
+            file(BackupSelector::NthLatest(3)).as_deref(),
+            Some("may.zip")
+        );
+        assert_eq!(file(BackupSelector::NthLatest(4)), None);
+        assert_eq!(file(BackupSelector::NthLatest(0)), None);
+        assert_eq!(
+            file(BackupSelector::At("2024-06-01T23:59:59Z".parse().unwrap())).as_deref(),
+            Some("june_1.zip")
+        );
+        assert_eq!(
+            file(BackupSelector::At("2024-05-01T00:00:00Z".parse().unwrap())),
+            None
+        );
+        assert_eq!(
+            file(BackupSelector::File("may.zip".to_string())).as_deref(),
+            Some("may.zip")
+        );
+        assert_eq!(file(BackupSelector::File("june_3.zip".to_string())), None);
+    }
+
+    #[test]
+    fn test_verified_before() {
+        let now = Utc::now();
+        let mut entry = CatalogEntry::start(&create_test_database_config());
+        entry.finished_at = Some(now - chrono::Duration::days(40));
+        assert!(entry.verified_before(now - chrono::Duration::days(30)));
+
+        entry.verified_at = Some(now - chrono::Duration::days(2));
+        assert!(!entry.verified_before(now - chrono::Duration::days(30)));
+    }
+
+    #[test]
+    fn test_backup_status_serialization() {
+        assert_eq!(
+            serde_json::to_string(&BackupStatus::Success).unwrap(),
+            "\"success\""
+        );
+        assert_eq!(BackupStatus::Failed.to_string(), "failed");
+        assert_eq!(BackupStatus::Aborted.to_string(), "aborted");
+        assert_eq!(BackupStatus::Skipped.to_string(), "skipped (unchanged)");
+    }
+}
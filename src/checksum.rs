@@ -0,0 +1,96 @@
+use crate::error::{BackupError, Result};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Hex-encoded SHA-256 of a file, read in chunks so large backups are not
+/// loaded into memory.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let hasher = hash_prefix(path, u64::MAX)?;
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// A hasher fed with the first `len` bytes of a file, so hashing can carry
+/// on as more data is appended to it.
+pub fn hash_prefix(path: &Path, len: u64) -> Result<Sha256> {
+    let file = File::open(path)
+        .map_err(|e| BackupError::from_fs(format!("Failed to open {}", path.display()), e))?;
+    let mut file = file.take(len);
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher)
+}
+
+/// Hex-encoded SHA-256 of an in-memory buffer.
+pub fn sha256_bytes(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Whether the file still matches a recorded checksum.
+pub fn verify_file(path: &Path, expected: &str) -> Result<bool> {
+    Ok(sha256_file(path)?.eq_ignore_ascii_case(expected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sha256_file() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("backup.zip");
+        std::fs::write(&path, b"abc").unwrap();
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_verify_file() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("backup.zip");
+        std::fs::write(&path, b"abc").unwrap();
+        assert!(verify_file(
+            &path,
+            "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD"
+        )
+        .unwrap());
+
+        std::fs::write(&path, b"abd").unwrap();
+        assert!(!verify_file(
+            &path,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_hash_prefix() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("backup.zip.partial");
+        std::fs::write(&path, b"abcdef").unwrap();
+        let mut hasher = hash_prefix(&path, 2).unwrap();
+        hasher.update(b"c");
+        assert_eq!(to_hex(&hasher.finalize()), sha256_bytes(b"abc"));
+    }
+
+    #[test]
+    fn test_sha256_missing_file() {
+        let result = sha256_file(Path::new("/nonexistent/backup.zip"));
+        assert!(matches!(result.unwrap_err().root(), BackupError::Io(_)));
+    }
+}
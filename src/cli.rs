@@ -1,4 +1,12 @@
-use clap::{Parser, Subcommand};
+use crate::backup::RestorePart;
+use crate::catalog::BackupSelector;
+use crate::config::{self, DatabaseConfig};
+use crate::convert::{ConvertFormat, ExtractPart};
+use crate::export::ExportFormat;
+use crate::output::{ColorChoice, OutputFormat};
+use crate::progress::ProgressMode;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
 #[command(name = "odoo-backup")]
@@ -19,6 +27,26 @@ pub struct Cli {
     /// Enable verbose logging
     #[arg(short, long)]
     pub verbose: bool,
+
+    /// Print only errors, e.g. for cron jobs that mail any output
+    #[arg(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// File holding the passphrase for an encrypted configuration file
+    #[arg(long, global = true)]
+    pub config_key_file: Option<String>,
+
+    /// How to print results: table, json or plain
+    #[arg(long, value_enum, global = true, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+
+    /// When to color output: auto, always or never
+    #[arg(long, value_enum, global = true, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Report backup progress on stderr: none or json
+    #[arg(long, value_enum, default_value_t = ProgressMode::None)]
+    pub progress: ProgressMode,
 }
 
 #[derive(Subcommand)]
@@ -28,26 +56,616 @@ pub enum Commands {
         /// Backup only a specific client by name
         #[arg(short, long)]
         client: Option<String>,
+
+        /// Backup only databases carrying this tag
+        #[arg(short, long, conflicts_with = "client")]
+        tag: Option<String>,
+
+        /// Prompt for the master password of every selected database
+        #[arg(long)]
+        ask_password: bool,
+
+        /// Back up databases that are in a blackout window
+        #[arg(long)]
+        force: bool,
+
+        /// Label recorded with the backups, e.g. "pre-migration-v16"
+        #[arg(long, value_parser = parse_label)]
+        label: Option<String>,
     },
     /// List all configured databases
-    List,
-    /// Check status of Docker containers
-    Status,
+    List {
+        /// Check each database against the databases its server lists, and
+        /// show the ones served but not configured
+        #[arg(long)]
+        live: bool,
+    },
+    /// Check status of Docker containers and backups
+    Status {
+        /// Print the status as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Exit non-zero if an enabled database's container is down, Odoo
+        /// fails its HTTP check or its backup is stale
+        #[arg(long)]
+        exit_code: bool,
+    },
     /// Clean old backup files
     Clean {
         /// Clean backups for a specific client by name
         #[arg(short, long)]
         client: Option<String>,
+
+        /// Clean backups for databases carrying this tag
+        #[arg(short, long, conflicts_with = "client")]
+        tag: Option<String>,
+
+        /// Also remove temp files that interrupted runs left in the containers
+        #[arg(long)]
+        container: bool,
+    },
+    /// Apply retention to every enabled database and storage location in one pass
+    Prune {
+        /// Report what would be removed without removing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
     },
     /// List existing backup files
     ListBackups {
         /// List backups for a specific database
         #[arg(short, long)]
         database: Option<String>,
+        /// Print the listing as JSON
+        #[arg(long)]
+        json: bool,
+        /// Recompute checksums and compare them with the recorded ones
+        #[arg(long)]
+        verify: bool,
+        /// Only backups modified on or after this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long, value_parser = parse_since)]
+        since: Option<DateTime<Utc>>,
+        /// Only backups modified on or before this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long, value_parser = parse_until)]
+        until: Option<DateTime<Utc>>,
+        /// Only backups in this format
+        #[arg(long, value_parser = ["zip", "dump"])]
+        format: Option<String>,
+        /// Only backups at least this large, e.g. 500MB
+        #[arg(long, value_parser = crate::size::parse_size)]
+        min_size: Option<u64>,
+        /// Only backups whose label contains this text
+        #[arg(long)]
+        label: Option<String>,
+        /// Sort order: name (A-Z), date (newest first) or size (largest first)
+        #[arg(long, value_enum, default_value_t = SortKey::Name)]
+        sort: SortKey,
+        /// Show at most this many backups, after sorting
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Show recorded backup attempts, newest first
+    History {
+        /// Show history for a specific client
+        #[arg(short, long)]
+        client: Option<String>,
+        /// Maximum number of entries to show
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
+    /// Rebuild a split or deduplicated backup as a plain file
+    Materialize {
+        /// File name of the backup, as shown by `history`
+        backup: String,
+        /// Where to write the file (defaults to the backup directory)
+        #[arg(short, long)]
+        out: Option<String>,
+    },
+    /// Convert a backup between the zip and plain SQL dump formats
+    Convert {
+        /// Backup to convert: a path, or a file name in the backup directory
+        #[arg(short, long)]
+        file: String,
+        /// Format to convert to
+        #[arg(long, value_enum)]
+        to: ConvertFormat,
+        /// Filestore directory to pack into the zip, or to extract the zip's
+        /// filestore into
+        #[arg(long)]
+        filestore: Option<String>,
+        /// Where to write the converted backup (defaults to next to the input)
+        #[arg(short, long)]
+        out: Option<String>,
+    },
+    /// Extract parts of a zip backup into a directory, without restoring it
+    Extract {
+        /// Backup to extract from: a path, or a file name in the backup directory
+        #[arg(short, long)]
+        file: String,
+        /// Part to extract, repeatable; everything by default
+        #[arg(long, value_enum)]
+        part: Vec<ExtractPart>,
+        /// Directory to extract into
+        #[arg(short, long)]
+        dest: String,
+    },
+    /// Show what a backup holds, its catalog entry and whether its checksum
+    /// still matches
+    Inspect {
+        /// Backup to inspect: a path, or a file name in the backup directory
+        file: String,
+        /// Print the details as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Compare the modules, filestore and dump of two zip backups
+    Diff {
+        /// Older backup: a path, or a file name in the backup directory
+        old: String,
+        /// Newer backup: a path, or a file name in the backup directory
+        new: String,
+        /// Print the differences as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Re-check stored backups against the checksums in the catalog
+    Verify {
+        /// File name of the backup to verify
+        #[arg(required_unless_present_any = ["all", "selector"], conflicts_with = "selector")]
+        backup: Option<String>,
+        /// Verify every stored backup
+        #[arg(long, conflicts_with_all = ["backup", "selector"])]
+        all: bool,
+        /// Only verify backups of this client
+        #[arg(short, long)]
+        client: Option<String>,
+        /// Skip backups checked (or taken) more recently than this, e.g. 30d
+        #[arg(long, value_parser = crate::size::parse_duration)]
+        older_than: Option<Duration>,
+        #[command(flatten)]
+        selector: BackupSelectorArgs,
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Restore a stored backup of a client as a new database
+    Restore {
+        /// Client whose backup is restored
+        #[arg(short, long)]
+        client: String,
+        /// Backup to restore, the newest by default
+        #[command(flatten)]
+        backup: BackupSelectorArgs,
+        /// Name of the new database
+        #[arg(long = "as", value_name = "DATABASE", value_parser = parse_database_name)]
+        database: String,
+        /// Configured client whose Odoo instance receives the copy, e.g. a
+        /// staging one; defaults to --client
+        #[arg(long)]
+        target: Option<String>,
+        /// Disable scheduled actions, outgoing mail servers and payment
+        /// providers of the copy
+        #[arg(long)]
+        neutralize: bool,
+        /// Load the backup with psql or pg_restore and move its filestore into
+        /// the data directory, for instances without the database manager
+        #[arg(long)]
+        direct: bool,
+        /// Restore only the database or only the filestore, directly
+        #[arg(long, value_enum)]
+        only: Option<RestorePart>,
+        /// Drop the existing database named by --as first, e.g. to roll back
+        /// its SQL while keeping the current filestore; needs --only db
+        #[arg(long, requires = "only", conflicts_with = "neutralize")]
+        replace: bool,
+        /// Mask personal and bank data of the copy: `default` for the
+        /// built-in masking or the path of an SQL script; repeatable, run in
+        /// order
+        #[arg(long, value_name = "SCRUB", value_parser = crate::scrub::Scrub::parse)]
+        scrub: Vec<crate::scrub::Scrub>,
+    },
+    /// Unpack a pg_basebackup or pg_dump backup of a client into a
+    /// directory: a PostgreSQL data directory or dump directory, and the
+    /// filestore
+    RestorePhysical {
+        /// Client whose backup is unpacked
+        #[arg(short, long)]
+        client: String,
+        /// Backup to unpack, the newest by default
+        #[command(flatten)]
+        backup: BackupSelectorArgs,
+        /// Empty or new directory to unpack into
+        #[arg(long)]
+        into: String,
+    },
+    /// Restore the newest backup of a client into a scratch database, then drop it
+    TestRestore {
+        /// Client whose newest backup is restored
+        #[arg(short, long)]
+        client: String,
+    },
+    /// Take a labeled backup of a client, verify it and optionally
+    /// restore-test it, then say whether it is safe to upgrade
+    PreUpgrade {
+        /// Client about to be upgraded
+        #[arg(short, long)]
+        client: String,
+
+        /// Label recorded with the backup
+        #[arg(long, value_parser = parse_label, default_value = "pre-upgrade")]
+        label: String,
+
+        /// Also restore the backup into a scratch database
+        #[arg(long)]
+        restore_test: bool,
+
+        /// Prompt for the master password
+        #[arg(long)]
+        ask_password: bool,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check that every database with max_age_hours has a recent enough
+    /// backup; exits with an error if any does not, for use from monitoring
+    CheckFreshness {
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check that Odoo accepts the master password of each database, without
+    /// changing anything; exits with an error if any is rejected
+    CheckAuth {
+        /// Check a specific client by name
+        #[arg(short, long)]
+        client: Option<String>,
+
+        /// Check databases carrying this tag
+        #[arg(short, long, conflicts_with = "client")]
+        tag: Option<String>,
+
+        /// Prompt for master passwords instead of using the configured ones
+        #[arg(long)]
+        ask_password: bool,
+
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Run scheduled backups, verification and restore drills until stopped
+    Daemon {
+        /// Write the daemon's PID here and refuse to start while another
+        /// running instance holds it
+        #[arg(long)]
+        pid_file: Option<String>,
+    },
+    /// Write a starter configuration file and create the backup directory
+    Init {
+        /// Where to write the configuration, instead of --config
+        #[arg(long)]
+        path: Option<String>,
+
+        /// Replace an existing file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check Docker, the containers, Odoo, master passwords, the backup
+    /// directory and the configuration, with hints for what fails
+    Doctor,
+    /// Find the databases an Odoo instance serves that are not configured
+    Discover {
+        /// Odoo URL inside the container
+        #[arg(long, default_value = "http://localhost:8069")]
+        url: String,
+
+        /// Docker container running Odoo
+        #[arg(long)]
+        container: String,
+
+        /// Add one entry per database to the configuration file, asking
+        /// for their master password
+        #[arg(long)]
+        write: bool,
+    },
+    /// Manage the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommands,
+    },
+    /// Maintain the backup catalog
+    Catalog {
+        #[command(subcommand)]
+        action: CatalogCommands,
+    },
+    /// Measure settings on this host
+    Bench {
+        #[command(subcommand)]
+        action: BenchCommands,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BenchCommands {
+    /// Compress the data of a backup with gzip, zstd and xz at several
+    /// levels and recommend a zip_compression_level
+    Compress {
+        /// Backup to compress: a path, or a file name in the backup directory
+        file: String,
+        /// Only compress this much of the start of the backup, e.g. 500MB
+        #[arg(long, value_parser = crate::size::parse_size)]
+        sample: Option<u64>,
+        /// Slowest compression speed to recommend, per second
+        #[arg(long, value_parser = crate::size::parse_size, default_value = "10MB")]
+        min_speed: u64,
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
     },
 }
 
+#[derive(Subcommand)]
+pub enum CatalogCommands {
+    /// Mark backups whose file is gone as missing and add rows for backup
+    /// files the catalog does not know
+    Gc {
+        /// Report the differences without changing the catalog
+        #[arg(long)]
+        dry_run: bool,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print every recorded backup run, oldest first
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// Only runs started on or after this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long, value_parser = parse_since)]
+        since: Option<DateTime<Utc>>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// Encrypt the configuration file
+    Encrypt {
+        /// Write the encrypted file here instead of replacing the original
+        #[arg(short, long)]
+        out: Option<String>,
+    },
+    /// Decrypt an encrypted configuration file
+    Decrypt {
+        /// Write the decrypted file here instead of replacing the original
+        #[arg(short, long)]
+        out: Option<String>,
+    },
+    /// Edit the configuration file in $VISUAL or $EDITOR, only saving a
+    /// valid result and keeping a backup of the previous version
+    Edit,
+    /// Flag settings that are valid but likely mistakes
+    Lint {
+        /// Print the findings as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Add a database to the configuration file
+    Add {
+        /// Ask for each field, checking the answers against the container
+        #[arg(short, long, conflicts_with_all = ["name", "container", "master_password"])]
+        interactive: bool,
+
+        #[command(flatten)]
+        database: Box<AddDatabaseArgs>,
+    },
+    /// Remove a database from the configuration file
+    Remove {
+        /// Name of the database to remove
+        name: String,
+
+        #[command(flatten)]
+        backups: ExistingBackupsArgs,
+    },
+    /// Exclude a database from runs over all databases
+    Disable {
+        /// Name of the database to disable
+        name: String,
+
+        #[command(flatten)]
+        backups: ExistingBackupsArgs,
+    },
+    /// Include a disabled database in runs over all databases again
+    Enable {
+        /// Name of the database to enable
+        name: String,
+    },
+}
+
+/// What `config remove` and `config disable` do with the local backups of
+/// the database.
+#[derive(Args, Debug, Default)]
+pub struct ExistingBackupsArgs {
+    /// Also delete the database's local backups
+    #[arg(long, conflicts_with = "archive")]
+    pub purge: bool,
+
+    /// Move the database's local backups to the configured archive
+    #[arg(long)]
+    pub archive: bool,
+}
+
+/// Which stored backup of `--client` a command works on, instead of naming
+/// the file.
+#[derive(Args, Debug, Default)]
+#[group(id = "selector", multiple = false, requires = "client")]
+pub struct BackupSelectorArgs {
+    /// Backup file name
+    #[arg(short, long)]
+    pub file: Option<String>,
+
+    /// Newest backup taken at or before this date or time, e.g. 2024-06-01
+    #[arg(long, value_parser = parse_until)]
+    pub at: Option<DateTime<Utc>>,
+
+    /// Newest backup
+    #[arg(long)]
+    pub latest: bool,
+
+    /// N-th newest backup, 1 being the newest
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub nth_latest: Option<u32>,
+}
+
+impl BackupSelectorArgs {
+    /// The selector given, if any.
+    pub fn selector(&self) -> Option<BackupSelector> {
+        if let Some(file) = &self.file {
+            Some(BackupSelector::File(file.clone()))
+        } else if let Some(at) = self.at {
+            Some(BackupSelector::At(at))
+        } else if let Some(n) = self.nth_latest {
+            Some(BackupSelector::NthLatest(n as usize))
+        } else {
+            self.latest.then(BackupSelector::default)
+        }
+    }
+}
+
+/// Fields of a database added with `config add` without `--interactive`.
+#[derive(Args, Debug, Default)]
+pub struct AddDatabaseArgs {
+    /// Client name
+    #[arg(long, required_unless_present = "interactive")]
+    pub name: Option<String>,
+
+    /// Odoo database name, by default derived from the name
+    #[arg(long)]
+    pub database_name: Option<String>,
+
+    /// Docker container running Odoo
+    #[arg(long, required_unless_present = "interactive")]
+    pub container: Option<String>,
+
+    /// Odoo URL inside the container
+    #[arg(long, default_value = "http://localhost:8069")]
+    pub url: String,
+
+    /// Master password or a secret reference such as env:NAME
+    #[arg(long, required_unless_present = "interactive")]
+    pub master_password: Option<String>,
+
+    #[arg(long, default_value = "zip", value_parser = ["zip", "dump"])]
+    pub format: String,
+
+    /// Directory for temporary backups inside the container
+    #[arg(long, default_value = "/tmp/backups")]
+    pub output_path: String,
+
+    #[arg(long, default_value_t = 30)]
+    pub retention_days: u32,
+
+    #[arg(long, default_value_t = 1)]
+    pub keep_at_least: u32,
+
+    /// Tag to add; may be repeated
+    #[arg(long = "tag")]
+    pub tags: Vec<String>,
+
+    #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+    pub priority: i32,
+
+    /// Cron expression for the daemon, e.g. "0 2 * * *"
+    #[arg(long)]
+    pub schedule: Option<String>,
+
+    /// IANA time zone of the schedule, e.g. Europe/Madrid
+    #[arg(long)]
+    pub timezone: Option<String>,
+
+    #[arg(long)]
+    pub max_age_hours: Option<u64>,
+
+    /// Add the database disabled
+    #[arg(long)]
+    pub disabled: bool,
+}
+
+impl AddDatabaseArgs {
+    /// The database to add; `None` without the required fields.
+    pub fn database_config(&self) -> Option<DatabaseConfig> {
+        let name = self.name.clone()?;
+        Some(DatabaseConfig {
+            database_name: self
+                .database_name
+                .clone()
+                .unwrap_or_else(|| config::default_database_name(&name)),
+            name,
+            url: self.url.trim_end_matches('/').to_string(),
+            container_name: self.container.clone()?,
+            master_password: self.master_password.clone()?,
+            backup_format: self.format.clone(),
+            output_path: self.output_path.clone(),
+            retention_days: self.retention_days,
+            tags: self.tags.clone(),
+            enabled: !self.disabled,
+            keep_at_least: self.keep_at_least,
+            priority: self.priority,
+            schedule: self.schedule.clone(),
+            timezone: self.timezone.clone(),
+            max_age_hours: self.max_age_hours,
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortKey {
+    Name,
+    Date,
+    Size,
+}
+
+fn parse_datetime(input: &str, end_of_day: bool) -> Result<DateTime<Utc>, String> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(input) {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD or RFC 3339", input))?;
+    let start = date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    // A bare date as upper bound includes the whole day
+    Ok(if end_of_day {
+        start + Duration::days(1) - Duration::nanoseconds(1)
+    } else {
+        start
+    })
+}
+
+fn parse_since(input: &str) -> Result<DateTime<Utc>, String> {
+    parse_datetime(input, false)
+}
+
+fn parse_until(input: &str) -> Result<DateTime<Utc>, String> {
+    parse_datetime(input, true)
+}
+
+fn parse_database_name(input: &str) -> Result<String, String> {
+    crate::odoo_api::validate_database_name(input)?;
+    Ok(input.to_string())
+}
+
+fn parse_label(input: &str) -> Result<String, String> {
+    let label = input.trim();
+    if label.is_empty() {
+        return Err("Label must not be empty".to_string());
+    }
+    Ok(label.to_string())
+}
+
 #[cfg(test)]
+#[allow(clippy::needless_borrows_for_generic_args)]
 mod tests {
     use super::*;
     use clap::Parser;
@@ -55,7 +673,7 @@ mod tests {
     #[test]
     fn test_cli_parsing_backup_command() {
         let cli = Cli::try_parse_from(&["odoo-backup", "backup"]).unwrap();
-        assert!(matches!(cli.command, Commands::Backup { client: None }));
+        assert!(matches!(cli.command, Commands::Backup { client: None, .. }));
         assert_eq!(cli.config, "/etc/odoo-backup/config.json");
         assert_eq!(cli.backup_dir, "/var/backups/odoo");
         assert!(!cli.verbose);
@@ -66,29 +684,247 @@ mod tests {
         let cli =
             Cli::try_parse_from(&["odoo-backup", "backup", "--client", "Test Client"]).unwrap();
         match cli.command {
-            Commands::Backup { client } => {
+            Commands::Backup {
+                client,
+                tag,
+                ask_password,
+                force,
+                label,
+            } => {
                 assert_eq!(client, Some("Test Client".to_string()));
+                assert_eq!(tag, None);
+                assert!(!ask_password);
+                assert!(!force);
+                assert_eq!(label, None);
+            }
+            _ => panic!("Expected Backup command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parsing_backup_with_label() {
+        let cli = Cli::try_parse_from(["odoo-backup", "backup", "--label", " pre-migration-v16 "])
+            .unwrap();
+        match cli.command {
+            Commands::Backup { label, .. } => {
+                assert_eq!(label.as_deref(), Some("pre-migration-v16"));
+            }
+            _ => panic!("Expected Backup command"),
+        }
+        assert!(Cli::try_parse_from(["odoo-backup", "backup", "--label", " "]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parsing_prune() {
+        let cli = Cli::try_parse_from(["odoo-backup", "prune", "--dry-run", "--json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Prune {
+                dry_run: true,
+                json: true
+            }
+        ));
+    }
+
+    #[test]
+    fn test_cli_parsing_catalog_gc() {
+        let cli = Cli::try_parse_from(["odoo-backup", "catalog", "gc", "--dry-run"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Catalog {
+                action: CatalogCommands::Gc {
+                    dry_run: true,
+                    json: false
+                }
+            }
+        ));
+    }
+
+    #[test]
+    fn test_cli_parsing_bench_compress() {
+        let cli = Cli::try_parse_from([
+            "odoo-backup",
+            "bench",
+            "compress",
+            "backup_acme_20240101_120000.dump",
+            "--sample",
+            "500MB",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Bench {
+                action:
+                    BenchCommands::Compress {
+                        file,
+                        sample,
+                        min_speed,
+                        json,
+                    },
+            } => {
+                assert_eq!(file, "backup_acme_20240101_120000.dump");
+                assert_eq!(sample, Some(500 * 1024 * 1024));
+                assert_eq!(min_speed, 10 * 1024 * 1024);
+                assert!(!json);
+            }
+            _ => panic!("expected bench compress"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parsing_catalog_export() {
+        let cli = Cli::try_parse_from(["odoo-backup", "catalog", "export"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Catalog {
+                action: CatalogCommands::Export {
+                    format: ExportFormat::Csv,
+                    since: None
+                }
+            }
+        ));
+        let cli = Cli::try_parse_from([
+            "odoo-backup",
+            "catalog",
+            "export",
+            "--format",
+            "json",
+            "--since",
+            "2024-01-01",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Catalog {
+                action: CatalogCommands::Export { format, since },
+            } => {
+                assert_eq!(format, ExportFormat::Json);
+                assert_eq!(since, Some("2024-01-01T00:00:00Z".parse().unwrap()));
+            }
+            _ => panic!("Expected catalog export"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parsing_backup_with_tag() {
+        let cli = Cli::try_parse_from(["odoo-backup", "backup", "--tag", "prod"]).unwrap();
+        match cli.command {
+            Commands::Backup { client, tag, .. } => {
+                assert_eq!(client, None);
+                assert_eq!(tag, Some("prod".to_string()));
             }
             _ => panic!("Expected Backup command"),
         }
     }
 
+    #[test]
+    fn test_cli_parsing_backup_with_ask_password() {
+        let cli = Cli::try_parse_from(["odoo-backup", "backup", "--ask-password"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Backup {
+                ask_password: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_cli_parsing_backup_with_force() {
+        let cli = Cli::try_parse_from(["odoo-backup", "backup", "--force"]).unwrap();
+        assert!(matches!(cli.command, Commands::Backup { force: true, .. }));
+    }
+
+    #[test]
+    fn test_cli_parsing_backup_client_conflicts_with_tag() {
+        let cli = Cli::try_parse_from([
+            "odoo-backup",
+            "backup",
+            "--client",
+            "Test Client",
+            "--tag",
+            "prod",
+        ]);
+        assert!(cli.is_err());
+    }
+
     #[test]
     fn test_cli_parsing_list_command() {
         let cli = Cli::try_parse_from(&["odoo-backup", "list"]).unwrap();
-        assert!(matches!(cli.command, Commands::List));
+        assert!(matches!(cli.command, Commands::List { live: false }));
+        let cli = Cli::try_parse_from(&["odoo-backup", "list", "--live"]).unwrap();
+        assert!(matches!(cli.command, Commands::List { live: true }));
+    }
+
+    #[test]
+    fn test_cli_parsing_pre_upgrade() {
+        let cli = Cli::try_parse_from([
+            "odoo-backup",
+            "pre-upgrade",
+            "--client",
+            "Acme",
+            "--restore-test",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::PreUpgrade {
+                client,
+                label,
+                restore_test,
+                ..
+            } => {
+                assert_eq!(client, "Acme");
+                assert_eq!(label, "pre-upgrade");
+                assert!(restore_test);
+            }
+            _ => panic!("Expected PreUpgrade command"),
+        }
+        assert!(Cli::try_parse_from(["odoo-backup", "pre-upgrade"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parsing_discover() {
+        let cli =
+            Cli::try_parse_from(["odoo-backup", "discover", "--container", "odoo", "--write"])
+                .unwrap();
+        match cli.command {
+            Commands::Discover {
+                url,
+                container,
+                write,
+            } => {
+                assert_eq!(url, "http://localhost:8069");
+                assert_eq!(container, "odoo");
+                assert!(write);
+            }
+            _ => panic!("Expected Discover command"),
+        }
+        assert!(Cli::try_parse_from(["odoo-backup", "discover"]).is_err());
     }
 
     #[test]
     fn test_cli_parsing_status_command() {
         let cli = Cli::try_parse_from(&["odoo-backup", "status"]).unwrap();
-        assert!(matches!(cli.command, Commands::Status));
+        assert!(matches!(
+            cli.command,
+            Commands::Status {
+                json: false,
+                exit_code: false
+            }
+        ));
+
+        let cli = Cli::try_parse_from(&["odoo-backup", "status", "--json", "--exit-code"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Status {
+                json: true,
+                exit_code: true
+            }
+        ));
     }
 
     #[test]
     fn test_cli_parsing_clean_command() {
         let cli = Cli::try_parse_from(&["odoo-backup", "clean"]).unwrap();
-        assert!(matches!(cli.command, Commands::Clean { client: None }));
+        assert!(matches!(cli.command, Commands::Clean { client: None, .. }));
     }
 
     #[test]
@@ -96,8 +932,119 @@ mod tests {
         let cli =
             Cli::try_parse_from(&["odoo-backup", "clean", "--client", "Test Client"]).unwrap();
         match cli.command {
-            Commands::Clean { client } => {
+            Commands::Clean { client, tag, .. } => {
                 assert_eq!(client, Some("Test Client".to_string()));
+                assert_eq!(tag, None);
+            }
+            _ => panic!("Expected Clean command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parsing_clean_container() {
+        let cli = Cli::try_parse_from(["odoo-backup", "clean", "--container"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Clean {
+                container: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_cli_parsing_convert() {
+        let cli = Cli::try_parse_from([
+            "odoo-backup",
+            "convert",
+            "-f",
+            "backup_db_20240101_120000.zip",
+            "--to",
+            "dump",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Convert {
+                file,
+                to,
+                filestore,
+                out,
+            } => {
+                assert_eq!(file, "backup_db_20240101_120000.zip");
+                assert_eq!(to, ConvertFormat::Dump);
+                assert_eq!(filestore, None);
+                assert_eq!(out, None);
+            }
+            _ => panic!("Expected Convert command"),
+        }
+
+        assert!(Cli::try_parse_from(["odoo-backup", "convert", "-f", "b.zip"]).is_err());
+        assert!(
+            Cli::try_parse_from(["odoo-backup", "convert", "-f", "b.zip", "--to", "tar"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_cli_parsing_extract() {
+        let cli = Cli::try_parse_from([
+            "odoo-backup",
+            "extract",
+            "-f",
+            "b.zip",
+            "--part",
+            "dump",
+            "--part",
+            "manifest",
+            "--dest",
+            "/tmp/out",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Extract { file, part, dest } => {
+                assert_eq!(file, "b.zip");
+                assert_eq!(part, vec![ExtractPart::Dump, ExtractPart::Manifest]);
+                assert_eq!(dest, "/tmp/out");
+            }
+            _ => panic!("Expected Extract command"),
+        }
+
+        assert!(Cli::try_parse_from(["odoo-backup", "extract", "-f", "b.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parsing_inspect() {
+        let cli = Cli::try_parse_from(["odoo-backup", "inspect", "b.zip", "--json"]).unwrap();
+        match cli.command {
+            Commands::Inspect { file, json } => {
+                assert_eq!(file, "b.zip");
+                assert!(json);
+            }
+            _ => panic!("Expected Inspect command"),
+        }
+        assert!(Cli::try_parse_from(["odoo-backup", "inspect"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parsing_diff() {
+        let cli = Cli::try_parse_from(["odoo-backup", "diff", "a.zip", "b.zip"]).unwrap();
+        match cli.command {
+            Commands::Diff { old, new, json } => {
+                assert_eq!(old, "a.zip");
+                assert_eq!(new, "b.zip");
+                assert!(!json);
+            }
+            _ => panic!("Expected Diff command"),
+        }
+        assert!(Cli::try_parse_from(["odoo-backup", "diff", "a.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parsing_clean_with_tag() {
+        let cli = Cli::try_parse_from(["odoo-backup", "clean", "-t", "eu"]).unwrap();
+        match cli.command {
+            Commands::Clean { client, tag, .. } => {
+                assert_eq!(client, None);
+                assert_eq!(tag, Some("eu".to_string()));
             }
             _ => panic!("Expected Clean command"),
         }
@@ -108,7 +1055,14 @@ mod tests {
         let cli = Cli::try_parse_from(&["odoo-backup", "list-backups"]).unwrap();
         assert!(matches!(
             cli.command,
-            Commands::ListBackups { database: None }
+            Commands::ListBackups {
+                database: None,
+                json: false,
+                verify: false,
+                sort: SortKey::Name,
+                limit: None,
+                ..
+            }
         ));
     }
 
@@ -117,32 +1071,446 @@ mod tests {
         let cli =
             Cli::try_parse_from(&["odoo-backup", "list-backups", "--database", "test_db"]).unwrap();
         match cli.command {
-            Commands::ListBackups { database } => {
+            Commands::ListBackups { database, .. } => {
                 assert_eq!(database, Some("test_db".to_string()));
             }
             _ => panic!("Expected ListBackups command"),
         }
     }
 
+    #[test]
+    fn test_cli_parsing_list_backups_json_verify() {
+        let cli =
+            Cli::try_parse_from(["odoo-backup", "list-backups", "--json", "--verify"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::ListBackups {
+                database: None,
+                json: true,
+                verify: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_cli_parsing_list_backups_filters() {
+        let cli = Cli::try_parse_from([
+            "odoo-backup",
+            "list-backups",
+            "--since",
+            "2024-01-01",
+            "--until",
+            "2024-01-31",
+            "--format",
+            "zip",
+            "--min-size",
+            "1GB",
+            "--sort",
+            "size",
+            "--limit",
+            "5",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::ListBackups {
+                since,
+                until,
+                format,
+                min_size,
+                sort,
+                limit,
+                ..
+            } => {
+                assert_eq!(since.unwrap().to_rfc3339(), "2024-01-01T00:00:00+00:00");
+                assert_eq!(
+                    until.unwrap().format("%Y-%m-%d %H:%M:%S").to_string(),
+                    "2024-01-31 23:59:59"
+                );
+                assert_eq!(format.as_deref(), Some("zip"));
+                assert_eq!(min_size, Some(1024 * 1024 * 1024));
+                assert_eq!(sort, SortKey::Size);
+                assert_eq!(limit, Some(5));
+            }
+            _ => panic!("Expected ListBackups command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parsing_list_backups_invalid_filters() {
+        for args in [
+            ["--since", "last tuesday"],
+            ["--format", "tar"],
+            ["--min-size", "big"],
+            ["--sort", "age"],
+        ] {
+            let mut argv = vec!["odoo-backup", "list-backups"];
+            argv.extend(args);
+            assert!(Cli::try_parse_from(argv).is_err(), "{:?} should fail", args);
+        }
+    }
+
+    #[test]
+    fn test_cli_parsing_materialize() {
+        let cli = Cli::try_parse_from([
+            "odoo-backup",
+            "materialize",
+            "backup_db_20240101_120000.zip",
+            "-o",
+            "/tmp/restore.zip",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Materialize { backup, out } => {
+                assert_eq!(backup, "backup_db_20240101_120000.zip");
+                assert_eq!(out.as_deref(), Some("/tmp/restore.zip"));
+            }
+            _ => panic!("Expected Materialize command"),
+        }
+
+        assert!(Cli::try_parse_from(["odoo-backup", "materialize"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parsing_history() {
+        let cli =
+            Cli::try_parse_from(["odoo-backup", "history", "--client", "Acme", "-n", "5"]).unwrap();
+        match cli.command {
+            Commands::History { client, limit } => {
+                assert_eq!(client, Some("Acme".to_string()));
+                assert_eq!(limit, 5);
+            }
+            _ => panic!("Expected History command"),
+        }
+
+        let cli = Cli::try_parse_from(["odoo-backup", "history"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::History {
+                client: None,
+                limit: 20
+            }
+        ));
+    }
+
+    #[test]
+    fn test_cli_parsing_verify() {
+        let cli =
+            Cli::try_parse_from(["odoo-backup", "verify", "--all", "--older-than", "30d"]).unwrap();
+        match cli.command {
+            Commands::Verify {
+                backup,
+                all,
+                older_than,
+                ..
+            } => {
+                assert_eq!(backup, None);
+                assert!(all);
+                assert_eq!(older_than, Some(Duration::days(30)));
+            }
+            _ => panic!("Expected Verify command"),
+        }
+
+        let cli = Cli::try_parse_from(["odoo-backup", "verify", "backup.zip"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Verify {
+                backup: Some(_),
+                all: false,
+                ..
+            }
+        ));
+
+        assert!(Cli::try_parse_from(["odoo-backup", "verify"]).is_err());
+        assert!(Cli::try_parse_from(["odoo-backup", "verify", "backup.zip", "--all"]).is_err());
+        assert!(
+            Cli::try_parse_from(["odoo-backup", "verify", "--all", "--older-than", "soon"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_cli_parsing_backup_selectors() {
+        let selector = |args: &[&str]| {
+            let mut argv = vec!["odoo-backup", "restore", "-c", "Acme", "--as", "copy"];
+            argv.extend_from_slice(args);
+            match Cli::try_parse_from(argv).map(|cli| cli.command) {
+                Ok(Commands::Restore { backup, .. }) => Ok(backup.selector()),
+                Ok(_) => panic!("Expected Restore command"),
+                Err(e) => Err(e.kind()),
+            }
+        };
+        assert_eq!(
+            selector(&["--at", "2024-06-01"]),
+            Ok(Some(BackupSelector::At(
+                "2024-06-01T23:59:59.999999999Z".parse().unwrap()
+            )))
+        );
+        assert_eq!(
+            selector(&["--latest"]),
+            Ok(Some(BackupSelector::NthLatest(1)))
+        );
+        assert_eq!(
+            selector(&["--nth-latest", "2"]),
+            Ok(Some(BackupSelector::NthLatest(2)))
+        );
+        assert_eq!(
+            selector(&["-f", "backup.zip"]),
+            Ok(Some(BackupSelector::File("backup.zip".to_string())))
+        );
+        assert!(selector(&["--nth-latest", "0"]).is_err());
+        for name in ["x'; id; '", "../../x", "_copy"] {
+            assert!(
+                Cli::try_parse_from(["odoo-backup", "restore", "-c", "Acme", "--as", name])
+                    .is_err()
+            );
+        }
+        assert!(selector(&["--latest", "--at", "2024-06-01"]).is_err());
+
+        let cli = Cli::try_parse_from(["odoo-backup", "verify", "-c", "Acme", "--nth-latest", "2"])
+            .unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Verify { backup: None, ref selector, .. } if selector.selector().is_some()
+        ));
+        assert!(Cli::try_parse_from(["odoo-backup", "verify", "--latest"]).is_err());
+        assert!(
+            Cli::try_parse_from(["odoo-backup", "verify", "b.zip", "-c", "Acme", "--latest"])
+                .is_err()
+        );
+        assert!(
+            Cli::try_parse_from(["odoo-backup", "verify", "--all", "-c", "Acme", "--latest"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_cli_parsing_restore_physical() {
+        let cli = Cli::try_parse_from([
+            "odoo-backup",
+            "restore-physical",
+            "-c",
+            "Acme",
+            "--latest",
+            "--into",
+            "/srv/restore/acme",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::RestorePhysical {
+                client,
+                backup,
+                into,
+            } => {
+                assert_eq!(client, "Acme");
+                assert_eq!(backup.selector(), Some(BackupSelector::default()));
+                assert_eq!(into, "/srv/restore/acme");
+            }
+            _ => panic!("Expected RestorePhysical command"),
+        }
+        assert!(Cli::try_parse_from(["odoo-backup", "restore-physical", "-c", "Acme"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parsing_restore() {
+        let cli = Cli::try_parse_from([
+            "odoo-backup",
+            "restore",
+            "-c",
+            "Acme",
+            "--as",
+            "acme_staging",
+            "--target",
+            "Staging",
+            "--neutralize",
+            "--scrub",
+            "default",
+            "--scrub",
+            "./scrub.sql",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Restore {
+                client,
+                backup,
+                database,
+                target,
+                neutralize,
+                direct,
+                only,
+                replace,
+                scrub,
+            } => {
+                assert_eq!(client, "Acme");
+                assert_eq!(backup.selector(), None);
+                assert_eq!(database, "acme_staging");
+                assert_eq!(target.as_deref(), Some("Staging"));
+                assert!(neutralize);
+                assert!(!direct);
+                assert_eq!(only, None);
+                assert!(!replace);
+                assert_eq!(
+                    scrub,
+                    vec![
+                        crate::scrub::Scrub::Default,
+                        crate::scrub::Scrub::Script("./scrub.sql".into())
+                    ]
+                );
+            }
+            _ => panic!("Expected Restore command"),
+        }
+        assert!(Cli::try_parse_from(["odoo-backup", "restore", "-c", "Acme"]).is_err());
+
+        let cli = Cli::try_parse_from([
+            "odoo-backup",
+            "restore",
+            "-c",
+            "Acme",
+            "--as",
+            "acme",
+            "--only",
+            "db",
+            "--replace",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Restore {
+                only: Some(RestorePart::Db),
+                replace: true,
+                ..
+            }
+        ));
+        assert!(Cli::try_parse_from([
+            "odoo-backup",
+            "restore",
+            "-c",
+            "Acme",
+            "--as",
+            "acme",
+            "--replace"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_cli_parsing_test_restore() {
+        let cli = Cli::try_parse_from(["odoo-backup", "test-restore", "-c", "Acme"]).unwrap();
+        match cli.command {
+            Commands::TestRestore { client } => assert_eq!(client, "Acme"),
+            _ => panic!("Expected TestRestore command"),
+        }
+        assert!(Cli::try_parse_from(["odoo-backup", "test-restore"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parsing_check_freshness() {
+        let cli = Cli::try_parse_from(["odoo-backup", "check-freshness", "--json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::CheckFreshness { json: true }
+        ));
+    }
+
+    #[test]
+    fn test_cli_parsing_init() {
+        let cli = Cli::try_parse_from(["odoo-backup", "init"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Init {
+                path: None,
+                force: false
+            }
+        ));
+
+        let cli = Cli::try_parse_from(["odoo-backup", "init", "--path", "config.json", "--force"])
+            .unwrap();
+        match cli.command {
+            Commands::Init { path, force } => {
+                assert_eq!(path.as_deref(), Some("config.json"));
+                assert!(force);
+            }
+            _ => panic!("Expected Init command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parsing_doctor() {
+        let cli = Cli::try_parse_from(["odoo-backup", "doctor"]).unwrap();
+        assert!(matches!(cli.command, Commands::Doctor));
+    }
+
+    #[test]
+    fn test_cli_parsing_check_auth() {
+        let cli = Cli::try_parse_from(["odoo-backup", "check-auth", "--tag", "prod"]).unwrap();
+        match cli.command {
+            Commands::CheckAuth {
+                client, tag, json, ..
+            } => {
+                assert_eq!(client, None);
+                assert_eq!(tag.as_deref(), Some("prod"));
+                assert!(!json);
+            }
+            _ => panic!("Expected CheckAuth command"),
+        }
+        assert!(Cli::try_parse_from([
+            "odoo-backup",
+            "check-auth",
+            "--client",
+            "Acme",
+            "--tag",
+            "prod"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_cli_parsing_daemon() {
+        let cli = Cli::try_parse_from(["odoo-backup", "daemon"]).unwrap();
+        assert!(matches!(cli.command, Commands::Daemon { pid_file: None }));
+
+        let cli = Cli::try_parse_from([
+            "odoo-backup",
+            "daemon",
+            "--pid-file",
+            "/run/odoo-backup.pid",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Daemon { pid_file } => {
+                assert_eq!(pid_file.as_deref(), Some("/run/odoo-backup.pid"))
+            }
+            _ => panic!("Expected Daemon command"),
+        }
+    }
+
     #[test]
     fn test_cli_parsing_with_custom_config() {
         let cli = Cli::try_parse_from(&["odoo-backup", "-c", "custom.json", "list"]).unwrap();
         assert_eq!(cli.config, "custom.json");
-        assert!(matches!(cli.command, Commands::List));
+        assert!(matches!(cli.command, Commands::List { .. }));
     }
 
     #[test]
     fn test_cli_parsing_with_custom_backup_dir() {
         let cli = Cli::try_parse_from(&["odoo-backup", "-b", "/custom/backups", "list"]).unwrap();
         assert_eq!(cli.backup_dir, "/custom/backups");
-        assert!(matches!(cli.command, Commands::List));
+        assert!(matches!(cli.command, Commands::List { .. }));
     }
 
     #[test]
     fn test_cli_parsing_with_verbose() {
         let cli = Cli::try_parse_from(&["odoo-backup", "-v", "list"]).unwrap();
         assert!(cli.verbose);
-        assert!(matches!(cli.command, Commands::List));
+        assert!(matches!(cli.command, Commands::List { .. }));
+    }
+
+    #[test]
+    fn test_cli_parsing_with_quiet() {
+        let cli = Cli::try_parse_from(["odoo-backup", "-q", "backup"]).unwrap();
+        assert!(cli.quiet);
+        assert!(!cli.verbose);
+        assert!(Cli::try_parse_from(["odoo-backup", "-q", "-v", "backup"]).is_err());
     }
 
     #[test]
@@ -160,7 +1528,7 @@ mod tests {
         assert_eq!(cli.config, "test.json");
         assert_eq!(cli.backup_dir, "/tmp");
         assert!(cli.verbose);
-        assert!(matches!(cli.command, Commands::Backup { client: None }));
+        assert!(matches!(cli.command, Commands::Backup { client: None, .. }));
     }
 
     #[test]
@@ -178,7 +1546,7 @@ mod tests {
         assert_eq!(cli.config, "test.json");
         assert_eq!(cli.backup_dir, "/tmp");
         assert!(cli.verbose);
-        assert!(matches!(cli.command, Commands::Backup { client: None }));
+        assert!(matches!(cli.command, Commands::Backup { client: None, .. }));
     }
 
     #[test]
@@ -196,19 +1564,65 @@ mod tests {
     #[test]
     fn test_commands_enum_variants() {
         // Test that all command variants can be created
-        let _backup = Commands::Backup { client: None };
+        let _backup = Commands::Backup {
+            client: None,
+            tag: None,
+            ask_password: false,
+            force: false,
+            label: None,
+        };
         let _backup_with_client = Commands::Backup {
             client: Some("test".to_string()),
+            tag: None,
+            ask_password: false,
+            force: false,
+            label: None,
+        };
+        let _backup_with_tag = Commands::Backup {
+            client: None,
+            tag: Some("prod".to_string()),
+            ask_password: false,
+            force: false,
+            label: None,
+        };
+        let _list = Commands::List { live: false };
+        let _status = Commands::Status {
+            json: false,
+            exit_code: false,
+        };
+        let _clean = Commands::Clean {
+            client: None,
+            tag: None,
+            container: false,
         };
-        let _list = Commands::List;
-        let _status = Commands::Status;
-        let _clean = Commands::Clean { client: None };
         let _clean_with_client = Commands::Clean {
             client: Some("test".to_string()),
+            tag: None,
+            container: false,
+        };
+        let _list_backups = Commands::ListBackups {
+            database: None,
+            json: false,
+            verify: false,
+            since: None,
+            until: None,
+            format: None,
+            min_size: None,
+            label: None,
+            sort: SortKey::Name,
+            limit: None,
         };
-        let _list_backups = Commands::ListBackups { database: None };
         let _list_backups_with_db = Commands::ListBackups {
             database: Some("test".to_string()),
+            json: true,
+            verify: true,
+            since: None,
+            until: None,
+            format: Some("zip".to_string()),
+            min_size: Some(1024),
+            label: Some("migration".to_string()),
+            sort: SortKey::Size,
+            limit: Some(5),
         };
     }
 
@@ -218,5 +1632,221 @@ mod tests {
         assert_eq!(cli.config, "/etc/odoo-backup/config.json");
         assert_eq!(cli.backup_dir, "/var/backups/odoo");
         assert!(!cli.verbose);
+        assert_eq!(cli.config_key_file, None);
+        assert_eq!(cli.output, OutputFormat::Table);
+    }
+
+    #[test]
+    fn test_cli_parsing_output_format() {
+        let cli = Cli::try_parse_from(["odoo-backup", "--output", "plain", "history"]).unwrap();
+        assert_eq!(cli.output, OutputFormat::Plain);
+        assert!(Cli::try_parse_from(["odoo-backup", "--output", "yaml", "list"]).is_err());
+
+        // Like the other global flags, it also goes after the subcommand
+        let cli = Cli::try_parse_from(["odoo-backup", "history", "--output", "json"]).unwrap();
+        assert_eq!(cli.output, OutputFormat::Json);
+
+        // Subcommands name their file paths --out
+        let cli = Cli::try_parse_from([
+            "odoo-backup",
+            "materialize",
+            "b.zip",
+            "--output",
+            "json",
+            "--out",
+            "/tmp/b.zip",
+        ])
+        .unwrap();
+        assert_eq!(cli.output, OutputFormat::Json);
+        assert!(matches!(
+            cli.command,
+            Commands::Materialize { out: Some(_), .. }
+        ));
+    }
+
+    #[test]
+    fn test_cli_parsing_color() {
+        let cli = Cli::try_parse_from(["odoo-backup", "status"]).unwrap();
+        assert_eq!(cli.color, ColorChoice::Auto);
+        let cli =
+            Cli::try_parse_from(["odoo-backup", "config", "lint", "--color", "never"]).unwrap();
+        assert_eq!(cli.color, ColorChoice::Never);
+        assert!(Cli::try_parse_from(["odoo-backup", "--color", "rainbow", "status"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parsing_progress() {
+        let cli = Cli::try_parse_from(["odoo-backup", "backup"]).unwrap();
+        assert_eq!(cli.progress, ProgressMode::None);
+        let cli = Cli::try_parse_from(["odoo-backup", "--progress", "json", "backup"]).unwrap();
+        assert_eq!(cli.progress, ProgressMode::Json);
+    }
+
+    #[test]
+    fn test_cli_parsing_config_encrypt() {
+        let cli = Cli::try_parse_from([
+            "odoo-backup",
+            "config",
+            "encrypt",
+            "--config-key-file",
+            "/etc/odoo-backup/config.key",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli.config_key_file,
+            Some("/etc/odoo-backup/config.key".to_string())
+        );
+        assert!(matches!(
+            cli.command,
+            Commands::Config {
+                action: ConfigCommands::Encrypt { out: None }
+            }
+        ));
+    }
+
+    #[test]
+    fn test_cli_parsing_config_decrypt_with_output() {
+        let cli =
+            Cli::try_parse_from(["odoo-backup", "config", "decrypt", "-o", "plain.json"]).unwrap();
+        match cli.command {
+            Commands::Config {
+                action: ConfigCommands::Decrypt { out },
+            } => assert_eq!(out, Some("plain.json".to_string())),
+            _ => panic!("Expected Config Decrypt command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parsing_config_add() {
+        let cli = Cli::try_parse_from(["odoo-backup", "config", "add", "--interactive"]).unwrap();
+        match cli.command {
+            Commands::Config {
+                action:
+                    ConfigCommands::Add {
+                        interactive,
+                        database,
+                    },
+            } => {
+                assert!(interactive);
+                assert!(database.database_config().is_none());
+            }
+            _ => panic!("Expected Config Add command"),
+        }
+
+        // Without --interactive the required fields must be given
+        assert!(Cli::try_parse_from(["odoo-backup", "config", "add"]).is_err());
+        assert!(Cli::try_parse_from([
+            "odoo-backup",
+            "config",
+            "add",
+            "--interactive",
+            "--name",
+            "Acme"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_cli_parsing_config_lint() {
+        let cli = Cli::try_parse_from(["odoo-backup", "config", "lint", "--json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Config {
+                action: ConfigCommands::Lint { json: true }
+            }
+        ));
+    }
+
+    #[test]
+    fn test_cli_parsing_config_edit() {
+        let cli = Cli::try_parse_from(["odoo-backup", "config", "edit"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Config {
+                action: ConfigCommands::Edit
+            }
+        ));
+    }
+
+    #[test]
+    fn test_cli_parsing_config_remove_and_disable() {
+        let cli =
+            Cli::try_parse_from(["odoo-backup", "config", "remove", "Acme", "--purge"]).unwrap();
+        match cli.command {
+            Commands::Config {
+                action: ConfigCommands::Remove { name, backups },
+            } => {
+                assert_eq!(name, "Acme");
+                assert!(backups.purge);
+                assert!(!backups.archive);
+            }
+            _ => panic!("Expected Config Remove command"),
+        }
+
+        let cli =
+            Cli::try_parse_from(["odoo-backup", "config", "disable", "Acme", "--archive"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Config {
+                action: ConfigCommands::Disable {
+                    backups: ExistingBackupsArgs { archive: true, .. },
+                    ..
+                }
+            }
+        ));
+
+        assert!(Cli::try_parse_from([
+            "odoo-backup",
+            "config",
+            "remove",
+            "Acme",
+            "--purge",
+            "--archive"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_cli_parsing_config_add_fields() {
+        let cli = Cli::try_parse_from([
+            "odoo-backup",
+            "config",
+            "add",
+            "--name",
+            "Acme Corp",
+            "--container",
+            "odoo-acme",
+            "--master-password",
+            "env:ACME_MASTER",
+            "--url",
+            "http://odoo:8069/",
+            "--tag",
+            "prod",
+            "--tag",
+            "eu",
+            "--priority",
+            "-1",
+            "--schedule",
+            "0 2 * * *",
+        ])
+        .unwrap();
+        let Commands::Config {
+            action: ConfigCommands::Add { database, .. },
+        } = cli.command
+        else {
+            panic!("Expected Config Add command");
+        };
+        let db = database.database_config().unwrap();
+        assert_eq!(db.name, "Acme Corp");
+        assert_eq!(db.database_name, "acme_corp");
+        assert_eq!(db.container_name, "odoo-acme");
+        assert_eq!(db.master_password, "env:ACME_MASTER");
+        assert_eq!(db.url, "http://odoo:8069");
+        assert_eq!(db.backup_format, "zip");
+        assert_eq!(db.retention_days, 30);
+        assert_eq!(db.tags, vec!["prod".to_string(), "eu".to_string()]);
+        assert_eq!(db.priority, -1);
+        assert_eq!(db.schedule.as_deref(), Some("0 2 * * *"));
+        assert!(db.enabled);
     }
 }
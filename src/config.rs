@@ -1,8 +1,27 @@
+use crate::blackout::{self, BlackoutWindow};
+use crate::cron::CronSchedule;
+use crate::daemon::ScheduleConfig;
+use crate::dedupe::DedupeConfig;
+use crate::encryption;
 use crate::error::{BackupError, Result};
+use crate::notify::NotificationConfig;
+use crate::odoo_api;
+use crate::proxy;
+use crate::redact;
+use crate::repack;
+use crate::retention::{RetentionTier, TierRule};
+use crate::secrets::{self, SecretSource};
+use crate::size;
+use crate::statsd::StatsdConfig;
+use crate::storage::StorageConfig;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
+use std::path::Path;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct DatabaseConfig {
     pub name: String,
     pub database_name: String,
@@ -12,28 +31,1246 @@ pub struct DatabaseConfig {
     pub backup_format: String,
     pub output_path: String,
     pub retention_days: u32,
+    /// Retention per tier, e.g. `{"daily": 14, "weekly": 52}`. Each backup
+    /// is assigned a tier; backups without one keep `retention_days`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub retention: BTreeMap<RetentionTier, TierRule>,
+    /// Periods whose first successful backup is also copied to the archive,
+    /// e.g. `["weekly", "monthly"]`. Copies are never cleaned up.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub archive_copies: Vec<RetentionTier>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Number of most recent backups cleanup never deletes, whatever their age
+    #[serde(default = "default_keep_at_least")]
+    pub keep_at_least: u32,
+    /// Cap on the disk space taken by local backups, e.g. "200GB"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_total_size: Option<String>,
+    /// Split backups larger than this into parts, e.g. "4GB"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split_size: Option<String>,
+    /// Databases with a higher priority are backed up first
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub priority: i32,
+    /// Skip the backup when the database saw no writes since the last one
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skip_unchanged: bool,
+    /// Cron expression for when the daemon backs this database up, e.g.
+    /// "0 2 * * *". Overrides `backup_interval`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<String>,
+    /// IANA time zone `schedule` and `blackouts` are given in, e.g.
+    /// "Europe/Madrid". Defaults to the host's local time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// Periods during which the database must not be backed up
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blackouts: Vec<BlackoutWindow>,
+    /// Alert when the last successful backup is older than this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_hours: Option<u64>,
+    /// Odoo's data directory inside the container, holding the filestores;
+    /// used by direct restores. Detected in the container when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_dir: Option<String>,
+    /// Host directory bind-mounted into the container at `output_path`.
+    /// Backups are then written straight to the host and moved into place
+    /// instead of being copied out of the container.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mount_dir: Option<String>,
+    /// Where Odoo writes backups inside the container before they are
+    /// copied out. Defaults to `output_path`; ignored with `mount_dir`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_tmp_dir: Option<String>,
+    /// Used instead of the temp directory when it lacks the space for a
+    /// backup the size of the last one. Ignored with `mount_dir`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_tmp_fallback: Option<String>,
+    /// Re-pack zip backups on the host at this deflate level, from 0 (store
+    /// uncompressed) to 9. Odoo always uses its default level.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zip_compression_level: Option<u32>,
+    /// `.netrc` file inside the container with credentials for the Odoo
+    /// URL, e.g. when it sits behind basic auth.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub netrc_file: Option<String>,
+    /// Extra headers sent with every request to Odoo, e.g. for an
+    /// authenticating reverse proxy. Values may be secret references.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub http_headers: BTreeMap<String, String>,
+    /// Outbound proxy for requests to Odoo. Defaults to `HTTPS_PROXY` or
+    /// `HTTP_PROXY` of the host.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// Comma-separated hosts reached without the proxy. Defaults to
+    /// `NO_PROXY` of the host; localhost never uses the proxy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+    /// Back up with the session cookie of a user login instead of the
+    /// master password, for instances whose database manager is disabled.
+    /// `master_password` may then be empty; restores still need it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_auth: Option<SessionAuth>,
+    /// Puts the instance into maintenance while Odoo writes the backup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintenance: Option<MaintenanceConfig>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub backup_method: BackupMethod,
+    /// The PostgreSQL server `pg_basebackup` backs up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub postgres: Option<PostgresConfig>,
+    /// Encrypts this client's backups in the dedupe store; a secret
+    /// reference like `master_password`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dedupe_passphrase: Option<String>,
+    /// Extra `pg_dump` flags for `backup_method` `pg_dump`, from
+    /// `PG_DUMP_ARGS`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pg_dump_extra_args: Vec<String>,
+    /// Extra `pg_restore` flags for direct restores of dump backups into
+    /// this database's instance, from `PG_RESTORE_ARGS`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pg_restore_extra_args: Vec<String>,
+    /// Applied to every database restored into this instance, e.g. a
+    /// staging one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub staging: Option<StagingProfile>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// How a database is backed up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupMethod {
+    /// Odoo's database manager writes a zip or dump backup
+    #[default]
+    Odoo,
+    /// A physical base backup of the whole PostgreSQL cluster, plus the
+    /// filestore, for databases too big to dump
+    PgBasebackup,
+    /// A directory-format `pg_dump` of the database, written by
+    /// `postgres.dump_jobs` parallel jobs, plus the filestore
+    PgDump,
+}
+
+impl BackupMethod {
+    /// The method as `backup_method` names it.
+    pub fn name(self) -> &'static str {
+        match self {
+            BackupMethod::Odoo => "odoo",
+            BackupMethod::PgBasebackup => "pg_basebackup",
+            BackupMethod::PgDump => "pg_dump",
+        }
+    }
+}
+
+/// Where `pg_basebackup` or `pg_dump` runs and how it connects.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct PostgresConfig {
+    /// Container running PostgreSQL, where `pg_basebackup` or `pg_dump` runs
+    pub container_name: String,
+    #[serde(default = "default_postgres_host")]
+    pub host: String,
+    #[serde(default = "default_postgres_port")]
+    pub port: u16,
+    /// A user with the REPLICATION privilege for `pg_basebackup`, or one
+    /// that can read the database for `pg_dump`
+    #[serde(default = "default_postgres_user")]
+    pub user: String,
+    /// The user's password, or a secret reference
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// The server is a streaming replica, checked to be one before each
+    /// backup so the primary is never backed up by mistake
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub standby: bool,
+    /// Largest replication lag a standby may have when a backup starts,
+    /// e.g. "5m"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_lag: Option<String>,
+    /// Parallel jobs of `pg_dump`, each with its own connection
+    #[serde(default = "default_dump_jobs")]
+    pub dump_jobs: u32,
+}
+
+impl PostgresConfig {
+    /// `max_lag` as a duration. Validation guarantees it parses.
+    pub fn max_lag(&self) -> Option<chrono::Duration> {
+        self.max_lag
+            .as_deref()
+            .and_then(|lag| size::parse_duration(lag).ok())
+    }
+}
+
+impl std::fmt::Debug for PostgresConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PostgresConfig")
+            .field("container_name", &self.container_name)
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("user", &self.user)
+            .field("password", &self.password.as_ref().map(|_| redact::MASK))
+            .field("standby", &self.standby)
+            .field("max_lag", &self.max_lag)
+            .field("dump_jobs", &self.dump_jobs)
+            .finish()
+    }
+}
+
+fn default_postgres_host() -> String {
+    "localhost".to_string()
+}
+
+fn default_postgres_port() -> u16 {
+    5432
+}
+
+fn default_postgres_user() -> String {
+    "postgres".to_string()
+}
+
+fn default_dump_jobs() -> u32 {
+    4
+}
+
+/// `pg_dump` flags `pg_dump_extra_args` may hold. Flags that change where
+/// or in which format the dump is written, or how it connects, are left
+/// out as the service sets them.
+pub const PG_DUMP_ARGS: [&str; 21] = [
+    "--no-owner",
+    "--no-privileges",
+    "--no-acl",
+    "--no-comments",
+    "--no-publications",
+    "--no-subscriptions",
+    "--no-security-labels",
+    "--no-tablespaces",
+    "--no-toast-compression",
+    "--no-unlogged-table-data",
+    "--no-synchronized-snapshots",
+    "--quote-all-identifiers",
+    "--serializable-deferrable",
+    "--load-via-partition-root",
+    "--exclude-schema",
+    "--exclude-table",
+    "--exclude-table-data",
+    "--schema",
+    "--table",
+    "--compress",
+    "--lock-wait-timeout",
+];
+
+/// `pg_restore` flags `pg_restore_extra_args` may hold.
+pub const PG_RESTORE_ARGS: [&str; 18] = [
+    "--no-owner",
+    "--no-privileges",
+    "--no-acl",
+    "--no-comments",
+    "--no-publications",
+    "--no-subscriptions",
+    "--no-security-labels",
+    "--no-tablespaces",
+    "--no-data-for-failed-tables",
+    "--disable-triggers",
+    "--exit-on-error",
+    "--single-transaction",
+    "--use-set-session-authorization",
+    "--exclude-schema",
+    "--schema",
+    "--table",
+    "--jobs",
+    "--role",
+];
+
+/// Checks that every one of `args` is a long flag in `allowed`, with any
+/// value given as `--flag=value` so it cannot pass as a flag of its own.
+fn validate_extra_args(args: &[String], allowed: &[&str]) -> std::result::Result<(), String> {
+    for arg in args {
+        let name = arg.split_once('=').map_or(arg.as_str(), |(name, _)| name);
+        if !allowed.contains(&name) {
+            return Err(format!(
+                "'{}' is not allowed, use one of {}",
+                arg,
+                allowed.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// A user login for session cookie authentication.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionAuth {
+    pub login: String,
+    /// The user's password, or a secret reference
+    pub password: String,
+    /// Route of a companion module that backs the database up for the
+    /// logged-in user. Stock `/web/database/backup` ignores the session and
+    /// always checks the master password.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_route: Option<String>,
+}
+
+impl std::fmt::Debug for SessionAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionAuth")
+            .field("login", &self.login)
+            .field("password", &redact::MASK)
+            .field("backup_route", &self.backup_route)
+            .finish()
+    }
+}
+
+/// How to quiesce an instance while Odoo writes a backup, so huge databases
+/// are dumped without users and cron jobs writing to them. Each step is
+/// undone after the backup, whether it succeeded or not.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MaintenanceConfig {
+    /// `ir.config_parameter` set to `True` during the backup, e.g. for a
+    /// custom module that refuses logins. Needs `session_auth`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_parameter: Option<String>,
+    /// Host command that turns maintenance on, e.g. a maintenance page in
+    /// nginx
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enable_command: Option<String>,
+    /// Host command that turns maintenance off again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disable_command: Option<String>,
+    /// Deactivate the scheduled actions during the backup, waiting for
+    /// running ones to finish first
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pause_crons: bool,
+    /// Containers stopped during the backup, e.g. cron workers
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop_containers: Vec<String>,
+}
+
+impl MaintenanceConfig {
+    pub fn validate(&self, db: &DatabaseConfig) -> std::result::Result<(), String> {
+        if self.config_parameter.is_none()
+            && self.enable_command.is_none()
+            && !self.pause_crons
+            && self.stop_containers.is_empty()
+        {
+            return Err(
+                "set config_parameter, enable_command, pause_crons or stop_containers".to_string(),
+            );
+        }
+        if self
+            .config_parameter
+            .as_ref()
+            .is_some_and(|key| key.trim().is_empty())
+        {
+            return Err("config_parameter cannot be empty".to_string());
+        }
+        if self.config_parameter.is_some() && db.session_auth.is_none() {
+            return Err("config_parameter needs session_auth".to_string());
+        }
+        if self.enable_command.is_some() != self.disable_command.is_some() {
+            return Err("enable_command and disable_command go together".to_string());
+        }
+        if self.stop_containers.contains(&db.container_name) {
+            return Err(format!(
+                "stop_containers cannot stop {}, which takes the backup",
+                db.container_name
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// What `restore` changes in a copy restored into a staging instance, so it
+/// links to itself and cannot reach the outside world. Runs after
+/// `--neutralize`, with or without it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StagingProfile {
+    /// Written to `web.base.url`, which is then frozen so the first admin
+    /// login does not overwrite it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub disable_mail_servers: bool,
+    #[serde(default = "default_enabled")]
+    pub disable_payment_providers: bool,
+    /// Carriers with a live shipping API; fixed and rule-based ones stay
+    #[serde(default = "default_enabled")]
+    pub disable_shipping_providers: bool,
+}
+
+impl Default for StagingProfile {
+    fn default() -> Self {
+        StagingProfile {
+            base_url: None,
+            disable_mail_servers: true,
+            disable_payment_providers: true,
+            disable_shipping_providers: true,
+        }
+    }
+}
+
+impl StagingProfile {
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if let Some(url) = &self.base_url {
+            if !(url.starts_with("http://") || url.starts_with("https://"))
+                || url.chars().any(char::is_whitespace)
+            {
+                return Err(format!("base_url '{}' is not an http(s) URL", url));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How requests to an `https://` Odoo URL verify and authenticate. Paths
+/// are inside the container, where the requests run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// CA bundle to verify the server with, e.g. for an internal CA
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_file: Option<String>,
+    /// Accept any server certificate
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub insecure_skip_verify: bool,
+    /// Client certificate for mutual TLS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_cert: Option<String>,
+    /// Key of `client_cert`, when it is not in the same file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_key: Option<String>,
+}
+
+impl TlsConfig {
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        let paths = [
+            ("ca_file", &self.ca_file),
+            ("client_cert", &self.client_cert),
+            ("client_key", &self.client_key),
+        ];
+        for (field, path) in paths {
+            if path.as_ref().is_some_and(|path| !path.starts_with('/')) {
+                return Err(format!("{} must be an absolute path", field));
+            }
+        }
+        if self.client_key.is_some() && self.client_cert.is_none() {
+            return Err("client_key needs client_cert".to_string());
+        }
+        if self.insecure_skip_verify && self.ca_file.is_some() {
+            return Err("ca_file is unused with insecure_skip_verify".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Data directory of the official Odoo image.
+pub const DEFAULT_DATA_DIR: &str = "/var/lib/odoo";
+
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_keep_at_least() -> u32 {
+    1
+}
+
+/// The defaults of `config add`, with no name, container or password.
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            name: String::new(),
+            database_name: String::new(),
+            url: "http://localhost:8069".to_string(),
+            container_name: String::new(),
+            master_password: String::new(),
+            backup_format: "zip".to_string(),
+            output_path: "/tmp/backups".to_string(),
+            retention_days: 30,
+            retention: BTreeMap::new(),
+            archive_copies: Vec::new(),
+            tags: Vec::new(),
+            enabled: default_enabled(),
+            keep_at_least: default_keep_at_least(),
+            max_total_size: None,
+            split_size: None,
+            priority: 0,
+            skip_unchanged: false,
+            schedule: None,
+            timezone: None,
+            blackouts: Vec::new(),
+            max_age_hours: None,
+            data_dir: None,
+            mount_dir: None,
+            container_tmp_dir: None,
+            container_tmp_fallback: None,
+            zip_compression_level: None,
+            netrc_file: None,
+            http_headers: BTreeMap::new(),
+            proxy: None,
+            no_proxy: None,
+            tls: None,
+            session_auth: None,
+            maintenance: None,
+            backup_method: BackupMethod::default(),
+            postgres: None,
+            dedupe_passphrase: None,
+            pg_dump_extra_args: Vec::new(),
+            pg_restore_extra_args: Vec::new(),
+            staging: None,
+        }
+    }
+}
+
+/// Like a derived `Debug`, with the master password masked.
+impl std::fmt::Debug for DatabaseConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let DatabaseConfig {
+            name,
+            database_name,
+            url,
+            container_name,
+            master_password: _,
+            backup_format,
+            output_path,
+            retention_days,
+            retention,
+            archive_copies,
+            tags,
+            enabled,
+            keep_at_least,
+            max_total_size,
+            split_size,
+            priority,
+            skip_unchanged,
+            schedule,
+            timezone,
+            blackouts,
+            max_age_hours,
+            data_dir,
+            mount_dir,
+            container_tmp_dir,
+            container_tmp_fallback,
+            zip_compression_level,
+            netrc_file,
+            http_headers,
+            proxy,
+            no_proxy,
+            tls,
+            session_auth,
+            maintenance,
+            backup_method,
+            postgres,
+            dedupe_passphrase,
+            pg_dump_extra_args,
+            pg_restore_extra_args,
+            staging,
+        } = self;
+        f.debug_struct("DatabaseConfig")
+            .field("name", name)
+            .field("database_name", database_name)
+            .field("url", url)
+            .field("container_name", container_name)
+            .field("master_password", &redact::MASK)
+            .field("backup_format", backup_format)
+            .field("output_path", output_path)
+            .field("retention_days", retention_days)
+            .field("retention", retention)
+            .field("archive_copies", archive_copies)
+            .field("tags", tags)
+            .field("enabled", enabled)
+            .field("keep_at_least", keep_at_least)
+            .field("max_total_size", max_total_size)
+            .field("split_size", split_size)
+            .field("priority", priority)
+            .field("skip_unchanged", skip_unchanged)
+            .field("schedule", schedule)
+            .field("timezone", timezone)
+            .field("blackouts", blackouts)
+            .field("max_age_hours", max_age_hours)
+            .field("data_dir", data_dir)
+            .field("mount_dir", mount_dir)
+            .field("container_tmp_dir", container_tmp_dir)
+            .field("container_tmp_fallback", container_tmp_fallback)
+            .field("zip_compression_level", zip_compression_level)
+            .field("netrc_file", netrc_file)
+            .field("http_headers", &http_headers.keys().collect::<Vec<_>>())
+            .field("proxy", proxy)
+            .field("no_proxy", no_proxy)
+            .field("tls", tls)
+            .field("session_auth", session_auth)
+            .field("maintenance", maintenance)
+            .field("backup_method", backup_method)
+            .field("postgres", postgres)
+            .field(
+                "dedupe_passphrase",
+                &dedupe_passphrase.as_ref().map(|_| redact::MASK),
+            )
+            .field("pg_dump_extra_args", pg_dump_extra_args)
+            .field("pg_restore_extra_args", pg_restore_extra_args)
+            .field("staging", staging)
+            .finish()
+    }
+}
+
+impl DatabaseConfig {
+    /// Where Odoo writes backups inside the container. Always
+    /// `output_path` with `mount_dir`, as that is where the mount is.
+    pub fn container_tmp_dir(&self) -> &str {
+        match &self.container_tmp_dir {
+            Some(dir) if self.mount_dir.is_none() => dir,
+            _ => &self.output_path,
+        }
+    }
+
+    /// `max_total_size` in bytes. Validation guarantees it parses.
+    pub fn max_total_size_bytes(&self) -> Option<u64> {
+        self.max_total_size
+            .as_deref()
+            .and_then(|size| size::parse_size(size).ok())
+    }
+
+    /// `split_size` in bytes. Validation guarantees it parses.
+    pub fn split_size_bytes(&self) -> Option<u64> {
+        self.split_size
+            .as_deref()
+            .and_then(|size| size::parse_size(size).ok())
+    }
+
+    /// The parsed `schedule`. Validation guarantees it parses.
+    pub fn cron_schedule(&self) -> Option<CronSchedule> {
+        self.schedule
+            .as_deref()
+            .and_then(|schedule| CronSchedule::parse(schedule).ok())
+    }
+
+    /// The parsed `timezone`; `None` means the host's local time.
+    /// Validation guarantees it parses.
+    pub fn time_zone(&self) -> Option<Tz> {
+        self.timezone.as_deref().and_then(|zone| zone.parse().ok())
+    }
+
+    /// When the blackout window `now` falls in ends, if it falls in one.
+    pub fn blackout_end(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self.time_zone() {
+            Some(zone) => blackout::blackout_end(&self.blackouts, &now.with_timezone(&zone))
+                .map(|end| end.with_timezone(&Utc)),
+            None => blackout::blackout_end(&self.blackouts, &now.with_timezone(&Local))
+                .map(|end| end.with_timezone(&Utc)),
+        }
+    }
+
+    /// The day `time` falls on in the database's time zone.
+    pub fn local_date(&self, time: DateTime<Utc>) -> NaiveDate {
+        match self.time_zone() {
+            Some(zone) => time.with_timezone(&zone).date_naive(),
+            None => time.with_timezone(&Local).date_naive(),
+        }
+    }
+
+    /// Whether a backup of `tier` taken at `taken` is past its retention
+    /// at `now`. Backups without a configured tier are kept
+    /// `retention_days`.
+    pub fn retention_expired(
+        &self,
+        tier: Option<RetentionTier>,
+        taken: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> bool {
+        match tier.and_then(|tier| Some((tier, self.retention.get(&tier)?))) {
+            Some((tier, rule)) => tier.expiry(taken, rule.keep()) <= now,
+            None => taken < now - chrono::Duration::days(self.retention_days as i64),
+        }
+    }
+
+    /// `time` in the database's time zone, for messages about its
+    /// schedule and blackouts.
+    pub fn format_local(&self, time: DateTime<Utc>) -> String {
+        match self.time_zone() {
+            Some(zone) => time.with_timezone(&zone).format("%Y-%m-%d %H:%M %Z"),
+            None => time.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+        }
+        .to_string()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    pub fn needs_password_prompt(&self) -> bool {
+        self.master_password == secrets::PROMPT_MARKER
+    }
+
+    /// Prompts for the master password when it is configured as `-`, or
+    /// unconditionally when `force_prompt` is set.
+    pub fn resolve_master_password(&mut self, force_prompt: bool) -> Result<()> {
+        if force_prompt || self.needs_password_prompt() {
+            self.master_password =
+                secrets::prompt_secret(&format!("Master password for {}", self.name))?;
+            redact::register(&self.master_password);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Config {
     pub databases: Vec<DatabaseConfig>,
+    /// Storage that cleanup moves backups to instead of deleting them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive: Option<StorageConfig>,
+    /// Chunk store that backups are deduplicated into instead of kept whole
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dedupe: Option<DedupeConfig>,
+    /// Channels alerts are sent to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notifications: Vec<NotificationConfig>,
+    /// Jobs run by the daemon command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<ScheduleConfig>,
+    /// How many backups may run at once, on different containers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallel_backups: Option<usize>,
+    /// Minimum pause between two backups against the same Odoo URL, e.g. "5m"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_cooldown: Option<String>,
+    /// Alert when a backup runs this many times longer than its average
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slow_backup_factor: Option<f64>,
+    /// `.prom` file refreshed after each backup run, for the node_exporter
+    /// textfile collector
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_textfile: Option<String>,
+    /// StatsD server each backup run reports its metrics to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statsd: Option<StatsdConfig>,
+    /// OTLP/HTTP collector backup run traces are sent to, e.g.
+    /// "http://localhost:4318". Needs the `otel` feature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub otlp_endpoint: Option<String>,
 }
 
-impl Config {
-    pub fn from_file(path: &str) -> Result<Self> {
-        let content = fs::read_to_string(path).map_err(|e| {
-            BackupError::FileSystem(format!("Failed to read config file {}: {}", path, e))
+/// A configured database as `list` prints it, without its master password.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DatabaseListing {
+    pub name: String,
+    pub database_name: String,
+    pub container_name: String,
+    pub url: String,
+    pub backup_format: String,
+    pub enabled: bool,
+    pub tags: Vec<String>,
+    pub schedule: Option<String>,
+    pub timezone: Option<String>,
+    /// Whether the server still serves the database, checked by `list --live`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exists: Option<bool>,
+}
+
+impl From<&DatabaseConfig> for DatabaseListing {
+    fn from(db: &DatabaseConfig) -> Self {
+        Self {
+            name: db.name.clone(),
+            database_name: db.database_name.clone(),
+            container_name: db.container_name.clone(),
+            url: db.url.clone(),
+            backup_format: db.backup_format.clone(),
+            enabled: db.enabled,
+            tags: db.tags.clone(),
+            schedule: db.schedule.clone(),
+            timezone: db.timezone.clone(),
+            exists: None,
+        }
+    }
+}
+
+/// A database a server serves that no config entry backs up.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnconfiguredDatabase {
+    pub database_name: String,
+    pub container_name: String,
+    pub url: String,
+}
+
+/// What `list --live` prints: the configured databases, checked against
+/// the servers they are on, and what else those servers serve.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LiveListing {
+    pub databases: Vec<DatabaseListing>,
+    pub unconfigured: Vec<UnconfiguredDatabase>,
+}
+
+impl LiveListing {
+    /// Cross-references `databases` with `served`, the databases each
+    /// server lists by container and URL. Servers missing from `served`
+    /// could not be asked, so nothing is known about their databases.
+    pub fn new(
+        databases: &[DatabaseConfig],
+        served: &BTreeMap<(String, String), Vec<String>>,
+    ) -> Self {
+        let server = |db: &DatabaseConfig| (db.container_name.clone(), db.url.clone());
+        let listings = databases
+            .iter()
+            .map(|db| DatabaseListing {
+                exists: served
+                    .get(&server(db))
+                    .map(|names| names.contains(&db.database_name)),
+                ..DatabaseListing::from(db)
+            })
+            .collect();
+        let mut unconfigured = Vec::new();
+        for ((container_name, url), names) in served {
+            for name in names {
+                let configured = databases.iter().any(|db| {
+                    &db.database_name == name && server(db) == (container_name.clone(), url.clone())
+                });
+                if !configured {
+                    unconfigured.push(UnconfiguredDatabase {
+                        database_name: name.clone(),
+                        container_name: container_name.clone(),
+                        url: url.clone(),
+                    });
+                }
+            }
+        }
+        Self {
+            databases: listings,
+            unconfigured,
+        }
+    }
+}
+
+/// Parses the config file body: either a bare array of databases or an
+/// object with `databases` and global settings.
+fn parse_config(content: &str) -> Result<Config> {
+    let parsed = if content.trim_start().starts_with('[') {
+        serde_json::from_str(content).map(|databases| Config {
+            databases,
+            ..Default::default()
+        })
+    } else {
+        serde_json::from_str(content)
+    };
+    parsed.map_err(|e| BackupError::Config(format!("Invalid JSON in config file: {}", e)))
+}
+
+/// Default `database_name` for a client, e.g. `acme_corp` for "Acme Corp".
+pub fn default_database_name(client: &str) -> String {
+    client
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Example configuration written by `init`, with every optional section
+/// disabled by a leading `_` in its key.
+pub const STARTER_CONFIG: &str = include_str!("starter_config.json");
+
+/// Writes `STARTER_CONFIG` to `path`, creating its directory. An existing
+/// file is only replaced with `force`.
+pub fn write_starter_config(path: &str, force: bool) -> Result<()> {
+    if !force && Path::new(path).exists() {
+        return Err(BackupError::Config(format!(
+            "{} already exists, use --force to replace it",
+            path
+        )));
+    }
+    if let Some(dir) = Path::new(path)
+        .parent()
+        .filter(|d| !d.as_os_str().is_empty())
+    {
+        fs::create_dir_all(dir)
+            .map_err(|e| BackupError::from_fs(format!("Failed to create {}", dir.display()), e))?;
+    }
+    write_config_file(path, STARTER_CONFIG)
+}
+
+/// Environment variable holding the passphrase for encrypted config files.
+pub const CONFIG_KEY_ENV: &str = "ODOO_BACKUP_CONFIG_KEY";
+
+/// Passphrase for encrypted config files: the key file if given, then
+/// `ODOO_BACKUP_CONFIG_KEY`, then an interactive prompt.
+pub fn config_passphrase(key_file: Option<&str>) -> Result<String> {
+    if let Some(key_file) = key_file {
+        let key = fs::read_to_string(key_file).map_err(|e| {
+            BackupError::from_fs(format!("Failed to read key file {}", key_file), e)
         })?;
+        let key = key.trim().to_string();
+        if key.is_empty() {
+            return Err(BackupError::Config(format!(
+                "Key file {} is empty",
+                key_file
+            )));
+        }
+        return Ok(key);
+    }
+
+    match std::env::var(CONFIG_KEY_ENV) {
+        Ok(key) if !key.is_empty() => Ok(key),
+        _ => secrets::prompt_secret("Config passphrase"),
+    }
+}
+
+/// Encrypts a plaintext config file, in place unless `output` is given.
+pub fn encrypt_config_file(path: &str, output: Option<&str>, key_file: Option<&str>) -> Result<()> {
+    let content = read_config_file(path)?;
+    if encryption::is_armored(&content) {
+        return Err(BackupError::Config(format!(
+            "Config file {} is already encrypted",
+            path
+        )));
+    }
+    // Refuse to lock away a config that could not be loaded afterwards
+    parse_config(&content)?;
+
+    let armored = encryption::encrypt_armored(content.as_bytes(), &config_passphrase(key_file)?)?;
+    write_config_file(output.unwrap_or(path), &armored)
+}
+
+/// Decrypts an encrypted config file, in place unless `output` is given.
+pub fn decrypt_config_file(path: &str, output: Option<&str>, key_file: Option<&str>) -> Result<()> {
+    let content = read_config_file(path)?;
+    if !encryption::is_armored(&content) {
+        return Err(BackupError::Config(format!(
+            "Config file {} is not encrypted",
+            path
+        )));
+    }
+
+    let plaintext = encryption::decrypt_armored(&content, &config_passphrase(key_file)?)?;
+    let plaintext = String::from_utf8(plaintext)
+        .map_err(|e| BackupError::Config(format!("Decrypted config is not UTF-8: {}", e)))?;
+    write_config_file(output.unwrap_or(path), &plaintext)
+}
+
+/// Adds `db` to the databases of the config file at `path`, which is
+/// created if missing.
+pub fn append_database(path: &str, key_file: Option<&str>, db: &DatabaseConfig) -> Result<()> {
+    edit_databases(path, key_file, true, |databases| {
+        if databases.iter().any(|d| d["name"] == db.name.as_str()) {
+            return Err(BackupError::Config(format!(
+                "A database named {} already exists",
+                db.name
+            )));
+        }
+        databases.push(serde_json::to_value(db)?);
+        Ok(())
+    })
+}
+
+/// Deletes the database called `name` from the config file at `path`.
+pub fn remove_database(path: &str, key_file: Option<&str>, name: &str) -> Result<()> {
+    edit_databases(path, key_file, false, |databases| {
+        let index = database_index(databases, name)?;
+        databases.remove(index);
+        Ok(())
+    })
+}
+
+/// Sets `enabled` of the database called `name` in the config file at `path`.
+pub fn set_database_enabled(
+    path: &str,
+    key_file: Option<&str>,
+    name: &str,
+    enabled: bool,
+) -> Result<()> {
+    edit_databases(path, key_file, false, |databases| {
+        let index = database_index(databases, name)?;
+        let db = databases[index]
+            .as_object_mut()
+            .ok_or_else(|| BackupError::Config(format!("Database {} is not an object", name)))?;
+        if enabled {
+            // Enabled is the default, so the key is left out like in new entries
+            db.remove("enabled");
+        } else {
+            db.insert("enabled".to_string(), serde_json::Value::Bool(false));
+        }
+        Ok(())
+    })
+}
+
+fn database_index(databases: &[serde_json::Value], name: &str) -> Result<usize> {
+    databases
+        .iter()
+        .position(|d| d["name"] == name)
+        .ok_or_else(|| BackupError::Config(format!("No database named {}", name)))
+}
+
+/// Applies `edit` to the list of databases in the config file at `path`
+/// and writes the file back if the result is valid. With `create`, a
+/// missing file starts as an empty list. Encrypted files are re-encrypted
+/// with the same passphrase. The other contents of the file, `_comment`
+/// keys included, are kept in their order.
+fn edit_databases(
+    path: &str,
+    key_file: Option<&str>,
+    create: bool,
+    edit: impl FnOnce(&mut Vec<serde_json::Value>) -> Result<()>,
+) -> Result<()> {
+    let (content, passphrase) = if create && !Path::new(path).exists() {
+        ("[]".to_string(), None)
+    } else {
+        read_plaintext_config(path, key_file)?
+    };
+
+    let mut document: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| BackupError::Config(format!("Invalid JSON in config file: {}", e)))?;
+    let databases = match &mut document {
+        serde_json::Value::Array(databases) => Some(databases),
+        serde_json::Value::Object(root) => root
+            .entry("databases")
+            .or_insert_with(|| serde_json::Value::Array(Vec::new()))
+            .as_array_mut(),
+        _ => None,
+    }
+    .ok_or_else(|| BackupError::Config(format!("{} has no list of databases", path)))?;
+    edit(databases)?;
 
-        // Try to parse as direct array first, then as Config struct
-        let databases: Vec<DatabaseConfig> = serde_json::from_str(&content)
-            .map_err(|e| BackupError::Config(format!("Invalid JSON in config file: {}", e)))?;
+    // Same four-space indentation as the documented examples
+    let mut content = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut content, formatter);
+    serde::Serialize::serialize(&document, &mut serializer)?;
+    let mut content = String::from_utf8(content).expect("serde_json writes UTF-8");
+    content.push('\n');
 
-        let config = Config { databases };
+    parse_config(&content)?.validate()?;
+    match passphrase {
+        Some(passphrase) => write_config_file(
+            path,
+            &encryption::encrypt_armored(content.as_bytes(), &passphrase)?,
+        ),
+        None => write_config_file(path, &content),
+    }
+}
+
+/// Editor used by `config edit` when neither `VISUAL` nor `EDITOR` is set.
+pub const DEFAULT_EDITOR: &str = "vi";
+
+/// Lets the user change the config file at `path` in `editor`, a shell
+/// command the file name is appended to. The edited text only replaces the
+/// file once it is valid; `retry` is asked whether to edit again after an
+/// invalid attempt, and the edit is dropped when it says no. The previous
+/// version is kept next to the file as `<path>.<timestamp>.bak`, whose
+/// path is returned, or `None` when nothing changed.
+pub fn edit_config_file(
+    path: &str,
+    key_file: Option<&str>,
+    editor: &str,
+    mut retry: impl FnMut(&BackupError) -> Result<bool>,
+) -> Result<Option<String>> {
+    let original = read_config_file(path)?;
+    let (plaintext, passphrase) = read_plaintext_config(path, key_file)?;
+
+    // Plaintext of an encrypted file must not be readable by others
+    let scratch = std::env::temp_dir().join(format!("odoo-backup-{}.json", uuid::Uuid::new_v4()));
+    let result = write_private_file(&scratch, &plaintext)
+        .map_err(|e| BackupError::from_fs(format!("Failed to write {}", scratch.display()), e))
+        .and_then(|_| edit_until_valid(&scratch, editor, &mut retry));
+    let _ = fs::remove_file(&scratch);
+
+    let edited = result?;
+    if edited == plaintext {
+        return Ok(None);
+    }
+    let backup_path = format!("{}.{}.bak", path, Local::now().format("%Y%m%d%H%M%S"));
+    // The previous version holds the same passwords as the config itself
+    write_private_file(Path::new(&backup_path), &original)
+        .map_err(|e| BackupError::from_fs(format!("Failed to write backup {}", backup_path), e))?;
+    match passphrase {
+        Some(passphrase) => write_config_file(
+            path,
+            &encryption::encrypt_armored(edited.as_bytes(), &passphrase)?,
+        )?,
+        None => write_config_file(path, &edited)?,
+    }
+    Ok(Some(backup_path))
+}
+
+/// Runs `editor` on `file` until the text it leaves there is a valid
+/// config, or `retry` gives up.
+fn edit_until_valid(
+    file: &Path,
+    editor: &str,
+    retry: &mut impl FnMut(&BackupError) -> Result<bool>,
+) -> Result<String> {
+    loop {
+        let status = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$1\"", editor))
+            .arg("sh")
+            .arg(file)
+            .status()
+            .map_err(|e| BackupError::Io(e).context(format!("Failed to run {}", editor)))?;
+        if !status.success() {
+            return Err(BackupError::Config(format!(
+                "{} exited with {}, the configuration was not changed",
+                editor, status
+            )));
+        }
+
+        let content = fs::read_to_string(file)
+            .map_err(|e| BackupError::from_fs(format!("Failed to read {}", file.display()), e))?;
+        match parse_config(&content).and_then(|config| config.validate()) {
+            Ok(()) => return Ok(content),
+            Err(e) => {
+                if !retry(&e)? {
+                    return Err(e.context("The configuration was not changed"));
+                }
+            }
+        }
+    }
+}
+
+/// Loads and validates the config at `path` but leaves secret references
+/// as written, and tells whether the file is encrypted.
+pub fn load_unresolved(path: &str, key_file: Option<&str>) -> Result<(Config, bool)> {
+    let (content, passphrase) = read_plaintext_config(path, key_file)?;
+    let config = parse_config(&content)?;
+    config.validate()?;
+    Ok((config, passphrase.is_some()))
+}
+
+/// Names of the databases in the config file at `path`, without resolving
+/// secrets; none if the file does not exist yet.
+pub fn configured_names(path: &str, key_file: Option<&str>) -> Result<Vec<String>> {
+    Ok(configured_databases(path, key_file)?
+        .into_iter()
+        .map(|db| db.name)
+        .collect())
+}
+
+/// Databases in the config file at `path`, without resolving their
+/// secrets; none if it does not exist yet.
+pub fn configured_databases(path: &str, key_file: Option<&str>) -> Result<Vec<DatabaseConfig>> {
+    if !Path::new(path).exists() {
+        return Ok(Vec::new());
+    }
+    let (content, _) = read_plaintext_config(path, key_file)?;
+    Ok(parse_config(&content)?.databases)
+}
+
+/// The config file body, decrypted if needed, and the passphrase that
+/// decrypted it.
+fn read_plaintext_config(path: &str, key_file: Option<&str>) -> Result<(String, Option<String>)> {
+    let content = read_config_file(path)?;
+    if !encryption::is_armored(&content) {
+        return Ok((content, None));
+    }
+    let passphrase = config_passphrase(key_file)?;
+    let plaintext = encryption::decrypt_armored(&content, &passphrase)?;
+    let plaintext = String::from_utf8(plaintext)
+        .map_err(|e| BackupError::Config(format!("Decrypted config is not UTF-8: {}", e)))?;
+    Ok((plaintext, Some(passphrase)))
+}
+
+fn read_config_file(path: &str) -> Result<String> {
+    fs::read_to_string(path)
+        .map_err(|e| BackupError::from_fs(format!("Failed to read config file {}", path), e))
+}
+
+/// Writes through a temporary file so an interrupted write never leaves a
+/// truncated config behind. The file holds passwords, so it is created
+/// readable by its owner only, and under a fresh name so no file left
+/// behind with other permissions is reused.
+fn write_config_file(path: &str, content: &str) -> Result<()> {
+    let tmp_path = format!("{}.{}.tmp", path, uuid::Uuid::new_v4());
+    let written = write_private_file(Path::new(&tmp_path), content)
+        .map_err(|e| BackupError::from_fs(format!("Failed to write config file {}", tmp_path), e));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+    fs::rename(&tmp_path, path)
+        .map_err(|e| BackupError::from_fs(format!("Failed to replace config file {}", path), e))
+}
+
+/// Replaces the secret reference in `value` with the secret, and masks it
+/// in log lines from then on. `label` names the setting in errors; only the
+/// master password can be prompted for.
+fn resolve_secret(label: &str, value: &mut String) -> Result<()> {
+    let source = SecretSource::parse(value);
+    if source == SecretSource::Prompt {
+        return Err(BackupError::Config(format!(
+            "{} cannot be prompted for",
+            label
+        )));
+    }
+    if let Some(resolved) = source
+        .resolve_non_interactive()
+        .map_err(|e| BackupError::Config(format!("{}: {}", label, e)))?
+    {
+        *value = resolved;
+    }
+    redact::register(value);
+    Ok(())
+}
+
+/// Creates `path` readable by its owner only and writes `content` to it.
+/// Fails when the file already exists, so its permissions are never
+/// inherited from an older file.
+fn write_private_file(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(path)
+        .and_then(|mut file| std::io::Write::write_all(&mut file, content.as_bytes()))
+}
+
+impl Config {
+    #[allow(dead_code)]
+    pub fn from_file(path: &str) -> Result<Self> {
+        Self::from_file_with_key(path, None)
+    }
+
+    /// Loads the config, transparently decrypting it when it is encrypted.
+    pub fn from_file_with_key(path: &str, key_file: Option<&str>) -> Result<Self> {
+        let (content, _) = read_plaintext_config(path, key_file)?;
+        let mut config = parse_config(&content)?;
         config.validate()?;
+        config.resolve_secrets()?;
         Ok(config)
     }
 
+    /// Replaces secret references with their values, which are masked in
+    /// log lines from then on. Prompted secrets are left in place until the
+    /// command that needs them runs.
+    fn resolve_secrets(&mut self) -> Result<()> {
+        for db in &mut self.databases {
+            let label = |field: &str| format!("Database {}: {}", db.name, field);
+            if !db.needs_password_prompt() {
+                resolve_secret(&label("master_password"), &mut db.master_password)?;
+            }
+            if let Some(session) = &mut db.session_auth {
+                resolve_secret(&label("session_auth: password"), &mut session.password)?;
+            }
+            if let Some(password) = db.postgres.as_mut().and_then(|p| p.password.as_mut()) {
+                resolve_secret(&label("postgres: password"), password)?;
+            }
+            if let Some(passphrase) = &mut db.dedupe_passphrase {
+                resolve_secret(&label("dedupe_passphrase"), passphrase)?;
+            }
+            for (name, value) in &mut db.http_headers {
+                resolve_secret(&label(&format!("http_headers: {}", name)), value)?;
+            }
+        }
+        Ok(())
+    }
+
     fn validate(&self) -> Result<()> {
         if self.databases.is_empty() {
             return Err(BackupError::Config("No databases configured".to_string()));
@@ -64,26 +1301,344 @@ impl Config {
                     i
                 )));
             }
-            if db.master_password.is_empty() {
+            if db.master_password.is_empty() && db.session_auth.is_none() {
                 return Err(BackupError::Config(format!(
                     "Database {}: master_password cannot be empty",
                     i
                 )));
             }
+            if let Some(session) = &db.session_auth {
+                if session.login.is_empty() || session.password.is_empty() {
+                    return Err(BackupError::Config(format!(
+                        "Database {}: session_auth needs a login and a password",
+                        i
+                    )));
+                }
+                match session.backup_route.as_deref() {
+                    None | Some(odoo_api::BACKUP_ROUTE) => {
+                        return Err(BackupError::Config(format!(
+                            "Database {}: session_auth needs backup_route, the route of a \
+                             companion module: {} ignores the session and always checks \
+                             the master password",
+                            i,
+                            odoo_api::BACKUP_ROUTE
+                        )));
+                    }
+                    Some(route)
+                        if !route.starts_with('/')
+                            || route.contains(|c: char| c.is_whitespace() || c == '\'') =>
+                    {
+                        return Err(BackupError::Config(format!(
+                            "Database {}: session_auth: backup_route '{}' is not a path",
+                            i, route
+                        )));
+                    }
+                    Some(_) => {}
+                }
+            }
             if !["zip", "dump"].contains(&db.backup_format.as_str()) {
                 return Err(BackupError::Config(format!(
                     "Database {}: backup_format must be 'zip' or 'dump'",
                     i
                 )));
             }
+            if db.tags.iter().any(|t| t.trim().is_empty()) {
+                return Err(BackupError::Config(format!(
+                    "Database {}: tags cannot be empty",
+                    i
+                )));
+            }
+            for (tier, rule) in &db.retention {
+                if rule.keep() == 0 {
+                    return Err(BackupError::Config(format!(
+                        "Database {}: retention {} must keep at least 1",
+                        i, tier
+                    )));
+                }
+                if rule
+                    .backup_format()
+                    .is_some_and(|format| !["zip", "dump"].contains(&format))
+                {
+                    return Err(BackupError::Config(format!(
+                        "Database {}: retention {}: backup_format must be 'zip' or 'dump'",
+                        i, tier
+                    )));
+                }
+            }
+            if !db.archive_copies.is_empty() && self.archive.is_none() {
+                return Err(BackupError::Config(format!(
+                    "Database {}: archive_copies needs an archive to copy to",
+                    i
+                )));
+            }
+            if db.dedupe_passphrase.is_some() && self.dedupe.is_none() {
+                return Err(BackupError::Config(format!(
+                    "Database {}: dedupe_passphrase needs a dedupe store",
+                    i
+                )));
+            }
+            if db.dedupe_passphrase.as_deref() == Some("") {
+                return Err(BackupError::Config(format!(
+                    "Database {}: dedupe_passphrase cannot be empty",
+                    i
+                )));
+            }
+            if db.max_age_hours == Some(0) {
+                return Err(BackupError::Config(format!(
+                    "Database {}: max_age_hours must be at least 1",
+                    i
+                )));
+            }
+            if db.keep_at_least == 0 {
+                return Err(BackupError::Config(format!(
+                    "Database {}: keep_at_least must be at least 1",
+                    i
+                )));
+            }
+            if let Some(max_total_size) = &db.max_total_size {
+                size::parse_size(max_total_size).map_err(|e| {
+                    BackupError::Config(format!("Database {}: max_total_size: {}", i, e))
+                })?;
+            }
+            if let Some(schedule) = &db.schedule {
+                CronSchedule::parse(schedule)
+                    .map_err(|e| BackupError::Config(format!("Database {}: schedule: {}", i, e)))?;
+            }
+            if let Some(zone) = &db.timezone {
+                zone.parse::<Tz>().map_err(|_| {
+                    BackupError::Config(format!(
+                        "Database {}: timezone '{}' is not an IANA time zone name",
+                        i, zone
+                    ))
+                })?;
+            }
+            for (j, window) in db.blackouts.iter().enumerate() {
+                window.validate().map_err(|e| {
+                    BackupError::Config(format!("Database {}: blackouts {}: {}", i, j, e))
+                })?;
+            }
+            if db
+                .mount_dir
+                .as_ref()
+                .is_some_and(|dir| !dir.starts_with('/'))
+            {
+                return Err(BackupError::Config(format!(
+                    "Database {}: mount_dir must be an absolute path",
+                    i
+                )));
+            }
+            if db
+                .netrc_file
+                .as_ref()
+                .is_some_and(|file| !file.starts_with('/'))
+            {
+                return Err(BackupError::Config(format!(
+                    "Database {}: netrc_file must be an absolute path",
+                    i
+                )));
+            }
+            if let Some(tls) = &db.tls {
+                tls.validate()
+                    .map_err(|e| BackupError::Config(format!("Database {}: tls: {}", i, e)))?;
+            }
+            match (db.backup_method, &db.postgres) {
+                (BackupMethod::Odoo, _) => {}
+                (method, None) => {
+                    return Err(BackupError::Config(format!(
+                        "Database {}: backup_method {} needs postgres",
+                        i,
+                        method.name()
+                    )));
+                }
+                (_, Some(postgres)) if postgres.container_name.is_empty() => {
+                    return Err(BackupError::Config(format!(
+                        "Database {}: postgres: container_name cannot be empty",
+                        i
+                    )));
+                }
+                _ => {}
+            }
+            validate_extra_args(&db.pg_dump_extra_args, &PG_DUMP_ARGS).map_err(|e| {
+                BackupError::Config(format!("Database {}: pg_dump_extra_args: {}", i, e))
+            })?;
+            if !db.pg_dump_extra_args.is_empty() && db.backup_method != BackupMethod::PgDump {
+                return Err(BackupError::Config(format!(
+                    "Database {}: pg_dump_extra_args only applies to backup_method pg_dump",
+                    i
+                )));
+            }
+            validate_extra_args(&db.pg_restore_extra_args, &PG_RESTORE_ARGS).map_err(|e| {
+                BackupError::Config(format!("Database {}: pg_restore_extra_args: {}", i, e))
+            })?;
+            if let Some(postgres) = &db.postgres {
+                if postgres.dump_jobs == 0 {
+                    return Err(BackupError::Config(format!(
+                        "Database {}: postgres: dump_jobs must be at least 1",
+                        i
+                    )));
+                }
+                if let Some(lag) = &postgres.max_lag {
+                    size::parse_duration(lag).map_err(|e| {
+                        BackupError::Config(format!("Database {}: postgres: max_lag: {}", i, e))
+                    })?;
+                    if !postgres.standby {
+                        return Err(BackupError::Config(format!(
+                            "Database {}: postgres: max_lag needs standby",
+                            i
+                        )));
+                    }
+                }
+            }
+            if let Some(maintenance) = &db.maintenance {
+                maintenance.validate(db).map_err(|e| {
+                    BackupError::Config(format!("Database {}: maintenance: {}", i, e))
+                })?;
+            }
+            if let Some(staging) = &db.staging {
+                staging
+                    .validate()
+                    .map_err(|e| BackupError::Config(format!("Database {}: staging: {}", i, e)))?;
+            }
+            if let Some(proxy) = &db.proxy {
+                proxy::validate(proxy)
+                    .map_err(|e| BackupError::Config(format!("Database {}: proxy: {}", i, e)))?;
+            }
+            for (name, value) in &db.http_headers {
+                let valid_name = !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+                if !valid_name {
+                    return Err(BackupError::Config(format!(
+                        "Database {}: http_headers: '{}' is not a valid header name",
+                        i, name
+                    )));
+                }
+                if value.contains(['\r', '\n']) {
+                    return Err(BackupError::Config(format!(
+                        "Database {}: http_headers: {} cannot contain line breaks",
+                        i, name
+                    )));
+                }
+            }
+            if let Some(level) = db.zip_compression_level {
+                if level > repack::MAX_LEVEL {
+                    return Err(BackupError::Config(format!(
+                        "Database {}: zip_compression_level must be between 0 and {}",
+                        i,
+                        repack::MAX_LEVEL
+                    )));
+                }
+                let takes_zips = db.backup_format == "zip"
+                    || db
+                        .retention
+                        .values()
+                        .any(|rule| rule.backup_format() == Some("zip"));
+                if !takes_zips {
+                    return Err(BackupError::Config(format!(
+                        "Database {}: zip_compression_level only applies to zip backups",
+                        i
+                    )));
+                }
+            }
+            if let Some(split_size) = &db.split_size {
+                let bytes = size::parse_size(split_size).map_err(|e| {
+                    BackupError::Config(format!("Database {}: split_size: {}", i, e))
+                })?;
+                if bytes == 0 {
+                    return Err(BackupError::Config(format!(
+                        "Database {}: split_size must be greater than zero",
+                        i
+                    )));
+                }
+            }
         }
 
+        if let Some(archive) = &self.archive {
+            archive
+                .validate()
+                .map_err(|e| BackupError::Config(format!("archive: {}", e)))?;
+        }
+        if self.dedupe.as_ref().is_some_and(|d| d.path.is_empty()) {
+            return Err(BackupError::Config(
+                "dedupe: path cannot be empty".to_string(),
+            ));
+        }
+        for (i, channel) in self.notifications.iter().enumerate() {
+            channel
+                .validate()
+                .map_err(|e| BackupError::Config(format!("notifications {}: {}", i, e)))?;
+        }
+        if self.parallel_backups == Some(0) {
+            return Err(BackupError::Config(
+                "parallel_backups must be at least 1".to_string(),
+            ));
+        }
+        if self
+            .slow_backup_factor
+            .is_some_and(|factor| factor.is_nan() || factor < 1.0)
+        {
+            return Err(BackupError::Config(
+                "slow_backup_factor must be at least 1".to_string(),
+            ));
+        }
+        if self
+            .metrics_textfile
+            .as_ref()
+            .is_some_and(|path| !path.ends_with(".prom"))
+        {
+            return Err(BackupError::Config(
+                "metrics_textfile must end in .prom to be picked up by node_exporter".to_string(),
+            ));
+        }
+        if let Some(statsd) = &self.statsd {
+            statsd
+                .validate()
+                .map_err(|e| BackupError::Config(format!("statsd: {}", e)))?;
+        }
+        if let Some(endpoint) = &self.otlp_endpoint {
+            if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+                return Err(BackupError::Config(format!(
+                    "otlp_endpoint '{}' must be an http:// or https:// URL",
+                    endpoint
+                )));
+            }
+        }
+        if let Some(cooldown) = &self.instance_cooldown {
+            size::parse_duration(cooldown)
+                .map_err(|e| BackupError::Config(format!("instance_cooldown: {}", e)))?;
+        }
+        if let Some(schedule) = &self.schedule {
+            schedule
+                .validate()
+                .map_err(|e| BackupError::Config(format!("schedule: {}", e)))?;
+        }
         Ok(())
     }
 
+    /// `instance_cooldown` as a duration. Validation guarantees it parses.
+    pub fn instance_cooldown(&self) -> Option<chrono::Duration> {
+        self.instance_cooldown
+            .as_deref()
+            .and_then(|cooldown| size::parse_duration(cooldown).ok())
+    }
+
     pub fn get_database(&self, name: &str) -> Option<&DatabaseConfig> {
         self.databases.iter().find(|db| db.name == name)
     }
+
+    pub fn databases_with_tag(&self, tag: &str) -> Vec<&DatabaseConfig> {
+        self.databases.iter().filter(|db| db.has_tag(tag)).collect()
+    }
+
+    /// Databases taking part in batch operations, optionally restricted to a tag.
+    pub fn enabled_databases(&self, tag: Option<&str>) -> Vec<&DatabaseConfig> {
+        let databases = match tag {
+            Some(tag) => self.databases_with_tag(tag),
+            None => self.databases.iter().collect(),
+        };
+        databases.into_iter().filter(|db| db.enabled).collect()
+    }
 }
 
 #[cfg(test)]
@@ -96,12 +1651,10 @@ mod tests {
         DatabaseConfig {
             name: "Test Client".to_string(),
             database_name: "test_database".to_string(),
-            url: "http://localhost:8069".to_string(),
             container_name: "test_container".to_string(),
             master_password: "admin".to_string(),
-            backup_format: "zip".to_string(),
-            output_path: "/tmp/backups".to_string(),
-            retention_days: 30,
+            tags: vec!["prod".to_string(), "eu".to_string()],
+            ..Default::default()
         }
     }
 
@@ -111,16 +1664,182 @@ mod tests {
             DatabaseConfig {
                 name: "Test Client 2".to_string(),
                 database_name: "test_database_2".to_string(),
-                url: "http://localhost:8069".to_string(),
                 container_name: "test_container_2".to_string(),
                 master_password: "admin".to_string(),
                 backup_format: "dump".to_string(),
-                output_path: "/tmp/backups".to_string(),
                 retention_days: 7,
+                tags: vec!["staging".to_string(), "eu".to_string()],
+                ..Default::default()
             },
         ]
     }
 
+    #[test]
+    fn test_starter_config_is_valid() {
+        let config = parse_config(STARTER_CONFIG).unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.databases.len(), 1);
+        assert!(config.archive.is_none());
+        assert!(config.notifications.is_empty());
+
+        // Every disabled section must still be valid once enabled
+        let enabled = parse_config(&STARTER_CONFIG.replace("\"_", "\"")).unwrap();
+        enabled.validate().unwrap();
+        assert!(enabled.archive.is_some());
+        assert!(enabled.dedupe.is_some());
+        assert_eq!(enabled.notifications.len(), 2);
+        assert_eq!(enabled.databases[0].max_age_hours, Some(26));
+    }
+
+    #[test]
+    fn test_write_starter_config() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("etc/config.json");
+        let path = path.to_str().unwrap();
+
+        write_starter_config(path, false).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), STARTER_CONFIG);
+
+        fs::write(path, "[]").unwrap();
+        assert!(matches!(
+            write_starter_config(path, false).unwrap_err(),
+            BackupError::Config(_)
+        ));
+        assert_eq!(fs::read_to_string(path).unwrap(), "[]");
+        write_starter_config(path, true).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), STARTER_CONFIG);
+    }
+
+    #[test]
+    fn test_append_database() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("config.json");
+        let path = path.to_str().unwrap();
+        let db = create_test_config();
+
+        // A missing file becomes a plain list
+        append_database(path, None, &db).unwrap();
+        let config = Config::from_file(path).unwrap();
+        assert_eq!(config.databases, vec![db.clone()]);
+        assert!(fs::read_to_string(path).unwrap().starts_with("[\n    {"));
+
+        assert!(matches!(
+            append_database(path, None, &db).unwrap_err(),
+            BackupError::Config(_)
+        ));
+
+        write_starter_config(path, true).unwrap();
+        append_database(path, None, &db).unwrap();
+        let content = fs::read_to_string(path).unwrap();
+        let config = parse_config(&content).unwrap();
+        assert_eq!(config.databases.len(), 2);
+        assert_eq!(config.databases[1], db);
+        // Comments stay first, where they were
+        assert!(content.starts_with("{\n    \"_comment\""));
+    }
+
+    #[test]
+    fn test_remove_and_disable_database() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("config.json");
+        let path = path.to_str().unwrap();
+        write_starter_config(path, false).unwrap();
+        let db = create_test_config();
+        append_database(path, None, &db).unwrap();
+
+        set_database_enabled(path, None, "Test Client", false).unwrap();
+        let config = parse_config(&fs::read_to_string(path).unwrap()).unwrap();
+        assert!(!config.databases[1].enabled);
+        set_database_enabled(path, None, "Test Client", true).unwrap();
+        let config = parse_config(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(config.databases[1], db);
+
+        remove_database(path, None, "Example Client").unwrap();
+        let content = fs::read_to_string(path).unwrap();
+        assert!(content.starts_with("{\n    \"_comment\""));
+        assert_eq!(parse_config(&content).unwrap().databases, vec![db]);
+
+        for result in [
+            remove_database(path, None, "Missing"),
+            set_database_enabled(path, None, "Missing", false),
+            // The file must keep at least one database
+            remove_database(path, None, "Test Client"),
+        ] {
+            assert!(matches!(result.unwrap_err(), BackupError::Config(_)));
+        }
+        assert_eq!(
+            parse_config(&fs::read_to_string(path).unwrap())
+                .unwrap()
+                .databases
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_edit_config_file() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("config.json");
+        let path = path.to_str().unwrap();
+        write_starter_config(path, false).unwrap();
+        let no_retry = |_: &BackupError| -> Result<bool> { panic!("the edit is valid") };
+
+        assert_eq!(
+            edit_config_file(path, None, "true", no_retry).unwrap(),
+            None
+        );
+
+        // Invalid edits are retried, then dropped
+        let mut attempts = 0;
+        let error = edit_config_file(path, None, "printf '[]' >", |e| {
+            assert!(e.to_string().contains("No databases configured"));
+            attempts += 1;
+            Ok(attempts < 2)
+        })
+        .unwrap_err();
+        assert_eq!(attempts, 2);
+        assert!(error.to_string().contains("not changed"));
+        assert_eq!(fs::read_to_string(path).unwrap(), STARTER_CONFIG);
+        assert!(edit_config_file(path, None, "false", no_retry).is_err());
+
+        let backup = edit_config_file(path, None, "sed -i 's/Example Client/Acme/'", no_retry)
+            .unwrap()
+            .unwrap();
+        assert_eq!(fs::read_to_string(&backup).unwrap(), STARTER_CONFIG);
+        assert!(backup.starts_with(&format!("{}.", path)) && backup.ends_with(".bak"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&backup).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let config = Config::from_file(path).unwrap();
+        assert_eq!(config.databases[0].name, "Acme");
+    }
+
+    #[test]
+    fn test_append_database_encrypted() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("config.json");
+        let path = path.to_str().unwrap();
+        let key_file = temp_dir.path().join("key");
+        let key_file = key_file.to_str().unwrap();
+        fs::write(key_file, "passphrase").unwrap();
+        append_database(path, None, &create_test_config()).unwrap();
+        encrypt_config_file(path, None, Some(key_file)).unwrap();
+
+        let mut db = create_test_config();
+        db.name = "Second".to_string();
+        append_database(path, Some(key_file), &db).unwrap();
+        assert!(encryption::is_armored(&fs::read_to_string(path).unwrap()));
+        let config = Config::from_file_with_key(path, Some(key_file)).unwrap();
+        assert_eq!(config.databases.len(), 2);
+        assert_eq!(
+            configured_names(path, Some(key_file)).unwrap(),
+            vec!["Test Client".to_string(), "Second".to_string()]
+        );
+    }
+
     #[test]
     fn test_config_from_file_valid() {
         let temp_dir = tempdir().unwrap();
@@ -151,12 +1870,12 @@ mod tests {
     fn test_config_from_file_missing_file() {
         let result = Config::from_file("nonexistent_file.json");
         assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), BackupError::FileSystem(_)));
+        assert!(matches!(result.unwrap_err().root(), BackupError::Io(_)));
     }
 
     #[test]
     fn test_config_validation_empty_databases() {
-        let config = Config { databases: vec![] };
+        let config = Config::default();
         let result = config.validate();
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), BackupError::Config(_)));
@@ -168,6 +1887,7 @@ mod tests {
         config.name = String::new();
         let config = Config {
             databases: vec![config],
+            ..Default::default()
         };
 
         let result = config.validate();
@@ -181,6 +1901,7 @@ mod tests {
         config.database_name = String::new();
         let config = Config {
             databases: vec![config],
+            ..Default::default()
         };
 
         let result = config.validate();
@@ -194,6 +1915,7 @@ mod tests {
         config.url = String::new();
         let config = Config {
             databases: vec![config],
+            ..Default::default()
         };
 
         let result = config.validate();
@@ -207,6 +1929,7 @@ mod tests {
         config.container_name = String::new();
         let config = Config {
             databases: vec![config],
+            ..Default::default()
         };
 
         let result = config.validate();
@@ -220,6 +1943,7 @@ mod tests {
         config.master_password = String::new();
         let config = Config {
             databases: vec![config],
+            ..Default::default()
         };
 
         let result = config.validate();
@@ -227,12 +1951,107 @@ mod tests {
         assert!(matches!(result.unwrap_err(), BackupError::Config(_)));
     }
 
+    #[test]
+    fn test_config_validation_session_auth() {
+        let mut db = create_test_config();
+        db.master_password = String::new();
+        db.session_auth = Some(SessionAuth {
+            login: "backup@acme.com".to_string(),
+            password: "s3ssion-Pw".to_string(),
+            backup_route: Some("/session_backup/backup".to_string()),
+        });
+        let mut config = Config {
+            databases: vec![db],
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        assert!(!format!("{:?}", config).contains("s3ssion-Pw"));
+
+        // Stock Odoo never honours the session for backups
+        for route in [None, Some("/web/database/backup"), Some("backup")] {
+            config.databases[0]
+                .session_auth
+                .as_mut()
+                .unwrap()
+                .backup_route = route.map(str::to_string);
+            assert!(config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains("backup_route"));
+        }
+        config.databases[0]
+            .session_auth
+            .as_mut()
+            .unwrap()
+            .backup_route = Some("/session_backup/backup".to_string());
+
+        config.databases[0].session_auth.as_mut().unwrap().login = String::new();
+        assert!(matches!(
+            config.validate().unwrap_err(),
+            BackupError::Config(_)
+        ));
+    }
+
+    #[test]
+    fn test_config_validation_maintenance() {
+        let mut db = create_test_config();
+        db.maintenance = Some(MaintenanceConfig {
+            stop_containers: vec!["odoo-cron".to_string()],
+            ..Default::default()
+        });
+        assert!(db.maintenance.as_ref().unwrap().validate(&db).is_ok());
+
+        let maintenance = MaintenanceConfig {
+            config_parameter: Some("acme.maintenance".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            maintenance.validate(&db).unwrap_err(),
+            "config_parameter needs session_auth"
+        );
+        let maintenance = MaintenanceConfig {
+            enable_command: Some("touch /tmp/on".to_string()),
+            ..Default::default()
+        };
+        assert!(maintenance.validate(&db).is_err());
+        let maintenance = MaintenanceConfig {
+            stop_containers: vec![db.container_name.clone()],
+            ..Default::default()
+        };
+        assert!(maintenance.validate(&db).is_err());
+        assert!(MaintenanceConfig::default().validate(&db).is_err());
+    }
+
+    #[test]
+    fn test_config_validation_staging() {
+        let staging: StagingProfile =
+            serde_json::from_str(r#"{"base_url": "https://staging.example.com"}"#).unwrap();
+        assert!(staging.disable_mail_servers && staging.disable_shipping_providers);
+        let mut config = Config {
+            databases: vec![create_test_config()],
+            ..Default::default()
+        };
+        config.databases[0].staging = Some(staging);
+        assert!(config.validate().is_ok());
+
+        for url in ["staging.example.com", "https://staging example.com"] {
+            config.databases[0].staging.as_mut().unwrap().base_url = Some(url.to_string());
+            assert!(config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains("staging: base_url"));
+        }
+    }
+
     #[test]
     fn test_config_validation_invalid_backup_format() {
         let mut config = create_test_config();
         config.backup_format = "invalid_format".to_string();
         let config = Config {
             databases: vec![config],
+            ..Default::default()
         };
 
         let result = config.validate();
@@ -240,12 +2059,190 @@ mod tests {
         assert!(matches!(result.unwrap_err(), BackupError::Config(_)));
     }
 
+    #[test]
+    fn test_config_validation_relative_mount_dir() {
+        let mut config = create_test_config();
+        config.mount_dir = Some("backups".to_string());
+        let config = Config {
+            databases: vec![config],
+            ..Default::default()
+        };
+
+        let result = config.validate();
+        assert!(result.unwrap_err().to_string().contains("mount_dir"));
+    }
+
+    #[test]
+    fn test_container_tmp_dir() {
+        let mut config = create_test_config();
+        assert_eq!(config.container_tmp_dir(), "/tmp/backups");
+        config.container_tmp_dir = Some("/var/tmp/odoo".to_string());
+        assert_eq!(config.container_tmp_dir(), "/var/tmp/odoo");
+
+        config.mount_dir = Some("/srv/odoo-backups/acme".to_string());
+        assert_eq!(config.container_tmp_dir(), "/tmp/backups");
+    }
+
+    #[test]
+    fn test_config_validation_zip_compression_level() {
+        let mut config = create_test_config();
+        config.zip_compression_level = Some(10);
+        let mut dump = create_test_config();
+        dump.backup_format = "dump".to_string();
+        dump.zip_compression_level = Some(0);
+        for db in [config, dump] {
+            let config = Config {
+                databases: vec![db],
+                ..Default::default()
+            };
+            assert!(config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains("zip_compression_level"));
+        }
+    }
+
+    #[test]
+    fn test_config_validation_retention_tiers() {
+        let mut db = create_test_config();
+        db.backup_format = "dump".to_string();
+        db.zip_compression_level = Some(1);
+        db.retention = serde_json::from_str(
+            r#"{"daily": 14, "weekly": {"keep": 52, "backup_format": "zip"}}"#,
+        )
+        .unwrap();
+        let mut config = Config {
+            databases: vec![db],
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        for (retention, error) in [
+            (r#"{"daily": 0}"#, "retention daily must keep at least 1"),
+            (
+                r#"{"monthly": {"keep": 12, "backup_format": "tar"}}"#,
+                "retention monthly: backup_format",
+            ),
+        ] {
+            config.databases[0].retention = serde_json::from_str(retention).unwrap();
+            let message = config.validate().unwrap_err().to_string();
+            assert!(message.contains(error), "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_config_validation_archive_copies_need_archive() {
+        let mut db = create_test_config();
+        db.archive_copies = vec![RetentionTier::Weekly, RetentionTier::Monthly];
+        let mut config = Config {
+            databases: vec![db],
+            ..Default::default()
+        };
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("archive_copies needs an archive"));
+
+        config.archive =
+            serde_json::from_str(r#"{"type": "local", "path": "/srv/archive"}"#).unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_config_validation_pg_dump() {
+        let mut db = create_test_config();
+        db.backup_method = BackupMethod::PgDump;
+        let mut config = Config {
+            databases: vec![db],
+            ..Default::default()
+        };
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("backup_method pg_dump needs postgres"));
+
+        config.databases[0].postgres = serde_json::from_value(serde_json::json!({
+            "container_name": "odoo-db",
+            "dump_jobs": 0,
+        }))
+        .unwrap();
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("dump_jobs must be at least 1"));
+        config.databases[0].postgres.as_mut().unwrap().dump_jobs = 8;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_config_validation_pg_extra_args() {
+        let mut db = create_test_config();
+        db.pg_restore_extra_args = vec!["--no-privileges".to_string(), "--jobs=4".to_string()];
+        let mut config = Config {
+            databases: vec![db],
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        config.databases[0].pg_restore_extra_args = vec!["--clean".to_string()];
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("pg_restore_extra_args: '--clean' is not allowed"));
+        // Values must be joined to their flag
+        config.databases[0].pg_restore_extra_args = vec!["--schema".to_string(), "-c".to_string()];
+        assert!(config.validate().is_err());
+        config.databases[0].pg_restore_extra_args.clear();
+
+        config.databases[0].pg_dump_extra_args =
+            vec!["--exclude-table-data=mail_message".to_string()];
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("only applies to backup_method pg_dump"));
+        config.databases[0].backup_method = BackupMethod::PgDump;
+        config.databases[0].postgres = serde_json::from_value(serde_json::json!({
+            "container_name": "odoo-db",
+        }))
+        .unwrap();
+        assert!(config.validate().is_ok());
+        config.databases[0].pg_dump_extra_args = vec!["--file=/etc/passwd".to_string()];
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_validation_dedupe_passphrase_needs_dedupe() {
+        let mut db = create_test_config();
+        db.dedupe_passphrase = Some("secret".to_string());
+        let mut config = Config {
+            databases: vec![db],
+            ..Default::default()
+        };
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("dedupe_passphrase needs a dedupe store"));
+
+        config.dedupe = Some(DedupeConfig {
+            path: "/var/backups/odoo-dedupe".to_string(),
+        });
+        assert!(config.validate().is_ok());
+    }
+
     #[test]
     fn test_config_validation_valid_zip_format() {
         let mut config = create_test_config();
         config.backup_format = "zip".to_string();
         let config = Config {
             databases: vec![config],
+            ..Default::default()
         };
 
         let result = config.validate();
@@ -258,6 +2255,7 @@ mod tests {
         config.backup_format = "dump".to_string();
         let config = Config {
             databases: vec![config],
+            ..Default::default()
         };
 
         let result = config.validate();
@@ -267,7 +2265,10 @@ mod tests {
     #[test]
     fn test_get_database_existing() {
         let configs = create_test_configs();
-        let config = Config { databases: configs };
+        let config = Config {
+            databases: configs,
+            ..Default::default()
+        };
 
         let found = config.get_database("Test Client");
         assert!(found.is_some());
@@ -277,12 +2278,480 @@ mod tests {
     #[test]
     fn test_get_database_nonexistent() {
         let configs = create_test_configs();
-        let config = Config { databases: configs };
+        let config = Config {
+            databases: configs,
+            ..Default::default()
+        };
 
         let found = config.get_database("Nonexistent Client");
         assert!(found.is_none());
     }
 
+    #[test]
+    fn test_config_validation_empty_tag() {
+        let mut config = create_test_config();
+        config.tags = vec!["prod".to_string(), " ".to_string()];
+        let config = Config {
+            databases: vec![config],
+            ..Default::default()
+        };
+
+        let result = config.validate();
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), BackupError::Config(_)));
+    }
+
+    #[test]
+    fn test_config_tags_default_to_empty() {
+        let json = r#"[{
+            "name": "No Tags",
+            "database_name": "no_tags_db",
+            "url": "http://localhost:8069",
+            "container_name": "no_tags_container",
+            "master_password": "admin",
+            "backup_format": "zip",
+            "output_path": "/tmp/backups",
+            "retention_days": 30
+        }]"#;
+        let databases: Vec<DatabaseConfig> = serde_json::from_str(json).unwrap();
+        assert!(databases[0].tags.is_empty());
+        assert!(databases[0].enabled);
+        assert_eq!(databases[0].keep_at_least, 1);
+    }
+
+    #[test]
+    fn test_config_max_total_size() {
+        let mut config = create_test_config();
+        assert_eq!(config.max_total_size_bytes(), None);
+
+        config.max_total_size = Some("200GB".to_string());
+        assert_eq!(
+            config.max_total_size_bytes(),
+            Some(200 * 1024 * 1024 * 1024)
+        );
+        let valid = Config {
+            databases: vec![config.clone()],
+            ..Default::default()
+        };
+        assert!(valid.validate().is_ok());
+
+        config.max_total_size = Some("lots".to_string());
+        let invalid = Config {
+            databases: vec![config],
+            ..Default::default()
+        };
+        assert!(matches!(
+            invalid.validate().unwrap_err(),
+            BackupError::Config(_)
+        ));
+    }
+
+    #[test]
+    fn test_config_object_form_with_archive() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.json");
+        let json = r#"{
+            "archive": {"type": "s3", "bucket": "cold-backups", "storage_class": "GLACIER"},
+            "databases": [{
+                "name": "Test Client",
+                "database_name": "test_db",
+                "url": "http://localhost:8069",
+                "container_name": "odoo",
+                "master_password": "admin",
+                "backup_format": "zip",
+                "output_path": "/tmp/backups",
+                "retention_days": 30
+            }]
+        }"#;
+        fs::write(&config_path, json).unwrap();
+
+        let config = Config::from_file(config_path.to_str().unwrap()).unwrap();
+        assert_eq!(config.databases.len(), 1);
+        assert!(matches!(
+            config.archive,
+            Some(StorageConfig::S3 { ref bucket, .. }) if bucket == "cold-backups"
+        ));
+    }
+
+    #[test]
+    fn test_config_invalid_archive() {
+        let config = Config {
+            databases: vec![create_test_config()],
+            archive: Some(StorageConfig::Local {
+                path: String::new(),
+            }),
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.validate().unwrap_err(),
+            BackupError::Config(msg) if msg.starts_with("archive")
+        ));
+    }
+
+    #[test]
+    fn test_config_invalid_schedule_and_notifications() {
+        let config = Config {
+            databases: vec![create_test_config()],
+            schedule: Some(ScheduleConfig {
+                backup_interval: Some("every day".to_string()),
+                verify_interval: None,
+                verify_older_than: "30d".to_string(),
+                restore_drill_interval: None,
+                backup_jitter: None,
+                backup_spread: None,
+                catch_up: false,
+                catch_up_max_age: None,
+                container_down_alert: None,
+                backup_on_stop: false,
+            }),
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.validate().unwrap_err(),
+            BackupError::Config(msg) if msg.starts_with("schedule: backup_interval")
+        ));
+
+        let config = Config {
+            databases: vec![create_test_config()],
+            notifications: vec![NotificationConfig::Command {
+                command: String::new(),
+            }],
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.validate().unwrap_err(),
+            BackupError::Config(msg) if msg.starts_with("notifications 0")
+        ));
+    }
+
+    #[test]
+    fn test_config_parallel_backups() {
+        let config: Config =
+            serde_json::from_str(r#"{"parallel_backups": 4, "databases": []}"#).unwrap();
+        assert_eq!(config.parallel_backups, Some(4));
+
+        let config = Config {
+            databases: vec![create_test_config()],
+            parallel_backups: Some(0),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_database_schedule() {
+        let mut database = create_test_config();
+        database.schedule = Some("30 1 * * mon-fri".to_string());
+        let mut config = Config {
+            databases: vec![database],
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        assert!(config.databases[0].cron_schedule().is_some());
+
+        config.databases[0].schedule = Some("every night".to_string());
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("Database 0: schedule"));
+    }
+
+    #[test]
+    fn test_config_blackouts() {
+        let mut config: Config = serde_json::from_str(
+            r#"{"databases": [{
+                "name": "Acme", "database_name": "acme", "url": "http://localhost:8069",
+                "container_name": "odoo", "master_password": "admin", "backup_format": "zip",
+                "output_path": "/tmp/backups", "retention_days": 7,
+                "blackouts": [{"start": "08:00", "end": "20:00", "days": "28-31"}]
+            }]}"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.databases[0].blackouts[0].days.as_deref(),
+            Some("28-31")
+        );
+
+        config.databases[0].blackouts[0].end = "8pm".to_string();
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("Database 0: blackouts 0"));
+    }
+
+    #[test]
+    fn test_config_timezone() {
+        let mut database = create_test_config();
+        database.timezone = Some("Asia/Tokyo".to_string());
+        database.blackouts = vec![BlackoutWindow {
+            start: "08:00".to_string(),
+            end: "20:00".to_string(),
+            days: None,
+            months: None,
+            weekdays: None,
+        }];
+        let mut config = Config {
+            databases: vec![database],
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        // 01:00 UTC is 10:00 in Tokyo, inside the window until 11:00 UTC
+        let now = DateTime::parse_from_rfc3339("2024-03-08T01:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let database = &config.databases[0];
+        let until = database.blackout_end(now).unwrap();
+        assert_eq!(until.to_rfc3339(), "2024-03-08T11:00:00+00:00");
+        assert_eq!(database.format_local(until), "2024-03-08 20:00 JST");
+
+        config.databases[0].timezone = Some("Mars/Olympus_Mons".to_string());
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("Database 0: timezone"));
+    }
+
+    #[test]
+    fn test_config_slow_backup_factor() {
+        let mut config = Config {
+            databases: vec![create_test_config()],
+            slow_backup_factor: Some(2.0),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        config.slow_backup_factor = Some(0.5);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_metrics_textfile() {
+        let mut config = Config {
+            databases: vec![create_test_config()],
+            metrics_textfile: Some("/var/lib/node_exporter/odoo_backup.prom".to_string()),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        config.metrics_textfile = Some("/var/lib/node_exporter/odoo_backup.txt".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_statsd() {
+        let statsd: StatsdConfig =
+            serde_json::from_str(r#"{"address": "datadog-agent:8125", "tags": ["env:prod"]}"#)
+                .unwrap();
+        let mut config = Config {
+            databases: vec![create_test_config()],
+            statsd: Some(statsd),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        config.statsd.as_mut().unwrap().address = "datadog-agent".to_string();
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("statsd: address"));
+    }
+
+    #[test]
+    fn test_config_otlp_endpoint() {
+        let mut config = Config {
+            databases: vec![create_test_config()],
+            otlp_endpoint: Some("http://tempo:4318".to_string()),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        config.otlp_endpoint = Some("tempo:4318".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_instance_cooldown() {
+        let mut config = Config {
+            databases: vec![create_test_config()],
+            instance_cooldown: Some("5m".to_string()),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.instance_cooldown(),
+            Some(chrono::Duration::minutes(5))
+        );
+
+        config.instance_cooldown = Some("soon".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_split_size() {
+        let mut config = create_test_config();
+        config.split_size = Some("4GB".to_string());
+        assert_eq!(config.split_size_bytes(), Some(4 * 1024 * 1024 * 1024));
+
+        config.split_size = Some("0".to_string());
+        let invalid = Config {
+            databases: vec![config],
+            ..Default::default()
+        };
+        assert!(matches!(
+            invalid.validate().unwrap_err(),
+            BackupError::Config(msg) if msg.contains("split_size")
+        ));
+    }
+
+    #[test]
+    fn test_config_validation_keep_at_least_zero() {
+        let mut config = create_test_config();
+        config.keep_at_least = 0;
+        let config = Config {
+            databases: vec![config],
+            ..Default::default()
+        };
+
+        let result = config.validate();
+        assert!(matches!(result.unwrap_err(), BackupError::Config(_)));
+    }
+
+    #[test]
+    fn test_config_from_file_resolves_env_password() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("env_config.json");
+
+        let mut test_configs = create_test_configs();
+        test_configs[0].master_password = "env:ODOO_BACKUP_CONFIG_TEST_PWD".to_string();
+        test_configs[1].master_password = "-".to_string();
+        fs::write(
+            &config_path,
+            serde_json::to_string_pretty(&test_configs).unwrap(),
+        )
+        .unwrap();
+
+        std::env::set_var("ODOO_BACKUP_CONFIG_TEST_PWD", "from-env");
+        let config = Config::from_file(config_path.to_str().unwrap()).unwrap();
+        assert_eq!(config.databases[0].master_password, "from-env");
+        assert!(!config.databases[0].needs_password_prompt());
+        assert!(config.databases[1].needs_password_prompt());
+    }
+
+    #[test]
+    fn test_config_http_auth() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("auth_config.json");
+
+        let mut test_configs = create_test_configs();
+        test_configs[0].netrc_file = Some("/etc/odoo/.netrc".to_string());
+        test_configs[0].http_headers.insert(
+            "X-Auth-Token".to_string(),
+            "env:ODOO_BACKUP_CONFIG_TEST_TOKEN".to_string(),
+        );
+        fs::write(
+            &config_path,
+            serde_json::to_string_pretty(&test_configs).unwrap(),
+        )
+        .unwrap();
+
+        std::env::set_var("ODOO_BACKUP_CONFIG_TEST_TOKEN", "t0ken-from-env");
+        let config = Config::from_file(config_path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            config.databases[0].http_headers["X-Auth-Token"],
+            "t0ken-from-env"
+        );
+        assert!(!format!("{:?}", config).contains("t0ken-from-env"));
+
+        let mut relative = create_test_config();
+        relative.netrc_file = Some(".netrc".to_string());
+        let mut bad_name = create_test_config();
+        bad_name
+            .http_headers
+            .insert("X Auth".to_string(), "x".to_string());
+        let mut bad_tls = create_test_config();
+        bad_tls.tls = Some(TlsConfig {
+            client_key: Some("/etc/ssl/backup.key".to_string()),
+            ..Default::default()
+        });
+        let mut bad_proxy = create_test_config();
+        bad_proxy.proxy = Some("proxy.corp:3128".to_string());
+        for db in [relative, bad_name, bad_tls, bad_proxy] {
+            let config = Config {
+                databases: vec![db],
+                ..Default::default()
+            };
+            assert!(matches!(
+                config.validate().unwrap_err(),
+                BackupError::Config(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_enabled_databases_skips_disabled() {
+        let mut configs = create_test_configs();
+        configs[1].enabled = false;
+        let config = Config {
+            databases: configs,
+            ..Default::default()
+        };
+
+        let enabled = config.enabled_databases(None);
+        assert_eq!(enabled.len(), 1);
+        assert_eq!(enabled[0].name, "Test Client");
+
+        assert_eq!(config.enabled_databases(Some("eu")).len(), 1);
+        assert!(config.enabled_databases(Some("staging")).is_empty());
+        // Explicit lookups still find disabled entries
+        assert!(config.get_database("Test Client 2").is_some());
+    }
+
+    #[test]
+    fn test_live_listing() {
+        let configs = create_test_configs();
+        let served = BTreeMap::from([(
+            (
+                "test_container".to_string(),
+                "http://localhost:8069".to_string(),
+            ),
+            vec!["staging".to_string(), "test_database".to_string()],
+        )]);
+        let listing = LiveListing::new(&configs, &served);
+        assert_eq!(listing.databases[0].exists, Some(true));
+        // Its server could not be asked
+        assert_eq!(listing.databases[1].exists, None);
+        assert_eq!(
+            listing.unconfigured,
+            vec![UnconfiguredDatabase {
+                database_name: "staging".to_string(),
+                container_name: "test_container".to_string(),
+                url: "http://localhost:8069".to_string(),
+            }]
+        );
+
+        let served = BTreeMap::from([(
+            (
+                "test_container".to_string(),
+                "http://localhost:8069".to_string(),
+            ),
+            Vec::new(),
+        )]);
+        assert_eq!(
+            LiveListing::new(&configs, &served).databases[0].exists,
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn test_databases_with_tag() {
+        let configs = create_test_configs();
+        let config = Config {
+            databases: configs,
+            ..Default::default()
+        };
+
+        let prod = config.databases_with_tag("prod");
+        assert_eq!(prod.len(), 1);
+        assert_eq!(prod[0].name, "Test Client");
+
+        assert_eq!(config.databases_with_tag("eu").len(), 2);
+        assert!(config.databases_with_tag("us").is_empty());
+    }
+
     #[test]
     fn test_database_config_creation() {
         let config = create_test_config();
@@ -295,4 +2764,88 @@ mod tests {
         assert_eq!(config.output_path, "/tmp/backups");
         assert_eq!(config.retention_days, 30);
     }
+
+    #[test]
+    fn test_encrypted_config_roundtrip() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.json");
+        let key_path = temp_dir.path().join("config.key");
+        let config_path = config_path.to_str().unwrap();
+        let key_path = key_path.to_str().unwrap();
+
+        let json_content = serde_json::to_string_pretty(&create_test_configs()).unwrap();
+        fs::write(config_path, &json_content).unwrap();
+        fs::write(key_path, "correct horse battery staple\n").unwrap();
+
+        encrypt_config_file(config_path, None, Some(key_path)).unwrap();
+        let encrypted = fs::read_to_string(config_path).unwrap();
+        assert!(encryption::is_armored(&encrypted));
+        assert!(!encrypted.contains("Test Client"));
+
+        let config = Config::from_file_with_key(config_path, Some(key_path)).unwrap();
+        assert_eq!(config.databases.len(), 2);
+        assert_eq!(config.databases[0].name, "Test Client");
+
+        decrypt_config_file(config_path, None, Some(key_path)).unwrap();
+        assert_eq!(fs::read_to_string(config_path).unwrap(), json_content);
+
+        // The plaintext is only readable by its owner, and no temporary
+        // file is left next to it
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(config_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_encrypt_config_file_to_output() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.json");
+        let output_path = temp_dir.path().join("config.json.enc");
+        let key_path = temp_dir.path().join("config.key");
+
+        let json_content = serde_json::to_string_pretty(&create_test_configs()).unwrap();
+        fs::write(&config_path, &json_content).unwrap();
+        fs::write(&key_path, "passphrase").unwrap();
+
+        encrypt_config_file(
+            config_path.to_str().unwrap(),
+            output_path.to_str(),
+            key_path.to_str(),
+        )
+        .unwrap();
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), json_content);
+        assert!(encryption::is_armored(
+            &fs::read_to_string(&output_path).unwrap()
+        ));
+
+        // Encrypting twice is refused
+        let result = encrypt_config_file(output_path.to_str().unwrap(), None, key_path.to_str());
+        assert!(matches!(result.unwrap_err(), BackupError::Config(_)));
+    }
+
+    #[test]
+    fn test_encrypt_config_file_rejects_invalid_json() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.json");
+        let key_path = temp_dir.path().join("config.key");
+        fs::write(&config_path, "not json").unwrap();
+        fs::write(&key_path, "passphrase").unwrap();
+
+        let result = encrypt_config_file(config_path.to_str().unwrap(), None, key_path.to_str());
+        assert!(matches!(result.unwrap_err(), BackupError::Config(_)));
+    }
+
+    #[test]
+    fn test_config_passphrase_empty_key_file() {
+        let temp_dir = tempdir().unwrap();
+        let key_path = temp_dir.path().join("config.key");
+        fs::write(&key_path, "\n").unwrap();
+
+        let result = config_passphrase(key_path.to_str());
+        assert!(matches!(result.unwrap_err(), BackupError::Config(_)));
+    }
 }
@@ -0,0 +1,418 @@
+use crate::error::{BackupError, Result, ResultExt};
+use crate::manifest::{self, DUMP_ENTRY, FILESTORE_PREFIX, ODOO_MANIFEST_ENTRY};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// First bytes of a pg_dump custom-format archive.
+const CUSTOM_DUMP_MAGIC: &[u8] = b"PGDMP";
+
+/// Formats a backup can be converted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConvertFormat {
+    /// Plain SQL script, as `psql -f` takes it
+    Dump,
+    /// Zip backup as Odoo's database manager writes it
+    Zip,
+}
+
+/// Parts of a zip backup `extract` can pull out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExtractPart {
+    /// `dump.sql`
+    Dump,
+    /// Odoo's `manifest.json` and ours, if the backup has them
+    Manifest,
+    /// The `filestore/` tree
+    Filestore,
+}
+
+impl ExtractPart {
+    pub const ALL: [ExtractPart; 3] = [Self::Dump, Self::Manifest, Self::Filestore];
+
+    fn matches(self, name: &Path) -> bool {
+        match self {
+            Self::Dump => name == Path::new(DUMP_ENTRY),
+            Self::Manifest => {
+                name == Path::new(ODOO_MANIFEST_ENTRY)
+                    || name == Path::new(manifest::MANIFEST_ENTRY)
+            }
+            Self::Filestore => name.starts_with(FILESTORE_PREFIX),
+        }
+    }
+}
+
+/// Where a converted copy of `input` goes by default: next to it, with the
+/// extension of the new format.
+pub fn default_output(input: &Path, to: ConvertFormat) -> PathBuf {
+    input.with_extension(match to {
+        ConvertFormat::Dump => "sql",
+        ConvertFormat::Zip => "zip",
+    })
+}
+
+/// Converts the backup at `input` and writes it to `output`, which must not
+/// exist yet. To a dump, the SQL of a zip backup is extracted and its
+/// filestore goes to `filestore` when given. To a zip, a plain SQL script or
+/// a custom-format dump is packed with the filestore directory `filestore`.
+pub fn convert(
+    input: &Path,
+    to: ConvertFormat,
+    filestore: Option<&Path>,
+    output: &Path,
+) -> Result<()> {
+    if output.exists() {
+        return Err(BackupError::FileSystem(format!(
+            "{} already exists",
+            output.display()
+        )));
+    }
+    let is_zip = ZipArchive::new(open(input)?).is_ok();
+    let mut partial = output.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    let result = match to {
+        ConvertFormat::Dump if !is_zip => Err(BackupError::Config(format!(
+            "{} is not a zip backup",
+            input.display()
+        ))),
+        ConvertFormat::Dump => extract_zip(input, &partial, filestore),
+        ConvertFormat::Zip if is_zip => Err(BackupError::Config(format!(
+            "{} is already a zip backup",
+            input.display()
+        ))),
+        ConvertFormat::Zip if is_custom_dump(input)? => {
+            let mut sql = partial.clone().into_os_string();
+            sql.push(".sql");
+            let sql = PathBuf::from(sql);
+            let result =
+                custom_dump_to_sql(input, &sql).and_then(|()| write_zip(&sql, filestore, &partial));
+            let _ = fs::remove_file(&sql);
+            result
+        }
+        ConvertFormat::Zip => write_zip(input, filestore, &partial),
+    }
+    .and_then(|()| {
+        fs::rename(&partial, output).map_err(|e| {
+            BackupError::from_fs(format!("Failed to move {} into place", output.display()), e)
+        })
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+fn open(path: &Path) -> Result<File> {
+    File::open(path)
+        .map_err(|e| BackupError::from_fs(format!("Failed to open {}", path.display()), e))
+}
+
+fn is_custom_dump(path: &Path) -> Result<bool> {
+    let mut magic = Vec::with_capacity(CUSTOM_DUMP_MAGIC.len());
+    open(path)?
+        .take(CUSTOM_DUMP_MAGIC.len() as u64)
+        .read_to_end(&mut magic)?;
+    Ok(magic == CUSTOM_DUMP_MAGIC)
+}
+
+/// Writes `dump.sql` of the zip backup at `input` to `sql`, and its
+/// filestore into the directory `filestore`.
+fn extract_zip(input: &Path, sql: &Path, filestore: Option<&Path>) -> Result<()> {
+    let mut archive = ZipArchive::new(open(input)?).map_err(|e| zip_error(input, e))?;
+    {
+        let mut dump = archive.by_name(DUMP_ENTRY).map_err(|_| {
+            BackupError::Config(format!("{} has no {}", input.display(), DUMP_ENTRY))
+        })?;
+        let mut file = File::create(sql)
+            .map_err(|e| BackupError::from_fs(format!("Failed to create {}", sql.display()), e))?;
+        io::copy(&mut dump, &mut file)
+            .map_err(|e| BackupError::from_fs(format!("Failed to write {}", sql.display()), e))?;
+    }
+    let Some(filestore) = filestore else {
+        return Ok(());
+    };
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| zip_error(input, e))?;
+        // Entry names that would escape the filestore directory are skipped
+        let Some(relative) = entry.enclosed_name().and_then(|name| {
+            name.strip_prefix(FILESTORE_PREFIX)
+                .ok()
+                .map(Path::to_path_buf)
+        }) else {
+            continue;
+        };
+        write_entry(&mut entry, &filestore.join(relative))?;
+    }
+    Ok(())
+}
+
+/// Extracts the `parts` of the zip backup at `input` into `target`, keeping
+/// their paths in the archive, e.g. `target/filestore/ab/abcdef`. Existing
+/// files are not overwritten. `progress` is called with the bytes written
+/// so far and the total after each file. Returns how many files were written.
+pub fn extract(
+    input: &Path,
+    parts: &[ExtractPart],
+    target: &Path,
+    mut progress: impl FnMut(u64, u64),
+) -> Result<u64> {
+    let mut archive = ZipArchive::new(open(input)?).map_err(|e| zip_error(input, e))?;
+    // Entry names that would escape `target` are never extracted
+    let mut selected = Vec::new();
+    let mut total = 0;
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i).map_err(|e| zip_error(input, e))?;
+        let Some(name) = entry.enclosed_name() else {
+            log::warn!(
+                "Skipping unsafe entry {} of {}",
+                entry.name(),
+                input.display()
+            );
+            continue;
+        };
+        if parts.iter().any(|part| part.matches(&name)) {
+            total += entry.size();
+            selected.push((i, name));
+        }
+    }
+    if selected.is_empty() {
+        return Err(BackupError::Config(format!(
+            "{} has none of the requested parts",
+            input.display()
+        )));
+    }
+
+    let mut done = 0;
+    let mut files = 0;
+    for (i, name) in selected {
+        let mut entry = archive.by_index(i).map_err(|e| zip_error(input, e))?;
+        if !entry.is_dir() {
+            files += 1;
+        }
+        done += write_entry(&mut entry, &target.join(name))?;
+        progress(done, total);
+    }
+    Ok(files)
+}
+
+/// Writes a zip entry to `path`, which must not exist yet, creating the
+/// directories it is in. Returns the bytes written.
+fn write_entry(entry: &mut zip::read::ZipFile, path: &Path) -> Result<u64> {
+    if entry.is_dir() {
+        fs::create_dir_all(path)?;
+        return Ok(0);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| BackupError::from_fs(format!("Failed to create {}", path.display()), e))?;
+    io::copy(entry, &mut file)
+        .map_err(|e| BackupError::from_fs(format!("Failed to write {}", path.display()), e))
+}
+
+/// Turns a custom-format dump into a plain SQL script with the host's
+/// `pg_restore`, which needs no database server for this.
+fn custom_dump_to_sql(input: &Path, sql: &Path) -> Result<()> {
+    let output = Command::new("pg_restore")
+        .arg("--no-owner")
+        .arg("-f")
+        .arg(sql)
+        .arg(input)
+        .output()
+        .context("pg_restore is needed to convert a custom-format dump")?;
+    if !output.status.success() {
+        return Err(BackupError::Unknown(format!(
+            "pg_restore failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Packs the SQL script `sql` and the files under `filestore` as a zip
+/// backup Odoo can restore.
+fn write_zip(sql: &Path, filestore: Option<&Path>, output: &Path) -> Result<()> {
+    let file = File::create(output)
+        .map_err(|e| BackupError::from_fs(format!("Failed to create {}", output.display()), e))?;
+    let mut writer = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut dump = open(sql)?;
+    let size = dump.metadata()?.len();
+    writer
+        .start_file(DUMP_ENTRY, options.large_file(size >= u32::MAX as u64))
+        .map_err(|e| zip_error(output, e))?;
+    io::copy(&mut dump, &mut writer)?;
+
+    if let Some(filestore) = filestore {
+        if !filestore.is_dir() {
+            return Err(BackupError::Config(format!(
+                "{} is not a directory",
+                filestore.display()
+            )));
+        }
+        add_directory(&mut writer, filestore, FILESTORE_PREFIX, options, output)?;
+    }
+    writer
+        .finish()
+        .map_err(|e| zip_error(output, e))?
+        .sync_all()?;
+    Ok(())
+}
+
+fn add_directory(
+    writer: &mut ZipWriter<File>,
+    dir: &Path,
+    prefix: &str,
+    options: SimpleFileOptions,
+    output: &Path,
+) -> Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .map_err(|e| BackupError::from_fs(format!("Failed to read {}", dir.display()), e))?
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            writer
+                .add_directory(name.as_str(), options)
+                .map_err(|e| zip_error(output, e))?;
+            add_directory(
+                writer,
+                &entry.path(),
+                &format!("{}/", name),
+                options,
+                output,
+            )?;
+        } else {
+            let mut file = open(&entry.path())?;
+            let size = file.metadata()?.len();
+            writer
+                .start_file(name, options.large_file(size >= u32::MAX as u64))
+                .map_err(|e| zip_error(output, e))?;
+            io::copy(&mut file, writer)?;
+        }
+    }
+    Ok(())
+}
+
+fn zip_error(path: &Path, e: impl std::fmt::Display) -> BackupError {
+    BackupError::FileSystem(format!("Zip backup {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let sql = temp_dir.path().join("backup.sql");
+        fs::write(&sql, "CREATE TABLE res_partner ();\n").unwrap();
+        let filestore = temp_dir.path().join("filestore");
+        fs::create_dir_all(filestore.join("ab")).unwrap();
+        fs::write(filestore.join("ab/abcdef"), "attachment").unwrap();
+
+        let zip = default_output(&sql, ConvertFormat::Zip);
+        convert(&sql, ConvertFormat::Zip, Some(&filestore), &zip).unwrap();
+        let mut archive = ZipArchive::new(File::open(&zip).unwrap()).unwrap();
+        assert!(archive.by_name("dump.sql").is_ok());
+        assert!(archive.by_name("filestore/ab/abcdef").is_ok());
+        drop(archive);
+
+        let extracted = temp_dir.path().join("extracted");
+        let back = temp_dir.path().join("back.sql");
+        convert(&zip, ConvertFormat::Dump, Some(&extracted), &back).unwrap();
+        assert_eq!(
+            fs::read_to_string(&back).unwrap(),
+            "CREATE TABLE res_partner ();\n"
+        );
+        assert_eq!(
+            fs::read_to_string(extracted.join("ab/abcdef")).unwrap(),
+            "attachment"
+        );
+        assert!(!temp_dir.path().join("back.sql.partial").exists());
+    }
+
+    #[test]
+    fn test_convert_refuses() {
+        let temp_dir = tempdir().unwrap();
+        let sql = temp_dir.path().join("backup.sql");
+        fs::write(&sql, "SELECT 1;\n").unwrap();
+        let zip = temp_dir.path().join("backup.zip");
+
+        // A plain script is not a zip backup to extract
+        let error = convert(&sql, ConvertFormat::Dump, None, &zip).unwrap_err();
+        assert!(matches!(error, BackupError::Config(_)));
+        assert!(!zip.exists());
+
+        convert(&sql, ConvertFormat::Zip, None, &zip).unwrap();
+        let error = convert(&sql, ConvertFormat::Zip, None, &zip).unwrap_err();
+        assert!(error.to_string().contains("already exists"));
+        let again = temp_dir.path().join("again.zip");
+        let error = convert(&zip, ConvertFormat::Zip, None, &again).unwrap_err();
+        assert!(error.to_string().contains("already a zip backup"));
+    }
+
+    #[test]
+    fn test_extract() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("backup.zip");
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        let options = SimpleFileOptions::default();
+        for (name, content) in [
+            ("dump.sql", "SELECT 1;\n"),
+            ("manifest.json", "{}"),
+            ("filestore/ab/abcdef", "attachment"),
+            ("../escape", "outside"),
+        ] {
+            writer.start_file(name, options).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+        let target = temp_dir.path().join("out");
+
+        let mut reported = Vec::new();
+        let files = extract(
+            &path,
+            &[ExtractPart::Manifest, ExtractPart::Filestore],
+            &target,
+            |done, total| reported.push((done, total)),
+        )
+        .unwrap();
+        assert_eq!(files, 2);
+        assert_eq!(reported, vec![(2, 12), (12, 12)]);
+        assert_eq!(
+            fs::read_to_string(target.join("filestore/ab/abcdef")).unwrap(),
+            "attachment"
+        );
+        assert!(target.join("manifest.json").exists());
+        assert!(!target.join("dump.sql").exists());
+        assert!(!temp_dir.path().join("escape").exists());
+
+        // Files already there are left alone
+        let error = extract(&path, &ExtractPart::ALL, &target, |_, _| {}).unwrap_err();
+        assert!(error.to_string().contains("manifest.json"));
+    }
+
+    #[test]
+    fn test_is_custom_dump() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("backup.dump");
+        fs::write(&path, b"PGDMP\x01\x0e\x00").unwrap();
+        assert!(is_custom_dump(&path).unwrap());
+        fs::write(&path, b"--\n-- PostgreSQL database dump\n").unwrap();
+        assert!(!is_custom_dump(&path).unwrap());
+    }
+}
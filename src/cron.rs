@@ -0,0 +1,271 @@
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDate, TimeZone, Timelike};
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// How far ahead `next_after` looks; enough for `0 0 29 2 *` across a
+/// century year without a leap day.
+const SEARCH_DAYS: i64 = 366 * 9;
+
+/// A five-field cron expression: minute, hour, day of month, month and day
+/// of week, e.g. `0 2 * * *` for 02:00 every day. Fields take `*`, values,
+/// ranges, lists and steps (`*/15`, `1-5`, `mon,thu`), and `@daily`,
+/// `@hourly`, `@weekly`, `@monthly` and `@yearly` stand for the usual
+/// expressions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Both day fields were given, so a day matching either one runs
+    either_day: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let expression = match input.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!(
+                "'{}' must have five fields: minute hour day month weekday",
+                input
+            ));
+        };
+
+        let mut weekdays = parse_field(weekday, "weekday", 0, 7, &WEEKDAYS)?;
+        // Both 0 and 7 are Sunday
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse_field(minute, "minute", 0, 59, &[])?,
+            hours: parse_field(hour, "hour", 0, 23, &[])?,
+            days: parse_field(day, "day", 1, 31, &[])?,
+            months: parse_field(month, "month", 1, 12, &MONTHS)?,
+            weekdays,
+            either_day: !day.starts_with('*') && !weekday.starts_with('*'),
+        })
+    }
+
+    /// The first time after `after` the schedule fires, in `after`'s time
+    /// zone. Times skipped by a daylight saving change do not fire; times
+    /// repeated by one fire once.
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let timezone = after.timezone();
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let mut date = start.date();
+        for _ in 0..SEARCH_DAYS {
+            if self.fires_on(date) {
+                for hour in bits(self.hours) {
+                    for minute in bits(self.minutes) {
+                        let time = date.and_hms_opt(hour, minute, 0)?;
+                        if time < start {
+                            continue;
+                        }
+                        match timezone.from_local_datetime(&time) {
+                            LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => {
+                                return Some(time)
+                            }
+                            LocalResult::None => {}
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
+    /// Whether the schedule fires at some time on `date`.
+    pub fn fires_on(&self, date: NaiveDate) -> bool {
+        if !has(self.months, date.month()) {
+            return false;
+        }
+        let day = has(self.days, date.day());
+        let weekday = has(self.weekdays, date.weekday().num_days_from_sunday());
+        if self.either_day {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+fn has(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+/// Set bits of `mask`, lowest first.
+fn bits(mask: u64) -> impl Iterator<Item = u32> {
+    (0..64).filter(move |bit| has(mask, *bit))
+}
+
+/// Parses one field into a bit mask of the values it selects. `names` are
+/// accepted for the values from `min` on.
+fn parse_field(field: &str, what: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let value = |text: &str| -> Result<u32, String> {
+        let value = match names.iter().position(|n| n.eq_ignore_ascii_case(text)) {
+            Some(index) => index as u32 + min,
+            None => text
+                .parse()
+                .map_err(|_| format!("invalid {} '{}'", what, text))?,
+        };
+        if value < min || value > max {
+            return Err(format!("{} {} is not within {}-{}", what, value, min, max));
+        }
+        Ok(value)
+    };
+
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!("invalid {} step '{}'", what, step))?;
+                if step == 0 {
+                    return Err(format!("{} step must be greater than zero", what));
+                }
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+        let (first, last) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((first, last)) => (value(first)?, value(last)?),
+            // `5/10` runs from 5 to the end of the range
+            None if step.is_some() => (value(range)?, max),
+            None => {
+                let single = value(range)?;
+                (single, single)
+            }
+        };
+        if first > last {
+            return Err(format!("{} range {} is backwards", what, range));
+        }
+        for bit in (first..=last).step_by(step.unwrap_or(1) as usize) {
+            mask |= 1 << bit;
+        }
+    }
+    Ok(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, Utc};
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn next(expression: &str, after: &str) -> String {
+        CronSchedule::parse(expression)
+            .unwrap()
+            .next_after(&at(after))
+            .unwrap()
+            .to_rfc3339()
+    }
+
+    #[test]
+    fn test_next_after() {
+        assert_eq!(
+            next("0 2 * * *", "2024-03-10T01:30:00Z"),
+            "2024-03-10T02:00:00+00:00"
+        );
+        // Strictly after, so a schedule firing right now waits a day
+        assert_eq!(
+            next("0 2 * * *", "2024-03-10T02:00:00Z"),
+            "2024-03-11T02:00:00+00:00"
+        );
+        assert_eq!(
+            next("*/15 * * * *", "2024-03-10T02:07:45Z"),
+            "2024-03-10T02:15:00+00:00"
+        );
+        // Saturday night, so the next weekday run is on Monday
+        assert_eq!(
+            next("30 22 * * mon-fri", "2024-03-09T23:00:00Z"),
+            "2024-03-11T22:30:00+00:00"
+        );
+        assert_eq!(
+            next("0 3 1 */3 *", "2024-02-15T00:00:00Z"),
+            "2024-04-01T03:00:00+00:00"
+        );
+        assert_eq!(
+            next("0 0 29 feb *", "2024-03-01T00:00:00Z"),
+            "2028-02-29T00:00:00+00:00"
+        );
+        assert_eq!(
+            next("@weekly", "2024-03-10T12:00:00Z"),
+            "2024-03-17T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_day_of_month_or_weekday() {
+        // The 15th, or any Sunday: Sunday the 10th comes first
+        assert_eq!(
+            next("0 1 15 * 7", "2024-03-08T00:00:00Z"),
+            "2024-03-10T01:00:00+00:00"
+        );
+        assert_eq!(
+            next("0 1 15 * 0", "2024-03-11T00:00:00Z"),
+            "2024-03-15T01:00:00+00:00"
+        );
+        // With either day field left open, the other one decides
+        assert_eq!(
+            next("0 1 */10 * *", "2024-03-12T00:00:00Z"),
+            "2024-03-21T01:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_next_after_keeps_time_zone() {
+        let offset = FixedOffset::east_opt(2 * 3600).unwrap();
+        let after = at("2024-03-10T01:30:00Z").with_timezone(&offset);
+        let next = CronSchedule::parse("0 2 * * *")
+            .unwrap()
+            .next_after(&after)
+            .unwrap();
+        // 02:00 local on the next day, not 02:00 UTC
+        assert_eq!(next.to_rfc3339(), "2024-03-11T02:00:00+02:00");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for expression in [
+            "",
+            "0 2 * *",
+            "0 2 * * * *",
+            "60 2 * * *",
+            "0 24 * * *",
+            "0 2 0 * *",
+            "0 2 * 13 *",
+            "0 2 * * 8",
+            "0 2 * * sunday",
+            "*/0 * * * *",
+            "0 5-2 * * *",
+            "@sometimes",
+        ] {
+            assert!(
+                CronSchedule::parse(expression).is_err(),
+                "{} should not parse",
+                expression
+            );
+        }
+        assert!(CronSchedule::parse(" 0,30 1-5/2 * jan-mar,dec Sat ").is_ok());
+    }
+}
@@ -0,0 +1,1248 @@
+use crate::backup::BackupManager;
+use crate::catalog::Catalog;
+use crate::config::{Config, DatabaseConfig};
+use crate::cron::CronSchedule;
+use crate::error::{BackupError, Result};
+use crate::freshness::{self, Freshness};
+use crate::lifecycle::{self, ContainerAction, ContainerStates};
+use crate::metrics;
+use crate::notify::{self, Notification};
+use crate::shutdown::Shutdown;
+use crate::size;
+use crate::summary;
+use chrono::{DateTime, Duration, Local, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Schedule file the daemon keeps in the host backup directory while it runs.
+pub const SCHEDULE_FILE: &str = ".odoo-backup-schedule.json";
+
+/// How often the daemon checks databases with `max_age_hours` for stale backups.
+const FRESHNESS_CHECK_INTERVAL: Duration = Duration::hours(1);
+
+/// Jobs the daemon runs and how often.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleConfig {
+    /// How often to back up every enabled database, e.g. "24h"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_interval: Option<String>,
+    /// How often to re-verify stored backups, e.g. "1d"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify_interval: Option<String>,
+    /// Re-verification skips backups checked more recently than this
+    #[serde(default = "default_verify_older_than")]
+    pub verify_older_than: String,
+    /// How often to restore-test one randomly picked database, e.g. "7d"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restore_drill_interval: Option<String>,
+    /// Delays each scheduled backup by a random amount up to this, e.g. "10m"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_jitter: Option<String>,
+    /// Spreads backups that are due at the same time evenly over this
+    /// window instead of starting them together, e.g. "2h"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_spread: Option<String>,
+    /// Runs backups of databases with a `schedule` that were missed while
+    /// the daemon was not running, instead of waiting for the next one
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub catch_up: bool,
+    /// Missed backups older than this are not caught up, e.g. "12h"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub catch_up_max_age: Option<String>,
+    /// Alerts when a configured container stays stopped for longer than
+    /// this, e.g. "15m"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_down_alert: Option<String>,
+    /// Backs up the databases of a container as soon as it is asked to
+    /// stop, e.g. for an image upgrade, and alerts when that fails
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub backup_on_stop: bool,
+}
+
+fn default_verify_older_than() -> String {
+    "30d".to_string()
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            backup_interval: None,
+            verify_interval: None,
+            verify_older_than: default_verify_older_than(),
+            restore_drill_interval: None,
+            backup_jitter: None,
+            backup_spread: None,
+            catch_up: false,
+            catch_up_max_age: None,
+            container_down_alert: None,
+            backup_on_stop: false,
+        }
+    }
+}
+
+impl ScheduleConfig {
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        let fields = [
+            ("backup_interval", self.backup_interval.as_deref()),
+            ("verify_interval", self.verify_interval.as_deref()),
+            ("verify_older_than", Some(self.verify_older_than.as_str())),
+            (
+                "restore_drill_interval",
+                self.restore_drill_interval.as_deref(),
+            ),
+            ("backup_jitter", self.backup_jitter.as_deref()),
+            ("backup_spread", self.backup_spread.as_deref()),
+            ("catch_up_max_age", self.catch_up_max_age.as_deref()),
+            ("container_down_alert", self.container_down_alert.as_deref()),
+        ];
+        for (field, value) in fields {
+            if let Some(value) = value {
+                let duration =
+                    size::parse_duration(value).map_err(|e| format!("{}: {}", field, e))?;
+                if duration <= Duration::zero() {
+                    return Err(format!("{} must be greater than zero", field));
+                }
+            }
+        }
+        if let (Some(spread), Some(every)) = (
+            interval(self.backup_spread.as_deref()),
+            interval(self.backup_interval.as_deref()),
+        ) {
+            if spread >= every {
+                return Err("backup_spread must be shorter than backup_interval".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// When the running daemon next backs up each client, as shown by `status`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleSnapshot {
+    pub updated_at: Option<DateTime<Utc>>,
+    pub next_backup: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl ScheduleSnapshot {
+    /// The schedule last written to `backup_dir`, if a daemon is running.
+    pub fn load(backup_dir: &str) -> Result<Option<Self>> {
+        let path = Path::new(backup_dir).join(SCHEDULE_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| BackupError::from_fs(format!("Failed to read {}", path.display()), e))?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// Publishes the snapshot in `backup_dir`.
+    pub fn save(&self, backup_dir: &str) -> Result<()> {
+        let path = Path::new(backup_dir).join(SCHEDULE_FILE);
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(self)?).map_err(|e| {
+            BackupError::from_fs(format!("Failed to write {}", tmp_path.display()), e)
+        })?;
+        fs::rename(&tmp_path, &path)
+            .map_err(|e| BackupError::from_fs(format!("Failed to replace {}", path.display()), e))
+    }
+
+    /// Removes the snapshot once the daemon stops.
+    pub fn remove(backup_dir: &str) -> Result<()> {
+        let path = Path::new(backup_dir).join(SCHEDULE_FILE);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(BackupError::from_fs(
+                format!("Failed to remove {}", path.display()),
+                e,
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Parses a validated schedule duration.
+fn interval(value: Option<&str>) -> Option<Duration> {
+    value.and_then(|v| size::parse_duration(v).ok())
+}
+
+/// A random duration between zero and `max`.
+fn jitter(max: Option<Duration>) -> Duration {
+    max.map_or(Duration::zero(), |max| {
+        let millis = max.num_milliseconds().max(1) as u128;
+        Duration::milliseconds((uuid::Uuid::new_v4().as_u128() % millis) as i64)
+    })
+}
+
+/// Offset of the `position`-th of `count` backups spread over `window`.
+fn spread_offset(window: Option<Duration>, position: usize, count: usize) -> Duration {
+    match window {
+        Some(window) if count > 1 => window * position as i32 / count as i32,
+        _ => Duration::zero(),
+    }
+}
+
+/// When a job that last ran at `last` is next due. Overdue jobs run now.
+fn next_run(last: Option<DateTime<Utc>>, interval: Duration, now: DateTime<Utc>) -> DateTime<Utc> {
+    last.map_or(now, |last| (last + interval).max(now))
+}
+
+/// The next time after `now` that `cron` fires in `zone`, or in the host's
+/// local time without one.
+fn next_cron(cron: &CronSchedule, zone: Option<Tz>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    match zone {
+        Some(zone) => cron
+            .next_after(&now.with_timezone(&zone))
+            .map(|next| next.with_timezone(&Utc)),
+        None => cron
+            .next_after(&now.with_timezone(&Local))
+            .map(|next| next.with_timezone(&Utc)),
+    }
+}
+
+/// Clients that differ between two configurations, by name.
+#[derive(Debug, Default, PartialEq)]
+struct DatabaseDiff {
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<String>,
+}
+
+fn diff_databases(old: &[DatabaseConfig], new: &[DatabaseConfig]) -> DatabaseDiff {
+    let mut diff = DatabaseDiff::default();
+    for db in new {
+        match old.iter().find(|o| o.name == db.name) {
+            None => diff.added.push(db.name.clone()),
+            Some(o) if o != db => diff.changed.push(db.name.clone()),
+            Some(_) => {}
+        }
+    }
+    for db in old {
+        if !new.iter().any(|n| n.name == db.name) {
+            diff.removed.push(db.name.clone());
+        }
+    }
+    diff
+}
+
+/// When each job is next due. Backups are tracked per client, so clients
+/// added by a reload get their own cadence. Clients with a `schedule` of
+/// their own follow it instead of `backup_interval`.
+struct Jobs {
+    backup_interval: Option<Duration>,
+    verify_interval: Option<Duration>,
+    verify_older_than: Option<Duration>,
+    drill_interval: Option<Duration>,
+    backup_jitter: Option<Duration>,
+    backup_spread: Option<Duration>,
+    catch_up: bool,
+    catch_up_max_age: Option<Duration>,
+    container_down_alert: Option<Duration>,
+    backup_on_stop: bool,
+    /// Cron schedules of the clients that have one, with their time zone
+    crons: BTreeMap<String, (CronSchedule, Option<Tz>)>,
+    next_backup: BTreeMap<String, DateTime<Utc>>,
+    next_verify: Option<DateTime<Utc>>,
+    next_drill: Option<DateTime<Utc>>,
+    next_freshness: Option<DateTime<Utc>>,
+    /// Clients whose backup waits for their container to start
+    postponed: BTreeSet<String>,
+}
+
+impl Jobs {
+    fn new(config: &Config, backup_dir: &str) -> Result<Self> {
+        let databases = config.enabled_databases(None);
+        let has_crons = databases.iter().any(|db| db.schedule.is_some());
+        let has_max_age = databases.iter().any(|db| db.max_age_hours.is_some());
+        let default_schedule = ScheduleConfig::default();
+        let schedule = match &config.schedule {
+            Some(schedule) => schedule,
+            None if has_crons || has_max_age => &default_schedule,
+            None => {
+                return Err(BackupError::Config(
+                    "No schedule configured, nothing for the daemon to do".to_string(),
+                ))
+            }
+        };
+        let mut jobs = Self {
+            backup_interval: interval(schedule.backup_interval.as_deref()),
+            verify_interval: interval(schedule.verify_interval.as_deref()),
+            verify_older_than: interval(Some(&schedule.verify_older_than)),
+            drill_interval: interval(schedule.restore_drill_interval.as_deref()),
+            backup_jitter: interval(schedule.backup_jitter.as_deref()),
+            backup_spread: interval(schedule.backup_spread.as_deref()),
+            catch_up: schedule.catch_up,
+            catch_up_max_age: interval(schedule.catch_up_max_age.as_deref()),
+            container_down_alert: interval(schedule.container_down_alert.as_deref()),
+            backup_on_stop: schedule.backup_on_stop,
+            crons: BTreeMap::new(),
+            next_backup: BTreeMap::new(),
+            next_verify: None,
+            next_drill: None,
+            next_freshness: None,
+            postponed: BTreeSet::new(),
+        };
+        if jobs.backup_interval.is_none()
+            && jobs.verify_interval.is_none()
+            && jobs.drill_interval.is_none()
+            && !has_crons
+            && !has_max_age
+        {
+            return Err(BackupError::Config(
+                "schedule: set backup_interval, verify_interval, restore_drill_interval, \
+                 or a database schedule or max_age_hours"
+                    .to_string(),
+            ));
+        }
+
+        let now = Utc::now();
+        jobs.schedule_backups(config, &last_backups(backup_dir)?, now);
+        jobs.next_verify = jobs.verify_interval.map(|_| now);
+        // Drills are expensive, so the first one waits a full interval
+        jobs.next_drill = jobs.drill_interval.map(|i| now + i);
+        jobs.next_freshness = has_max_age.then_some(now);
+        Ok(jobs)
+    }
+
+    /// Schedules backups of the clients in `config` that have none yet and
+    /// forgets clients that are gone. Backups pick up the cadence of the
+    /// client's last successful backup, so a restart does not trigger an
+    /// extra round. Clients that are due now are spread over
+    /// `backup_spread` rather than all starting at once.
+    fn schedule_backups(
+        &mut self,
+        config: &Config,
+        last_backups: &BTreeMap<String, DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) {
+        let databases = unattended_databases(config);
+        self.crons = databases
+            .iter()
+            .filter_map(|db| Some((db.name.clone(), (db.cron_schedule()?, db.time_zone()))))
+            .collect();
+        let mut due = BTreeMap::new();
+        for db in databases {
+            match self.next_due(&db.name, last_backups.get(&db.name).copied(), now) {
+                Some(next) => {
+                    due.insert(db.name, next);
+                }
+                None if self.crons.contains_key(&db.name) => {
+                    log::warn!("Not scheduling {}: its schedule never fires", db.name)
+                }
+                None => {}
+            }
+        }
+        self.next_backup.retain(|name, _| due.contains_key(name));
+        let new: Vec<_> = due
+            .into_iter()
+            .filter(|(name, _)| !self.next_backup.contains_key(name))
+            .collect();
+        let due_now = new.iter().filter(|(_, due)| *due <= now).count();
+        let mut position = 0;
+        for (name, due) in new {
+            let offset = if due <= now {
+                position += 1;
+                spread_offset(self.backup_spread, position - 1, due_now)
+            } else {
+                Duration::zero()
+            };
+            self.next_backup
+                .insert(name, due + offset + jitter(self.backup_jitter));
+        }
+    }
+
+    /// When the backup of `client`, last taken at `last`, is next due:
+    /// the next time its cron fires, or one `backup_interval` after `last`.
+    /// With `catch_up`, a cron run missed since `last` is due now.
+    fn next_due(
+        &self,
+        client: &str,
+        last: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        let Some((cron, zone)) = self.crons.get(client) else {
+            return self.backup_interval.map(|every| next_run(last, every, now));
+        };
+        if self.catch_up {
+            // Only runs missed within catch_up_max_age count
+            let since = last
+                .into_iter()
+                .chain(self.catch_up_max_age.map(|age| now - age))
+                .max();
+            let missed = since.map_or(Some(now), |since| next_cron(cron, *zone, since));
+            if let Some(missed) = missed.filter(|missed| *missed <= now) {
+                log::info!("Catching up the backup of {} missed at {}", client, missed);
+                return Some(now);
+            }
+        }
+        next_cron(cron, *zone, now)
+    }
+
+    /// Moves the backups of `databases` that fall in a blackout window to
+    /// the end of the window, returning the ones that can run now.
+    fn defer_blackouts(
+        &mut self,
+        databases: Vec<DatabaseConfig>,
+        now: DateTime<Utc>,
+    ) -> Vec<DatabaseConfig> {
+        databases
+            .into_iter()
+            .filter(|db| match db.blackout_end(now) {
+                Some(until) => {
+                    log::info!(
+                        "Deferring backup of {} until {}, the end of its blackout window",
+                        db.name,
+                        until
+                    );
+                    self.next_backup.insert(db.name.clone(), until);
+                    false
+                }
+                None => true,
+            })
+            .collect()
+    }
+
+    /// Holds back the backups of `databases` whose container is down until
+    /// it starts again, returning the ones that can run now.
+    fn postpone_stopped(
+        &mut self,
+        databases: Vec<DatabaseConfig>,
+        containers: &ContainerStates,
+    ) -> Vec<DatabaseConfig> {
+        databases
+            .into_iter()
+            .filter(|db| {
+                if !containers.is_down(&db.container_name) {
+                    return true;
+                }
+                log::info!(
+                    "Postponing backup of {} until container {} starts",
+                    db.name,
+                    db.container_name
+                );
+                self.next_backup.remove(&db.name);
+                self.postponed.insert(db.name.clone());
+                false
+            })
+            .collect()
+    }
+
+    /// Makes the postponed backups of the clients in `container` due at `now`.
+    fn resume_postponed(&mut self, config: &Config, container: &str, now: DateTime<Utc>) {
+        for db in &config.databases {
+            if db.container_name == container && self.postponed.remove(&db.name) {
+                log::info!("Running the postponed backup of {}", db.name);
+                self.next_backup.insert(db.name.clone(), now);
+            }
+        }
+    }
+
+    /// Takes over the schedule of a reloaded configuration. Jobs whose
+    /// interval or cron did not change keep their due time.
+    fn reload(&mut self, config: &Config, backup_dir: &str) -> Result<()> {
+        let mut reloaded = Jobs::new(config, backup_dir)?;
+        let mut kept = std::mem::take(&mut self.next_backup);
+        kept.retain(
+            |name, _| match (self.crons.get(name), reloaded.crons.get(name)) {
+                (None, None) => reloaded.backup_interval == self.backup_interval,
+                (old, new) => old == new,
+            },
+        );
+        reloaded.next_backup = kept;
+        reloaded.schedule_backups(config, &last_backups(backup_dir)?, Utc::now());
+        if reloaded.verify_interval == self.verify_interval {
+            reloaded.next_verify = self.next_verify;
+        }
+        if reloaded.drill_interval == self.drill_interval {
+            reloaded.next_drill = self.next_drill;
+        }
+        if reloaded.next_freshness.is_some() && self.next_freshness.is_some() {
+            reloaded.next_freshness = self.next_freshness;
+        }
+        *self = reloaded;
+        Ok(())
+    }
+
+    /// Clients whose backup is due at `now`.
+    fn due_backups(&self, now: DateTime<Utc>) -> Vec<String> {
+        self.next_backup
+            .iter()
+            .filter(|(_, due)| **due <= now)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    fn next_wake(&self) -> Option<DateTime<Utc>> {
+        self.next_backup
+            .values()
+            .copied()
+            .chain(self.next_verify)
+            .chain(self.next_drill)
+            .chain(self.next_freshness)
+            .min()
+    }
+}
+
+fn last_backups(backup_dir: &str) -> Result<BTreeMap<String, DateTime<Utc>>> {
+    Ok(Catalog::load(backup_dir)?.last_backups())
+}
+
+/// Runs the scheduled jobs until `shutdown` is triggered. On SIGHUP the
+/// configuration is read again through `load_config`; a backup that is
+/// running finishes first.
+pub async fn run<F>(
+    mut config: Config,
+    load_config: F,
+    manager: &Arc<BackupManager>,
+    backup_dir: &str,
+    shutdown: &Shutdown,
+) -> Result<()>
+where
+    F: Fn() -> Result<Config>,
+{
+    let mut jobs = Jobs::new(&config, backup_dir)?;
+    let mut reload = ReloadSignal::new();
+    let mut stale_alerted = BTreeSet::new();
+    let mut containers = ContainerStates::load(watched_containers(&config));
+    let mut container_events = lifecycle::watch();
+    manager.resume_paused_crons(&config.databases).await;
+
+    log::info!("Daemon started");
+    while !shutdown.is_triggered() {
+        let due = jobs.due_backups(Utc::now());
+        if !due.is_empty() {
+            let databases: Vec<_> = unattended_databases(&config)
+                .into_iter()
+                .filter(|db| due.contains(&db.name))
+                .collect();
+            let databases = jobs.defer_blackouts(databases, Utc::now());
+            let databases = jobs.postpone_stopped(databases, &containers);
+            if !databases.is_empty() {
+                let started_at = Utc::now();
+                run_scheduled_backups(manager, &databases).await;
+                summary::write_last_run(backup_dir, &databases, started_at);
+                metrics::update(&config, backup_dir);
+            }
+            let now = Utc::now();
+            for db in &databases {
+                match jobs.next_due(&db.name, Some(now), now) {
+                    Some(next) => {
+                        jobs.next_backup
+                            .insert(db.name.clone(), next + jitter(jobs.backup_jitter));
+                    }
+                    None => {
+                        jobs.next_backup.remove(&db.name);
+                    }
+                }
+            }
+        }
+        if let (Some(due), Some(every)) = (jobs.next_verify, jobs.verify_interval) {
+            if due <= Utc::now() && !shutdown.is_triggered() {
+                run_scheduled_verification(&config, manager, jobs.verify_older_than).await;
+                jobs.next_verify = Some(Utc::now() + every);
+            }
+        }
+        if let (Some(due), Some(every)) = (jobs.next_drill, jobs.drill_interval) {
+            if due <= Utc::now() && !shutdown.is_triggered() {
+                run_restore_drill(&config, manager).await;
+                jobs.next_drill = Some(Utc::now() + every);
+            }
+        }
+        if let Some(due) = jobs.next_freshness {
+            if due <= Utc::now() && !shutdown.is_triggered() {
+                run_freshness_check(&config, backup_dir, &mut stale_alerted).await;
+                jobs.next_freshness = Some(Utc::now() + FRESHNESS_CHECK_INTERVAL);
+            }
+        }
+        if let Some(threshold) = jobs.container_down_alert {
+            let now = Utc::now();
+            let overdue = containers.newly_overdue(threshold, now);
+            if let Some(alert) = notify::container_down_alert(&overdue, now) {
+                log::warn!("{}: {}", alert.subject, alert.message);
+                notify::notify_all(&config.notifications, &alert).await;
+            }
+        }
+
+        let snapshot = ScheduleSnapshot {
+            updated_at: Some(Utc::now()),
+            next_backup: jobs.next_backup.clone(),
+        };
+        if let Err(e) = snapshot.save(backup_dir) {
+            log::warn!("Failed to publish the schedule: {}", e);
+        }
+
+        // Without clients to back up there may be nothing scheduled at all
+        // until a reload adds some
+        let sleep = jobs
+            .next_wake()
+            .into_iter()
+            .chain(
+                jobs.container_down_alert
+                    .and_then(|threshold| containers.next_overdue(threshold)),
+            )
+            .min()
+            .map(|wake| {
+                log::debug!("Next scheduled job at {}", wake);
+                (wake - Utc::now()).to_std().unwrap_or_default()
+            })
+            .unwrap_or(std::time::Duration::from_secs(3600));
+        tokio::select! {
+            _ = tokio::time::sleep(sleep) => {}
+            _ = shutdown.wait() => {}
+            Some(event) = container_events.recv() => {
+                if event.action == ContainerAction::Stopping && jobs.backup_on_stop {
+                    backup_before_stop(&config, manager, backup_dir, &event.container).await;
+                }
+                if containers.apply(&event) {
+                    log::info!("Container {} started", event.container);
+                    jobs.resume_postponed(&config, &event.container, Utc::now());
+                } else if event.action == ContainerAction::Stopped
+                    && containers.is_down(&event.container)
+                {
+                    log::warn!("Container {} stopped", event.container);
+                }
+            }
+            _ = reload.recv() => match load_config() {
+                Ok(new_config) => {
+                    if let Err(e) = jobs.reload(&new_config, backup_dir) {
+                        log::error!("Keeping the current configuration: {}", e);
+                        continue;
+                    }
+                    log_reload(&diff_databases(&config.databases, &new_config.databases));
+                    containers.set_watched(watched_containers(&new_config));
+                    config = new_config;
+                }
+                Err(e) => log::error!("Failed to reload configuration, keeping the current one: {}", e),
+            },
+        }
+    }
+    if let Err(e) = ScheduleSnapshot::remove(backup_dir) {
+        log::warn!("{}", e);
+    }
+    log::info!("Daemon stopped");
+    Ok(())
+}
+
+fn log_reload(diff: &DatabaseDiff) {
+    log::info!(
+        "Configuration reloaded: {} added, {} removed, {} changed",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len()
+    );
+    for (what, names) in [
+        ("Added", &diff.added),
+        ("Removed", &diff.removed),
+        ("Changed", &diff.changed),
+    ] {
+        if !names.is_empty() {
+            log::info!("{}: {}", what, names.join(", "));
+        }
+    }
+}
+
+/// SIGHUP, which asks the daemon to reload its configuration.
+struct ReloadSignal {
+    #[cfg(unix)]
+    hangup: Option<tokio::signal::unix::Signal>,
+}
+
+impl ReloadSignal {
+    fn new() -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            let hangup = signal(SignalKind::hangup())
+                .map_err(|e| log::warn!("Cannot listen for SIGHUP, reload disabled: {}", e))
+                .ok();
+            Self { hangup }
+        }
+        #[cfg(not(unix))]
+        Self {}
+    }
+
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        if let Some(hangup) = &mut self.hangup {
+            hangup.recv().await;
+            return;
+        }
+        std::future::pending::<()>().await
+    }
+}
+
+/// Backs up the databases in `container` while it is being stopped, and
+/// alerts about the ones that went down without a fresh backup.
+async fn backup_before_stop(
+    config: &Config,
+    manager: &BackupManager,
+    backup_dir: &str,
+    container: &str,
+) {
+    let databases: Vec<_> = unattended_databases(config)
+        .into_iter()
+        .filter(|db| db.container_name == container)
+        .collect();
+    if databases.is_empty() {
+        return;
+    }
+    log::warn!(
+        "Container {} is stopping, backing up {} databases first",
+        container,
+        databases.len()
+    );
+    let mut failed = Vec::new();
+    for db in &databases {
+        match manager.backup_database(db).await {
+            Ok(path) => log::info!(
+                "Backed up {} before its container stopped: {}",
+                db.name,
+                path
+            ),
+            Err(e) => failed.push((db.name.clone(), e.to_string())),
+        }
+    }
+    let last = last_backups(backup_dir).unwrap_or_default();
+    if let Some(alert) = notify::stopped_without_backup_alert(container, &failed, &last, Utc::now())
+    {
+        log::error!("{}: {}", alert.subject, alert.message);
+        notify::notify_all(&config.notifications, &alert).await;
+    }
+}
+
+/// Containers of the enabled databases, whose starts and stops the daemon
+/// follows.
+fn watched_containers(config: &Config) -> BTreeSet<String> {
+    config
+        .enabled_databases(None)
+        .iter()
+        .map(|db| db.container_name.clone())
+        .collect()
+}
+
+/// Enabled databases the daemon can work on unattended. Nobody is around
+/// to answer a password prompt, so databases that need one are skipped.
+fn unattended_databases(config: &Config) -> Vec<DatabaseConfig> {
+    config
+        .enabled_databases(None)
+        .into_iter()
+        .filter(|db| {
+            if db.needs_password_prompt() {
+                log::warn!(
+                    "Skipping {}: its master password must be entered interactively",
+                    db.name
+                );
+            }
+            !db.needs_password_prompt()
+        })
+        .cloned()
+        .collect()
+}
+
+async fn run_scheduled_backups(manager: &Arc<BackupManager>, databases: &[DatabaseConfig]) {
+    log::info!("Running scheduled backup of {} databases", databases.len());
+    match manager.backup_all_databases(databases).await {
+        Ok(results) => log::info!(
+            "Scheduled backup completed {} of {} databases",
+            results.len(),
+            databases.len()
+        ),
+        Err(e) => log::error!("Scheduled backup failed: {}", e),
+    }
+    for db in databases {
+        if let Err(e) = manager.cleanup_old_backups(db).await {
+            log::error!("Cleanup failed for {}: {}", db.name, e);
+        }
+    }
+}
+
+async fn run_scheduled_verification(
+    config: &Config,
+    manager: &BackupManager,
+    older_than: Option<Duration>,
+) {
+    log::info!("Running scheduled backup verification");
+    match manager.verify_backups(None, None, older_than) {
+        Ok(results) => {
+            log::info!("Re-verified {} backups", results.len());
+            if let Some(alert) = notify::verification_alert(&results) {
+                notify::notify_all(&config.notifications, &alert).await;
+            }
+        }
+        Err(e) => log::error!("Scheduled verification failed: {}", e),
+    }
+}
+
+async fn run_restore_drill(config: &Config, manager: &BackupManager) {
+    let databases = unattended_databases(config);
+    if databases.is_empty() {
+        log::warn!("No database available for a restore drill");
+        return;
+    }
+    let db = &databases[pick_index(databases.len())];
+
+    log::info!("Running restore drill for {}", db.name);
+    let started = Utc::now();
+    let result = manager.test_restore(db).await;
+    let alert = restore_drill_notification(&db.name, &result, (Utc::now() - started).num_seconds());
+    match &result {
+        Ok(_) => log::info!("{}", alert.subject),
+        Err(_) => log::error!("{}: {}", alert.subject, alert.message),
+    }
+    notify::notify_all(&config.notifications, &alert).await;
+}
+
+/// Alerts about databases whose backups went stale since the last check.
+/// Each one is reported once until it has a fresh backup again.
+async fn run_freshness_check(config: &Config, backup_dir: &str, alerted: &mut BTreeSet<String>) {
+    let now = Utc::now();
+    let results = match freshness::check(config, backup_dir, now) {
+        Ok(results) => results,
+        Err(e) => {
+            log::error!("Freshness check failed: {}", e);
+            return;
+        }
+    };
+    let newly_stale = newly_stale(&results, alerted);
+    if let Some(alert) = notify::freshness_alert(&newly_stale, now) {
+        log::warn!("{}: {}", alert.subject, alert.message);
+        notify::notify_all(&config.notifications, &alert).await;
+    }
+}
+
+/// Stale results not in `alerted` yet. `alerted` is updated to the
+/// databases that are stale now.
+fn newly_stale(results: &[Freshness], alerted: &mut BTreeSet<String>) -> Vec<Freshness> {
+    let newly_stale = results
+        .iter()
+        .filter(|r| r.stale && !alerted.contains(&r.client))
+        .cloned()
+        .collect();
+    *alerted = results
+        .iter()
+        .filter(|r| r.stale)
+        .map(|r| r.client.clone())
+        .collect();
+    newly_stale
+}
+
+/// A random index below `len`.
+fn pick_index(len: usize) -> usize {
+    (uuid::Uuid::new_v4().as_u128() % len as u128) as usize
+}
+
+fn restore_drill_notification(
+    client: &str,
+    result: &Result<String>,
+    duration_secs: i64,
+) -> Notification {
+    match result {
+        Ok(file_name) => Notification {
+            subject: format!("Restore drill passed for {}", client),
+            message: format!(
+                "{} was restored into a scratch database in {}s",
+                file_name, duration_secs
+            ),
+        },
+        Err(e) => Notification {
+            subject: format!("Restore drill FAILED for {}", client),
+            message: e.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+
+    #[test]
+    fn test_schedule_config_parsing_and_validation() {
+        let schedule: ScheduleConfig =
+            serde_json::from_str(r#"{"backup_interval": "24h", "verify_interval": "1d"}"#).unwrap();
+        assert_eq!(schedule.verify_older_than, "30d");
+        assert!(schedule.validate().is_ok());
+        assert_eq!(
+            interval(schedule.backup_interval.as_deref()),
+            Some(Duration::hours(24))
+        );
+
+        let invalid = ScheduleConfig {
+            backup_interval: Some("daily".to_string()),
+            ..schedule.clone()
+        };
+        assert!(invalid.validate().unwrap_err().contains("backup_interval"));
+
+        let zero = ScheduleConfig {
+            verify_interval: Some("0h".to_string()),
+            ..schedule
+        };
+        assert!(zero.validate().is_err());
+    }
+
+    #[test]
+    fn test_next_run() {
+        let now = Utc::now();
+        let day = Duration::days(1);
+        assert_eq!(next_run(None, day, now), now);
+        assert_eq!(
+            next_run(Some(now - Duration::hours(6)), day, now),
+            now + Duration::hours(18)
+        );
+        assert_eq!(next_run(Some(now - Duration::days(3)), day, now), now);
+    }
+
+    fn database(name: &str) -> DatabaseConfig {
+        DatabaseConfig {
+            name: name.to_string(),
+            database_name: name.to_lowercase(),
+            container_name: "odoo".to_string(),
+            master_password: "admin".to_string(),
+            retention_days: 7,
+            ..Default::default()
+        }
+    }
+
+    fn scheduled_config(databases: Vec<DatabaseConfig>, backup_interval: &str) -> Config {
+        Config {
+            databases,
+            schedule: Some(ScheduleConfig {
+                backup_interval: Some(backup_interval.to_string()),
+                verify_interval: Some("1d".to_string()),
+                verify_older_than: default_verify_older_than(),
+                restore_drill_interval: None,
+                backup_jitter: None,
+                backup_spread: None,
+                catch_up: false,
+                catch_up_max_age: None,
+                container_down_alert: None,
+                backup_on_stop: false,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff_databases() {
+        let mut changed = database("B");
+        changed.retention_days = 30;
+        let diff = diff_databases(
+            &[database("A"), database("B"), database("C")],
+            &[database("A"), changed, database("D")],
+        );
+        assert_eq!(
+            diff,
+            DatabaseDiff {
+                added: vec!["D".to_string()],
+                removed: vec!["C".to_string()],
+                changed: vec!["B".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_jobs_reload_keeps_schedule_of_unchanged_clients() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let backup_dir = temp_dir.path().to_str().unwrap();
+        let config = scheduled_config(vec![database("A"), database("B")], "24h");
+        let mut jobs = Jobs::new(&config, backup_dir).unwrap();
+        // Without earlier backups every client is due right away
+        assert_eq!(jobs.due_backups(Utc::now()), vec!["A", "B"]);
+
+        let later = Utc::now() + Duration::hours(24);
+        jobs.next_backup.insert("A".to_string(), later);
+        let verify_due = jobs.next_verify;
+
+        let mut password_prompt = database("P");
+        password_prompt.master_password = "-".to_string();
+        let reloaded = scheduled_config(vec![database("A"), database("C"), password_prompt], "24h");
+        jobs.reload(&reloaded, backup_dir).unwrap();
+        assert_eq!(jobs.next_backup.keys().collect::<Vec<_>>(), vec!["A", "C"]);
+        assert_eq!(jobs.next_backup["A"], later);
+        assert_eq!(jobs.next_verify, verify_due);
+
+        // A new interval starts the backup cadence over
+        jobs.reload(&scheduled_config(vec![database("A")], "12h"), backup_dir)
+            .unwrap();
+        assert!(jobs.next_backup["A"] < later);
+
+        // A configuration without schedule is rejected and changes nothing
+        let unscheduled = Config {
+            databases: vec![database("A")],
+            ..Default::default()
+        };
+        assert!(jobs.reload(&unscheduled, backup_dir).is_err());
+        assert_eq!(jobs.backup_interval, Some(Duration::hours(12)));
+    }
+
+    #[test]
+    fn test_database_schedules() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let backup_dir = temp_dir.path().to_str().unwrap();
+        let mut nightly = database("Nightly");
+        nightly.schedule = Some("0 2 * * *".to_string());
+        let cron = nightly.cron_schedule().unwrap();
+
+        // A database schedule is enough for the daemon to run
+        let config = Config {
+            databases: vec![database("A"), nightly.clone()],
+            ..Default::default()
+        };
+        let now = Utc::now();
+        let mut jobs = Jobs::new(&config, backup_dir).unwrap();
+        assert_eq!(jobs.next_backup.keys().collect::<Vec<_>>(), vec!["Nightly"]);
+        assert_eq!(
+            jobs.next_backup["Nightly"],
+            next_cron(&cron, None, now).unwrap()
+        );
+        assert!(jobs.due_backups(Utc::now()).is_empty());
+
+        // Alongside backup_interval, it only overrides its own database
+        let config = scheduled_config(vec![database("A"), nightly.clone()], "24h");
+        jobs.reload(&config, backup_dir).unwrap();
+        assert_eq!(
+            jobs.next_backup["Nightly"],
+            next_cron(&cron, None, now).unwrap()
+        );
+        assert_eq!(jobs.due_backups(Utc::now()), vec!["A"]);
+
+        // Changing the cron reschedules, other clients keep their due time
+        let a_due = jobs.next_backup["A"];
+        nightly.schedule = Some("0 3 * * *".to_string());
+        let cron = nightly.cron_schedule().unwrap();
+        let config = scheduled_config(vec![database("A"), nightly], "24h");
+        jobs.reload(&config, backup_dir).unwrap();
+        assert_eq!(jobs.next_backup["A"], a_due);
+        assert_eq!(
+            jobs.next_backup["Nightly"],
+            next_cron(&cron, None, Utc::now()).unwrap()
+        );
+
+        // Evaluated in the database's time zone
+        let mut tokyo = database("Tokyo");
+        tokyo.schedule = Some("0 2 * * *".to_string());
+        tokyo.timezone = Some("Asia/Tokyo".to_string());
+        let config = Config {
+            databases: vec![tokyo],
+            ..Default::default()
+        };
+        let due = Jobs::new(&config, backup_dir).unwrap().next_backup["Tokyo"];
+        assert_eq!(due.with_timezone(&chrono_tz::Asia::Tokyo).hour(), 2);
+        assert_eq!(due.minute(), 0);
+
+        let mut never = database("Never");
+        never.schedule = Some("0 0 31 2 *".to_string());
+        let config = Config {
+            databases: vec![never],
+            ..Default::default()
+        };
+        assert!(Jobs::new(&config, backup_dir)
+            .unwrap()
+            .next_backup
+            .is_empty());
+    }
+
+    #[test]
+    fn test_catch_up_missed_backups() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let backup_dir = temp_dir.path().to_str().unwrap();
+        let now = Utc::now();
+        // Fires daily, last three hours ago
+        let missed = now - Duration::hours(3);
+        let mut nightly = database("Nightly");
+        nightly.schedule = Some(format!("{} {} * * *", missed.minute(), missed.hour()));
+        nightly.timezone = Some("UTC".to_string());
+        let mut config = Config {
+            databases: vec![nightly],
+            schedule: Some(ScheduleConfig::default()),
+            ..Default::default()
+        };
+        let last = Some(now - Duration::days(2));
+
+        let jobs = Jobs::new(&config, backup_dir).unwrap();
+        assert!(jobs.next_due("Nightly", last, now).unwrap() > now + Duration::hours(20));
+
+        let schedule = config.schedule.as_mut().unwrap();
+        schedule.catch_up = true;
+        let jobs = Jobs::new(&config, backup_dir).unwrap();
+        assert_eq!(jobs.next_due("Nightly", last, now), Some(now));
+        // Never backed up counts as missed
+        assert_eq!(jobs.next_due("Nightly", None, now), Some(now));
+        // Nothing missed since the last backup
+        let recent = Some(now - Duration::hours(1));
+        assert!(jobs.next_due("Nightly", recent, now).unwrap() > now);
+
+        let schedule = config.schedule.as_mut().unwrap();
+        schedule.catch_up_max_age = Some("1h".to_string());
+        let jobs = Jobs::new(&config, backup_dir).unwrap();
+        assert!(jobs.next_due("Nightly", last, now).unwrap() > now);
+
+        let schedule = config.schedule.as_mut().unwrap();
+        schedule.catch_up_max_age = Some("4h".to_string());
+        let jobs = Jobs::new(&config, backup_dir).unwrap();
+        assert_eq!(jobs.next_due("Nightly", last, now), Some(now));
+    }
+
+    #[test]
+    fn test_backups_deferred_by_blackouts() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let backup_dir = temp_dir.path().to_str().unwrap();
+        let now = Utc::now();
+        let local = now.with_timezone(&Local);
+        let mut closing = database("Closing");
+        closing.blackouts = vec![crate::blackout::BlackoutWindow {
+            start: (local - Duration::hours(1)).format("%H:%M").to_string(),
+            end: (local + Duration::hours(1)).format("%H:%M").to_string(),
+            days: None,
+            months: None,
+            weekdays: None,
+        }];
+        let config = scheduled_config(vec![database("A"), closing.clone()], "24h");
+        let mut jobs = Jobs::new(&config, backup_dir).unwrap();
+
+        let runnable = jobs.defer_blackouts(unattended_databases(&config), now);
+        assert_eq!(
+            runnable
+                .iter()
+                .map(|db| db.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["A"]
+        );
+        let until = jobs.next_backup["Closing"];
+        assert_eq!(Some(until), closing.blackout_end(now));
+        assert!(until > now && until <= now + Duration::hours(1));
+        assert_eq!(jobs.due_backups(Utc::now()), vec!["A"]);
+    }
+
+    #[test]
+    fn test_backups_postponed_while_container_down() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let backup_dir = temp_dir.path().to_str().unwrap();
+        let now = Utc::now();
+        let mut stopped = database("Stopped");
+        stopped.container_name = "odoo-stopped".to_string();
+        let config = scheduled_config(vec![database("A"), stopped], "24h");
+        let mut jobs = Jobs::new(&config, backup_dir).unwrap();
+        let containers =
+            ContainerStates::new(watched_containers(&config), &["odoo".to_string()], now);
+
+        let runnable = jobs.postpone_stopped(unattended_databases(&config), &containers);
+        assert_eq!(runnable.len(), 1);
+        assert_eq!(runnable[0].name, "A");
+        assert!(!jobs.next_backup.contains_key("Stopped"));
+
+        jobs.resume_postponed(&config, "odoo", now);
+        assert!(!jobs.next_backup.contains_key("Stopped"));
+        jobs.resume_postponed(&config, "odoo-stopped", now);
+        assert_eq!(jobs.next_backup["Stopped"], now);
+        assert!(jobs.postponed.is_empty());
+    }
+
+    #[test]
+    fn test_backups_spread_and_jittered() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let backup_dir = temp_dir.path().to_str().unwrap();
+        let mut config = scheduled_config(
+            vec![database("A"), database("B"), database("C"), database("D")],
+            "24h",
+        );
+        config.schedule.as_mut().unwrap().backup_spread = Some("2h".to_string());
+
+        let before = Utc::now();
+        let jobs = Jobs::new(&config, backup_dir).unwrap();
+        let offsets: Vec<_> = jobs
+            .next_backup
+            .values()
+            .map(|due| (*due - before).num_minutes())
+            .collect();
+        assert_eq!(offsets, vec![0, 30, 60, 90]);
+
+        for _ in 0..100 {
+            let delay = jitter(Some(Duration::minutes(10)));
+            assert!(delay >= Duration::zero() && delay < Duration::minutes(10));
+        }
+        assert_eq!(jitter(None), Duration::zero());
+    }
+
+    #[test]
+    fn test_backup_spread_must_fit_interval() {
+        let schedule = ScheduleConfig {
+            backup_interval: Some("1h".to_string()),
+            verify_interval: None,
+            verify_older_than: default_verify_older_than(),
+            restore_drill_interval: None,
+            backup_jitter: Some("5m".to_string()),
+            backup_spread: Some("2h".to_string()),
+            catch_up: false,
+            catch_up_max_age: None,
+            container_down_alert: None,
+            backup_on_stop: false,
+        };
+        assert!(schedule.validate().unwrap_err().contains("backup_spread"));
+    }
+
+    #[test]
+    fn test_restore_drill_notification() {
+        let passed = restore_drill_notification("Acme", &Ok("backup.zip".to_string()), 42);
+        assert_eq!(passed.subject, "Restore drill passed for Acme");
+        assert_eq!(
+            passed.message,
+            "backup.zip was restored into a scratch database in 42s"
+        );
+
+        let failed = restore_drill_notification(
+            "Acme",
+            &Err(BackupError::Docker("Restore command failed".to_string())),
+            3,
+        );
+        assert_eq!(failed.subject, "Restore drill FAILED for Acme");
+        assert!(failed.message.contains("Restore command failed"));
+    }
+
+    #[test]
+    fn test_schedule_snapshot_save_and_load() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let backup_dir = temp_dir.path().to_str().unwrap();
+        assert_eq!(ScheduleSnapshot::load(backup_dir).unwrap(), None);
+
+        let snapshot = ScheduleSnapshot {
+            updated_at: Some(Utc::now()),
+            next_backup: BTreeMap::from([("Acme".to_string(), Utc::now())]),
+        };
+        snapshot.save(backup_dir).unwrap();
+        assert_eq!(ScheduleSnapshot::load(backup_dir).unwrap(), Some(snapshot));
+
+        ScheduleSnapshot::remove(backup_dir).unwrap();
+        assert!(!temp_dir.path().join(SCHEDULE_FILE).exists());
+        ScheduleSnapshot::remove(backup_dir).unwrap();
+    }
+
+    #[test]
+    fn test_newly_stale() {
+        let result = |client: &str, stale: bool| Freshness {
+            client: client.to_string(),
+            last_backup: None,
+            max_age_hours: 24,
+            stale,
+        };
+        let mut alerted = BTreeSet::new();
+        let first = newly_stale(&[result("A", true), result("B", false)], &mut alerted);
+        assert_eq!(first, vec![result("A", true)]);
+
+        // Still stale: not reported again
+        let second = newly_stale(&[result("A", true), result("B", true)], &mut alerted);
+        assert_eq!(second, vec![result("B", true)]);
+
+        // Fresh again, then stale again: reported again
+        newly_stale(&[result("A", false), result("B", true)], &mut alerted);
+        let fourth = newly_stale(&[result("A", true), result("B", true)], &mut alerted);
+        assert_eq!(fourth, vec![result("A", true)]);
+    }
+
+    #[test]
+    fn test_pick_index() {
+        assert!((0..100).all(|_| pick_index(3) < 3));
+    }
+}
@@ -0,0 +1,727 @@
+use crate::checksum;
+use crate::config::DatabaseConfig;
+use crate::encryption;
+use crate::error::{BackupError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Lock file in the store root. Ingests hold it shared, garbage collection
+/// exclusively, so chunks are never deleted while a backup still being
+/// ingested relies on them.
+const LOCK_FILE: &str = ".lock";
+
+/// Global `dedupe` setting: where the chunk store lives.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DedupeConfig {
+    pub path: String,
+}
+
+impl DedupeConfig {
+    /// The store, with the passphrases of the `databases` that have one.
+    pub fn build(&self, databases: &[DatabaseConfig]) -> DedupeStore {
+        databases
+            .iter()
+            .fold(DedupeStore::new(&self.path), |store, db| {
+                match &db.dedupe_passphrase {
+                    Some(passphrase) => store.with_passphrase(&db.database_name, passphrase),
+                    None => store,
+                }
+            })
+    }
+}
+
+/// Content-defined chunking parameters. Boundaries are placed where the
+/// rolling hash matches `mask`, so an insertion only changes the chunks
+/// around it instead of shifting every chunk after it.
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkParams {
+    pub min_size: usize,
+    pub max_size: usize,
+    pub mask: u64,
+}
+
+impl Default for ChunkParams {
+    /// Chunks of 512 KiB to 8 MiB, about 1 MiB on average.
+    fn default() -> Self {
+        Self {
+            min_size: 512 * 1024,
+            max_size: 8 * 1024 * 1024,
+            mask: (1 << 20) - 1,
+        }
+    }
+}
+
+/// Random values for the gear rolling hash, generated with splitmix64.
+const GEAR: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// Splits a stream into content-defined chunks, passing each one to `emit`.
+pub fn chunk_stream<R: Read>(
+    mut reader: R,
+    params: ChunkParams,
+    mut emit: impl FnMut(&[u8]) -> Result<()>,
+) -> Result<()> {
+    let mut chunk = Vec::with_capacity(params.max_size);
+    let mut hash: u64 = 0;
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        for &byte in &buffer[..read] {
+            chunk.push(byte);
+            hash = (hash << 1).wrapping_add(GEAR[byte as usize]);
+            if (chunk.len() >= params.min_size && hash & params.mask == 0)
+                || chunk.len() >= params.max_size
+            {
+                emit(&chunk)?;
+                chunk.clear();
+                hash = 0;
+            }
+        }
+    }
+    if !chunk.is_empty() {
+        emit(&chunk)?;
+    }
+    Ok(())
+}
+
+/// The chunk list needed to rebuild one backup file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DedupeIndex {
+    pub file_name: String,
+    pub database_name: String,
+    pub created_at: DateTime<Utc>,
+    pub size: u64,
+    pub sha256: String,
+    pub chunks: Vec<String>,
+    /// Keys of the chunks, in order, encrypted with the database's
+    /// `dedupe_passphrase`; set when the chunks are encrypted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sealed_keys: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IngestStats {
+    pub chunks: usize,
+    pub new_chunks: usize,
+    pub new_bytes: u64,
+}
+
+/// Stores backups as content-addressed chunks, so successive backups that
+/// share most of their content only add the chunks that changed.
+///
+/// Layout: `chunks/<2 hex>/<sha256>` and `indexes/<backup file name>.json`.
+/// Chunks are shared by all databases. Those of databases with a passphrase
+/// are encrypted under a key derived from their content, so encrypted
+/// databases still share chunks with each other, and the chunk keys are
+/// stored in the index encrypted with the passphrase.
+pub struct DedupeStore {
+    root: PathBuf,
+    params: ChunkParams,
+    /// Passphrases by database name
+    passphrases: HashMap<String, String>,
+}
+
+fn fs_error(action: &str, path: &Path, e: std::io::Error) -> BackupError {
+    BackupError::from_fs(format!("Failed to {} {}", action, path.display()), e)
+}
+
+impl DedupeStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            params: ChunkParams::default(),
+            passphrases: HashMap::new(),
+        }
+    }
+
+    /// Encrypts the backups of `database_name` ingested from now on with
+    /// `passphrase`, which also reads its encrypted backups back.
+    pub fn with_passphrase(mut self, database_name: &str, passphrase: &str) -> Self {
+        self.passphrases
+            .insert(database_name.to_string(), passphrase.to_string());
+        self
+    }
+
+    #[cfg(test)]
+    fn with_params(mut self, params: ChunkParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    fn chunk_path(&self, hash: &str) -> PathBuf {
+        self.root.join("chunks").join(&hash[..2]).join(hash)
+    }
+
+    fn indexes_dir(&self) -> PathBuf {
+        self.root.join("indexes")
+    }
+
+    fn index_path(&self, file_name: &str) -> PathBuf {
+        self.indexes_dir().join(format!("{}.json", file_name))
+    }
+
+    /// Takes the advisory lock on the store, held until the file is dropped.
+    fn lock(&self, exclusive: bool) -> Result<File> {
+        fs::create_dir_all(&self.root).map_err(|e| fs_error("create", &self.root, e))?;
+        let path = self.root.join(LOCK_FILE);
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(|e| fs_error("open", &path, e))?;
+        let locked = if exclusive {
+            file.lock()
+        } else {
+            file.lock_shared()
+        };
+        locked.map_err(|e| fs_error("lock", &path, e))?;
+        Ok(file)
+    }
+
+    /// Adds a backup file to the store and writes its index.
+    pub fn ingest(&self, path: &Path, database_name: &str) -> Result<IngestStats> {
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| {
+                BackupError::FileSystem(format!("Invalid backup path {}", path.display()))
+            })?
+            .to_string();
+        let file = File::open(path).map_err(|e| fs_error("open", path, e))?;
+        let _lock = self.lock(false)?;
+
+        let passphrase = self.passphrases.get(database_name);
+        let mut stats = IngestStats::default();
+        let mut chunks = Vec::new();
+        let mut keys = Vec::new();
+        let mut file_hasher = Sha256::new();
+        let mut size = 0u64;
+        chunk_stream(file, self.params, |chunk| {
+            file_hasher.update(chunk);
+            size += chunk.len() as u64;
+            let stored = match passphrase {
+                Some(_) => {
+                    let key = encryption::convergent_key(chunk);
+                    keys.extend_from_slice(&key);
+                    Cow::Owned(encryption::seal_convergent(chunk, &key)?)
+                }
+                None => Cow::Borrowed(chunk),
+            };
+            let hash = checksum::sha256_bytes(&stored);
+            if self.write_chunk(&hash, &stored)? {
+                stats.new_chunks += 1;
+                stats.new_bytes += stored.len() as u64;
+            }
+            chunks.push(hash);
+            Ok(())
+        })?;
+        stats.chunks = chunks.len();
+        let sealed_keys = passphrase
+            .map(|passphrase| encryption::encrypt_armored(&keys, passphrase))
+            .transpose()?;
+
+        let index = DedupeIndex {
+            file_name,
+            database_name: database_name.to_string(),
+            created_at: Utc::now(),
+            size,
+            sha256: checksum::to_hex(&file_hasher.finalize()),
+            chunks,
+            sealed_keys,
+        };
+        self.write_index(&index)?;
+        Ok(stats)
+    }
+
+    /// Writes a chunk unless it is already stored. Returns whether it was new.
+    fn write_chunk(&self, hash: &str, data: &[u8]) -> Result<bool> {
+        let path = self.chunk_path(hash);
+        if path.exists() {
+            return Ok(false);
+        }
+        let parent = path.parent().expect("chunk paths have a parent");
+        fs::create_dir_all(parent).map_err(|e| fs_error("create", parent, e))?;
+        // A name of its own, so concurrent ingests never write the same file
+        let tmp_path = path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4()));
+        fs::write(&tmp_path, data).map_err(|e| fs_error("write", &tmp_path, e))?;
+        if let Err(e) = fs::rename(&tmp_path, &path) {
+            let _ = fs::remove_file(&tmp_path);
+            // Another ingest stored the same chunk first
+            if path.exists() {
+                return Ok(false);
+            }
+            return Err(fs_error("write", &path, e));
+        }
+        Ok(true)
+    }
+
+    fn write_index(&self, index: &DedupeIndex) -> Result<()> {
+        let dir = self.indexes_dir();
+        fs::create_dir_all(&dir).map_err(|e| fs_error("create", &dir, e))?;
+        let path = self.index_path(&index.file_name);
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(index)?)
+            .map_err(|e| fs_error("write", &tmp_path, e))?;
+        fs::rename(&tmp_path, &path).map_err(|e| fs_error("write", &path, e))
+    }
+
+    pub fn read_index(&self, file_name: &str) -> Result<DedupeIndex> {
+        let path = self.index_path(file_name);
+        if !path.exists() {
+            return Err(BackupError::FileSystem(format!(
+                "Backup {} is not in the dedupe store",
+                file_name
+            )));
+        }
+        let content = fs::read_to_string(&path).map_err(|e| fs_error("read", &path, e))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Indexes in the store, optionally only those of one database.
+    pub fn list(&self, database_name: Option<&str>) -> Result<Vec<DedupeIndex>> {
+        let dir = self.indexes_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut indexes = Vec::new();
+        for entry in fs::read_dir(&dir).map_err(|e| fs_error("read", &dir, e))? {
+            let path = entry.map_err(|e| fs_error("read", &dir, e))?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let content = fs::read_to_string(&path).map_err(|e| fs_error("read", &path, e))?;
+            let index: DedupeIndex = serde_json::from_str(&content)?;
+            if database_name.is_none_or(|db| index.database_name == db) {
+                indexes.push(index);
+            }
+        }
+        indexes.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+        Ok(indexes)
+    }
+
+    /// Rebuilds the full backup file at `output`, checking it against the
+    /// checksum recorded at ingest.
+    pub fn materialize(&self, file_name: &str, output: &Path) -> Result<()> {
+        let index = self.read_index(file_name)?;
+        let tmp_output = output.with_extension("partial");
+        let mut writer =
+            File::create(&tmp_output).map_err(|e| fs_error("create", &tmp_output, e))?;
+        let mut hasher = Sha256::new();
+        let written = self
+            .read_chunks(&index, |data| {
+                hasher.update(data);
+                writer
+                    .write_all(data)
+                    .map_err(|e| fs_error("write", &tmp_output, e))
+            })
+            .and_then(|()| Ok(writer.flush()?));
+        if let Err(e) = written {
+            let _ = fs::remove_file(&tmp_output);
+            return Err(e);
+        }
+
+        if checksum::to_hex(&hasher.finalize()) != index.sha256 {
+            let _ = fs::remove_file(&tmp_output);
+            return Err(BackupError::FileSystem(format!(
+                "Materialized {} does not match its recorded checksum",
+                file_name
+            )));
+        }
+        fs::rename(&tmp_output, output).map_err(|e| fs_error("write", output, e))
+    }
+
+    pub fn contains(&self, file_name: &str) -> bool {
+        self.index_path(file_name).exists()
+    }
+
+    /// SHA-256 of a stored backup, recomputed from its chunks.
+    pub fn sha256_of(&self, file_name: &str) -> Result<String> {
+        let index = self.read_index(file_name)?;
+        let mut hasher = Sha256::new();
+        self.read_chunks(&index, |data| {
+            hasher.update(data);
+            Ok(())
+        })?;
+        Ok(checksum::to_hex(&hasher.finalize()))
+    }
+
+    /// Calls `f` with the content of each chunk of `index` in order,
+    /// decrypting them when the backup is encrypted.
+    fn read_chunks(
+        &self,
+        index: &DedupeIndex,
+        mut f: impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        let keys = match &index.sealed_keys {
+            Some(sealed) => {
+                let passphrase = self.passphrases.get(&index.database_name).ok_or_else(|| {
+                    BackupError::Config(format!(
+                        "Backup {} is encrypted, {} needs its dedupe_passphrase",
+                        index.file_name, index.database_name
+                    ))
+                })?;
+                let keys = encryption::decrypt_armored(sealed, passphrase).map_err(|e| {
+                    e.context(format!("Cannot read the chunk keys of {}", index.file_name))
+                })?;
+                if keys.len() != index.chunks.len() * 32 {
+                    return Err(BackupError::FileSystem(format!(
+                        "Chunk keys of {} do not match its chunks",
+                        index.file_name
+                    )));
+                }
+                Some(keys)
+            }
+            None => None,
+        };
+        for (i, hash) in index.chunks.iter().enumerate() {
+            let chunk_path = self.chunk_path(hash);
+            let data = fs::read(&chunk_path).map_err(|e| fs_error("read chunk", &chunk_path, e))?;
+            match &keys {
+                Some(keys) => {
+                    let key = keys[i * 32..(i + 1) * 32]
+                        .try_into()
+                        .expect("chunk keys are 32 bytes");
+                    let data = encryption::open_convergent(&data, &key).map_err(|_| {
+                        BackupError::FileSystem(format!(
+                            "Chunk {} of {} is corrupt",
+                            hash, index.file_name
+                        ))
+                    })?;
+                    f(&data)?;
+                }
+                None => f(&data)?,
+            }
+        }
+        Ok(())
+    }
+
+    pub fn remove(&self, file_name: &str) -> Result<()> {
+        let path = self.index_path(file_name);
+        fs::remove_file(&path).map_err(|e| fs_error("delete", &path, e))
+    }
+
+    /// Deletes chunks no index refers to. Returns the number of chunks and
+    /// bytes freed.
+    pub fn gc(&self) -> Result<(usize, u64)> {
+        let _lock = self.lock(true)?;
+        let unused = self.find_unused_chunks(&[])?;
+        let mut freed = 0;
+        for (chunk, size) in &unused {
+            fs::remove_file(chunk).map_err(|e| fs_error("delete", chunk, e))?;
+            freed += size;
+        }
+        Ok((unused.len(), freed))
+    }
+
+    /// Chunks, with their sizes, that no index would refer to once the
+    /// backups in `removed` are gone.
+    pub fn unused_chunks(&self, removed: &[String]) -> Result<Vec<(PathBuf, u64)>> {
+        let _lock = self.lock(true)?;
+        self.find_unused_chunks(removed)
+    }
+
+    fn find_unused_chunks(&self, removed: &[String]) -> Result<Vec<(PathBuf, u64)>> {
+        let referenced: HashSet<String> = self
+            .list(None)?
+            .into_iter()
+            .filter(|index| !removed.contains(&index.file_name))
+            .flat_map(|index| index.chunks)
+            .collect();
+
+        let chunks_dir = self.root.join("chunks");
+        if !chunks_dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut unused = Vec::new();
+        for prefix in fs::read_dir(&chunks_dir).map_err(|e| fs_error("read", &chunks_dir, e))? {
+            let prefix = prefix.map_err(|e| fs_error("read", &chunks_dir, e))?.path();
+            for chunk in fs::read_dir(&prefix).map_err(|e| fs_error("read", &prefix, e))? {
+                let chunk = chunk.map_err(|e| fs_error("read", &prefix, e))?;
+                let name = chunk.file_name().to_string_lossy().to_string();
+                if !referenced.contains(&name) {
+                    let size = chunk.metadata().map(|m| m.len()).unwrap_or(0);
+                    unused.push((chunk.path(), size));
+                }
+            }
+        }
+        Ok(unused)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn small_params() -> ChunkParams {
+        ChunkParams {
+            min_size: 64,
+            max_size: 1024,
+            mask: (1 << 8) - 1,
+        }
+    }
+
+    /// Deterministic pseudo-random bytes.
+    fn test_data(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+                (state >> 33) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_chunk_stream_respects_bounds() {
+        let data = test_data(50_000, 1);
+        let mut chunks = Vec::new();
+        chunk_stream(&data[..], small_params(), |chunk| {
+            chunks.push(chunk.to_vec());
+            Ok(())
+        })
+        .unwrap();
+
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.concat(), data);
+        let (last, rest) = chunks.split_last().unwrap();
+        assert!(rest.iter().all(|c| c.len() >= 64 && c.len() <= 1024));
+        assert!(last.len() <= 1024);
+    }
+
+    #[test]
+    fn test_ingest_and_materialize_roundtrip() {
+        let temp_dir = tempdir().unwrap();
+        let store = DedupeStore::new(temp_dir.path().join("store")).with_params(small_params());
+        let backup = temp_dir.path().join("backup_db_20240101_120000.zip");
+        let data = test_data(20_000, 7);
+        fs::write(&backup, &data).unwrap();
+
+        let stats = store.ingest(&backup, "db").unwrap();
+        assert_eq!(stats.chunks, stats.new_chunks);
+        assert_eq!(stats.new_bytes, 20_000);
+
+        let output = temp_dir.path().join("restored.zip");
+        store
+            .materialize("backup_db_20240101_120000.zip", &output)
+            .unwrap();
+        assert_eq!(fs::read(&output).unwrap(), data);
+        assert_eq!(
+            store.sha256_of("backup_db_20240101_120000.zip").unwrap(),
+            checksum::sha256_bytes(&data)
+        );
+    }
+
+    #[test]
+    fn test_similar_backups_share_chunks() {
+        let temp_dir = tempdir().unwrap();
+        let store = DedupeStore::new(temp_dir.path().join("store")).with_params(small_params());
+
+        let first_data = test_data(50_000, 3);
+        let mut second_data = first_data.clone();
+        second_data.splice(25_000..25_000, test_data(100, 9));
+
+        let first = temp_dir.path().join("backup_db_20240101_120000.zip");
+        let second = temp_dir.path().join("backup_db_20240102_120000.zip");
+        fs::write(&first, &first_data).unwrap();
+        fs::write(&second, &second_data).unwrap();
+
+        store.ingest(&first, "db").unwrap();
+        let stats = store.ingest(&second, "db").unwrap();
+        // Only the chunks around the insertion are new
+        assert!(stats.new_chunks < stats.chunks / 4);
+        assert!(stats.new_bytes < 10_000);
+    }
+
+    #[test]
+    fn test_databases_share_chunks() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("store");
+        let store = DedupeStore::new(&root).with_params(small_params());
+        let encrypted = DedupeStore::new(&root)
+            .with_params(small_params())
+            .with_passphrase("acme", "acme secret")
+            .with_passphrase("globex", "globex secret");
+
+        // The same media in every client's backup
+        let data = test_data(20_000, 4);
+        let backup = |name: &str| {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, &data).unwrap();
+            path
+        };
+        store
+            .ingest(&backup("backup_a_20240101_120000.zip"), "a")
+            .unwrap();
+        let stats = store
+            .ingest(&backup("backup_b_20240101_120000.zip"), "b")
+            .unwrap();
+        assert_eq!(stats.new_chunks, 0);
+
+        // Encrypted clients share chunks with each other, not with the
+        // plaintext ones
+        let stats = encrypted
+            .ingest(&backup("backup_acme_20240101_120000.zip"), "acme")
+            .unwrap();
+        assert_eq!(stats.new_chunks, stats.chunks);
+        let stats = encrypted
+            .ingest(&backup("backup_globex_20240101_120000.zip"), "globex")
+            .unwrap();
+        assert_eq!(stats.new_chunks, 0);
+
+        let index = encrypted
+            .read_index("backup_acme_20240101_120000.zip")
+            .unwrap();
+        assert!(index.sealed_keys.is_some());
+        let stored = fs::read(encrypted.chunk_path(&index.chunks[0])).unwrap();
+        // The first chunk, sealed with its 16-byte tag
+        let plain_len = stored.len() - 16;
+        assert_ne!(&stored[..plain_len], &data[..plain_len]);
+
+        let output = temp_dir.path().join("restored.zip");
+        encrypted
+            .materialize("backup_globex_20240101_120000.zip", &output)
+            .unwrap();
+        assert_eq!(fs::read(&output).unwrap(), data);
+        assert_eq!(
+            encrypted
+                .sha256_of("backup_acme_20240101_120000.zip")
+                .unwrap(),
+            index.sha256
+        );
+    }
+
+    #[test]
+    fn test_encrypted_backup_needs_passphrase() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("store");
+        let backup = temp_dir.path().join("backup_acme_20240101_120000.zip");
+        fs::write(&backup, test_data(5_000, 6)).unwrap();
+        DedupeStore::new(&root)
+            .with_params(small_params())
+            .with_passphrase("acme", "secret")
+            .ingest(&backup, "acme")
+            .unwrap();
+
+        let output = temp_dir.path().join("restored.zip");
+        let missing = DedupeStore::new(&root)
+            .materialize("backup_acme_20240101_120000.zip", &output)
+            .unwrap_err();
+        assert!(missing.to_string().contains("needs its dedupe_passphrase"));
+        let wrong = DedupeStore::new(&root)
+            .with_passphrase("acme", "wrong")
+            .sha256_of("backup_acme_20240101_120000.zip");
+        assert!(wrong.is_err());
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn test_remove_and_gc() {
+        let temp_dir = tempdir().unwrap();
+        let store = DedupeStore::new(temp_dir.path().join("store")).with_params(small_params());
+        let first = temp_dir.path().join("backup_a_20240101_120000.zip");
+        let second = temp_dir.path().join("backup_b_20240101_120000.zip");
+        fs::write(&first, test_data(10_000, 1)).unwrap();
+        fs::write(&second, test_data(10_000, 2)).unwrap();
+        store.ingest(&first, "a").unwrap();
+        store.ingest(&second, "b").unwrap();
+
+        assert_eq!(store.list(None).unwrap().len(), 2);
+        assert_eq!(store.list(Some("a")).unwrap().len(), 1);
+        assert_eq!(store.gc().unwrap(), (0, 0));
+        let unused = store
+            .unused_chunks(&["backup_a_20240101_120000.zip".to_string()])
+            .unwrap();
+        assert_eq!(unused.iter().map(|(_, size)| size).sum::<u64>(), 10_000);
+
+        store.remove("backup_a_20240101_120000.zip").unwrap();
+        let (removed, freed) = store.gc().unwrap();
+        assert!(removed > 0);
+        assert_eq!(freed, 10_000);
+
+        let output = temp_dir.path().join("restored.zip");
+        store
+            .materialize("backup_b_20240101_120000.zip", &output)
+            .unwrap();
+        assert_eq!(fs::read(&output).unwrap(), test_data(10_000, 2));
+    }
+
+    #[test]
+    fn test_concurrent_ingests_of_shared_chunks() {
+        let temp_dir = tempdir().unwrap();
+        let store = DedupeStore::new(temp_dir.path().join("store")).with_params(small_params());
+        let files: Vec<_> = (0..4)
+            .map(|i| {
+                let path = temp_dir
+                    .path()
+                    .join(format!("backup_db{}_20240101_120000.zip", i));
+                fs::write(&path, test_data(50_000, 7)).unwrap();
+                path
+            })
+            .collect();
+
+        std::thread::scope(|scope| {
+            for (i, path) in files.iter().enumerate() {
+                let store = &store;
+                scope.spawn(move || store.ingest(path, &format!("db{}", i)).unwrap());
+            }
+            scope.spawn(|| store.gc().unwrap());
+        });
+
+        for path in &files {
+            let name = path.file_name().unwrap().to_str().unwrap();
+            let output = temp_dir.path().join(format!("{}.restored", name));
+            store.materialize(name, &output).unwrap();
+            assert_eq!(fs::read(&output).unwrap(), test_data(50_000, 7));
+        }
+        // No temporary chunk files are left behind
+        assert_eq!(store.gc().unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn test_materialize_detects_corrupt_chunk() {
+        let temp_dir = tempdir().unwrap();
+        let store = DedupeStore::new(temp_dir.path().join("store")).with_params(small_params());
+        let backup = temp_dir.path().join("backup_db_20240101_120000.zip");
+        fs::write(&backup, test_data(5_000, 5)).unwrap();
+        store.ingest(&backup, "db").unwrap();
+
+        let index = store.read_index("backup_db_20240101_120000.zip").unwrap();
+        fs::write(store.chunk_path(&index.chunks[0]), b"garbage").unwrap();
+
+        let output = temp_dir.path().join("restored.zip");
+        let result = store.materialize("backup_db_20240101_120000.zip", &output);
+        assert!(matches!(result.unwrap_err(), BackupError::FileSystem(_)));
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn test_materialize_unknown_backup() {
+        let temp_dir = tempdir().unwrap();
+        let store = DedupeStore::new(temp_dir.path());
+        let result = store.materialize("missing.zip", &temp_dir.path().join("out.zip"));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,198 @@
+use crate::error::{BackupError, Result};
+use crate::manifest::{self, FILESTORE_PREFIX};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+use zip::ZipArchive;
+
+/// A module whose version differs between two backups.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModuleChange {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// What changed from one zip backup of a database to a later one.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BackupDiff {
+    pub odoo_version: (Option<String>, Option<String>),
+    /// Modules only in the newer backup, with their versions
+    pub modules_added: BTreeMap<String, String>,
+    /// Modules only in the older backup, with their versions
+    pub modules_removed: BTreeMap<String, String>,
+    pub modules_changed: Vec<ModuleChange>,
+    pub filestore_files_added: u64,
+    pub filestore_files_removed: u64,
+    pub filestore_files_delta: i64,
+    pub filestore_size_delta: i64,
+    pub database_size_delta: i64,
+}
+
+impl BackupDiff {
+    pub fn is_empty(&self) -> bool {
+        self.odoo_version.0 == self.odoo_version.1
+            && self.modules_added.is_empty()
+            && self.modules_removed.is_empty()
+            && self.modules_changed.is_empty()
+            && self.filestore_files_added == 0
+            && self.filestore_files_removed == 0
+            && self.database_size_delta == 0
+    }
+}
+
+/// Compares the manifests and filestore listings of the zip backups `old`
+/// and `new`. Filestore files are named after their content, so a file
+/// present in both is unchanged.
+pub fn diff(old: &Path, new: &Path) -> Result<BackupDiff> {
+    let old_contents = manifest::inspect_zip(old)?;
+    let new_contents = manifest::inspect_zip(new)?;
+    if let (Some(a), Some(b)) = (&old_contents.manifest, &new_contents.manifest) {
+        if a.database_name != b.database_name {
+            log::warn!(
+                "Comparing backups of different databases: {} and {}",
+                a.database_name,
+                b.database_name
+            );
+        }
+    }
+
+    let mut diff = BackupDiff {
+        odoo_version: (old_contents.odoo_version, new_contents.odoo_version),
+        filestore_files_delta: new_contents.filestore_files as i64
+            - old_contents.filestore_files as i64,
+        filestore_size_delta: new_contents.filestore_size as i64
+            - old_contents.filestore_size as i64,
+        database_size_delta: new_contents.database_size as i64 - old_contents.database_size as i64,
+        ..Default::default()
+    };
+    for (name, version) in &new_contents.modules {
+        match old_contents.modules.get(name) {
+            None => {
+                diff.modules_added.insert(name.clone(), version.clone());
+            }
+            Some(old_version) if old_version != version => {
+                diff.modules_changed.push(ModuleChange {
+                    name: name.clone(),
+                    from: old_version.clone(),
+                    to: version.clone(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, version) in &old_contents.modules {
+        if !new_contents.modules.contains_key(name) {
+            diff.modules_removed.insert(name.clone(), version.clone());
+        }
+    }
+
+    let old_files = filestore_listing(old)?;
+    let new_files = filestore_listing(new)?;
+    diff.filestore_files_added = new_files
+        .keys()
+        .filter(|f| !old_files.contains_key(*f))
+        .count() as u64;
+    diff.filestore_files_removed = old_files
+        .keys()
+        .filter(|f| !new_files.contains_key(*f))
+        .count() as u64;
+    Ok(diff)
+}
+
+/// Files of the filestore in a zip backup, with their sizes.
+fn filestore_listing(path: &Path) -> Result<BTreeMap<String, u64>> {
+    let file = File::open(path)
+        .map_err(|e| BackupError::from_fs(format!("Failed to open {}", path.display()), e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| {
+        BackupError::FileSystem(format!(
+            "Failed to read zip backup {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    let mut files = BTreeMap::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i).map_err(|e| {
+            BackupError::FileSystem(format!(
+                "Failed to read zip backup {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        if let Some(name) = entry.name().strip_prefix(FILESTORE_PREFIX) {
+            if !entry.is_dir() {
+                files.insert(name.to_string(), entry.size());
+            }
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    fn write_backup(path: &Path, modules: &str, dump: &str, files: &[&str]) {
+        let mut writer = ZipWriter::new(File::create(path).unwrap());
+        let options = SimpleFileOptions::default();
+        writer.start_file("manifest.json", options).unwrap();
+        write!(writer, r#"{{"version": "17.0", "modules": {}}}"#, modules).unwrap();
+        writer.start_file("dump.sql", options).unwrap();
+        writer.write_all(dump.as_bytes()).unwrap();
+        for file in files {
+            writer
+                .start_file(format!("filestore/{}", file), options)
+                .unwrap();
+            writer.write_all(b"data").unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_diff() {
+        let temp_dir = tempdir().unwrap();
+        let old = temp_dir.path().join("old.zip");
+        let new = temp_dir.path().join("new.zip");
+        write_backup(
+            &old,
+            r#"{"base": "17.0.1.3", "sale": "17.0.1.2", "stock": "17.0.1.1"}"#,
+            "SELECT 1;",
+            &["ab/ab01", "cd/cd02"],
+        );
+        write_backup(
+            &new,
+            r#"{"base": "17.0.1.3", "sale": "17.0.1.3", "crm": "17.0.1.0"}"#,
+            "SELECT 1; SELECT 2;",
+            &["ab/ab01", "ef/ef03", "ef/ef04"],
+        );
+
+        let diff = diff(&old, &new).unwrap();
+        assert_eq!(diff.modules_added.keys().collect::<Vec<_>>(), vec!["crm"]);
+        assert_eq!(
+            diff.modules_removed.keys().collect::<Vec<_>>(),
+            vec!["stock"]
+        );
+        assert_eq!(
+            diff.modules_changed,
+            vec![ModuleChange {
+                name: "sale".to_string(),
+                from: "17.0.1.2".to_string(),
+                to: "17.0.1.3".to_string(),
+            }]
+        );
+        assert_eq!(diff.filestore_files_added, 2);
+        assert_eq!(diff.filestore_files_removed, 1);
+        assert_eq!(diff.filestore_files_delta, 1);
+        assert_eq!(diff.filestore_size_delta, 4);
+        assert_eq!(diff.database_size_delta, 10);
+        assert!(!diff.is_empty());
+
+        assert!(super::diff(&old, &old).unwrap().is_empty());
+    }
+}
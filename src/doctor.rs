@@ -0,0 +1,518 @@
+use crate::catalog::Catalog;
+use crate::config::{Config, DatabaseConfig};
+use crate::docker::DockerManager;
+use crate::error::{BackupError, Result};
+use crate::odoo_api;
+use crate::output::Color;
+use crate::size;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// One line of the `doctor` checklist.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to do about a warning or failure
+    pub hint: Option<String>,
+}
+
+impl Check {
+    pub fn ok(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Ok,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    pub fn warn(
+        name: impl Into<String>,
+        detail: impl Into<String>,
+        hint: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    pub fn fail(
+        name: impl Into<String>,
+        detail: impl Into<String>,
+        hint: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+/// Whether Odoo accepts the master password of one database.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuthCheck {
+    pub client: String,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+/// Checks the master password of each of `databases`, one after another.
+pub async fn check_auth(docker: &DockerManager, databases: &[DatabaseConfig]) -> Vec<AuthCheck> {
+    let mut checks = Vec::new();
+    for db in databases {
+        let result = docker.check_credentials(db).await;
+        checks.push(AuthCheck {
+            client: db.name.clone(),
+            ok: result.is_ok(),
+            error: result.err().map(|e| e.to_string()),
+        });
+    }
+    checks
+}
+
+/// Result of loading the configuration file at `path`.
+pub fn check_config(path: &str, config: &Result<Config>) -> Check {
+    match config {
+        Ok(config) => Check::ok(
+            "Configuration",
+            format!("{} is valid, {} databases", path, config.databases.len()),
+        ),
+        Err(e) => Check::fail(
+            "Configuration",
+            e.to_string(),
+            format!("Fix {} and run doctor again", path),
+        ),
+    }
+}
+
+/// Checks Docker, the backup directory and every enabled database.
+pub async fn run(config: &Config, docker: &DockerManager, backup_dir: &str) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let docker_ok = match docker.list_containers().await {
+        Ok(containers) => {
+            checks.push(Check::ok(
+                "Docker",
+                format!("daemon reachable, {} containers running", containers.len()),
+            ));
+            true
+        }
+        Err(e) => {
+            checks.push(Check::fail(
+                "Docker",
+                e.to_string(),
+                "Install Docker, start its daemon and make sure this user may use it \
+                 (e.g. is in the docker group)",
+            ));
+            false
+        }
+    };
+
+    checks.push(check_backup_dir_writable(backup_dir));
+    checks.push(check_free_space(config, backup_dir));
+
+    // Nothing below can work without Docker
+    if !docker_ok {
+        return checks;
+    }
+    for db in config.enabled_databases(None) {
+        let name = |what: &str| format!("{}: {}", db.name, what);
+
+        if let Err(e) = docker.check_container(&db.container_name).await {
+            let hint = match e.root() {
+                BackupError::ContainerNotRunning { name } => {
+                    format!("Start it with `docker start {}`", name)
+                }
+                _ => "Check container_name against `docker ps -a`".to_string(),
+            };
+            checks.push(Check::fail(name("container"), e.to_string(), hint));
+            continue;
+        }
+        checks.push(Check::ok(
+            name("container"),
+            format!("{} is running", db.container_name),
+        ));
+
+        if let Some(mount_dir) = &db.mount_dir {
+            match docker.check_mount_dir(db).await {
+                Ok(()) => checks.push(Check::ok(
+                    name("mount"),
+                    format!("{} is mounted at {}", mount_dir, db.output_path),
+                )),
+                Err(e) => checks.push(Check::fail(
+                    name("mount"),
+                    e.to_string(),
+                    "Bind-mount mount_dir at output_path when creating the container",
+                )),
+            }
+        }
+
+        match docker
+            .probe_http(db, crate::status::HTTP_CHECK_TIMEOUT_SECS)
+            .await
+        {
+            Ok(probe) if probe.is_healthy() => checks.push(Check::ok(
+                name("Odoo"),
+                format!(
+                    "{} answers HTTP {} in {} ms",
+                    db.url, probe.status, probe.latency_ms
+                ),
+            )),
+            Ok(probe) => checks.push(Check::fail(
+                name("Odoo"),
+                format!("{} answers HTTP {}", db.url, probe.status),
+                "Check the Odoo logs with `docker logs`",
+            )),
+            Err(e) => {
+                checks.push(Check::fail(
+                    name("Odoo"),
+                    e.to_string(),
+                    "Check that url is where Odoo listens inside the container",
+                ));
+                continue;
+            }
+        }
+
+        match docker.fetch_version_info(db).await {
+            Ok(info) => {
+                let warnings = info
+                    .major()
+                    .map(|major| odoo_api::compatibility_warnings(major, &db.backup_format))
+                    .unwrap_or_default();
+                if warnings.is_empty() {
+                    checks.push(Check::ok(name("Odoo version"), info.server_version));
+                } else {
+                    checks.push(Check::warn(
+                        name("Odoo version"),
+                        format!("{}: {}", info.server_version, warnings.join("; ")),
+                        "Upgrade Odoo or back this database up by other means",
+                    ));
+                }
+            }
+            Err(e) => checks.push(Check::warn(
+                name("Odoo version"),
+                e.to_string(),
+                "Backups still run, but their Odoo version is not recorded",
+            )),
+        }
+
+        if db.session_auth.is_some() {
+            match docker.check_credentials(db).await {
+                Ok(()) => {
+                    checks.push(Check::ok(name("session login"), "accepted"));
+                    checks.push(check_metadata(docker, db, name("Odoo metadata")).await);
+                }
+                Err(e) => {
+                    let hint = match e.root() {
+                        BackupError::AuthFailed(_) => {
+                            "Check session_auth login and password, and that the user may log in to this database"
+                        }
+                        _ => "Check that Odoo serves /web/session/authenticate",
+                    };
+                    checks.push(Check::fail(name("session login"), e.to_string(), hint));
+                }
+            }
+            continue;
+        }
+        if db.needs_password_prompt() {
+            checks.push(Check::warn(
+                name("master password"),
+                "not checked, it is prompted for",
+                "Run `backup --ask-password` to check it interactively",
+            ));
+            continue;
+        }
+        match docker.check_master_password(db).await {
+            Ok(()) => checks.push(Check::ok(name("master password"), "accepted")),
+            Err(e) => {
+                let hint = match e.root() {
+                    BackupError::AuthFailed(_) => {
+                        "Check master_password against admin_passwd in the Odoo configuration"
+                    }
+                    _ => "Check that the database manager is enabled (list_db)",
+                };
+                checks.push(Check::fail(name("master password"), e.to_string(), hint));
+            }
+        }
+    }
+    checks
+}
+
+/// Reads the metadata recorded for dump backups through Odoo's external API.
+async fn check_metadata(docker: &DockerManager, db: &DatabaseConfig, name: String) -> Check {
+    let metadata = async {
+        let rpc = docker.odoo_rpc(db).await?;
+        Ok::<_, BackupError>((
+            rpc.installed_modules().await?.len(),
+            rpc.attachment_count().await?,
+        ))
+    };
+    match metadata.await {
+        Ok((modules, attachments)) => Check::ok(
+            name,
+            format!("{} modules installed, {} attachments", modules, attachments),
+        ),
+        Err(e) => Check::warn(
+            name,
+            e.to_string(),
+            "Give the session_auth user read access to modules and attachments",
+        ),
+    }
+}
+
+fn check_backup_dir_writable(backup_dir: &str) -> Check {
+    let probe = Path::new(backup_dir).join(format!(".doctor-{}", uuid::Uuid::new_v4()));
+    let written = fs::create_dir_all(backup_dir).and_then(|_| fs::write(&probe, b"ok"));
+    let _ = fs::remove_file(&probe);
+    match written {
+        Ok(()) => Check::ok("Backup directory", format!("{} is writable", backup_dir)),
+        Err(e) => Check::fail(
+            "Backup directory",
+            format!("{} is not writable: {}", backup_dir, e),
+            format!(
+                "Create {} and give this user write access, or pass --backup-dir",
+                backup_dir
+            ),
+        ),
+    }
+}
+
+/// Compares the free space of the backup directory with the size of the
+/// last backup of every enabled database, which a full run needs again.
+fn check_free_space(config: &Config, backup_dir: &str) -> Check {
+    let available = match available_space(backup_dir) {
+        Ok(available) => available,
+        Err(e) => {
+            return Check::warn(
+                "Free space",
+                e.to_string(),
+                "Check the space left for backups by hand, e.g. with `df -h`",
+            )
+        }
+    };
+    let needed: u64 = Catalog::load(backup_dir)
+        .map(|catalog| {
+            config
+                .enabled_databases(None)
+                .iter()
+                .filter_map(|db| catalog.last_success(&db.name).and_then(|e| e.size))
+                .sum()
+        })
+        .unwrap_or(0);
+
+    let detail = format!(
+        "{} available, the last backups took {}",
+        size::format_size(available),
+        size::format_size(needed)
+    );
+    if available < needed {
+        Check::fail(
+            "Free space",
+            detail,
+            "Free up space, lower retention_days or move --backup-dir to a larger disk",
+        )
+    } else {
+        Check::ok("Free space", detail)
+    }
+}
+
+/// Bytes available to this user on the file system holding `path`.
+fn available_space(path: &str) -> Result<u64> {
+    let output = Command::new("df")
+        .args(["-Pk", path])
+        .output()
+        .map_err(|e| BackupError::Io(e).context("Failed to run df"))?;
+    if !output.status.success() {
+        return Err(BackupError::FileSystem(format!(
+            "df {} failed: {}",
+            path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    parse_df_available(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| BackupError::FileSystem(format!("Unexpected df output for {}", path)))
+}
+
+/// Available bytes from POSIX `df -Pk` output.
+fn parse_df_available(output: &str) -> Option<u64> {
+    let fields: Vec<&str> = output.lines().nth(1)?.split_whitespace().collect();
+    let kilobytes: u64 = fields.get(3)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// The checklist with hints, in color when `color` is set.
+pub fn render(checks: &[Check], color: bool) -> String {
+    let mut out = String::new();
+    for check in checks {
+        let (label, code) = match check.status {
+            CheckStatus::Ok => ("[ OK ]", Color::Green),
+            CheckStatus::Warn => ("[WARN]", Color::Yellow),
+            CheckStatus::Fail => ("[FAIL]", Color::Red),
+        };
+        let label = code.paint_if(color, label);
+        out.push_str(&format!("{} {} - {}\n", label, check.name, check.detail));
+        if let Some(hint) = &check.hint {
+            out.push_str(&format!("       -> {}\n", hint));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DatabaseConfig;
+    use crate::runtime::{ExecOutput, MockRuntime};
+    use tempfile::tempdir;
+
+    fn database(name: &str, container_name: &str) -> DatabaseConfig {
+        DatabaseConfig {
+            name: name.to_string(),
+            database_name: name.to_lowercase(),
+            container_name: container_name.to_string(),
+            master_password: "admin".to_string(),
+            retention_days: 7,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_df_available() {
+        let output = "Filesystem     1024-blocks     Used Available Capacity Mounted on\n\
+                      /dev/vda         264212084 23720948  77081328      24% /\n";
+        assert_eq!(parse_df_available(output), Some(77081328 * 1024));
+        assert_eq!(parse_df_available("Filesystem\n"), None);
+    }
+
+    #[test]
+    fn test_render() {
+        let checks = [
+            Check::ok("Docker", "daemon reachable"),
+            Check::fail("Acme: container", "missing", "Start it"),
+        ];
+        assert_eq!(
+            render(&checks, false),
+            "[ OK ] Docker - daemon reachable\n[FAIL] Acme: container - missing\n       -> Start it\n"
+        );
+        assert!(render(&checks, true).contains("\x1b[31m[FAIL]\x1b[0m"));
+    }
+
+    #[tokio::test]
+    async fn test_check_auth() {
+        let docker = DockerManager::with_runtime(Box::new(
+            MockRuntime::new()
+                .respond(
+                    "\"wrong\"",
+                    ExecOutput::ok(
+                        r#"{"jsonrpc": "2.0", "id": null, "error": {"code": 200, "message": "Odoo Server Error",
+                        "data": {"name": "odoo.exceptions.AccessDenied", "message": "Access Denied"}}}"#,
+                    ),
+                )
+                .respond(
+                    "/jsonrpc",
+                    ExecOutput::ok(r#"{"jsonrpc": "2.0", "id": null, "result": false}"#),
+                ),
+        ));
+        let mut wrong = database("Wrong", "odoo");
+        wrong.master_password = "wrong".to_string();
+
+        let checks = check_auth(&docker, &[database("Acme", "odoo"), wrong]).await;
+        assert_eq!(
+            checks,
+            vec![
+                AuthCheck {
+                    client: "Acme".to_string(),
+                    ok: true,
+                    error: None,
+                },
+                AuthCheck {
+                    client: "Wrong".to_string(),
+                    ok: false,
+                    error: Some("Authentication failed: Access Denied".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        let temp_dir = tempdir().unwrap();
+        let backup_dir = temp_dir.path().to_str().unwrap();
+        let mut prompted = database("Prompted", "odoo-prompted");
+        prompted.master_password = "-".to_string();
+        prompted.url = "http://legacy:8069".to_string();
+        let config = Config {
+            databases: vec![
+                database("Acme", "odoo-acme"),
+                database("Gone", "odoo-gone"),
+                prompted,
+            ],
+            ..Default::default()
+        };
+        let docker = DockerManager::with_runtime(Box::new(
+            MockRuntime::new()
+                .with_container("odoo-acme")
+                .respond(
+                    "exec odoo-gone true",
+                    ExecOutput::failed("Error: No such container: odoo-gone"),
+                )
+                .respond("/web/login", ExecOutput::ok("200 0.010"))
+                .respond(
+                    "legacy:8069/web/webclient/version_info",
+                    ExecOutput::ok(
+                        r#"{"jsonrpc": "2.0", "id": null, "result": {"server_version": "8.0"}}"#,
+                    ),
+                )
+                .respond(
+                    "/web/webclient/version_info",
+                    ExecOutput::ok(
+                        r#"{"jsonrpc": "2.0", "id": null, "result": {"server_version": "17.0"}}"#,
+                    ),
+                )
+                .respond(
+                    "/jsonrpc",
+                    ExecOutput::ok(r#"{"jsonrpc": "2.0", "id": null, "result": false}"#),
+                ),
+        ));
+
+        let checks = run(&config, &docker, backup_dir).await;
+        let statuses: Vec<_> = checks.iter().map(|c| (c.name.as_str(), c.status)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("Docker", CheckStatus::Ok),
+                ("Backup directory", CheckStatus::Ok),
+                ("Free space", CheckStatus::Ok),
+                ("Acme: container", CheckStatus::Ok),
+                ("Acme: Odoo", CheckStatus::Ok),
+                ("Acme: Odoo version", CheckStatus::Ok),
+                ("Acme: master password", CheckStatus::Ok),
+                ("Gone: container", CheckStatus::Fail),
+                ("Prompted: container", CheckStatus::Ok),
+                ("Prompted: Odoo", CheckStatus::Ok),
+                ("Prompted: Odoo version", CheckStatus::Warn),
+                ("Prompted: master password", CheckStatus::Warn),
+            ]
+        );
+        assert_eq!(
+            checks[7].hint.as_deref(),
+            Some("Check container_name against `docker ps -a`")
+        );
+    }
+}
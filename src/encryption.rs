@@ -0,0 +1,188 @@
+use crate::error::{BackupError, Result};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+
+pub const ARMOR_HEADER: &str = "-----BEGIN ODOO-BACKUP ENCRYPTED DATA-----";
+pub const ARMOR_FOOTER: &str = "-----END ODOO-BACKUP ENCRYPTED DATA-----";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+#[cfg(not(test))]
+const PBKDF2_ROUNDS: u32 = 600_000;
+// Unoptimized test builds would spend minutes in key derivation
+#[cfg(test)]
+const PBKDF2_ROUNDS: u32 = 1_000;
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Key<Aes256Gcm> {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    key.into()
+}
+
+/// Encrypts with AES-256-GCM under a key derived from `passphrase`.
+/// The output is `salt | nonce | ciphertext`.
+pub fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &salt));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| BackupError::Config("Encryption failed".to_string()))?;
+
+    let mut output = Vec::with_capacity(SALT_LEN + NONCE_LEN + ciphertext.len());
+    output.extend_from_slice(&salt);
+    output.extend_from_slice(&nonce);
+    output.extend_from_slice(&ciphertext);
+    Ok(output)
+}
+
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    if data.len() < SALT_LEN + NONCE_LEN {
+        return Err(BackupError::Config(
+            "Encrypted data is truncated".to_string(),
+        ));
+    }
+    let (salt, rest) = data.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, salt));
+
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| {
+            BackupError::Config("Decryption failed: wrong key or corrupted data".to_string())
+        })
+}
+
+/// Key for convergent encryption of `plaintext`, derived from the content
+/// itself so equal content encrypts to equal ciphertext.
+pub fn convergent_key(plaintext: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"odoo-backup convergent key\0");
+    hasher.update(plaintext);
+    hasher.finalize().into()
+}
+
+/// Encrypts with AES-256-GCM under `key` from `convergent_key`. The nonce
+/// is fixed, which is safe as every key encrypts a single plaintext.
+pub fn seal_convergent(plaintext: &[u8], key: &[u8; 32]) -> Result<Vec<u8>> {
+    Aes256Gcm::new(key.into())
+        .encrypt(&Nonce::default(), plaintext)
+        .map_err(|_| BackupError::Config("Encryption failed".to_string()))
+}
+
+pub fn open_convergent(ciphertext: &[u8], key: &[u8; 32]) -> Result<Vec<u8>> {
+    Aes256Gcm::new(key.into())
+        .decrypt(&Nonce::default(), ciphertext)
+        .map_err(|_| {
+            BackupError::Config("Decryption failed: wrong key or corrupted data".to_string())
+        })
+}
+
+pub fn is_armored(content: &str) -> bool {
+    content.trim_start().starts_with(ARMOR_HEADER)
+}
+
+/// Encrypts and wraps the result in a base64 text armor.
+pub fn encrypt_armored(plaintext: &[u8], passphrase: &str) -> Result<String> {
+    let encoded = BASE64.encode(encrypt(plaintext, passphrase)?);
+    let mut armored = String::from(ARMOR_HEADER);
+    armored.push('\n');
+    for line in encoded.as_bytes().chunks(64) {
+        armored.push_str(&String::from_utf8_lossy(line));
+        armored.push('\n');
+    }
+    armored.push_str(ARMOR_FOOTER);
+    armored.push('\n');
+    Ok(armored)
+}
+
+pub fn decrypt_armored(content: &str, passphrase: &str) -> Result<Vec<u8>> {
+    let body = content
+        .trim()
+        .strip_prefix(ARMOR_HEADER)
+        .and_then(|rest| rest.strip_suffix(ARMOR_FOOTER))
+        .ok_or_else(|| BackupError::Config("Malformed encrypted data armor".to_string()))?;
+
+    let encoded: String = body.split_whitespace().collect();
+    let data = BASE64
+        .decode(encoded)
+        .map_err(|e| BackupError::Config(format!("Malformed encrypted data: {}", e)))?;
+    decrypt(&data, passphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let encrypted = encrypt(b"secret config", "passphrase").unwrap();
+        assert_ne!(&encrypted[SALT_LEN + NONCE_LEN..], b"secret config");
+        assert_eq!(decrypt(&encrypted, "passphrase").unwrap(), b"secret config");
+    }
+
+    #[test]
+    fn test_encrypt_uses_fresh_salt_and_nonce() {
+        let first = encrypt(b"same input", "passphrase").unwrap();
+        let second = encrypt(b"same input", "passphrase").unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_decrypt_wrong_passphrase() {
+        let encrypted = encrypt(b"secret config", "passphrase").unwrap();
+        let result = decrypt(&encrypted, "wrong");
+        assert!(matches!(result.unwrap_err(), BackupError::Config(_)));
+    }
+
+    #[test]
+    fn test_decrypt_truncated_data() {
+        assert!(decrypt(&[0u8; 8], "passphrase").is_err());
+    }
+
+    #[test]
+    fn test_convergent_roundtrip() {
+        let key = convergent_key(b"shared font");
+        let sealed = seal_convergent(b"shared font", &key).unwrap();
+        // Equal content, equal ciphertext
+        assert_eq!(sealed, seal_convergent(b"shared font", &key).unwrap());
+        assert_ne!(key, convergent_key(b"other font"));
+        assert_eq!(open_convergent(&sealed, &key).unwrap(), b"shared font");
+        assert!(open_convergent(&sealed, &convergent_key(b"other font")).is_err());
+    }
+
+    #[test]
+    fn test_armored_roundtrip() {
+        let armored = encrypt_armored(b"[{\"name\": \"x\"}]", "passphrase").unwrap();
+        assert!(is_armored(&armored));
+        assert!(armored.trim_end().ends_with(ARMOR_FOOTER));
+        assert!(armored
+            .lines()
+            .all(|line| line.len() <= 64 || line.starts_with("-----")));
+
+        let decrypted = decrypt_armored(&armored, "passphrase").unwrap();
+        assert_eq!(decrypted, b"[{\"name\": \"x\"}]");
+    }
+
+    #[test]
+    fn test_is_armored() {
+        assert!(!is_armored("[]"));
+        assert!(is_armored(&format!(
+            "\n{}\nabc\n{}\n",
+            ARMOR_HEADER, ARMOR_FOOTER
+        )));
+    }
+
+    #[test]
+    fn test_decrypt_armored_malformed() {
+        assert!(decrypt_armored("[]", "passphrase").is_err());
+        let bad = format!("{}\n!!!\n{}\n", ARMOR_HEADER, ARMOR_FOOTER);
+        assert!(decrypt_armored(&bad, "passphrase").is_err());
+    }
+}
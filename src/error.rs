@@ -1,3 +1,4 @@
+use std::io;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -8,17 +9,54 @@ pub enum BackupError {
     #[error("Docker error: {0}")]
     Docker(String),
 
+    #[error("Container '{name}' is not running")]
+    ContainerNotRunning { name: String },
+
+    #[error("Container '{name}' does not exist")]
+    ContainerNotFound { name: String },
+
+    #[error("Authentication failed: {0}")]
+    AuthFailed(String),
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    #[error("Disk full: {0}")]
+    DiskFull(String),
+
     #[error("Network error: {0}")]
-    #[allow(dead_code)]
     Network(String),
 
     #[error("File system error: {0}")]
     FileSystem(String),
 
     #[error("Odoo API error: {0}")]
-    #[allow(dead_code)]
     OdooApi(String),
 
+    #[error("Another instance is already running (pid {pid})")]
+    AlreadyRunning { pid: u32 },
+
+    #[error("Cancelled by shutdown")]
+    Cancelled,
+
+    #[error("Stale backups: {0}")]
+    Stale(String),
+
+    #[error("Verification failed: {0}")]
+    VerificationFailed(String),
+
+    #[error("Unhealthy: {0}")]
+    Unhealthy(String),
+
+    #[error("{client} is in a blackout window until {until}")]
+    Blackout { client: String, until: String },
+
+    #[error("Notification error: {0}")]
+    Notification(String),
+
+    #[error("Maintenance error: {0}")]
+    Maintenance(String),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -28,6 +66,14 @@ pub enum BackupError {
     #[error("HTTP error: {0}")]
     Http(#[from] reqwest::Error),
 
+    /// `source` annotated with what was being done when it happened
+    #[error("{context}: {source}")]
+    Context {
+        context: String,
+        #[source]
+        source: Box<BackupError>,
+    },
+
     #[error("Unknown error: {0}")]
     #[allow(dead_code)]
     Unknown(String),
@@ -35,10 +81,116 @@ pub enum BackupError {
 
 pub type Result<T> = std::result::Result<T, BackupError>;
 
+/// Adds context to the error of a `Result`, e.g.
+/// `fs::read(path).context(format!("Failed to read {}", path))?`.
+pub trait ResultExt<T> {
+    fn context(self, context: impl Into<String>) -> Result<T>;
+
+    /// Like `context`, but only builds the message on error.
+    fn with_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T>;
+}
+
+impl<T, E: Into<BackupError>> ResultExt<T> for std::result::Result<T, E> {
+    fn context(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|e| e.into().context(context))
+    }
+
+    fn with_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T> {
+        self.map_err(|e| e.into().context(context()))
+    }
+}
+
+impl BackupError {
+    /// Wraps the error with a description of what was being done.
+    pub fn context(self, context: impl Into<String>) -> Self {
+        BackupError::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// The error underneath any added context.
+    pub fn root(&self) -> &BackupError {
+        match self {
+            BackupError::Context { source, .. } => source.root(),
+            error => error,
+        }
+    }
+
+    /// Whether trying the same operation again may succeed. Problems that
+    /// need someone to step in, such as a stopped container, bad credentials
+    /// or a full disk, are not retryable.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            BackupError::Context { source, .. } => source.is_retryable(),
+            BackupError::Docker(_)
+            | BackupError::Network(_)
+            | BackupError::Timeout(_)
+            | BackupError::Notification(_) => true,
+            BackupError::Io(e) => matches!(
+                e.kind(),
+                io::ErrorKind::Interrupted
+                    | io::ErrorKind::TimedOut
+                    | io::ErrorKind::WouldBlock
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+            ),
+            BackupError::Http(e) => {
+                e.is_timeout() || e.is_connect() || e.status().is_some_and(|s| s.is_server_error())
+            }
+            BackupError::Config(_)
+            | BackupError::ContainerNotRunning { .. }
+            | BackupError::ContainerNotFound { .. }
+            | BackupError::AuthFailed(_)
+            | BackupError::DiskFull(_)
+            | BackupError::FileSystem(_)
+            | BackupError::OdooApi(_)
+            | BackupError::Json(_)
+            | BackupError::Cancelled
+            | BackupError::Blackout { .. }
+            | BackupError::Stale(_)
+            | BackupError::VerificationFailed(_)
+            | BackupError::Unhealthy(_)
+            | BackupError::AlreadyRunning { .. }
+            | BackupError::Maintenance(_)
+            | BackupError::Unknown(_) => false,
+        }
+    }
+
+    /// Classifies a failed command run in `container` by its stderr, falling
+    /// back to `Docker` with `context` when nothing more specific matches.
+    pub fn from_docker_output(container: &str, context: &str, stderr: &str) -> Self {
+        let lower = stderr.to_lowercase();
+        if lower.contains("no such container") {
+            BackupError::ContainerNotFound {
+                name: container.to_string(),
+            }
+        } else if lower.contains("is not running") {
+            BackupError::ContainerNotRunning {
+                name: container.to_string(),
+            }
+        } else if lower.contains("no space left on device") {
+            BackupError::DiskFull(format!("{}: {}", context, stderr.trim()))
+        } else if lower.contains("timed out") {
+            BackupError::Timeout(format!("{}: {}", context, stderr.trim()))
+        } else {
+            BackupError::Docker(format!("{}: {}", context, stderr))
+        }
+    }
+
+    /// Wraps a host file system error, keeping a full disk recognizable.
+    pub fn from_fs(context: impl Into<String>, e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::StorageFull {
+            BackupError::DiskFull(format!("{}: {}", context.into(), e))
+        } else {
+            BackupError::Io(e).context(context)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io;
 
     #[test]
     fn test_backup_error_display() {
@@ -110,6 +262,99 @@ mod tests {
         assert!(debug_str.contains("Test error"));
     }
 
+    #[test]
+    fn test_is_retryable() {
+        assert!(BackupError::Network("connection reset".to_string()).is_retryable());
+        assert!(BackupError::Timeout("curl".to_string()).is_retryable());
+        assert!(!BackupError::AuthFailed("Access Denied".to_string()).is_retryable());
+        assert!(!BackupError::ContainerNotRunning {
+            name: "odoo".to_string()
+        }
+        .is_retryable());
+        assert!(!BackupError::DiskFull("copy".to_string()).is_retryable());
+        let damaged = BackupError::VerificationFailed("1 of 3 backups damaged".to_string());
+        assert!(!damaged.is_retryable());
+        assert_eq!(
+            damaged.to_string(),
+            "Verification failed: 1 of 3 backups damaged"
+        );
+        assert!(BackupError::from(io::Error::from(io::ErrorKind::TimedOut)).is_retryable());
+        assert!(
+            !BackupError::from(io::Error::from(io::ErrorKind::PermissionDenied)).is_retryable()
+        );
+    }
+
+    #[test]
+    fn test_from_docker_output() {
+        let error = BackupError::from_docker_output(
+            "odoo",
+            "Backup command failed",
+            "Error response from daemon: No such container: odoo",
+        );
+        assert!(matches!(error, BackupError::ContainerNotFound { ref name } if name == "odoo"));
+        assert_eq!(error.to_string(), "Container 'odoo' does not exist");
+
+        let error = BackupError::from_docker_output(
+            "odoo",
+            "Backup command failed",
+            "curl: (28) Operation timed out after 300000 milliseconds",
+        );
+        assert!(matches!(error, BackupError::Timeout(_)));
+
+        let error = BackupError::from_docker_output(
+            "odoo",
+            "Reading backup file failed",
+            "dd: error writing: No space left on device",
+        );
+        assert!(matches!(error, BackupError::DiskFull(_)));
+
+        let error = BackupError::from_docker_output("odoo", "Backup command failed", "exit 7");
+        assert!(
+            matches!(error, BackupError::Docker(ref msg) if msg == "Backup command failed: exit 7")
+        );
+    }
+
+    #[test]
+    fn test_from_fs() {
+        let error = BackupError::from_fs(
+            "Failed to write backup.zip".to_string(),
+            io::Error::from(io::ErrorKind::StorageFull),
+        );
+        assert!(matches!(error, BackupError::DiskFull(_)));
+        let error = BackupError::from_fs(
+            "Failed to write backup.zip".to_string(),
+            io::Error::from(io::ErrorKind::PermissionDenied),
+        );
+        assert!(
+            matches!(error.root(), BackupError::Io(e) if e.kind() == io::ErrorKind::PermissionDenied)
+        );
+    }
+
+    #[test]
+    fn test_context_chain() {
+        let result: std::result::Result<(), io::Error> =
+            Err(io::Error::new(io::ErrorKind::NotFound, "No such file"));
+        let error = result
+            .context("Failed to copy backup from container odoo")
+            .with_context(|| "Backup failed for Acme")
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Backup failed for Acme: Failed to copy backup from container odoo: IO error: No such file"
+        );
+        assert!(matches!(error.root(), BackupError::Io(_)));
+        let source = std::error::Error::source(&error).unwrap();
+        assert_eq!(
+            source.to_string(),
+            "Failed to copy backup from container odoo: IO error: No such file"
+        );
+        assert!(!error.is_retryable());
+        assert!(BackupError::Timeout("curl".to_string())
+            .context("Backup failed for Acme")
+            .is_retryable());
+    }
+
     #[test]
     fn test_result_type_alias() {
         fn returns_result() -> Result<String> {
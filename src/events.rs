@@ -0,0 +1,137 @@
+use serde::Serialize;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Something that happened while `BackupManager` worked on a client.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum BackupEvent {
+    Started {
+        client: String,
+    },
+    /// Bytes copied from the container to the host so far
+    Progress {
+        client: String,
+        bytes: u64,
+        total: u64,
+    },
+    /// Odoo finished writing the backup inside the container
+    ContainerDone {
+        client: String,
+        container_path: String,
+    },
+    /// The backup is on the host
+    Copied {
+        client: String,
+        host_path: String,
+    },
+    /// A backup file was stored in the archive
+    Uploaded {
+        client: String,
+        location: String,
+    },
+    Finished {
+        client: String,
+        path: String,
+    },
+    /// Nothing changed since `path` was backed up, so no backup was taken
+    Skipped {
+        client: String,
+        path: String,
+    },
+    Failed {
+        client: String,
+        error: String,
+    },
+}
+
+type Callback = Arc<dyn Fn(BackupEvent) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+enum Sink {
+    Channel(UnboundedSender<BackupEvent>),
+    Callback(Callback),
+}
+
+/// Delivers events to every registered channel and callback, in order.
+#[derive(Default)]
+pub struct EventBus {
+    sinks: Vec<Sink>,
+}
+
+impl EventBus {
+    pub fn add_channel(&mut self, sender: UnboundedSender<BackupEvent>) {
+        self.sinks.push(Sink::Channel(sender));
+    }
+
+    pub fn add_callback<F, Fut>(&mut self, callback: F)
+    where
+        F: Fn(BackupEvent) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.sinks.push(Sink::Callback(Arc::new(move |event| {
+            Box::pin(callback(event))
+        })));
+    }
+
+    pub async fn emit(&self, event: BackupEvent) {
+        for sink in &self.sinks {
+            match sink {
+                // A dropped receiver only means nobody is listening any more
+                Sink::Channel(sender) => {
+                    let _ = sender.send(event.clone());
+                }
+                Sink::Callback(callback) => callback(event.clone()).await,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_event_bus_delivers_to_channels_and_callbacks() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut bus = EventBus::default();
+        bus.add_channel(tx);
+        let sink = seen.clone();
+        bus.add_callback(move |event| {
+            let sink = sink.clone();
+            async move { sink.lock().unwrap().push(event) }
+        });
+
+        let event = BackupEvent::Started {
+            client: "Acme".to_string(),
+        };
+        bus.emit(event.clone()).await;
+        assert_eq!(rx.recv().await, Some(event.clone()));
+        assert_eq!(*seen.lock().unwrap(), vec![event]);
+
+        // Emitting to a closed channel is not an error
+        drop(rx);
+        bus.emit(BackupEvent::Failed {
+            client: "Acme".to_string(),
+            error: "boom".to_string(),
+        })
+        .await;
+    }
+
+    #[test]
+    fn test_backup_event_serialization() {
+        let event = BackupEvent::Progress {
+            client: "Acme".to_string(),
+            bytes: 10,
+            total: 20,
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"progress","client":"Acme","bytes":10,"total":20}"#
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use crate::catalog::{BackupStatus, CatalogEntry};
+use crate::error::Result;
+use crate::retention::RetentionTier;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// One backup run as exported by `catalog export`. Every field is always
+/// present, so CSV columns stay the same from one export to the next.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ExportRow {
+    pub id: String,
+    pub client: String,
+    pub database_name: String,
+    pub backup_format: String,
+    pub status: Option<BackupStatus>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub duration_secs: Option<i64>,
+    pub file_name: Option<String>,
+    pub size: Option<u64>,
+    pub sha256: Option<String>,
+    pub odoo_version: Option<String>,
+    pub label: Option<String>,
+    pub tier: Option<RetentionTier>,
+    pub archive_location: Option<String>,
+    pub archive_copy: Option<String>,
+    pub deduplicated: bool,
+    /// Whether the backup is still in the backup directory or dedupe store
+    pub stored: bool,
+}
+
+impl ExportRow {
+    pub fn new(entry: &CatalogEntry, stored: bool) -> Self {
+        Self {
+            id: entry.id.clone(),
+            client: entry.client.clone(),
+            database_name: entry.database_name.clone(),
+            backup_format: entry.backup_format.clone(),
+            status: Some(entry.status),
+            started_at: Some(entry.started_at),
+            finished_at: entry.finished_at,
+            duration_secs: entry.duration_secs(),
+            file_name: entry.file_name.clone(),
+            size: entry.size,
+            sha256: entry.sha256.clone(),
+            odoo_version: entry.odoo_version.clone(),
+            label: entry.label.clone(),
+            tier: entry.tier,
+            archive_location: entry.archive_location.clone(),
+            archive_copy: entry.archive_copy.clone(),
+            deduplicated: entry.deduplicated,
+            stored,
+        }
+    }
+}
+
+/// `rows` in `format`: a JSON array, or CSV with a header line.
+pub fn render(rows: &[ExportRow], format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::Json => Ok(format!("{}\n", serde_json::to_string_pretty(rows)?)),
+        ExportFormat::Csv => {
+            let mut csv = csv_line(object(&ExportRow::default())?.keys().map(String::as_str));
+            for row in rows {
+                let cells: Vec<_> = object(row)?.values().map(cell).collect();
+                csv.push_str(&csv_line(cells.iter().map(String::as_str)));
+            }
+            Ok(csv)
+        }
+    }
+}
+
+fn object(row: &ExportRow) -> Result<serde_json::Map<String, Value>> {
+    Ok(serde_json::to_value(row)?
+        .as_object()
+        .cloned()
+        .unwrap_or_default())
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Fields joined with commas, quoted when they contain a comma, a quote or
+/// a line break.
+fn csv_line<'a>(fields: impl Iterator<Item = &'a str>) -> String {
+    let fields: Vec<_> = fields
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect();
+    format!("{}\n", fields.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row() -> ExportRow {
+        ExportRow {
+            id: "run-1".to_string(),
+            client: "Acme, Inc.".to_string(),
+            database_name: "acme".to_string(),
+            backup_format: "zip".to_string(),
+            status: Some(BackupStatus::Success),
+            started_at: Some("2024-01-15T02:00:00Z".parse().unwrap()),
+            duration_secs: Some(90),
+            file_name: Some("backup_acme_20240115_020000.zip".to_string()),
+            size: Some(2048),
+            label: Some("before \"v17\"".to_string()),
+            tier: Some(RetentionTier::Weekly),
+            stored: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_csv() {
+        let csv = render(&[row()], ExportFormat::Csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            "id,client,database_name,backup_format,status,started_at,finished_at,\
+             duration_secs,file_name,size,sha256,odoo_version,label,tier,\
+             archive_location,archive_copy,deduplicated,stored"
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            "run-1,\"Acme, Inc.\",acme,zip,success,2024-01-15T02:00:00Z,,90,\
+             backup_acme_20240115_020000.zip,2048,,,\"before \"\"v17\"\"\",weekly,,,false,true"
+        );
+        assert_eq!(lines.next(), None);
+
+        let empty = render(&[], ExportFormat::Csv).unwrap();
+        assert_eq!(empty.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_render_json() {
+        let json = render(&[row()], ExportFormat::Json).unwrap();
+        let rows: Vec<Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(rows[0]["client"], "Acme, Inc.");
+        assert_eq!(rows[0]["finished_at"], Value::Null);
+        assert_eq!(rows[0]["tier"], "weekly");
+    }
+}
@@ -0,0 +1,123 @@
+use crate::catalog::Catalog;
+use crate::config::Config;
+use crate::error::Result;
+use crate::size;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+/// How recent the last good backup of a database is, against its
+/// `max_age_hours`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Freshness {
+    pub client: String,
+    /// Start of the last successful, or skipped as unchanged, backup
+    pub last_backup: Option<DateTime<Utc>>,
+    pub max_age_hours: u64,
+    pub stale: bool,
+}
+
+impl Freshness {
+    /// One line for reports and alerts, e.g. `Acme: last backup 2d 3h ago`.
+    pub fn describe(&self, now: DateTime<Utc>) -> String {
+        match self.last_backup {
+            Some(last) => format!(
+                "{}: last backup {} ago, expected within {}h",
+                self.client,
+                size::format_age((now - last).num_seconds()),
+                self.max_age_hours
+            ),
+            None => format!("{}: never backed up successfully", self.client),
+        }
+    }
+}
+
+/// Checks every enabled database that has a `max_age_hours` against the
+/// backup catalog in `backup_dir`.
+pub fn check(config: &Config, backup_dir: &str, now: DateTime<Utc>) -> Result<Vec<Freshness>> {
+    let last_backups = Catalog::load(backup_dir)?.last_backups();
+    Ok(config
+        .enabled_databases(None)
+        .into_iter()
+        .filter_map(|db| {
+            let max_age_hours = db.max_age_hours?;
+            let last_backup = last_backups.get(&db.name).copied();
+            let stale =
+                last_backup.is_none_or(|last| now - last > Duration::hours(max_age_hours as i64));
+            Some(Freshness {
+                client: db.name.clone(),
+                last_backup,
+                max_age_hours,
+                stale,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::{BackupStatus, CatalogEntry};
+    use crate::config::DatabaseConfig;
+    use tempfile::tempdir;
+
+    fn database(name: &str, max_age_hours: Option<u64>) -> DatabaseConfig {
+        DatabaseConfig {
+            name: name.to_string(),
+            database_name: name.to_lowercase(),
+            container_name: "odoo".to_string(),
+            master_password: "admin".to_string(),
+            retention_days: 7,
+            max_age_hours,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_check_freshness() {
+        let temp_dir = tempdir().unwrap();
+        let backup_dir = temp_dir.path().to_str().unwrap();
+        let now = Utc::now();
+
+        let mut disabled = database("Disabled", Some(1));
+        disabled.enabled = false;
+        let config = Config {
+            databases: vec![
+                database("Fresh", Some(26)),
+                database("Stale", Some(26)),
+                database("Never", Some(26)),
+                database("Unwatched", None),
+                disabled,
+            ],
+            ..Default::default()
+        };
+        for (client, hours_ago, status) in [
+            ("Fresh", 3, BackupStatus::Success),
+            ("Stale", 30, BackupStatus::Success),
+            // A recent failure does not make it fresh
+            ("Stale", 1, BackupStatus::Failed),
+        ] {
+            let mut entry = CatalogEntry::start(&database(client, None));
+            entry.started_at = now - Duration::hours(hours_ago);
+            entry.status = status;
+            Catalog::record(backup_dir, entry).unwrap();
+        }
+
+        let results = check(&config, backup_dir, now).unwrap();
+        let stale: Vec<_> = results
+            .iter()
+            .map(|r| (r.client.as_str(), r.stale))
+            .collect();
+        assert_eq!(
+            stale,
+            vec![("Fresh", false), ("Stale", true), ("Never", true)]
+        );
+        assert_eq!(
+            results[1].describe(now),
+            "Stale: last backup 1d 6h ago, expected within 26h"
+        );
+        assert_eq!(
+            results[2].describe(now),
+            "Never: never backed up successfully"
+        );
+    }
+}
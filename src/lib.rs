@@ -1,11 +1,67 @@
+pub mod audit;
 pub mod backup;
+pub mod bench;
+pub mod blackout;
+pub mod catalog;
+pub mod checksum;
 pub mod cli;
 pub mod config;
+pub mod convert;
+pub mod cron;
+pub mod daemon;
+pub mod dedupe;
+pub mod diff;
 pub mod docker;
+pub mod doctor;
+pub mod encryption;
 pub mod error;
+pub mod events;
+pub mod export;
+pub mod freshness;
+pub mod lifecycle;
+pub mod lint;
+pub mod maintenance;
+pub mod manifest;
+pub mod metrics;
+pub mod neutralize;
+pub mod notify;
+pub mod odoo_api;
+pub mod output;
+pub mod physical;
+pub mod pidfile;
+pub mod pre_upgrade;
+pub mod progress;
+pub mod proxy;
+pub mod queue;
+pub mod redact;
+pub mod repack;
+pub mod retention;
+pub mod runtime;
+pub mod scrub;
+pub mod secrets;
+pub mod shutdown;
+pub mod size;
+pub mod split;
+pub mod staging;
+pub mod statsd;
+pub mod status;
+pub mod storage;
+pub mod summary;
+pub mod telemetry;
+pub mod wizard;
 
-pub use backup::BackupManager;
-pub use cli::{Cli, Commands};
+pub use backup::{BackupFilter, BackupInfo, BackupManager, PruneReport, RestoreOptions};
+pub use cli::{CatalogCommands, Cli, Commands, ConfigCommands, SortKey};
 pub use config::{Config, DatabaseConfig};
 pub use docker::DockerManager;
-pub use error::{BackupError, Result};
+pub use error::{BackupError, Result, ResultExt};
+pub use events::BackupEvent;
+#[cfg(feature = "test-util")]
+pub use runtime::MockRuntime;
+pub use runtime::{ContainerRuntime, DockerRuntime, ExecOutput, Mount};
+pub use shutdown::Shutdown;
+pub use storage::{ByteStream, LocalStorage, ObjectInfo, StorageBackend};
+
+/// Re-exported so `StorageBackend` can be implemented without a direct
+/// dependency on the same `async-trait` version.
+pub use async_trait::async_trait;
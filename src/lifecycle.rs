@@ -0,0 +1,316 @@
+use crate::runtime::{ContainerRuntime, DockerRuntime};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+/// How long to wait before listening to `docker events` again after it
+/// exited.
+const RESTART_DELAY: std::time::Duration = std::time::Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerAction {
+    Started,
+    /// Asked to stop, e.g. by `docker stop` or an image upgrade; Odoo
+    /// still runs for a few moments
+    Stopping,
+    /// Stopped, killed or crashed
+    Stopped,
+}
+
+/// A container starting or stopping, as reported by `docker events`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerEvent {
+    pub container: String,
+    pub action: ContainerAction,
+    pub time: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawEvent {
+    #[serde(rename = "Action")]
+    action: String,
+    #[serde(rename = "Actor")]
+    actor: RawActor,
+    #[serde(default)]
+    time: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawActor {
+    #[serde(rename = "Attributes", default)]
+    attributes: BTreeMap<String, String>,
+}
+
+/// Signals of a `kill` event that end the container, as `docker stop`
+/// sends them; others such as `HUP` only ask it to reload.
+const STOP_SIGNALS: [&str; 6] = ["15", "9", "TERM", "KILL", "SIGTERM", "SIGKILL"];
+
+/// Parses a line of `docker events --format '{{json .}}'`. Docker reports
+/// every way a container ends, a crash included, as `die`, and the signal
+/// that asks it to stop first as `kill`.
+pub fn parse_event(line: &str) -> Option<ContainerEvent> {
+    let raw: RawEvent = serde_json::from_str(line).ok()?;
+    let action = match raw.action.as_str() {
+        "start" => ContainerAction::Started,
+        "kill"
+            if raw
+                .actor
+                .attributes
+                .get("signal")
+                .is_some_and(|signal| STOP_SIGNALS.contains(&signal.as_str())) =>
+        {
+            ContainerAction::Stopping
+        }
+        "die" => ContainerAction::Stopped,
+        _ => return None,
+    };
+    Some(ContainerEvent {
+        container: raw.actor.attributes.get("name")?.clone(),
+        action,
+        time: Utc
+            .timestamp_opt(raw.time, 0)
+            .single()
+            .unwrap_or_else(Utc::now),
+    })
+}
+
+/// Starts listening to `docker events` in the background, restarting it
+/// when it exits, until the receiver is dropped.
+pub fn watch() -> mpsc::UnboundedReceiver<ContainerEvent> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            match listen(&sender).await {
+                Ok(()) if sender.is_closed() => return,
+                Ok(()) => log::warn!("docker events exited, listening again shortly"),
+                Err(e) => log::warn!("Cannot listen to docker events: {}", e),
+            }
+            tokio::time::sleep(RESTART_DELAY).await;
+        }
+    });
+    receiver
+}
+
+async fn listen(sender: &mpsc::UnboundedSender<ContainerEvent>) -> std::io::Result<()> {
+    let mut child = Command::new("docker")
+        .args([
+            "events",
+            "--filter",
+            "type=container",
+            "--filter",
+            "event=start",
+            "--filter",
+            "event=kill",
+            "--filter",
+            "event=die",
+            "--format",
+            "{{json .}}",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let mut lines = BufReader::new(stdout).lines();
+    while let Some(line) = lines.next_line().await? {
+        if let Some(event) = parse_event(&line) {
+            if sender.send(event).is_err() {
+                return Ok(());
+            }
+        }
+    }
+    child.wait().await?;
+    Ok(())
+}
+
+/// Which of the watched containers are down, and since when.
+#[derive(Debug, Default)]
+pub struct ContainerStates {
+    watched: BTreeSet<String>,
+    down_since: BTreeMap<String, DateTime<Utc>>,
+    /// Containers alerted about, until they start again
+    alerted: BTreeSet<String>,
+}
+
+impl ContainerStates {
+    /// States of `containers`, of which `running` were up at `now`.
+    pub fn new(containers: BTreeSet<String>, running: &[String], now: DateTime<Utc>) -> Self {
+        let down_since = containers
+            .iter()
+            .filter(|container| !running.contains(container))
+            .map(|container| (container.clone(), now))
+            .collect();
+        Self {
+            watched: containers,
+            down_since,
+            alerted: BTreeSet::new(),
+        }
+    }
+
+    /// States of `containers`, asking Docker which ones are running. When
+    /// Docker cannot tell, all of them are taken to be up.
+    pub fn load(containers: BTreeSet<String>) -> Self {
+        let running = DockerRuntime.running_containers().unwrap_or_else(|e| {
+            log::warn!("Cannot list running containers: {}", e);
+            containers.iter().cloned().collect()
+        });
+        Self::new(containers, &running, Utc::now())
+    }
+
+    /// Watches `containers` from now on, e.g. after a reload. New ones are
+    /// taken to be up until an event says otherwise.
+    pub fn set_watched(&mut self, containers: BTreeSet<String>) {
+        self.down_since
+            .retain(|container, _| containers.contains(container));
+        self.alerted
+            .retain(|container| containers.contains(container));
+        self.watched = containers;
+    }
+
+    /// Records `event`, returning whether it brought a container that was
+    /// down back up.
+    pub fn apply(&mut self, event: &ContainerEvent) -> bool {
+        if !self.watched.contains(&event.container) {
+            return false;
+        }
+        match event.action {
+            ContainerAction::Started => {
+                self.alerted.remove(&event.container);
+                self.down_since.remove(&event.container).is_some()
+            }
+            ContainerAction::Stopping => false,
+            ContainerAction::Stopped => {
+                self.down_since
+                    .entry(event.container.clone())
+                    .or_insert(event.time);
+                false
+            }
+        }
+    }
+
+    pub fn is_down(&self, container: &str) -> bool {
+        self.down_since.contains_key(container)
+    }
+
+    /// Containers down for longer than `threshold` at `now` that were not
+    /// alerted about yet, with when they went down. They count as alerted
+    /// from now on.
+    pub fn newly_overdue(
+        &mut self,
+        threshold: Duration,
+        now: DateTime<Utc>,
+    ) -> Vec<(String, DateTime<Utc>)> {
+        let overdue: Vec<_> = self
+            .down_since
+            .iter()
+            .filter(|(container, since)| {
+                now - **since >= threshold && !self.alerted.contains(*container)
+            })
+            .map(|(container, since)| (container.clone(), *since))
+            .collect();
+        self.alerted
+            .extend(overdue.iter().map(|(container, _)| container.clone()));
+        overdue
+    }
+
+    /// When the next container not alerted about yet passes `threshold`.
+    pub fn next_overdue(&self, threshold: Duration) -> Option<DateTime<Utc>> {
+        self.down_since
+            .iter()
+            .filter(|(container, _)| !self.alerted.contains(*container))
+            .map(|(_, since)| *since + threshold)
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(container: &str, action: ContainerAction, time: DateTime<Utc>) -> ContainerEvent {
+        ContainerEvent {
+            container: container.to_string(),
+            action,
+            time,
+        }
+    }
+
+    #[test]
+    fn test_parse_event() {
+        let line = r#"{"status":"die","id":"4f2a","from":"odoo:17","Type":"container","Action":"die",
+            "Actor":{"ID":"4f2a","Attributes":{"exitCode":"137","image":"odoo:17","name":"odoo-acme"}},
+            "scope":"local","time":1700000000,"timeNano":1700000000123456789}"#;
+        assert_eq!(
+            parse_event(line),
+            Some(event(
+                "odoo-acme",
+                ContainerAction::Stopped,
+                Utc.timestamp_opt(1700000000, 0).unwrap()
+            ))
+        );
+        assert_eq!(
+            parse_event(r#"{"Action":"start","Actor":{"Attributes":{"name":"odoo"}},"time":0}"#)
+                .unwrap()
+                .action,
+            ContainerAction::Started
+        );
+        assert_eq!(
+            parse_event(r#"{"Action":"exec_start: sh","Actor":{"Attributes":{"name":"odoo"}}}"#),
+            None
+        );
+        let kill = |signal: &str| {
+            parse_event(&format!(
+                r#"{{"Action":"kill","Actor":{{"Attributes":{{"name":"odoo","signal":"{}"}}}}}}"#,
+                signal
+            ))
+            .map(|event| event.action)
+        };
+        assert_eq!(kill("15"), Some(ContainerAction::Stopping));
+        assert_eq!(kill("SIGKILL"), Some(ContainerAction::Stopping));
+        assert_eq!(kill("1"), None);
+        assert_eq!(parse_event("not json"), None);
+    }
+
+    #[test]
+    fn test_container_states() {
+        let now = Utc::now();
+        let containers = BTreeSet::from(["acme".to_string(), "globex".to_string()]);
+        let mut states = ContainerStates::new(containers, &["acme".to_string()], now);
+        assert!(!states.is_down("acme"));
+        assert!(states.is_down("globex"));
+
+        // Unwatched containers are ignored
+        assert!(!states.apply(&event("other", ContainerAction::Stopped, now)));
+        assert!(!states.is_down("other"));
+
+        let later = now + Duration::minutes(10);
+        states.apply(&event("acme", ContainerAction::Stopped, later));
+        assert_eq!(
+            states.next_overdue(Duration::minutes(15)),
+            Some(now + Duration::minutes(15))
+        );
+        assert_eq!(
+            states.newly_overdue(Duration::minutes(15), later + Duration::minutes(10)),
+            vec![("globex".to_string(), now)]
+        );
+        // Alerted once only
+        assert!(states
+            .newly_overdue(Duration::minutes(15), later + Duration::minutes(10))
+            .is_empty());
+        assert_eq!(
+            states.next_overdue(Duration::minutes(15)),
+            Some(later + Duration::minutes(15))
+        );
+
+        assert!(states.apply(&event("globex", ContainerAction::Started, later)));
+        assert!(!states.is_down("globex"));
+        assert!(!states.apply(&event("globex", ContainerAction::Started, later)));
+
+        states.set_watched(BTreeSet::from(["globex".to_string()]));
+        assert!(!states.is_down("acme"));
+    }
+}
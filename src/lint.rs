@@ -0,0 +1,281 @@
+use crate::config::Config;
+use crate::secrets::SecretSource;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Retention below this many days is flagged as suspiciously short.
+pub const MIN_RETENTION_DAYS: u32 = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A suspicious setting that validation lets through.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    /// Database the finding is about, if any
+    pub database: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.database {
+            Some(database) => write!(f, "{}: {}: {}", self.severity, database, self.message),
+            None => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+/// Checks a valid configuration for settings that are likely mistakes.
+/// Master passwords are checked as written, before secret references are
+/// resolved; plain-text ones are fine in an `encrypted` file.
+pub fn lint(config: &Config, encrypted: bool) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut finding = |severity, database: &str, message: String| {
+        findings.push(Finding {
+            severity,
+            database: Some(database.to_string()),
+            message,
+        })
+    };
+
+    let mut names: HashMap<&str, usize> = HashMap::new();
+    let mut targets: HashMap<(&str, &str), &str> = HashMap::new();
+    for db in &config.databases {
+        *names.entry(&db.name).or_default() += 1;
+        if names[db.name.as_str()] == 2 {
+            finding(
+                Severity::Error,
+                &db.name,
+                "the name is used by more than one database".to_string(),
+            );
+        }
+        if let Some(first) = targets.insert((&db.container_name, &db.database_name), &db.name) {
+            finding(
+                Severity::Error,
+                &db.name,
+                format!(
+                    "backs up {} in {} like {}",
+                    db.database_name, db.container_name, first
+                ),
+            );
+        }
+
+        if db.retention_days < MIN_RETENTION_DAYS {
+            finding(
+                Severity::Warning,
+                &db.name,
+                format!(
+                    "retention_days is {}, under {} days",
+                    db.retention_days, MIN_RETENTION_DAYS
+                ),
+            );
+        }
+        match SecretSource::parse(&db.master_password) {
+            SecretSource::Literal(password) if password == "admin" => finding(
+                Severity::Error,
+                &db.name,
+                "master_password is Odoo's default \"admin\"".to_string(),
+            ),
+            SecretSource::Literal(password) if !encrypted && !password.is_empty() => finding(
+                Severity::Warning,
+                &db.name,
+                "master_password is stored in plain text; use env:, aws-sm: or ssm:, \
+                 or encrypt the file"
+                    .to_string(),
+            ),
+            _ => {}
+        }
+        if let Some(host) = remote_http_host(&db.url) {
+            finding(
+                Severity::Warning,
+                &db.name,
+                format!(
+                    "url sends the master password unencrypted to {}; use https://",
+                    host
+                ),
+            );
+        }
+        if db.tls.as_ref().is_some_and(|tls| tls.insecure_skip_verify) {
+            finding(
+                Severity::Warning,
+                &db.name,
+                "tls.insecure_skip_verify accepts any server certificate; set tls.ca_file \
+                 to the internal CA instead"
+                    .to_string(),
+            );
+        }
+        let tmp_dir = db.container_tmp_dir();
+        if tmp_dir == "/tmp" || tmp_dir.starts_with("/tmp/") {
+            let field = if db.container_tmp_dir.is_some() {
+                "container_tmp_dir"
+            } else {
+                "output_path"
+            };
+            finding(
+                Severity::Warning,
+                &db.name,
+                format!(
+                    "{} {} is under /tmp, which may be cleared or size-limited",
+                    field, tmp_dir
+                ),
+            );
+        }
+    }
+
+    if config.databases.iter().all(|db| !db.enabled) {
+        findings.push(Finding {
+            severity: Severity::Warning,
+            database: None,
+            message: "every database is disabled".to_string(),
+        });
+    }
+    findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+    findings
+}
+
+/// The host of a plain `http://` URL that leaves the machine or container
+/// network. Loopback addresses and single-label names, such as a Compose
+/// service, count as local.
+fn remote_http_host(url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(url).ok()?;
+    if url.scheme() != "http" {
+        return None;
+    }
+    let host = url.host_str()?;
+    let local = host == "localhost"
+        || host
+            .trim_matches(['[', ']'])
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+        || !host.contains('.') && !host.contains(':');
+    (!local).then(|| host.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DatabaseConfig;
+
+    fn database(name: &str) -> DatabaseConfig {
+        DatabaseConfig {
+            name: name.to_string(),
+            database_name: name.to_lowercase(),
+            container_name: "odoo".to_string(),
+            master_password: "env:ODOO_MASTER".to_string(),
+            output_path: "/var/lib/odoo/backups".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn messages(config: &Config) -> Vec<String> {
+        lint(config, false).iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn test_clean_config() {
+        let config = Config {
+            databases: vec![database("Acme"), database("Globex")],
+            ..Default::default()
+        };
+        assert!(lint(&config, false).is_empty());
+    }
+
+    #[test]
+    fn test_lint_findings() {
+        let mut risky = database("Risky");
+        risky.retention_days = 3;
+        risky.master_password = "admin".to_string();
+        risky.url = "http://odoo.example.com:8069".to_string();
+        risky.output_path = "/tmp/backups".to_string();
+        let mut copy = database("Copy");
+        copy.database_name = "acme".to_string();
+        let mut plain = database("Plain");
+        plain.master_password = "s3cret".to_string();
+        let config = Config {
+            databases: vec![database("Acme"), risky, copy, plain],
+            ..Default::default()
+        };
+        assert_eq!(lint(&config, true).len(), 5);
+
+        assert_eq!(
+            messages(&config),
+            vec![
+                "error: Risky: master_password is Odoo's default \"admin\"",
+                "error: Copy: backs up acme in odoo like Acme",
+                "warning: Risky: retention_days is 3, under 7 days",
+                "warning: Risky: url sends the master password unencrypted to odoo.example.com; use https://",
+                "warning: Risky: output_path /tmp/backups is under /tmp, which may be cleared or size-limited",
+                "warning: Plain: master_password is stored in plain text; use env:, aws-sm: or ssm:, or encrypt the file",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_names_and_all_disabled() {
+        let mut first = database("Acme");
+        first.enabled = false;
+        let mut second = database("Acme");
+        second.database_name = "acme_2".to_string();
+        second.enabled = false;
+        let config = Config {
+            databases: vec![first, second],
+            ..Default::default()
+        };
+        assert_eq!(
+            messages(&config),
+            vec![
+                "error: Acme: the name is used by more than one database",
+                "warning: every database is disabled",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_insecure_tls() {
+        let mut insecure = database("Acme");
+        insecure.url = "https://odoo.internal".to_string();
+        insecure.tls = Some(crate::config::TlsConfig {
+            insecure_skip_verify: true,
+            ..Default::default()
+        });
+        let config = Config {
+            databases: vec![insecure],
+            ..Default::default()
+        };
+        assert_eq!(
+            messages(&config),
+            vec![
+                "warning: Acme: tls.insecure_skip_verify accepts any server certificate; \
+                 set tls.ca_file to the internal CA instead"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remote_http_host() {
+        assert_eq!(remote_http_host("http://localhost:8069"), None);
+        assert_eq!(remote_http_host("http://127.0.0.1:8069"), None);
+        assert_eq!(remote_http_host("http://[::1]:8069"), None);
+        assert_eq!(remote_http_host("http://odoo:8069"), None);
+        assert_eq!(remote_http_host("https://odoo.example.com"), None);
+        assert_eq!(
+            remote_http_host("http://10.0.0.5:8069").as_deref(),
+            Some("10.0.0.5")
+        );
+    }
+}
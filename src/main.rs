@@ -1,59 +1,262 @@
+use chrono::{DateTime, Utc};
 use clap::Parser;
+use indicatif::{ProgressBar, ProgressStyle};
 use log::{error, info, warn};
+use std::collections::BTreeMap;
 use std::env;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-mod backup;
-mod cli;
-mod config;
-mod docker;
-mod error;
-
-use backup::BackupManager;
-use cli::{Cli, Commands};
-use config::Config;
-use docker::DockerManager;
-use error::Result;
+use odoo_backup_service::audit::{self, AuditOperation, AuditRecord};
+use odoo_backup_service::backup::{
+    BackupFilter, BackupInfo, BackupManager, CatalogCheck, CleanedClient, PruneBackend,
+    PruneReason, PruneReport, RestoreOptions, RestorePart, VerifyStatus,
+};
+use odoo_backup_service::catalog::{BackupStatus, Catalog, CatalogEntry};
+use odoo_backup_service::cli::{
+    BenchCommands, CatalogCommands, Cli, Commands, ConfigCommands, ExistingBackupsArgs, SortKey,
+};
+use odoo_backup_service::config::{Config, DatabaseListing, LiveListing};
+use odoo_backup_service::convert::ExtractPart;
+use odoo_backup_service::daemon::ScheduleSnapshot;
+use odoo_backup_service::docker::DockerManager;
+use odoo_backup_service::error::{BackupError, Result};
+use odoo_backup_service::output::{self, Color, OutputFormat, Table};
+use odoo_backup_service::pidfile::PidFile;
+use odoo_backup_service::progress::{JsonProgress, ProgressMode};
+use odoo_backup_service::queue::QueueSnapshot;
+use odoo_backup_service::shutdown::{self, Shutdown};
+use odoo_backup_service::status::{self, DatabaseStatus, HttpHealth, StatusReport};
+use odoo_backup_service::wizard::{self, Prompter};
+use odoo_backup_service::{
+    bench, config, convert, daemon, diff, doctor, error, export, freshness, lint, metrics, notify,
+    physical, pre_upgrade, redact, size, summary, telemetry,
+};
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
 
     // Initialize logging
-    let log_level = if cli.verbose { "debug" } else { "info" };
+    let log_level = if cli.verbose {
+        "debug"
+    } else if cli.quiet {
+        "error"
+    } else {
+        "info"
+    };
     env::set_var("RUST_LOG", log_level);
-    env_logger::init();
+    env::set_var("RUST_LOG_STYLE", cli.color.log_style());
+    // The default format, with secrets masked in every message
+    env_logger::Builder::from_default_env()
+        .format(|buf, record| {
+            writeln!(
+                buf,
+                "[{} {:<5} {}] {}",
+                buf.timestamp(),
+                buf.default_styled_level(record.level()),
+                record.target(),
+                redact::redact(&record.args().to_string())
+            )
+        })
+        .init();
 
     info!("Starting Odoo Backup Service");
 
     if let Err(e) = run(cli).await {
+        if matches!(e.root(), BackupError::Cancelled) {
+            warn!("Stopped: {}", e);
+            std::process::exit(shutdown::EXIT_ABORTED);
+        }
         error!("Application error: {}", e);
         std::process::exit(1);
     }
 }
 
 async fn run(cli: Cli) -> Result<()> {
+    // Config management works on the raw file and must not require it to load
+    if let Commands::Config { action } = &cli.command {
+        return run_config_command(&cli, action).await;
+    }
+    if let Commands::Init { path, force } = &cli.command {
+        let path = path.as_deref().unwrap_or(&cli.config);
+        config::write_starter_config(path, *force)?;
+        std::fs::create_dir_all(&cli.backup_dir)
+            .map_err(|e| BackupError::from_fs(format!("Failed to create {}", cli.backup_dir), e))?;
+        println!("Wrote a starter configuration to {}", path);
+        println!("Created backup directory {}", cli.backup_dir);
+        println!("Edit the example database, then run `doctor` to check the setup");
+        return Ok(());
+    }
+    // Conversion only works on files and needs no configuration
+    if let Commands::Convert {
+        file,
+        to,
+        filestore,
+        out,
+    } = &cli.command
+    {
+        let mut input = PathBuf::from(file);
+        if !input.exists() {
+            input = Path::new(&cli.backup_dir).join(file);
+        }
+        let output = out
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| convert::default_output(&input, *to));
+        convert::convert(&input, *to, filestore.as_deref().map(Path::new), &output)?;
+        println!("Converted {} to {}", input.display(), output.display());
+        return Ok(());
+    }
+    if let Commands::Extract { file, part, dest } = &cli.command {
+        let mut input = PathBuf::from(file);
+        if !input.exists() {
+            input = Path::new(&cli.backup_dir).join(file);
+        }
+        let parts = if part.is_empty() {
+            &ExtractPart::ALL[..]
+        } else {
+            &part[..]
+        };
+        let progress = ProgressBar::new(0).with_style(
+            ProgressStyle::with_template("{bar:40} {bytes}/{total_bytes} {eta}")
+                .expect("valid progress template"),
+        );
+        let files = convert::extract(&input, parts, Path::new(dest), |done, total| {
+            progress.set_length(total);
+            progress.set_position(done);
+        })?;
+        progress.finish_and_clear();
+        println!(
+            "Extracted {} files from {} to {}",
+            files,
+            input.display(),
+            dest
+        );
+        return Ok(());
+    }
+    if let Commands::Bench {
+        action:
+            BenchCommands::Compress {
+                file,
+                sample,
+                min_speed,
+                json,
+            },
+    } = &cli.command
+    {
+        let mut input = PathBuf::from(file);
+        if !input.exists() {
+            input = Path::new(&cli.backup_dir).join(file);
+        }
+        let bench = bench::compress(&input, *sample, *min_speed)?;
+        if cli.output.or_json(*json) == OutputFormat::Json {
+            println!("{}", serde_json::to_string_pretty(&bench)?);
+        } else {
+            print!("{}", bench.render());
+        }
+        return Ok(());
+    }
+    if let Commands::Discover {
+        url,
+        container,
+        write,
+    } = &cli.command
+    {
+        return run_discover(&cli, url, container, *write).await;
+    }
+    // Reports a configuration that does not load like any other failed check
+    if let Commands::Doctor = &cli.command {
+        return run_doctor(&cli).await;
+    }
+
     // Load configuration
-    let config = Config::from_file(&cli.config)?;
+    let config = Config::from_file_with_key(&cli.config, cli.config_key_file.as_deref())?;
     info!(
         "Loaded configuration with {} databases",
         config.databases.len()
     );
 
-    let backup_manager = BackupManager::new(cli.backup_dir.clone());
+    let _telemetry = telemetry::init(config.otlp_endpoint.as_deref())?;
+
+    let shutdown = Shutdown::new();
+    shutdown.listen_for_signals();
+    let mut backup_manager = BackupManager::new(cli.backup_dir.clone())
+        .with_shutdown(shutdown.clone())
+        .with_max_parallel(config.parallel_backups.unwrap_or(1))
+        .with_notifications(config.notifications.clone());
+    if let Some(factor) = config.slow_backup_factor {
+        backup_manager = backup_manager.with_slow_backup_factor(factor);
+    }
+    if let Some(cooldown) = config.instance_cooldown() {
+        backup_manager = backup_manager.with_instance_cooldown(cooldown);
+    }
+    if let Some(archive) = &config.archive {
+        backup_manager = backup_manager.with_archive(archive.build());
+    }
+    if let Some(statsd) = &config.statsd {
+        backup_manager = backup_manager.with_statsd(statsd.build());
+    }
+    if let Some(dedupe) = &config.dedupe {
+        backup_manager = backup_manager.with_dedupe(dedupe.build(&config.databases));
+    }
+    if cli.progress == ProgressMode::Json {
+        let progress = JsonProgress::default();
+        backup_manager = backup_manager.with_event_callback(move |event| {
+            progress.report(&event);
+            std::future::ready(())
+        });
+    }
     let docker_manager = DockerManager::new();
 
     match cli.command {
-        Commands::Backup { client } => {
+        Commands::Backup {
+            client,
+            tag,
+            ask_password,
+            force,
+            label,
+        } => {
+            let backup_manager = Arc::new(match label {
+                Some(label) => backup_manager.with_label(label),
+                None => backup_manager,
+            });
             if let Some(client_name) = client {
                 // Backup specific client
                 if let Some(db_config) = config.get_database(&client_name) {
+                    if !db_config.enabled {
+                        warn!("Client '{}' is disabled, backing up anyway", client_name);
+                    }
+                    if let Some(until) = db_config.blackout_end(Utc::now()) {
+                        let error = error::BackupError::Blackout {
+                            client: client_name.clone(),
+                            until: db_config.format_local(until),
+                        };
+                        if !force {
+                            warn!("{}, use --force to back up anyway", error);
+                            return Err(error);
+                        }
+                        warn!("{}, backing up anyway", error);
+                    }
+                    let mut db_config = db_config.clone();
+                    db_config.resolve_master_password(ask_password)?;
+
                     info!("Backing up client: {}", client_name);
-                    match backup_manager.backup_database(db_config).await {
+                    let started_at = Utc::now();
+                    let result = backup_manager.backup_database(&db_config).await;
+                    summary::write_last_run(&cli.backup_dir, &[db_config], started_at);
+                    metrics::update(&config, &cli.backup_dir);
+                    match result {
                         Ok(backup_path) => {
-                            println!("Backup completed successfully: {}", backup_path);
+                            if !cli.quiet {
+                                let done = Color::Green
+                                    .paint_if(cli.color.enabled(), "Backup completed successfully");
+                                println!("{}: {}", done, backup_path);
+                            }
                         }
                         Err(e) => {
-                            error!("Backup failed for {}: {}", client_name, e);
+                            error!("{}", e);
                             return Err(e);
                         }
                     }
@@ -65,94 +268,1238 @@ async fn run(cli: Cli) -> Result<()> {
                     )));
                 }
             } else {
-                // Backup all clients
-                info!("Backing up all configured databases");
-                let results = backup_manager
-                    .backup_all_databases(&config.databases)
-                    .await?;
+                // Backup all enabled clients, optionally restricted to a tag
+                let mut databases: Vec<_> = config
+                    .enabled_databases(tag.as_deref())
+                    .into_iter()
+                    .cloned()
+                    .collect();
 
-                if results.is_empty() {
-                    warn!("No backups were completed successfully");
+                if let Some(tag) = &tag {
+                    if databases.is_empty() {
+                        error!("No enabled databases tagged '{}' in configuration", tag);
+                        return Err(error::BackupError::Config(format!(
+                            "No enabled databases tagged '{}'",
+                            tag
+                        )));
+                    }
+                    info!("Backing up {} databases tagged '{}'", databases.len(), tag);
                 } else {
-                    println!("Completed {} backups:", results.len());
-                    for (client_name, backup_path) in results {
-                        println!("  - {}: {}", client_name, backup_path);
+                    info!("Backing up all enabled databases");
+                }
+
+                let skipped = config.databases.iter().filter(|db| !db.enabled).count();
+                if skipped > 0 {
+                    info!("Skipping {} disabled databases", skipped);
+                }
+
+                let now = Utc::now();
+                databases.retain(|db| match db.blackout_end(now) {
+                    Some(until) if force => {
+                        warn!(
+                            "{} is in a blackout window until {}, backing up anyway",
+                            db.name,
+                            db.format_local(until)
+                        );
+                        true
                     }
+                    Some(until) => {
+                        warn!(
+                            "Skipping {}: in a blackout window until {}, use --force to back it up anyway",
+                            db.name,
+                            db.format_local(until)
+                        );
+                        false
+                    }
+                    None => true,
+                });
+
+                // Ask for every password up front so the run is not interrupted later
+                for db_config in &mut databases {
+                    db_config.resolve_master_password(ask_password)?;
+                }
+
+                let started_at = Utc::now();
+                let results = backup_manager.backup_all_databases(&databases).await;
+                summary::write_last_run(&cli.backup_dir, &databases, started_at);
+                metrics::update(&config, &cli.backup_dir);
+                let results = results?;
+                if !cli.quiet {
+                    print_backup_results(&results, cli.color.enabled());
                 }
             }
         }
-        Commands::List => {
-            println!("Configured databases:");
-            for (i, db) in config.databases.iter().enumerate() {
-                println!("  {}. {} ({})", i + 1, db.name, db.database_name);
-                println!("     Container: {}", db.container_name);
-                println!("     URL: {}", db.url);
-                println!("     Format: {}", db.backup_format);
-                println!();
+        Commands::List { live: false } => {
+            let databases: Vec<_> = config.databases.iter().map(DatabaseListing::from).collect();
+            print!(
+                "{}",
+                output::render(cli.output, &databases, || database_table(&databases))?
+            );
+        }
+        Commands::List { live: true } => {
+            let mut served = BTreeMap::new();
+            for db in &config.databases {
+                let server = (db.container_name.clone(), db.url.clone());
+                if served.contains_key(&server) {
+                    continue;
+                }
+                match docker_manager.list_databases(db).await {
+                    Ok(names) => {
+                        served.insert(server, names);
+                    }
+                    Err(e) => warn!(
+                        "Could not list the databases of {} in {}: {}",
+                        db.url, db.container_name, e
+                    ),
+                }
+            }
+            let listing = LiveListing::new(&config.databases, &served);
+            print!(
+                "{}",
+                output::render(cli.output, &listing, || {
+                    database_table(&listing.databases).colored(cli.color.enabled())
+                })?
+            );
+            if cli.output == OutputFormat::Table && !listing.unconfigured.is_empty() {
+                println!("\nServed but not configured for backup:");
+                for db in &listing.unconfigured {
+                    println!(
+                        "  - {} ({}, {})",
+                        db.database_name, db.container_name, db.url
+                    );
+                }
             }
         }
-        Commands::Status => {
-            println!("Docker container status:");
-            let containers = docker_manager.list_containers().await?;
-
+        Commands::Status { json, exit_code } => {
+            let catalog = Catalog::load(&cli.backup_dir)?;
+            let schedule = ScheduleSnapshot::load(&cli.backup_dir)?;
+            let freshness = freshness::check(&config, &cli.backup_dir, Utc::now())?;
+            let mut databases = Vec::new();
             for db in &config.databases {
                 let is_running = docker_manager
                     .is_container_running(&db.container_name)
                     .await?;
-                let status = if is_running { "Running" } else { "Stopped" };
-                println!("  - {} ({}) - {}", db.name, db.container_name, status);
+                let mut db_status =
+                    DatabaseStatus::new(db, is_running, &catalog, schedule.as_ref(), &freshness);
+                if is_running {
+                    let probe = docker_manager
+                        .probe_http(db, status::HTTP_CHECK_TIMEOUT_SECS)
+                        .await;
+                    db_status.http = Some(HttpHealth::from_probe(probe));
+                }
+                databases.push(db_status);
+            }
+            let report = StatusReport {
+                databases,
+                running_containers: docker_manager.list_containers().await?,
+                queue: QueueSnapshot::load(&cli.backup_dir)?,
+            };
+
+            let format = cli.output.or_json(json);
+            print!(
+                "{}",
+                output::render(format, &report, || {
+                    status_table(&report).colored(cli.color.enabled())
+                })?
+            );
+            if format == OutputFormat::Table {
+                print_status_details(&report);
+            }
+
+            let problems = report.problems();
+            if exit_code && !problems.is_empty() {
+                return Err(BackupError::Unhealthy(problems.join("; ")));
+            }
+        }
+        Commands::Clean {
+            client,
+            tag,
+            container,
+        } => {
+            let databases = match &client {
+                Some(client_name) => match config.get_database(client_name) {
+                    Some(db_config) => {
+                        info!("Cleaning old backups for client: {}", client_name);
+                        vec![db_config]
+                    }
+                    None => {
+                        error!(" Client '{}' not found in configuration", client_name);
+                        return Err(error::BackupError::Config(format!(
+                            "Client '{}' not found",
+                            client_name
+                        )));
+                    }
+                },
+                // Clean all enabled clients, optionally restricted to a tag
+                None => {
+                    match &tag {
+                        Some(tag) => info!("Cleaning old backups for databases tagged '{}'", tag),
+                        None => info!("Cleaning old backups for all enabled databases"),
+                    }
+                    config.enabled_databases(tag.as_deref())
+                }
+            };
+
+            let mut cleaned = Vec::new();
+            for db_config in databases {
+                let removed = backup_manager.cleanup_old_backups(db_config).await?;
+                let orphaned_files = if container {
+                    match backup_manager.remove_orphaned_files(db_config).await {
+                        Ok(removed) => Some(removed),
+                        Err(e) if client.is_some() => return Err(e),
+                        // A stopped container must not keep the others from being cleaned
+                        Err(e) => {
+                            warn!("{}: {}", db_config.name, e);
+                            None
+                        }
+                    }
+                } else {
+                    None
+                };
+                cleaned.push(CleanedClient {
+                    client: db_config.name.clone(),
+                    removed,
+                    orphaned_files,
+                });
+            }
+
+            // JSON was asked for explicitly, so it is printed even when quiet
+            if !cli.quiet || cli.output == OutputFormat::Json {
+                print!(
+                    "{}",
+                    output::render(cli.output, &cleaned, || clean_table(&cleaned, container))?
+                );
+            }
+            if cli.output == OutputFormat::Table && !cli.quiet {
+                let total: u32 = cleaned.iter().map(|c| c.removed).sum();
+                println!("\nCleaned up {} old backup files total", total);
+            }
+        }
+        Commands::Catalog {
+            action: CatalogCommands::Gc { dry_run, json },
+        } => {
+            let check = backup_manager.check_catalog(&config.databases, dry_run)?;
+            if cli.output.or_json(json) == OutputFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&check)?);
+            } else {
+                print_catalog_check(&check);
+            }
+        }
+        Commands::Catalog {
+            action: CatalogCommands::Export { format, since },
+        } => {
+            let rows = backup_manager.export_catalog(since).await?;
+            print!("{}", export::render(&rows, format)?);
+        }
+        Commands::Prune { dry_run, json } => {
+            let databases: Vec<_> = config
+                .enabled_databases(None)
+                .into_iter()
+                .cloned()
+                .collect();
+            let report = backup_manager.prune(&databases, dry_run).await?;
+            if cli.output.or_json(json) == OutputFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else if !cli.quiet {
+                print_prune_report(&report);
+            }
+        }
+        Commands::ListBackups {
+            database,
+            json,
+            verify,
+            since,
+            until,
+            format,
+            min_size,
+            label,
+            sort,
+            limit,
+        } => {
+            let filter = BackupFilter {
+                since,
+                until,
+                format,
+                min_size,
+                label,
+            };
+            let mut backups: Vec<_> = backup_manager
+                .list_backups(database.as_deref(), verify)
+                .await?
+                .into_iter()
+                .filter(|backup| filter.matches(backup))
+                .collect();
+
+            match sort {
+                SortKey::Name => {}
+                SortKey::Date => backups.sort_by_key(|b| std::cmp::Reverse(b.modified)),
+                SortKey::Size => backups.sort_by_key(|b| std::cmp::Reverse(b.size)),
+            }
+            if let Some(limit) = limit {
+                backups.truncate(limit);
             }
 
-            if containers.is_empty() {
-                println!("  No containers are currently running");
+            let format = cli.output.or_json(json);
+            if format == OutputFormat::Table && backups.is_empty() {
+                println!("No backup files found");
             } else {
-                println!("\nAll running containers:");
-                for container in containers {
-                    println!("  - {}", container);
+                print!(
+                    "{}",
+                    output::render(format, &backups, || backup_table(&backups))?
+                );
+                if format == OutputFormat::Table {
+                    let total: u64 = backups.iter().map(|b| b.size).sum();
+                    println!(
+                        "\n{} backups, {} total",
+                        backups.len(),
+                        size::format_size(total)
+                    );
                 }
             }
         }
-        Commands::Clean { client } => {
-            if let Some(client_name) = client {
-                // Clean specific client
-                if let Some(db_config) = config.get_database(&client_name) {
-                    info!("Cleaning old backups for client: {}", client_name);
-                    let deleted_count = backup_manager.cleanup_old_backups(db_config).await?;
+        Commands::History { client, limit } => {
+            let catalog = Catalog::load(&cli.backup_dir)?;
+            let history: Vec<_> = catalog
+                .history(client.as_deref())
+                .into_iter()
+                .take(limit)
+                .collect();
+
+            if cli.output == OutputFormat::Table && history.is_empty() {
+                println!("No backup history recorded");
+            } else {
+                print!(
+                    "{}",
+                    output::render(cli.output, &history, || history_table(&history))?
+                );
+            }
+        }
+        Commands::Materialize { backup, out } => {
+            let output = out.unwrap_or_else(|| format!("{}/{}", cli.backup_dir, backup));
+            backup_manager.materialize(&backup, std::path::Path::new(&output))?;
+            println!("Materialized {} to {}", backup, output);
+        }
+        Commands::Diff { old, new, json } => {
+            let old = backup_manager.backup_path(&old);
+            let new = backup_manager.backup_path(&new);
+            let diff = diff::diff(&old, &new)?;
+            if cli.output.or_json(json) == OutputFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&diff)?);
+                return Ok(());
+            }
+            println!("{} -> {}", old.display(), new.display());
+            if diff.is_empty() {
+                println!("  No differences");
+                return Ok(());
+            }
+            if diff.odoo_version.0 != diff.odoo_version.1 {
+                println!(
+                    "  Odoo: {} -> {}",
+                    diff.odoo_version.0.as_deref().unwrap_or("unknown"),
+                    diff.odoo_version.1.as_deref().unwrap_or("unknown")
+                );
+            }
+            for (name, version) in &diff.modules_added {
+                println!("  + {} {}", name, version);
+            }
+            for (name, version) in &diff.modules_removed {
+                println!("  - {} {}", name, version);
+            }
+            for change in &diff.modules_changed {
+                println!("  ~ {} {} -> {}", change.name, change.from, change.to);
+            }
+            println!(
+                "  Filestore: {} files added, {} removed ({:+} files, {})",
+                diff.filestore_files_added,
+                diff.filestore_files_removed,
+                diff.filestore_files_delta,
+                size::format_size_delta(diff.filestore_size_delta)
+            );
+            println!(
+                "  Database dump: {}",
+                size::format_size_delta(diff.database_size_delta)
+            );
+        }
+        Commands::Inspect { file, json } => {
+            let inspection = backup_manager.inspect(&file)?;
+            if cli.output.or_json(json) == OutputFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&inspection)?);
+                return Ok(());
+            }
+            println!("{}", inspection.path);
+            println!("  Size: {}", size::format_size(inspection.size));
+            match &inspection.contents {
+                Some(contents) => {
                     println!(
-                        "Cleaned up {} old backup files for {}",
-                        deleted_count, client_name
+                        "  Odoo: {}, PostgreSQL: {}",
+                        contents.odoo_version.as_deref().unwrap_or("unknown"),
+                        contents.pg_version.as_deref().unwrap_or("unknown")
                     );
-                } else {
-                    error!(" Client '{}' not found in configuration", client_name);
-                    return Err(error::BackupError::Config(format!(
-                        "Client '{}' not found",
-                        client_name
-                    )));
+                    println!("  Modules: {}", contents.modules.len());
+                    println!(
+                        "  Entries: {} ({} uncompressed)",
+                        contents.entries,
+                        size::format_size(contents.uncompressed_size)
+                    );
+                    println!("  Database: {}", size::format_size(contents.database_size));
+                    println!(
+                        "  Filestore: {} files, {}",
+                        contents.filestore_files,
+                        size::format_size(contents.filestore_size)
+                    );
+                    match &contents.manifest {
+                        Some(manifest) => println!(
+                            "  Taken by odoo-backup-service {} for {} at {}",
+                            manifest.tool_version,
+                            manifest.client,
+                            manifest.created_at.format("%Y-%m-%d %H:%M:%S")
+                        ),
+                        None => println!("  No odoo-backup-service manifest"),
+                    }
+                }
+                None => println!("  pg_dump archive, no manifest"),
+            }
+            match &inspection.catalog {
+                Some(entry) => println!(
+                    "  Catalog: {} backup of {} started {}",
+                    entry.status,
+                    entry.client,
+                    entry.started_at.format("%Y-%m-%d %H:%M:%S")
+                ),
+                None => println!("  Catalog: not recorded"),
+            }
+            println!(
+                "  Checksum: {}",
+                match inspection.checksum_ok {
+                    Some(true) => "ok",
+                    Some(false) => "MISMATCH",
+                    None => "not recorded",
                 }
+            );
+            if inspection.checksum_ok == Some(false) {
+                return Err(BackupError::FileSystem(format!(
+                    "{} does not match its recorded checksum",
+                    inspection.path
+                )));
+            }
+        }
+        Commands::Verify {
+            mut backup,
+            all: _,
+            client,
+            older_than,
+            selector,
+            json,
+        } => {
+            if let (Some(selector), Some(client)) = (selector.selector(), &client) {
+                backup = Some(backup_manager.select_backup(client, &selector)?);
+            }
+            let results =
+                backup_manager.verify_backups(backup.as_deref(), client.as_deref(), older_than)?;
+
+            if cli.output.or_json(json) == OutputFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&results)?);
+            } else if results.is_empty() {
+                println!("No backups due for verification");
             } else {
-                // Clean all clients
-                info!("Cleaning old backups for all databases");
-                let mut total_deleted = 0;
-                for db_config in &config.databases {
-                    let deleted_count = backup_manager.cleanup_old_backups(db_config).await?;
-                    total_deleted += deleted_count;
+                for result in &results {
+                    let status = match &result.status {
+                        VerifyStatus::Ok => "ok".to_string(),
+                        VerifyStatus::Missing => "MISSING".to_string(),
+                        VerifyStatus::Corrupt { reason } => format!("CORRUPT ({})", reason),
+                    };
+                    println!("  {} ({}): {}", result.file_name, result.client, status);
                 }
-                println!("Cleaned up {} old backup files total", total_deleted);
+            }
+
+            if let Some(alert) = notify::verification_alert(&results) {
+                notify::notify_all(&config.notifications, &alert).await;
+                let damaged = results
+                    .iter()
+                    .filter(|r| r.status != VerifyStatus::Ok)
+                    .count();
+                return Err(error::BackupError::VerificationFailed(format!(
+                    "{} of {} backups damaged",
+                    damaged,
+                    results.len()
+                )));
             }
         }
-        Commands::ListBackups { database } => {
-            let backups = backup_manager.list_backups(database.as_deref()).await?;
+        Commands::Restore {
+            client,
+            backup,
+            database,
+            target,
+            neutralize,
+            direct,
+            only,
+            replace,
+            scrub,
+        } => {
+            let find = |name: &str| {
+                config.get_database(name).cloned().ok_or_else(|| {
+                    error::BackupError::Config(format!("Client '{}' not found", name))
+                })
+            };
+            let source = find(&client)?;
+            let mut target = find(target.as_deref().unwrap_or(&client))?;
+            target.resolve_master_password(false)?;
 
-            if backups.is_empty() {
-                println!("No backup files found");
+            info!("Restoring a backup of {} as {}", client, database);
+            let options = RestoreOptions {
+                backup: backup.selector().unwrap_or_default(),
+                database_name: database.clone(),
+                neutralize,
+                direct,
+                only,
+                replace,
+                scrub,
+            };
+            let file_name = backup_manager.restore(&source, &target, &options).await?;
+            let part = match only {
+                Some(RestorePart::Db) => "the database of ",
+                Some(RestorePart::Filestore) => "the filestore of ",
+                None => "",
+            };
+            println!(
+                "Restored {}{} as {} in {}{}",
+                part,
+                file_name,
+                database,
+                target.container_name,
+                match (neutralize, options.scrub.is_empty()) {
+                    (true, true) => ", neutralized",
+                    (true, false) => ", neutralized and scrubbed",
+                    (false, false) => ", scrubbed",
+                    (false, true) => "",
+                }
+            );
+        }
+        Commands::RestorePhysical {
+            client,
+            backup,
+            into,
+        } => {
+            config.get_database(&client).ok_or_else(|| {
+                error::BackupError::Config(format!("Client '{}' not found", client))
+            })?;
+            let file_name = backup_manager.restore_physical(
+                &client,
+                &backup.selector().unwrap_or_default(),
+                Path::new(&into),
+            )?;
+            println!("Unpacked {} into {}", file_name, into);
+            if physical::is_dump(&file_name) {
+                println!(
+                    "Load {}/dump into a new database with pg_restore -j, \
+                     and put {}/filestore under the filestore directory of Odoo's data directory",
+                    into, into
+                );
+            } else {
+                println!(
+                    "Start a PostgreSQL server of the same major version on {}/pgdata, \
+                     and put {}/filestore under the filestore directory of Odoo's data directory",
+                    into, into
+                );
+            }
+        }
+        Commands::TestRestore { client } => {
+            let mut db_config = config
+                .get_database(&client)
+                .ok_or_else(|| {
+                    error::BackupError::Config(format!("Client '{}' not found", client))
+                })?
+                .clone();
+            db_config.resolve_master_password(false)?;
+
+            info!("Running restore test for client: {}", client);
+            let file_name = backup_manager.test_restore(&db_config).await?;
+            println!(
+                "Restore test passed: {} restored and dropped again",
+                file_name
+            );
+        }
+        Commands::PreUpgrade {
+            client,
+            label,
+            restore_test,
+            ask_password,
+            json,
+        } => {
+            let mut db_config = config
+                .get_database(&client)
+                .ok_or_else(|| {
+                    error::BackupError::Config(format!("Client '{}' not found", client))
+                })?
+                .clone();
+            db_config.resolve_master_password(ask_password)?;
+
+            info!("Running pre-upgrade checks for client: {}", client);
+            let backup_manager = backup_manager.with_label(label);
+            let started_at = Utc::now();
+            let report = pre_upgrade::run(&backup_manager, &db_config, restore_test).await;
+            summary::write_last_run(&cli.backup_dir, &[db_config], started_at);
+            metrics::update(&config, &cli.backup_dir);
+            if cli.output.or_json(json) == OutputFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{}", report.render(cli.color.enabled()));
+            }
+            if !report.go {
+                return Err(error::BackupError::Unhealthy(format!(
+                    "{} is not ready for an upgrade",
+                    client
+                )));
+            }
+        }
+        Commands::CheckFreshness { json } => {
+            let now = Utc::now();
+            let results = freshness::check(&config, &cli.backup_dir, now)?;
+            if cli.output.or_json(json) == OutputFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&results)?);
+            } else if results.is_empty() {
+                println!("No databases have max_age_hours set");
+            } else {
+                for result in &results {
+                    let status = if result.stale { "STALE" } else { "ok" };
+                    println!("  {} - {}", result.describe(now), status);
+                }
+            }
+
+            let stale: Vec<_> = results
+                .iter()
+                .filter(|r| r.stale)
+                .map(|r| r.client.as_str())
+                .collect();
+            if !stale.is_empty() {
+                return Err(error::BackupError::Stale(stale.join(", ")));
+            }
+        }
+        Commands::CheckAuth {
+            client,
+            tag,
+            ask_password,
+            json,
+        } => {
+            let selected = match &client {
+                Some(name) => vec![config.get_database(name).ok_or_else(|| {
+                    BackupError::Config(format!("Client '{}' not found in configuration", name))
+                })?],
+                None => config.enabled_databases(tag.as_deref()),
+            };
+            let mut databases = Vec::new();
+            for db in selected {
+                let mut db = db.clone();
+                db.resolve_master_password(ask_password)?;
+                databases.push(db);
+            }
+
+            let results = doctor::check_auth(&docker_manager, &databases).await;
+            if cli.output.or_json(json) == OutputFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&results)?);
             } else {
-                println!("Backup files:");
-                for backup in backups {
-                    println!("  - {}", backup);
+                for result in &results {
+                    match &result.error {
+                        None => println!("  {} - ok", result.client),
+                        Some(error) => println!("  {} - FAILED: {}", result.client, error),
+                    }
+                }
+            }
+
+            let failed: Vec<_> = results
+                .iter()
+                .filter(|r| !r.ok)
+                .map(|r| r.client.as_str())
+                .collect();
+            if !failed.is_empty() {
+                return Err(BackupError::AuthFailed(format!(
+                    "master password check failed for {}",
+                    failed.join(", ")
+                )));
+            }
+        }
+        Commands::Daemon { pid_file } => {
+            // Held until the daemon returns, which removes the file again
+            let _pid_file = pid_file.map(PidFile::acquire).transpose()?;
+            let load_config =
+                || Config::from_file_with_key(&cli.config, cli.config_key_file.as_deref());
+            daemon::run(
+                config,
+                load_config,
+                &Arc::new(backup_manager),
+                &cli.backup_dir,
+                &shutdown,
+            )
+            .await?
+        }
+        Commands::Config { .. }
+        | Commands::Init { .. }
+        | Commands::Convert { .. }
+        | Commands::Extract { .. }
+        | Commands::Bench { .. }
+        | Commands::Discover { .. }
+        | Commands::Doctor => {
+            unreachable!("handled before loading the configuration")
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_config_command(cli: &Cli, action: &ConfigCommands) -> Result<()> {
+    let key_file = cli.config_key_file.as_deref();
+    match action {
+        ConfigCommands::Encrypt { out } => {
+            config::encrypt_config_file(&cli.config, out.as_deref(), key_file)?;
+            audit_config_change(cli, "encrypt", None);
+            println!(
+                "Encrypted configuration written to {}",
+                out.as_deref().unwrap_or(&cli.config)
+            );
+        }
+        ConfigCommands::Decrypt { out } => {
+            config::decrypt_config_file(&cli.config, out.as_deref(), key_file)?;
+            audit_config_change(cli, "decrypt", None);
+            println!(
+                "Decrypted configuration written to {}",
+                out.as_deref().unwrap_or(&cli.config)
+            );
+        }
+        ConfigCommands::Lint { json } => {
+            let (config, encrypted) = config::load_unresolved(&cli.config, key_file)?;
+            let findings = lint::lint(&config, encrypted);
+            if cli.output.or_json(*json) == OutputFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&findings)?);
+            } else if findings.is_empty() {
+                println!("No problems found in {}", cli.config);
+            } else {
+                for finding in &findings {
+                    let color = match finding.severity {
+                        lint::Severity::Warning => Color::Yellow,
+                        lint::Severity::Error => Color::Red,
+                    };
+                    println!(
+                        "{}",
+                        color.paint_if(cli.color.enabled(), &finding.to_string())
+                    );
+                }
+            }
+
+            let errors = findings
+                .iter()
+                .filter(|f| f.severity == lint::Severity::Error)
+                .count();
+            if errors > 0 {
+                return Err(BackupError::Config(format!(
+                    "{} has {} lint errors",
+                    cli.config, errors
+                )));
+            }
+        }
+        ConfigCommands::Edit => {
+            let editor = env::var("VISUAL")
+                .or_else(|_| env::var("EDITOR"))
+                .unwrap_or_else(|_| config::DEFAULT_EDITOR.to_string());
+            let stdin = std::io::stdin();
+            let mut prompter = Prompter::new(stdin.lock(), std::io::stdout(), false);
+            let backup = config::edit_config_file(&cli.config, key_file, &editor, |e| {
+                prompter.say(&format!("Invalid configuration: {}", e))?;
+                prompter.confirm("Edit again?", true)
+            })?;
+            match backup {
+                Some(backup) => {
+                    audit_config_change(cli, "edit", None);
+                    println!("Saved {}, previous version in {}", cli.config, backup)
                 }
+                None => println!("No changes to {}", cli.config),
             }
         }
+        ConfigCommands::Add {
+            interactive,
+            database,
+        } => {
+            let db = match database.database_config() {
+                Some(db) if !interactive => db,
+                _ => {
+                    let existing = config::configured_names(&cli.config, key_file)?;
+                    let stdin = std::io::stdin();
+                    let hide_secrets = stdin.is_terminal();
+                    let mut prompter = Prompter::new(stdin.lock(), std::io::stdout(), hide_secrets);
+                    wizard::ask_database(&mut prompter, &DockerManager::new(), &existing).await?
+                }
+            };
+            config::append_database(&cli.config, key_file, &db)?;
+            audit_config_change(cli, "add", Some(&db.name));
+            println!("Added {} to {}", db.name, cli.config);
+        }
+        ConfigCommands::Remove { name, backups } => {
+            remove_client_backups(cli, name, backups).await?;
+            config::remove_database(&cli.config, key_file, name)?;
+            audit_config_change(cli, "remove", Some(name));
+            println!("Removed {} from {}", name, cli.config);
+        }
+        ConfigCommands::Disable { name, backups } => {
+            remove_client_backups(cli, name, backups).await?;
+            config::set_database_enabled(&cli.config, key_file, name, false)?;
+            audit_config_change(cli, "disable", Some(name));
+            println!("Disabled {} in {}", name, cli.config);
+        }
+        ConfigCommands::Enable { name } => {
+            config::set_database_enabled(&cli.config, key_file, name, true)?;
+            audit_config_change(cli, "enable", Some(name));
+            println!("Enabled {} in {}", name, cli.config);
+        }
+    }
+    Ok(())
+}
+
+/// Records a change to the configuration file in the audit log.
+fn audit_config_change(cli: &Cli, action: &str, client: Option<&str>) {
+    audit::record(
+        &cli.backup_dir,
+        AuditRecord::new(AuditOperation::ConfigChange, client)
+            .with("action", action)
+            .with("file", &cli.config),
+    );
+}
+
+/// Purges or archives the local backups of `name` as `backups` asks,
+/// before it leaves the configuration.
+async fn remove_client_backups(cli: &Cli, name: &str, backups: &ExistingBackupsArgs) -> Result<()> {
+    if !backups.purge && !backups.archive {
+        return Ok(());
+    }
+    let config = Config::from_file_with_key(&cli.config, cli.config_key_file.as_deref())?;
+    let db = config
+        .get_database(name)
+        .ok_or_else(|| BackupError::Config(format!("No database named {}", name)))?;
+    let mut backup_manager = BackupManager::new(cli.backup_dir.clone());
+    if let Some(archive) = &config.archive {
+        backup_manager = backup_manager.with_archive(archive.build());
+    }
+    if let Some(dedupe) = &config.dedupe {
+        backup_manager = backup_manager.with_dedupe(dedupe.build(&config.databases));
     }
 
+    let removed = backup_manager
+        .remove_client_backups(db, backups.archive)
+        .await?;
+    let action = if backups.archive {
+        "Archived"
+    } else {
+        "Deleted"
+    };
+    println!("{} {} backups of {}", action, removed, name);
     Ok(())
 }
+
+/// Lists the unconfigured databases at `url` in `container`, adding them
+/// to the configuration file with `write`.
+async fn run_discover(cli: &Cli, url: &str, container: &str, write: bool) -> Result<()> {
+    let key_file = cli.config_key_file.as_deref();
+    let docker = DockerManager::new();
+    docker.check_container(container).await?;
+    let existing = config::configured_databases(&cli.config, key_file)?;
+    let mut databases = wizard::discover_databases(&docker, container, url, &existing).await?;
+    if databases.is_empty() {
+        println!("No unconfigured databases at {} in {}", url, container);
+        return Ok(());
+    }
+    if !write {
+        println!("Unconfigured databases at {} in {}:", url, container);
+        for db in &databases {
+            println!("  - {}", db.database_name);
+        }
+        println!("Run again with --write to add them to {}", cli.config);
+        return Ok(());
+    }
+
+    let stdin = std::io::stdin();
+    let hide_secrets = stdin.is_terminal();
+    let mut prompter = Prompter::new(stdin.lock(), std::io::stdout(), hide_secrets);
+    // One instance has one master password
+    let mut first = databases[0].clone();
+    wizard::ask_master_password(&mut prompter, &docker, &mut first, true).await?;
+    for db in &mut databases {
+        db.master_password = first.master_password.clone();
+        config::append_database(&cli.config, key_file, db)?;
+        audit_config_change(cli, "add", Some(&db.name));
+        println!("Added {} to {}", db.name, cli.config);
+    }
+    Ok(())
+}
+
+async fn run_doctor(cli: &Cli) -> Result<()> {
+    let config = Config::from_file_with_key(&cli.config, cli.config_key_file.as_deref());
+    let mut checks = vec![doctor::check_config(&cli.config, &config)];
+    if let Ok(config) = &config {
+        checks.extend(doctor::run(config, &DockerManager::new(), &cli.backup_dir).await);
+    }
+    print!("{}", doctor::render(&checks, cli.color.enabled()));
+
+    let failed = checks
+        .iter()
+        .filter(|c| c.status == doctor::CheckStatus::Fail)
+        .count();
+    if failed > 0 {
+        return Err(BackupError::Unhealthy(format!("{} checks failed", failed)));
+    }
+    println!("All checks passed");
+    Ok(())
+}
+
+fn backup_table(backups: &[BackupInfo]) -> Table {
+    let now = chrono::Utc::now();
+    let mut table = Table::new(&[
+        "NAME", "DATABASE", "SIZE", "AGE", "ODOO", "CHECKSUM", "LABEL",
+    ])
+    .align_right(&[2, 3]);
+    for b in backups {
+        let checksum = match (b.checksum_verified, &b.sha256) {
+            (Some(true), _) => "ok",
+            (Some(false), _) => "MISMATCH",
+            (None, Some(_)) => "recorded",
+            (None, None) => "-",
+        };
+        table.row(vec![
+            b.name.clone(),
+            b.database.clone().unwrap_or_else(|| "-".to_string()),
+            size::format_size(b.size),
+            size::format_age((now - b.modified).num_seconds()),
+            b.odoo_version.clone().unwrap_or_else(|| "-".to_string()),
+            checksum.to_string(),
+            b.label.clone().unwrap_or_default(),
+        ]);
+    }
+    table
+}
+
+fn database_table(databases: &[DatabaseListing]) -> Table {
+    let mut headers = vec![
+        "NAME",
+        "DATABASE",
+        "CONTAINER",
+        "URL",
+        "FORMAT",
+        "ENABLED",
+        "SCHEDULE",
+        "TAGS",
+    ];
+    // Only `list --live` knows whether databases exist
+    let live = databases.iter().any(|db| db.exists.is_some());
+    if live {
+        headers.push("LIVE");
+    }
+    let mut table = Table::new(&headers);
+    for db in databases {
+        let schedule = db.schedule.as_ref().map(|schedule| {
+            let zone = db.timezone.as_deref().unwrap_or("local time");
+            format!("{} ({})", schedule, zone)
+        });
+        let mut cells = vec![
+            db.name.clone(),
+            db.database_name.clone(),
+            db.container_name.clone(),
+            db.url.clone(),
+            db.backup_format.clone(),
+            if db.enabled { "yes" } else { "no" }.to_string(),
+            schedule.unwrap_or_else(|| "-".to_string()),
+            db.tags.join(","),
+        ];
+        let state = match db.exists {
+            Some(true) => Some(("exists", Color::Green)),
+            Some(false) => Some(("missing", Color::Red)),
+            None if live => Some(("unknown", Color::Yellow)),
+            None => None,
+        };
+        cells.extend(state.map(|(state, _)| state.to_string()));
+        table.row(cells);
+        if let Some((_, color)) = state {
+            table.paint(headers.len() - 1, color);
+        }
+    }
+    table
+}
+
+fn history_table(history: &[&CatalogEntry]) -> Table {
+    let time = |t: DateTime<Utc>| t.format("%Y-%m-%d %H:%M:%S").to_string();
+    let mut table = Table::new(&[
+        "STARTED", "CLIENT", "STATUS", "ODOO", "SIZE", "DURATION", "FILE", "DETAILS",
+    ])
+    .align_right(&[4, 5]);
+    for entry in history {
+        let mut details = Vec::new();
+        if let Some(label) = &entry.label {
+            details.push(format!("label: {}", label));
+        }
+        if let Some(tier) = entry.tier {
+            details.push(format!("tier: {}", tier));
+        }
+        if let Some(copy) = &entry.archive_copy {
+            details.push(format!("archive copy: {}", copy));
+        }
+        if let Some(manifest) = entry.manifest.as_ref().filter(|m| !m.modules.is_empty()) {
+            details.push(format!("{} modules", manifest.modules.len()));
+        }
+        if entry.deduplicated {
+            details.push("in the dedupe store".to_string());
+        }
+        if let Some(verified_at) = entry.verified_at {
+            details.push(format!("verified: {}", time(verified_at)));
+        }
+        if let Some(tested_at) = entry.restore_tested_at {
+            details.push(format!("restore tested: {}", time(tested_at)));
+        }
+        if let Some(location) = &entry.archive_location {
+            details.push(format!("archived to: {}", location));
+        }
+        if let Some(error) = &entry.error {
+            details.push(format!("error: {}", error));
+        }
+        table.row(vec![
+            time(entry.started_at),
+            entry.client.clone(),
+            entry.status.to_string(),
+            entry
+                .odoo_version
+                .clone()
+                .unwrap_or_else(|| "-".to_string()),
+            entry
+                .size
+                .map(size::format_size)
+                .unwrap_or_else(|| "-".to_string()),
+            entry
+                .duration_secs()
+                .map(|secs| format!("{}s", secs))
+                .unwrap_or_else(|| "-".to_string()),
+            entry.file_name.clone().unwrap_or_else(|| "-".to_string()),
+            details.join("; "),
+        ]);
+    }
+    table
+}
+
+fn clean_table(cleaned: &[CleanedClient], container: bool) -> Table {
+    let mut headers = vec!["CLIENT", "REMOVED"];
+    if container {
+        headers.push("ORPHANED FILES");
+    }
+    let mut table = Table::new(&headers).align_right(&[1, 2]);
+    for client in cleaned {
+        let mut row = vec![client.client.clone(), client.removed.to_string()];
+        if container {
+            row.push(
+                client
+                    .orphaned_files
+                    .map(|n| n.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+            );
+        }
+        table.row(row);
+    }
+    table
+}
+
+fn print_catalog_check(check: &CatalogCheck) {
+    if check.is_consistent() {
+        println!("Catalog matches the backup directory");
+        return;
+    }
+    let (marked, added) = if check.dry_run {
+        ("would be marked missing", "would be added")
+    } else {
+        ("marked missing", "added")
+    };
+    for name in &check.missing {
+        println!("  {}: file gone, {}", name, marked);
+    }
+    for orphan in &check.orphans {
+        match &orphan.client {
+            Some(client) => println!(
+                "  {}: no catalog row, {} for {}",
+                orphan.name, added, client
+            ),
+            None => println!(
+                "  {}: no catalog row, not added as it matches no configured database",
+                orphan.name
+            ),
+        }
+    }
+    println!(
+        "\n{} missing files, {} files without catalog rows",
+        check.missing.len(),
+        check.orphans.len()
+    );
+}
+
+fn print_prune_report(report: &PruneReport) {
+    let verb = if report.dry_run {
+        "Would prune"
+    } else {
+        "Pruned"
+    };
+    for client in &report.clients {
+        if client.backups.is_empty() {
+            println!("{}: nothing to prune", client.client);
+            continue;
+        }
+        let mut reclaimed = format!(
+            "{} local",
+            size::format_size(client.reclaimed(PruneBackend::Local))
+        );
+        let dedupe = client.reclaimed(PruneBackend::Dedupe);
+        if dedupe > 0 {
+            reclaimed.push_str(&format!(", {} dedupe", size::format_size(dedupe)));
+        }
+        println!(
+            "{}: {} {} backups, {}",
+            client.client,
+            verb.to_lowercase(),
+            client.backups.len(),
+            reclaimed
+        );
+        for backup in &client.backups {
+            let reason = match backup.reason {
+                PruneReason::Retention => "retention",
+                PruneReason::SizeCap => "size cap",
+            };
+            println!(
+                "  {} ({}, {}, {}){}",
+                backup.name,
+                backup.backend,
+                size::format_size(backup.size),
+                reason,
+                if backup.archived { ", archived" } else { "" }
+            );
+        }
+    }
+
+    println!(
+        "\n{} {} backups: {} reclaimed locally",
+        verb,
+        report.pruned(),
+        size::format_size(report.reclaimed(PruneBackend::Local))
+    );
+    if report.dedupe_chunks > 0 {
+        println!(
+            "{} unused dedupe chunks, {} {}",
+            report.dedupe_chunks,
+            size::format_size(report.reclaimed(PruneBackend::Dedupe)),
+            if report.dry_run {
+                "would be freed"
+            } else {
+                "freed"
+            }
+        );
+    }
+}
+
+fn print_backup_results(results: &[(String, String)], color: bool) {
+    if results.is_empty() {
+        warn!("No backups were completed successfully");
+    } else {
+        let done = format!("Completed {} backups", results.len());
+        println!("{}:", Color::Green.paint_if(color, &done));
+        for (client_name, backup_path) in results {
+            println!("  - {}: {}", client_name, backup_path);
+        }
+    }
+}
+
+fn status_table(report: &StatusReport) -> Table {
+    let time = |t: DateTime<Utc>| t.format("%Y-%m-%d %H:%M:%S").to_string();
+    let mut table = Table::new(&[
+        "CLIENT",
+        "CONTAINER",
+        "STATE",
+        "HTTP",
+        "LAST BACKUP",
+        "SIZE",
+        "LAST ATTEMPT",
+        "NEXT RUN",
+    ])
+    .align_right(&[5]);
+    for db in &report.databases {
+        let mut last_backup = db
+            .last_backup
+            .map(time)
+            .unwrap_or_else(|| "never".to_string());
+        if db.stale == Some(true) {
+            last_backup.push_str(" STALE");
+        }
+        let last_attempt = match (db.last_attempt, db.last_attempt_at) {
+            (Some(outcome), Some(at)) => format!("{} at {}", outcome, time(at)),
+            _ => "-".to_string(),
+        };
+        table.row(vec![
+            db.client.clone(),
+            db.container.clone(),
+            if db.running { "running" } else { "stopped" }.to_string(),
+            db.http
+                .as_ref()
+                .map(|http| http.describe())
+                .unwrap_or_else(|| "-".to_string()),
+            last_backup,
+            db.last_backup_size
+                .map(size::format_size)
+                .unwrap_or_else(|| "-".to_string()),
+            last_attempt,
+            db.next_run.map(time).unwrap_or_else(|| "-".to_string()),
+        ]);
+        table.paint(2, if db.running { Color::Green } else { Color::Red });
+        if let Some(http) = &db.http {
+            let color = if http.is_healthy() {
+                Color::Green
+            } else {
+                Color::Red
+            };
+            table.paint(3, color);
+        }
+        if db.stale == Some(true) {
+            table.paint(4, Color::Yellow);
+        }
+        if let Some(outcome) = db.last_attempt {
+            let color = match outcome {
+                BackupStatus::Success => Color::Green,
+                BackupStatus::Failed => Color::Red,
+                BackupStatus::Skipped | BackupStatus::Aborted | BackupStatus::Missing => {
+                    Color::Yellow
+                }
+            };
+            table.paint(6, color);
+        }
+    }
+    table
+}
+
+/// The running containers and the backup queue, below the status table.
+fn print_status_details(report: &StatusReport) {
+    if report.running_containers.is_empty() {
+        println!("\nNo containers are currently running");
+    } else {
+        println!("\nAll running containers:");
+        for container in &report.running_containers {
+            println!("  - {}", container);
+        }
+    }
+
+    if let Some(queue) = &report.queue {
+        println!("\nBackup queue:");
+        for job in &queue.running {
+            let since = job
+                .started_at
+                .map(|t| format!(" since {}", t.format("%Y-%m-%d %H:%M:%S")))
+                .unwrap_or_default();
+            println!(
+                "  - {} ({}) - running{}, priority {}",
+                job.client, job.container, since, job.priority
+            );
+        }
+        for job in &queue.pending {
+            println!(
+                "  - {} ({}) - waiting, priority {}",
+                job.client, job.container, job.priority
+            );
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use crate::config::{DatabaseConfig, MaintenanceConfig};
+use crate::docker::DockerManager;
+use crate::error::{BackupError, Result};
+use tokio::process::Command;
+
+/// Deactivates the active scheduled actions, adding their ids to the
+/// `odoo_backup_service.paused_crons` parameter in the same transaction, so
+/// they are found again even when this process dies before resuming them.
+/// Updating the row of a running job waits for the job to finish.
+pub const PAUSE_CRONS_SQL: &str = "\
+BEGIN;
+SET LOCAL lock_timeout = '15min';
+INSERT INTO ir_config_parameter (key, value)
+    SELECT 'odoo_backup_service.paused_crons', coalesce(string_agg(id::text, ','), '')
+    FROM ir_cron WHERE active
+    ON CONFLICT (key) DO UPDATE SET value = concat_ws(',',
+        nullif(ir_config_parameter.value, ''), nullif(EXCLUDED.value, ''));
+UPDATE ir_cron SET active = false WHERE active;
+COMMIT;
+";
+
+/// Reactivates the scheduled actions listed in
+/// `odoo_backup_service.paused_crons` and removes the parameter.
+pub const RESUME_CRONS_SQL: &str = "\
+BEGIN;
+UPDATE ir_cron SET active = true WHERE id::text = ANY(string_to_array(
+    (SELECT value FROM ir_config_parameter WHERE key = 'odoo_backup_service.paused_crons'), ','));
+DELETE FROM ir_config_parameter WHERE key = 'odoo_backup_service.paused_crons';
+COMMIT;
+";
+
+/// What `enter` changed, for `leave` to undo.
+#[derive(Debug, Default)]
+pub struct Quiesced {
+    command: bool,
+    /// Value of the config parameter before, `None` when it was unset
+    parameter: Option<Option<String>>,
+    crons: bool,
+    stopped: Vec<String>,
+}
+
+/// Puts the instance of `config` into maintenance as its `maintenance`
+/// section says. When a step fails, the steps before it are undone.
+pub async fn enter(docker: &DockerManager, config: &DatabaseConfig) -> Result<Quiesced> {
+    let mut quiesced = Quiesced::default();
+    let Some(maintenance) = &config.maintenance else {
+        return Ok(quiesced);
+    };
+    log::info!("Putting {} into maintenance", config.name);
+    if let Err(e) = enter_steps(docker, config, maintenance, &mut quiesced).await {
+        if let Err(undo) = leave(docker, config, quiesced).await {
+            log::error!("{}", undo);
+        }
+        return Err(e.context(format!("Failed to put {} into maintenance", config.name)));
+    }
+    Ok(quiesced)
+}
+
+async fn enter_steps(
+    docker: &DockerManager,
+    config: &DatabaseConfig,
+    maintenance: &MaintenanceConfig,
+    quiesced: &mut Quiesced,
+) -> Result<()> {
+    if let Some(command) = &maintenance.enable_command {
+        run_command(config, command).await?;
+        quiesced.command = true;
+    }
+    if let Some(key) = &maintenance.config_parameter {
+        let rpc = docker.odoo_rpc(config).await?;
+        let previous = rpc.config_parameter(key).await?;
+        rpc.set_config_parameter(key, Some("True")).await?;
+        quiesced.parameter = Some(previous);
+    }
+    if maintenance.pause_crons {
+        // Resuming changes nothing when nothing was paused, so it is
+        // tried even when pausing failed half way
+        quiesced.crons = true;
+        docker.pause_crons(config).await?;
+    }
+    for container in &maintenance.stop_containers {
+        // Containers that were down already stay down afterwards
+        if !docker.is_container_running(container).await? {
+            continue;
+        }
+        docker.stop_container(container).await?;
+        quiesced.stopped.push(container.clone());
+    }
+    Ok(())
+}
+
+/// Takes the instance of `config` out of maintenance again, undoing what
+/// `enter` did in reverse order. Every step is tried; the first failure is
+/// returned.
+pub async fn leave(
+    docker: &DockerManager,
+    config: &DatabaseConfig,
+    quiesced: Quiesced,
+) -> Result<()> {
+    let Some(maintenance) = &config.maintenance else {
+        return Ok(());
+    };
+    let mut failures = Vec::new();
+    for container in quiesced.stopped.iter().rev() {
+        if let Err(e) = docker.start_container(container).await {
+            failures.push(e);
+        }
+    }
+    if quiesced.crons {
+        if let Err(e) = docker.resume_crons(config).await {
+            failures.push(e);
+        }
+    }
+    if let (Some(key), Some(previous)) = (&maintenance.config_parameter, &quiesced.parameter) {
+        let restored = match docker.odoo_rpc(config).await {
+            Ok(rpc) => rpc.set_config_parameter(key, previous.as_deref()).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = restored {
+            failures.push(e);
+        }
+    }
+    if let (Some(command), true) = (&maintenance.disable_command, quiesced.command) {
+        if let Err(e) = run_command(config, command).await {
+            failures.push(e);
+        }
+    }
+    match failures.into_iter().next() {
+        Some(e) => Err(e.context(format!("Failed to take {} out of maintenance", config.name))),
+        None => {
+            log::info!("{} is out of maintenance", config.name);
+            Ok(())
+        }
+    }
+}
+
+/// Runs a maintenance hook with `sh` on the host, telling it which
+/// database it is about.
+async fn run_command(config: &DatabaseConfig, command: &str) -> Result<()> {
+    let output = Command::new("sh")
+        .args(["-c", command])
+        .env("ODOO_BACKUP_CLIENT", &config.name)
+        .env("ODOO_BACKUP_DATABASE", &config.database_name)
+        .env("ODOO_BACKUP_CONTAINER", &config.container_name)
+        .output()
+        .await
+        .map_err(|e| BackupError::Maintenance(format!("Failed to run '{}': {}", command, e)))?;
+    if !output.status.success() {
+        return Err(BackupError::Maintenance(format!(
+            "'{}' failed: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SessionAuth;
+    use crate::runtime::{ExecOutput, MockRuntime};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    fn create_test_database_config(maintenance: MaintenanceConfig) -> DatabaseConfig {
+        let mut config: DatabaseConfig = serde_json::from_value(serde_json::json!({
+            "name": "Acme",
+            "database_name": "acme",
+            "url": "http://localhost:8069",
+            "container_name": "odoo",
+            "master_password": "admin",
+            "backup_format": "zip",
+            "output_path": "/tmp/backups",
+            "retention_days": 30,
+        }))
+        .unwrap();
+        config.session_auth = Some(SessionAuth {
+            login: "backup".to_string(),
+            password: "secret".to_string(),
+            backup_route: Some("/session_backup/backup".to_string()),
+        });
+        config.maintenance = Some(maintenance);
+        config
+    }
+
+    #[tokio::test]
+    async fn test_enter_and_leave_maintenance() {
+        let temp_dir = tempdir().unwrap();
+        let log = temp_dir.path().join("hooks.log");
+        let config = create_test_database_config(MaintenanceConfig {
+            config_parameter: Some("acme.maintenance".to_string()),
+            enable_command: Some(format!(
+                "echo \"on $ODOO_BACKUP_CLIENT\" >> {}",
+                log.display()
+            )),
+            disable_command: Some(format!("echo off >> {}", log.display())),
+            pause_crons: true,
+            stop_containers: vec!["odoo-cron".to_string(), "odoo-stopped".to_string()],
+        });
+        let runtime = Arc::new(
+            MockRuntime::new()
+                .with_container("odoo")
+                .with_container("odoo-cron")
+                .respond("set_param", ExecOutput::ok(r#"{"result": false}"#))
+                .respond("search_read", ExecOutput::ok(r#"{"result": []}"#))
+                .respond("\"login\"", ExecOutput::ok(r#"{"result": 2}"#)),
+        );
+        let docker = DockerManager::with_runtime(Box::new(runtime.clone()));
+
+        let quiesced = enter(&docker, &config).await.unwrap();
+        assert_eq!(quiesced.stopped, vec!["odoo-cron".to_string()]);
+        assert_eq!(quiesced.parameter, Some(None));
+        leave(&docker, &config, quiesced).await.unwrap();
+
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "on Acme\noff\n");
+        let calls = runtime.calls();
+        let set_params: Vec<_> = calls.iter().filter(|c| c.contains("set_param")).collect();
+        assert_eq!(set_params.len(), 2);
+        assert!(set_params[0].contains(r#""acme.maintenance","True""#));
+        // Unset before, so removed again
+        assert!(set_params[1].contains(r#""acme.maintenance",null"#));
+        let position = |pattern: &str| calls.iter().position(|c| c.contains(pattern)).unwrap();
+        assert!(position("SET active = false") < position("stop odoo-cron"));
+        assert!(position("start odoo-cron") < position("SET active = true"));
+        assert!(!calls.contains(&"stop odoo-stopped".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_enter_undoes_steps_on_failure() {
+        let temp_dir = tempdir().unwrap();
+        let log = temp_dir.path().join("hooks.log");
+        let config = create_test_database_config(MaintenanceConfig {
+            config_parameter: Some("acme.maintenance".to_string()),
+            enable_command: Some(format!("echo on >> {}", log.display())),
+            disable_command: Some(format!("echo off >> {}", log.display())),
+            pause_crons: false,
+            stop_containers: Vec::new(),
+        });
+        let docker = DockerManager::with_runtime(Box::new(
+            MockRuntime::new().respond("\"login\"", ExecOutput::ok(r#"{"result": false}"#)),
+        ));
+
+        let e = enter(&docker, &config).await.unwrap_err();
+        assert!(e
+            .to_string()
+            .contains("Failed to put Acme into maintenance"));
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "on\noff\n");
+    }
+}
@@ -0,0 +1,361 @@
+use crate::config::DatabaseConfig;
+use crate::error::{BackupError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Name of our manifest entry inside zip backups, next to Odoo's own `manifest.json`.
+pub const MANIFEST_ENTRY: &str = "odoo-backup-manifest.json";
+
+/// Entries Odoo writes into zip backups.
+pub const ODOO_MANIFEST_ENTRY: &str = "manifest.json";
+pub const DUMP_ENTRY: &str = "dump.sql";
+pub const FILESTORE_PREFIX: &str = "filestore/";
+
+/// Environment metadata recorded with every backup in the catalog, and
+/// inside zip backups, so a restore target can be checked for compatibility
+/// before restoring.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub tool_version: String,
+    pub created_at: DateTime<Utc>,
+    pub client: String,
+    pub database_name: String,
+    pub backup_format: String,
+    pub odoo_version: Option<String>,
+    pub odoo_major_version: Option<String>,
+    pub pg_version: Option<String>,
+    /// Installed modules and their versions
+    pub modules: BTreeMap<String, String>,
+    /// Size of the database in bytes as PostgreSQL reports it, or of the
+    /// uncompressed SQL dump when the instance could not be queried
+    pub database_size: u64,
+    pub filestore_size: u64,
+    pub filestore_files: u64,
+}
+
+/// The parts of Odoo's own `manifest.json` we care about.
+#[derive(Debug, Default, Deserialize)]
+struct OdooManifest {
+    version: Option<String>,
+    major_version: Option<String>,
+    pg_version: Option<String>,
+    #[serde(default)]
+    modules: BTreeMap<String, String>,
+}
+
+pub(crate) fn zip_error(path: &Path, e: impl std::fmt::Display) -> BackupError {
+    BackupError::FileSystem(format!(
+        "Failed to read zip backup {}: {}",
+        path.display(),
+        e
+    ))
+}
+
+/// What a zip backup holds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ZipContents {
+    /// From Odoo's `manifest.json`
+    pub odoo_version: Option<String>,
+    pub odoo_major_version: Option<String>,
+    pub pg_version: Option<String>,
+    pub modules: BTreeMap<String, String>,
+    pub entries: u64,
+    /// Uncompressed size of the SQL dump in bytes
+    pub database_size: u64,
+    pub filestore_size: u64,
+    pub filestore_files: u64,
+    /// Uncompressed size of every entry in bytes
+    pub uncompressed_size: u64,
+    /// Our manifest, for backups taken by this tool
+    pub manifest: Option<BackupManifest>,
+}
+
+/// Reads the manifests and entry sizes of the zip backup at `path`.
+pub fn inspect_zip(path: &Path) -> Result<ZipContents> {
+    let file = File::open(path)?;
+    let mut archive = ZipArchive::new(file).map_err(|e| zip_error(path, e))?;
+
+    let odoo_manifest = match archive.by_name(ODOO_MANIFEST_ENTRY) {
+        Ok(mut entry) => {
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            serde_json::from_str(&content)?
+        }
+        Err(_) => {
+            log::warn!("No Odoo manifest.json found in {}", path.display());
+            OdooManifest::default()
+        }
+    };
+    let manifest = BackupManifest::read_from_zip(path)?;
+
+    let mut contents = ZipContents {
+        odoo_version: odoo_manifest.version,
+        odoo_major_version: odoo_manifest.major_version,
+        pg_version: odoo_manifest.pg_version,
+        modules: odoo_manifest.modules,
+        entries: archive.len() as u64,
+        manifest,
+        ..Default::default()
+    };
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i).map_err(|e| zip_error(path, e))?;
+        contents.uncompressed_size += entry.size();
+        if entry.name() == DUMP_ENTRY {
+            contents.database_size = entry.size();
+        } else if entry.name().starts_with(FILESTORE_PREFIX) && !entry.is_dir() {
+            contents.filestore_size += entry.size();
+            contents.filestore_files += 1;
+        }
+    }
+    Ok(contents)
+}
+
+impl BackupManifest {
+    /// Starts the manifest of a backup of `config` taken now. What the
+    /// instance reports is filled in by the caller.
+    pub fn new(config: &DatabaseConfig) -> Self {
+        Self {
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: Utc::now(),
+            client: config.name.clone(),
+            database_name: config.database_name.clone(),
+            backup_format: config.backup_format.clone(),
+            odoo_version: None,
+            odoo_major_version: None,
+            pg_version: None,
+            modules: BTreeMap::new(),
+            database_size: 0,
+            filestore_size: 0,
+            filestore_files: 0,
+        }
+    }
+
+    /// Adds the filestore sizes of the zip backup at `path`, and whatever
+    /// the instance did not report from Odoo's `manifest.json` in it.
+    pub fn add_zip_contents(&mut self, path: &Path) -> Result<()> {
+        let contents = inspect_zip(path)?;
+        self.odoo_version = self.odoo_version.take().or(contents.odoo_version);
+        self.odoo_major_version = self
+            .odoo_major_version
+            .take()
+            .or(contents.odoo_major_version);
+        self.pg_version = self.pg_version.take().or(contents.pg_version);
+        if self.modules.is_empty() {
+            self.modules = contents.modules;
+        }
+        if self.database_size == 0 {
+            self.database_size = contents.database_size;
+        }
+        self.filestore_size = contents.filestore_size;
+        self.filestore_files = contents.filestore_files;
+        Ok(())
+    }
+
+    /// Appends the manifest as an entry of the zip backup.
+    pub fn embed_into_zip(&self, path: &Path) -> Result<()> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let mut writer = ZipWriter::new_append(file).map_err(|e| zip_error(path, e))?;
+
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        writer
+            .start_file(MANIFEST_ENTRY, options)
+            .map_err(|e| zip_error(path, e))?;
+        writer.write_all(&serde_json::to_vec_pretty(self)?)?;
+        writer.finish().map_err(|e| zip_error(path, e))?;
+        Ok(())
+    }
+
+    /// Reads the manifest embedded in a zip backup, if there is one.
+    pub fn read_from_zip(path: &Path) -> Result<Option<Self>> {
+        let file = File::open(path)?;
+        let mut archive = ZipArchive::new(file).map_err(|e| zip_error(path, e))?;
+        let mut entry = match archive.by_name(MANIFEST_ENTRY) {
+            Ok(entry) => entry,
+            Err(_) => return Ok(None),
+        };
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// Whether a server running `target_version` (e.g. "17.0" or
+    /// "saas~17.2") can restore this backup. Odoo only restores backups taken
+    /// on the same major version.
+    pub fn is_compatible_with(&self, target_version: &str) -> bool {
+        match self.major_version() {
+            Some(major) => major_of(target_version) == Some(major),
+            // Nothing recorded, so there is nothing to check against
+            None => true,
+        }
+    }
+
+    fn major_version(&self) -> Option<&str> {
+        self.odoo_major_version
+            .as_deref()
+            .or(self.odoo_version.as_deref())
+            .and_then(major_of)
+    }
+}
+
+fn major_of(version: &str) -> Option<&str> {
+    let version = version.trim_start_matches("saas~");
+    let major = version.split(['.', '-', '+']).next()?;
+    if major.is_empty() || !major.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(major)
+}
+
+/// Completes `manifest` from a freshly copied zip backup and embeds it. Only
+/// zip backups can carry extra entries; pg_dump files are left untouched and
+/// their manifest lives in the catalog alone.
+pub fn write_manifest(manifest: &mut BackupManifest, backup_path: &str) -> Result<()> {
+    if manifest.backup_format != "zip" {
+        return Ok(());
+    }
+
+    let path = Path::new(backup_path);
+    manifest.add_zip_contents(path)?;
+    manifest.embed_into_zip(path)?;
+    log::info!(
+        "Embedded manifest in {} (Odoo {}, {} modules)",
+        backup_path,
+        manifest.odoo_version.as_deref().unwrap_or("unknown"),
+        manifest.modules.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn create_test_database_config() -> DatabaseConfig {
+        DatabaseConfig {
+            name: "Test Client".to_string(),
+            database_name: "test_database".to_string(),
+            container_name: "test_container".to_string(),
+            master_password: "admin".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn create_odoo_zip(path: &Path) {
+        let mut writer = ZipWriter::new(File::create(path).unwrap());
+        let options = SimpleFileOptions::default();
+        writer.start_file("manifest.json", options).unwrap();
+        writer
+            .write_all(
+                br#"{
+                    "odoo_dump": "1",
+                    "db_name": "test_database",
+                    "version": "17.0-20240101",
+                    "major_version": "17.0",
+                    "pg_version": "15.4",
+                    "modules": {"base": "17.0.1.3", "sale": "17.0.1.2"}
+                }"#,
+            )
+            .unwrap();
+        writer.start_file("dump.sql", options).unwrap();
+        writer.write_all(&[b'x'; 1000]).unwrap();
+        writer.start_file("filestore/ab/abc123", options).unwrap();
+        writer.write_all(&[b'y'; 300]).unwrap();
+        writer.start_file("filestore/cd/cde456", options).unwrap();
+        writer.write_all(&[b'z'; 200]).unwrap();
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_manifest_add_zip_contents() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("backup.zip");
+        create_odoo_zip(&path);
+
+        let mut manifest = BackupManifest::new(&create_test_database_config());
+        manifest.add_zip_contents(&path).unwrap();
+        assert_eq!(manifest.tool_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(manifest.client, "Test Client");
+        assert_eq!(manifest.odoo_version.as_deref(), Some("17.0-20240101"));
+        assert_eq!(manifest.pg_version.as_deref(), Some("15.4"));
+        assert_eq!(manifest.modules.len(), 2);
+        assert_eq!(manifest.modules["sale"], "17.0.1.2");
+        assert_eq!(manifest.database_size, 1000);
+        assert_eq!(manifest.filestore_size, 500);
+        assert_eq!(manifest.filestore_files, 2);
+
+        // What the instance reported wins over Odoo's manifest.json
+        let mut manifest = BackupManifest::new(&create_test_database_config());
+        manifest.odoo_version = Some("17.0+e".to_string());
+        manifest
+            .modules
+            .insert("base".to_string(), "17.0.1.4".to_string());
+        manifest.database_size = 4096;
+        manifest.add_zip_contents(&path).unwrap();
+        assert_eq!(manifest.odoo_version.as_deref(), Some("17.0+e"));
+        assert_eq!(manifest.modules.len(), 1);
+        assert_eq!(manifest.database_size, 4096);
+        assert_eq!(manifest.filestore_files, 2);
+    }
+
+    #[test]
+    fn test_embed_and_read_manifest() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("backup.zip");
+        create_odoo_zip(&path);
+
+        assert!(BackupManifest::read_from_zip(&path).unwrap().is_none());
+
+        let mut written = BackupManifest::new(&create_test_database_config());
+        write_manifest(&mut written, path.to_str().unwrap()).unwrap();
+        let read = BackupManifest::read_from_zip(&path).unwrap().unwrap();
+        assert_eq!(read, written);
+
+        // The original entries are still intact
+        let mut archive = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(archive.len(), 5);
+        assert_eq!(archive.by_name("dump.sql").unwrap().size(), 1000);
+    }
+
+    #[test]
+    fn test_write_manifest_leaves_dump_format() {
+        let mut config = create_test_database_config();
+        config.backup_format = "dump".to_string();
+        let mut manifest = BackupManifest::new(&config);
+        let expected = manifest.clone();
+        write_manifest(&mut manifest, "/nonexistent/backup.dump").unwrap();
+        assert_eq!(manifest, expected);
+    }
+
+    #[test]
+    fn test_manifest_compatibility() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("backup.zip");
+        create_odoo_zip(&path);
+        let mut manifest = BackupManifest::new(&create_test_database_config());
+        manifest.add_zip_contents(&path).unwrap();
+
+        assert!(manifest.is_compatible_with("17.0"));
+        assert!(manifest.is_compatible_with("17.0+e"));
+        assert!(!manifest.is_compatible_with("16.0"));
+        assert!(!manifest.is_compatible_with("saas~18.1"));
+
+        manifest.odoo_major_version = None;
+        manifest.odoo_version = None;
+        assert!(manifest.is_compatible_with("16.0"));
+    }
+
+    #[test]
+    fn test_major_of() {
+        assert_eq!(major_of("17.0"), Some("17"));
+        assert_eq!(major_of("16.0-20231010"), Some("16"));
+        assert_eq!(major_of("saas~17.2"), Some("17"));
+        assert_eq!(major_of("unknown"), None);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::catalog::{BackupStatus, Catalog};
+use crate::config::Config;
+use crate::error::{BackupError, Result};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Metrics about each enabled database in the Prometheus text format, for
+/// the node_exporter textfile collector.
+pub fn render(config: &Config, catalog: &Catalog) -> String {
+    let last_backups = catalog.last_backups();
+    let mut last_success = String::new();
+    let mut duration = String::new();
+    let mut size = String::new();
+    let mut failures = String::new();
+    for db in config.enabled_databases(None) {
+        let labels = format!(
+            "client=\"{}\",database=\"{}\"",
+            escape(&db.name),
+            escape(&db.database_name)
+        );
+        if let Some(last) = last_backups.get(&db.name) {
+            let _ = writeln!(
+                last_success,
+                "odoo_backup_last_success_timestamp_seconds{{{}}} {}",
+                labels,
+                last.timestamp()
+            );
+        }
+        if let Some(entry) = catalog.last_success(&db.name) {
+            if let Some(secs) = entry.duration_secs() {
+                let _ = writeln!(
+                    duration,
+                    "odoo_backup_last_duration_seconds{{{}}} {}",
+                    labels, secs
+                );
+            }
+            if let Some(bytes) = entry.size {
+                let _ = writeln!(size, "odoo_backup_last_size_bytes{{{}}} {}", labels, bytes);
+            }
+        }
+        let failed = catalog
+            .history(Some(&db.name))
+            .iter()
+            .filter(|entry| entry.status == BackupStatus::Failed)
+            .count();
+        let _ = writeln!(
+            failures,
+            "odoo_backup_failures_total{{{}}} {}",
+            labels, failed
+        );
+    }
+
+    let mut text = String::new();
+    for (name, kind, help, samples) in [
+        (
+            "odoo_backup_last_success_timestamp_seconds",
+            "gauge",
+            "Start of the last successful, or skipped as unchanged, backup.",
+            last_success,
+        ),
+        (
+            "odoo_backup_last_duration_seconds",
+            "gauge",
+            "Duration of the last successful backup.",
+            duration,
+        ),
+        (
+            "odoo_backup_last_size_bytes",
+            "gauge",
+            "Size of the last successful backup.",
+            size,
+        ),
+        (
+            "odoo_backup_failures_total",
+            "counter",
+            "Failed backups recorded in the catalog.",
+            failures,
+        ),
+    ] {
+        let _ = writeln!(text, "# HELP {} {}", name, help);
+        let _ = writeln!(text, "# TYPE {} {}", name, kind);
+        text.push_str(&samples);
+    }
+    text
+}
+
+/// Label value with backslashes, quotes and line breaks escaped.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Writes the metrics to `path`. The file is written next to it first and
+/// then renamed, so the collector never reads a partial file.
+pub fn write_textfile(path: &str, config: &Config, backup_dir: &str) -> Result<()> {
+    let text = render(config, &Catalog::load(backup_dir)?);
+    let tmp = format!("{}.tmp", path);
+    fs::write(&tmp, text)
+        .map_err(|e| BackupError::from_fs(format!("Failed to write {}", tmp), e))?;
+    fs::rename(&tmp, Path::new(path))
+        .map_err(|e| BackupError::from_fs(format!("Failed to replace {}", path), e))
+}
+
+/// Refreshes the `metrics_textfile` if one is configured. A failure is only
+/// logged, so it never fails the backup run.
+pub fn update(config: &Config, backup_dir: &str) {
+    if let Some(path) = &config.metrics_textfile {
+        if let Err(e) = write_textfile(path, config, backup_dir) {
+            log::warn!("Failed to write metrics to {}: {}", path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::CatalogEntry;
+    use crate::config::DatabaseConfig;
+    use chrono::{Duration, Utc};
+    use tempfile::tempdir;
+
+    fn database(name: &str) -> DatabaseConfig {
+        DatabaseConfig {
+            name: name.to_string(),
+            database_name: name.to_lowercase(),
+            container_name: "odoo".to_string(),
+            master_password: "admin".to_string(),
+            retention_days: 7,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_write_textfile() {
+        let dir = tempdir().unwrap();
+        let backup_dir = dir.path().to_str().unwrap();
+        let acme = database("Acme");
+        let started = Utc::now() - Duration::hours(2);
+
+        let mut entry = CatalogEntry::start(&acme);
+        entry.started_at = started;
+        entry.succeed("/tmp/backups/backup_acme.zip");
+        entry.finished_at = Some(started + Duration::seconds(90));
+        entry.size = Some(2048);
+        Catalog::record(backup_dir, entry).unwrap();
+        let mut failed = CatalogEntry::start(&acme);
+        failed.fail(&BackupError::Config("boom".to_string()));
+        Catalog::record(backup_dir, failed).unwrap();
+
+        let config = Config {
+            databases: vec![acme, database("Globex \"EU\"")],
+            ..Default::default()
+        };
+        let path = dir.path().join("odoo_backup.prom");
+        write_textfile(path.to_str().unwrap(), &config, backup_dir).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+
+        let acme = "{client=\"Acme\",database=\"acme\"}";
+        assert!(text.contains(&format!(
+            "odoo_backup_last_success_timestamp_seconds{} {}\n",
+            acme,
+            started.timestamp()
+        )));
+        assert!(text.contains(&format!("odoo_backup_last_duration_seconds{} 90\n", acme)));
+        assert!(text.contains(&format!("odoo_backup_last_size_bytes{} 2048\n", acme)));
+        assert!(text.contains(&format!("odoo_backup_failures_total{} 1\n", acme)));
+        assert!(text.contains("# TYPE odoo_backup_failures_total counter\n"));
+
+        // A database never backed up only reports its failure count
+        let globex = "{client=\"Globex \\\"EU\\\"\",database=\"globex \\\"eu\\\"\"}";
+        assert!(text.contains(&format!("odoo_backup_failures_total{} 0\n", globex)));
+        assert!(!text.contains(&format!("odoo_backup_last_size_bytes{}", globex)));
+        assert!(!dir.path().join("odoo_backup.prom.tmp").exists());
+    }
+}
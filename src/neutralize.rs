@@ -0,0 +1,68 @@
+/// First Odoo version whose database manager can neutralize a database
+/// while restoring it (the `neutralize_database` form field).
+pub const NATIVE_MIN_MAJOR: u32 = 16;
+
+/// Whether Odoo `major` neutralizes restored databases itself. Unknown
+/// versions fall back to the SQL below.
+pub fn is_native(major: Option<u32>) -> bool {
+    major.is_some_and(|major| major >= NATIVE_MIN_MAJOR)
+}
+
+/// Disables scheduled actions, mail servers and payment providers of a
+/// restored database, for Odoo versions without a neutralize mechanism.
+/// Like Odoo, the autovacuum job is left running, since without it the
+/// database piles up transient records and stale sessions. Tables that only
+/// exist with some modules or versions are skipped.
+pub const NEUTRALIZE_SQL: &str = "\
+BEGIN;
+UPDATE ir_cron SET active = false
+    WHERE id NOT IN (SELECT res_id FROM ir_model_data WHERE module = 'base' AND name = 'autovacuum_job');
+UPDATE ir_mail_server SET active = false;
+DO $$
+BEGIN
+    IF to_regclass('fetchmail_server') IS NOT NULL THEN
+        UPDATE fetchmail_server SET active = false;
+    END IF;
+    IF to_regclass('payment_acquirer') IS NOT NULL THEN
+        UPDATE payment_acquirer SET state = 'disabled';
+    END IF;
+    IF to_regclass('payment_provider') IS NOT NULL THEN
+        UPDATE payment_provider SET state = 'disabled';
+    END IF;
+END
+$$;
+INSERT INTO ir_config_parameter (key, value) VALUES ('database.is_neutralized', 'True')
+    ON CONFLICT (key) DO UPDATE SET value = 'True';
+COMMIT;
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_native() {
+        assert!(is_native(Some(16)));
+        assert!(is_native(Some(18)));
+        assert!(!is_native(Some(15)));
+        assert!(!is_native(None));
+    }
+
+    #[test]
+    fn test_sql_covers_outgoing_integrations() {
+        for table in [
+            "ir_cron",
+            "ir_mail_server",
+            "payment_acquirer",
+            "payment_provider",
+        ] {
+            assert!(
+                NEUTRALIZE_SQL.contains(table),
+                "{} is not neutralized",
+                table
+            );
+        }
+        assert!(NEUTRALIZE_SQL.trim_end().ends_with("COMMIT;"));
+        assert!(NEUTRALIZE_SQL.contains("name = 'autovacuum_job'"));
+    }
+}
@@ -0,0 +1,370 @@
+use crate::backup::{VerifyResult, VerifyStatus};
+use crate::error::{BackupError, Result, ResultExt};
+use crate::freshness::Freshness;
+use crate::size;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::process::Command;
+
+/// A channel alerts are sent to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum NotificationConfig {
+    /// POSTs a JSON body with `text`, `subject` and `message` fields, which
+    /// Slack and Mattermost incoming webhooks accept as is
+    Webhook { url: String },
+    /// Runs a shell command with `ODOO_BACKUP_SUBJECT` and
+    /// `ODOO_BACKUP_MESSAGE` set, e.g. to send mail
+    Command { command: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub subject: String,
+    pub message: String,
+}
+
+impl NotificationConfig {
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        match self {
+            NotificationConfig::Webhook { url } if url.is_empty() => {
+                Err("url cannot be empty".to_string())
+            }
+            NotificationConfig::Command { command } if command.is_empty() => {
+                Err("command cannot be empty".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn send(&self, notification: &Notification) -> Result<()> {
+        match self {
+            NotificationConfig::Webhook { url } => {
+                let body = serde_json::json!({
+                    "text": format!("{}\n{}", notification.subject, notification.message),
+                    "subject": notification.subject,
+                    "message": notification.message,
+                });
+                reqwest::Client::new()
+                    .post(url)
+                    .json(&body)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                Ok(())
+            }
+            NotificationConfig::Command { command } => {
+                let output = Command::new("sh")
+                    .args(["-c", command])
+                    .env("ODOO_BACKUP_SUBJECT", &notification.subject)
+                    .env("ODOO_BACKUP_MESSAGE", &notification.message)
+                    .output()
+                    .context("Failed to run notification command")?;
+                if !output.status.success() {
+                    return Err(BackupError::Notification(format!(
+                        "Notification command failed: {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Sends `notification` to every channel. Failures are logged, since an
+/// unreachable channel must not stop the job that raised the alert.
+pub async fn notify_all(channels: &[NotificationConfig], notification: &Notification) {
+    if channels.is_empty() {
+        log::warn!(
+            "No notification channels configured for alert: {}",
+            notification.subject
+        );
+    }
+    for channel in channels {
+        if let Err(e) = channel.send(notification).await {
+            log::error!("Failed to send notification: {}", e);
+        }
+    }
+}
+
+/// Alert describing the backups that failed re-verification, if any did.
+pub fn verification_alert(results: &[VerifyResult]) -> Option<Notification> {
+    let problems: Vec<String> = results
+        .iter()
+        .filter_map(|result| match &result.status {
+            VerifyStatus::Ok => None,
+            VerifyStatus::Missing => Some(format!(
+                "{} ({}): backup file is missing",
+                result.file_name, result.client
+            )),
+            VerifyStatus::Corrupt { reason } => Some(format!(
+                "{} ({}): {}",
+                result.file_name, result.client, reason
+            )),
+        })
+        .collect();
+    if problems.is_empty() {
+        return None;
+    }
+
+    Some(Notification {
+        subject: format!(
+            "Backup verification found {} of {} backups damaged",
+            problems.len(),
+            results.len()
+        ),
+        message: problems.join("\n"),
+    })
+}
+
+/// Alert listing databases without a recent enough backup, if any.
+pub fn freshness_alert(stale: &[Freshness], now: DateTime<Utc>) -> Option<Notification> {
+    if stale.is_empty() {
+        return None;
+    }
+    Some(Notification {
+        subject: format!("{} databases have no recent backup", stale.len()),
+        message: stale
+            .iter()
+            .map(|f| f.describe(now))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    })
+}
+
+/// Alert for configured containers down since the given times.
+pub fn container_down_alert(
+    down: &[(String, DateTime<Utc>)],
+    now: DateTime<Utc>,
+) -> Option<Notification> {
+    if down.is_empty() {
+        return None;
+    }
+    Some(Notification {
+        subject: format!("{} containers are down", down.len()),
+        message: down
+            .iter()
+            .map(|(container, since)| {
+                format!(
+                    "{} has been down for {}; its backups wait until it starts",
+                    container,
+                    size::format_age((now - *since).num_seconds())
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    })
+}
+
+/// Alert for the databases of `container` whose backup before it stopped
+/// `failed`, with the error, and how old their last good backup in `last`
+/// is.
+pub fn stopped_without_backup_alert(
+    container: &str,
+    failed: &[(String, String)],
+    last: &BTreeMap<String, DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Option<Notification> {
+    if failed.is_empty() {
+        return None;
+    }
+    Some(Notification {
+        subject: format!(
+            "Container {} stopped without a fresh backup of {} databases",
+            container,
+            failed.len()
+        ),
+        message: failed
+            .iter()
+            .map(|(client, error)| {
+                let last = match last.get(client) {
+                    Some(at) => format!(
+                        "last backup {} ago",
+                        size::format_age((now - *at).num_seconds())
+                    ),
+                    None => "never backed up".to_string(),
+                };
+                format!("{} ({}): {}", client, last, error)
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    })
+}
+
+/// Alert for a backup still running `elapsed` into it, against an average
+/// of `average` for its recent backups.
+pub fn slow_backup_alert(
+    client: &str,
+    elapsed: std::time::Duration,
+    average: chrono::Duration,
+) -> Notification {
+    Notification {
+        subject: format!("Backup of {} is running slow", client),
+        message: format!(
+            "Still running after {}, against an average of {} for its recent backups. \
+             The backup continues.",
+            size::format_age(elapsed.as_secs() as i64),
+            size::format_age(average.num_seconds())
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn verify_result(file_name: &str, status: VerifyStatus) -> VerifyResult {
+        VerifyResult {
+            file_name: file_name.to_string(),
+            client: "Test Client".to_string(),
+            status,
+        }
+    }
+
+    #[test]
+    fn test_notification_config_parsing() {
+        let channels: Vec<NotificationConfig> = serde_json::from_str(
+            r#"[
+                {"type": "webhook", "url": "https://hooks.example.com/T000"},
+                {"type": "command", "command": "mail -s \"$ODOO_BACKUP_SUBJECT\" ops"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            channels[0],
+            NotificationConfig::Webhook {
+                url: "https://hooks.example.com/T000".to_string()
+            }
+        );
+        assert!(channels.iter().all(|c| c.validate().is_ok()));
+
+        let empty = NotificationConfig::Webhook { url: String::new() };
+        assert!(empty.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_command_notification() {
+        let temp_dir = tempdir().unwrap();
+        let out = temp_dir.path().join("alert.txt");
+        let channel = NotificationConfig::Command {
+            command: format!(
+                "printf '%s|%s' \"$ODOO_BACKUP_SUBJECT\" \"$ODOO_BACKUP_MESSAGE\" > {}",
+                out.display()
+            ),
+        };
+        let notification = Notification {
+            subject: "Backups damaged".to_string(),
+            message: "backup.zip: checksum mismatch".to_string(),
+        };
+        channel.send(&notification).await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            "Backups damaged|backup.zip: checksum mismatch"
+        );
+
+        let failing = NotificationConfig::Command {
+            command: "exit 3".to_string(),
+        };
+        assert!(failing.send(&notification).await.is_err());
+    }
+
+    #[test]
+    fn test_verification_alert() {
+        assert!(verification_alert(&[verify_result("a.zip", VerifyStatus::Ok)]).is_none());
+
+        let alert = verification_alert(&[
+            verify_result("a.zip", VerifyStatus::Ok),
+            verify_result("b.zip", VerifyStatus::Missing),
+            verify_result(
+                "c.zip",
+                VerifyStatus::Corrupt {
+                    reason: "checksum mismatch".to_string(),
+                },
+            ),
+        ])
+        .unwrap();
+        assert_eq!(
+            alert.subject,
+            "Backup verification found 2 of 3 backups damaged"
+        );
+        assert_eq!(
+            alert.message,
+            "b.zip (Test Client): backup file is missing\nc.zip (Test Client): checksum mismatch"
+        );
+    }
+
+    #[test]
+    fn test_freshness_alert() {
+        let now = Utc::now();
+        assert!(freshness_alert(&[], now).is_none());
+
+        let alert = freshness_alert(
+            &[
+                Freshness {
+                    client: "Acme".to_string(),
+                    last_backup: Some(now - chrono::Duration::hours(50)),
+                    max_age_hours: 26,
+                    stale: true,
+                },
+                Freshness {
+                    client: "Globex".to_string(),
+                    last_backup: None,
+                    max_age_hours: 26,
+                    stale: true,
+                },
+            ],
+            now,
+        )
+        .unwrap();
+        assert_eq!(alert.subject, "2 databases have no recent backup");
+        assert_eq!(
+            alert.message,
+            "Acme: last backup 2d 2h ago, expected within 26h\nGlobex: never backed up successfully"
+        );
+    }
+
+    #[test]
+    fn test_slow_backup_alert() {
+        let alert = slow_backup_alert(
+            "Acme",
+            std::time::Duration::from_secs(45 * 60),
+            chrono::Duration::minutes(20),
+        );
+        assert_eq!(alert.subject, "Backup of Acme is running slow");
+        assert_eq!(
+            alert.message,
+            "Still running after 45m, against an average of 20m for its recent backups. \
+             The backup continues."
+        );
+    }
+
+    #[test]
+    fn test_stopped_without_backup_alert() {
+        let now = Utc::now();
+        let last = BTreeMap::from([("Acme".to_string(), now - chrono::Duration::hours(5))]);
+        assert_eq!(stopped_without_backup_alert("odoo", &[], &last, now), None);
+        let failed = [
+            (
+                "Acme".to_string(),
+                "Docker error: container is not running".to_string(),
+            ),
+            (
+                "Globex".to_string(),
+                "Network error: no response".to_string(),
+            ),
+        ];
+        let alert = stopped_without_backup_alert("odoo", &failed, &last, now).unwrap();
+        assert_eq!(
+            alert.subject,
+            "Container odoo stopped without a fresh backup of 2 databases"
+        );
+        assert_eq!(
+            alert.message,
+            "Acme (last backup 5h 0m ago): Docker error: container is not running\n\
+             Globex (never backed up): Network error: no response"
+        );
+    }
+}
@@ -0,0 +1,572 @@
+use crate::error::{BackupError, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Body of an empty JSON-RPC call, as expected by Odoo's `type="json"` routes.
+pub const EMPTY_JSONRPC_CALL: &str = r#"{"jsonrpc":"2.0","method":"call","params":{}}"#;
+
+/// Page fetched to check that Odoo serves requests; it needs no login and
+/// no database.
+pub const HEALTH_CHECK_PATH: &str = "/web/login";
+
+/// Answer of Odoo to a health check request.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthProbe {
+    /// HTTP status code
+    pub status: u16,
+    pub latency_ms: u64,
+}
+
+impl HealthProbe {
+    /// Whether the status says Odoo is serving; redirects count, as a
+    /// database filter may send the login page elsewhere.
+    pub fn is_healthy(&self) -> bool {
+        (200..400).contains(&self.status)
+    }
+}
+
+/// Parses curl's `-w '%{http_code} %{time_total}'` output, e.g. `200 0.042`.
+pub fn parse_health_probe(output: &str) -> Result<HealthProbe> {
+    let unexpected =
+        || BackupError::OdooApi(format!("Unexpected health check output '{}'", output));
+    let (status, seconds) = output.trim().split_once(' ').ok_or_else(unexpected)?;
+    let status: u16 = status.parse().map_err(|_| unexpected())?;
+    let seconds: f64 = seconds.parse().map_err(|_| unexpected())?;
+    // curl reports 000 when no response arrived at all
+    if status == 0 {
+        return Err(BackupError::Network("no response from Odoo".to_string()));
+    }
+    Ok(HealthProbe {
+        status,
+        latency_ms: (seconds * 1000.0).round() as u64,
+    })
+}
+
+/// Result of `/web/webclient/version_info`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VersionInfo {
+    pub server_version: String,
+    /// e.g. `[17, 0, 0, "final", 0, "e"]`
+    #[serde(default)]
+    pub server_version_info: Vec<serde_json::Value>,
+}
+
+impl VersionInfo {
+    /// Major version, e.g. 17 for "17.0+e" and 16 for "saas~16.3".
+    pub fn major(&self) -> Option<u32> {
+        if let Some(major) = self.server_version_info.first().and_then(|v| v.as_u64()) {
+            return u32::try_from(major).ok();
+        }
+        let version = self.server_version.trim_start_matches("saas~");
+        let digits: String = version.chars().take_while(|c| c.is_ascii_digit()).collect();
+        digits.parse().ok()
+    }
+}
+
+/// First Odoo version whose database manager takes the `master_pwd`,
+/// `name` and `backup_format` form fields used for backups.
+pub const MIN_SUPPORTED_MAJOR: u32 = 9;
+
+/// Why backups in `backup_format` may not work against Odoo `major`.
+pub fn compatibility_warnings(major: u32, backup_format: &str) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if major < MIN_SUPPORTED_MAJOR {
+        warnings.push(format!(
+            "Odoo {} predates the database manager API used for backups, which needs Odoo {} or later",
+            major, MIN_SUPPORTED_MAJOR
+        ));
+    }
+    if major < 8 && backup_format == "zip" {
+        warnings.push(format!("Odoo {} only makes dump backups, not zip", major));
+    }
+    warnings
+}
+
+/// Checks `name` against the rule Odoo's database manager applies to new
+/// databases: a letter or digit, then letters, digits, `_`, `.` or `-`.
+pub fn validate_database_name(name: &str) -> std::result::Result<(), String> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphanumeric())
+        && name.len() > 1
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+    if !valid {
+        return Err(format!(
+            "'{}' is not a valid database name: use letters, digits, '_', '.' and '-', \
+             starting with a letter or digit",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Database manager route that streams a backup. It checks the master
+/// password itself, whatever session the request carries.
+pub const BACKUP_ROUTE: &str = "/web/database/backup";
+
+/// Database manager page that carries the CSRF token of the backup form.
+pub const DATABASE_MANAGER_PATH: &str = "/web/database/manager";
+
+/// First Odoo version whose backup form is sent with its CSRF token and
+/// session cookie, as the database manager page does.
+pub const CSRF_TOKEN_MIN_MAJOR: u32 = 17;
+
+/// Whether backups from Odoo `major` go through the CSRF token handshake.
+/// Unknown versions are treated as recent.
+pub fn backup_needs_csrf_token(major: Option<u32>) -> bool {
+    major.is_none_or(|major| major >= CSRF_TOKEN_MIN_MAJOR)
+}
+
+/// The `csrf_token` hidden input of a database manager page.
+pub fn parse_csrf_token(html: &str) -> Option<String> {
+    let input = &html[html.find("name=\"csrf_token\"")?..];
+    let input = &input[..input.find('>')?];
+    let value = &input[input.find("value=\"")? + "value=\"".len()..];
+    let token = &value[..value.find('"')?];
+    (!token.is_empty()).then(|| token.to_string())
+}
+
+/// Where a user logs in for a session cookie.
+pub const SESSION_AUTHENTICATE_PATH: &str = "/web/session/authenticate";
+
+/// The JSON-RPC call logging `login` in to `database`.
+pub fn session_authenticate_call(database: &str, login: &str, password: &str) -> String {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "call",
+        "params": {"db": database, "login": login, "password": password},
+    })
+    .to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionInfo {
+    #[serde(default)]
+    uid: serde_json::Value,
+}
+
+/// Checks the answer to a session login. Odoo rejects wrong credentials
+/// with an `AccessDenied` error, or in older versions with no `uid`.
+pub fn parse_session_info(body: &str) -> Result<()> {
+    let info: SessionInfo = parse_jsonrpc_result(body)?;
+    if info.uid.is_u64() {
+        Ok(())
+    } else {
+        Err(BackupError::AuthFailed(
+            "Session login rejected".to_string(),
+        ))
+    }
+}
+
+/// Status and content type of the backup download, as printed by curl's
+/// `-w '%{http_code} %{content_type}'`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupResponse {
+    pub status: u16,
+    pub content_type: String,
+}
+
+impl BackupResponse {
+    pub fn parse(write_out: &str) -> Option<Self> {
+        let (status, content_type) = write_out
+            .trim()
+            .split_once(' ')
+            .unwrap_or((write_out.trim(), ""));
+        Some(Self {
+            status: status.parse().ok().filter(|status| *status != 0)?,
+            content_type: content_type.trim().to_lowercase(),
+        })
+    }
+
+    /// Odoo answers failed backups with an HTML page or a JSON error body,
+    /// which curl saves in place of the backup.
+    pub fn is_error(&self) -> bool {
+        !(200..300).contains(&self.status)
+            || self.content_type.starts_with("text/html")
+            || self.content_type.starts_with("application/json")
+    }
+}
+
+/// Turns the error body Odoo sent instead of a backup into an error,
+/// `AuthFailed` when the master password was rejected.
+pub fn backup_error(response: &BackupResponse, body: &str) -> BackupError {
+    if let Ok(JsonRpcResponse::<serde_json::Value> {
+        error: Some(error), ..
+    }) = serde_json::from_str(body)
+    {
+        return error.into_error();
+    }
+    let detail = html_error_message(body)
+        .unwrap_or_else(|| format!("HTTP {} ({})", response.status, response.content_type));
+    if detail.contains("Access Denied") || detail.contains("Access denied") {
+        return BackupError::AuthFailed(detail);
+    }
+    BackupError::OdooApi(format!(
+        "Odoo sent an error instead of a backup: {}",
+        detail
+    ))
+}
+
+/// The message of the database manager's error alert, or else the page title.
+fn html_error_message(html: &str) -> Option<String> {
+    let start = html
+        .find("alert-danger")
+        .and_then(|i| html[i..].find('>').map(|j| i + j + 1))
+        .or_else(|| html.find("<title>").map(|i| i + "<title>".len()))?;
+    let rest = &html[start..];
+    let end = rest
+        .find("</div>")
+        .or_else(|| rest.find("</title>"))
+        .unwrap_or(rest.len());
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in rest[..end].chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcResponse<T> {
+    result: Option<T>,
+    error: Option<JsonRpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcError {
+    message: String,
+    #[serde(default)]
+    data: Option<JsonRpcErrorData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcErrorData {
+    /// Python exception class, e.g. `odoo.exceptions.AccessDenied`
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+impl JsonRpcError {
+    fn into_error(self) -> BackupError {
+        let (name, message) = self.data.map(|d| (d.name, d.message)).unwrap_or_default();
+        let detail = message.unwrap_or(self.message);
+        if name.is_some_and(|n| n.ends_with("AccessDenied")) {
+            return BackupError::AuthFailed(detail);
+        }
+        BackupError::OdooApi(detail)
+    }
+}
+
+/// Unwraps the result of an Odoo JSON-RPC response body.
+pub fn parse_jsonrpc_result<T: DeserializeOwned>(body: &str) -> Result<T> {
+    let response: JsonRpcResponse<T> = serde_json::from_str(body)
+        .map_err(|e| BackupError::OdooApi(format!("Unexpected response from Odoo: {}", e)))?;
+
+    if let Some(error) = response.error {
+        return Err(error.into_error());
+    }
+    response
+        .result
+        .ok_or_else(|| BackupError::OdooApi("Odoo response has no result".to_string()))
+}
+
+pub fn parse_version_info(body: &str) -> Result<VersionInfo> {
+    parse_jsonrpc_result(body)
+}
+
+/// Route of Odoo's external API, serving the `common` and `object` services.
+pub const JSONRPC_PATH: &str = "/jsonrpc";
+
+/// The external API call of `method` of `service` with `args`.
+pub fn service_call(service: &str, method: &str, args: serde_json::Value) -> String {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "call",
+        "params": {"service": service, "method": method, "args": args},
+    })
+    .to_string()
+}
+
+/// The user id `common.login` answered with. Odoo answers `false` to
+/// wrong credentials.
+pub fn parse_login(body: &str) -> Result<u64> {
+    let uid: serde_json::Value = parse_jsonrpc_result(body)?;
+    uid.as_u64()
+        .ok_or_else(|| BackupError::AuthFailed("API login rejected".to_string()))
+}
+
+#[derive(Debug, Deserialize)]
+struct ModuleRecord {
+    name: String,
+    /// `false` for modules installed before their version was recorded
+    #[serde(default)]
+    latest_version: serde_json::Value,
+}
+
+/// Installed modules and their versions from a `search_read` of
+/// `ir.module.module`, as Odoo lists them in the manifest of zip backups.
+pub fn parse_installed_modules(body: &str) -> Result<BTreeMap<String, String>> {
+    let records: Vec<ModuleRecord> = parse_jsonrpc_result(body)?;
+    Ok(records
+        .into_iter()
+        .map(|record| {
+            let version = record.latest_version.as_str().unwrap_or_default();
+            (record.name, version.to_string())
+        })
+        .collect())
+}
+
+#[derive(Debug, Deserialize)]
+struct ParameterRecord {
+    value: String,
+}
+
+/// The value from a `search_read` of `ir.config_parameter`, if the
+/// parameter is set.
+pub fn parse_config_parameter(body: &str) -> Result<Option<String>> {
+    let records: Vec<ParameterRecord> = parse_jsonrpc_result(body)?;
+    Ok(records.into_iter().next().map(|record| record.value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_database_name() {
+        for name in ["acme", "acme_staging", "client1.dev-2", "0db"] {
+            assert!(validate_database_name(name).is_ok(), "{}", name);
+        }
+        for name in [
+            "",
+            "a",
+            "_acme",
+            "../../x",
+            "acme'; rm -rf /",
+            "acme db",
+            "..",
+        ] {
+            assert!(validate_database_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_parse_session_info() {
+        assert!(
+            parse_session_info(r#"{"jsonrpc": "2.0", "id": null, "result": {"uid": 2}}"#).is_ok()
+        );
+        assert!(matches!(
+            parse_session_info(r#"{"jsonrpc": "2.0", "id": null, "result": {"uid": false}}"#),
+            Err(BackupError::AuthFailed(_))
+        ));
+        assert!(matches!(
+            parse_session_info(
+                r#"{"jsonrpc": "2.0", "id": null, "error": {"code": 200, "message": "Odoo Server Error",
+                "data": {"name": "odoo.exceptions.AccessDenied", "message": "Access Denied"}}}"#
+            ),
+            Err(BackupError::AuthFailed(_))
+        ));
+        assert_eq!(
+            session_authenticate_call("acme", "admin@acme.com", "it's"),
+            r#"{"jsonrpc":"2.0","method":"call","params":{"db":"acme","login":"admin@acme.com","password":"it's"}}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        assert_eq!(
+            parse_login(r#"{"jsonrpc": "2.0", "id": null, "result": 2}"#).unwrap(),
+            2
+        );
+        assert!(matches!(
+            parse_login(r#"{"jsonrpc": "2.0", "id": null, "result": false}"#),
+            Err(BackupError::AuthFailed(_))
+        ));
+
+        let modules = parse_installed_modules(
+            r#"{"jsonrpc": "2.0", "id": null, "result": [
+                {"id": 1, "name": "base", "latest_version": "17.0.1.3"},
+                {"id": 9, "name": "sale", "latest_version": false}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(modules["base"], "17.0.1.3");
+        assert_eq!(modules["sale"], "");
+
+        assert_eq!(
+            parse_config_parameter(
+                r#"{"jsonrpc": "2.0", "id": null, "result": [{"id": 3, "value": "https://erp.acme.com"}]}"#
+            )
+            .unwrap()
+            .as_deref(),
+            Some("https://erp.acme.com")
+        );
+        assert_eq!(
+            parse_config_parameter(r#"{"jsonrpc": "2.0", "id": null, "result": []}"#).unwrap(),
+            None
+        );
+        assert_eq!(
+            service_call(
+                "common",
+                "login",
+                serde_json::json!(["acme", "admin", "pw"])
+            ),
+            r#"{"jsonrpc":"2.0","method":"call","params":{"service":"common","method":"login","args":["acme","admin","pw"]}}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_version_info() {
+        let body = r#"{"jsonrpc": "2.0", "id": null, "result": {
+            "server_version": "17.0+e",
+            "server_version_info": [17, 0, 0, "final", 0, "e"],
+            "server_serie": "17.0",
+            "protocol_version": 1
+        }}"#;
+        let info = parse_version_info(body).unwrap();
+        assert_eq!(info.server_version, "17.0+e");
+    }
+
+    #[test]
+    fn test_version_major() {
+        let info = |body: &str| parse_version_info(body).unwrap();
+        assert_eq!(
+            info(r#"{"result": {"server_version": "17.0+e", "server_version_info": [17, 0, 0, "final", 0, "e"]}}"#).major(),
+            Some(17)
+        );
+        assert_eq!(
+            info(r#"{"result": {"server_version": "saas~16.3+e"}}"#).major(),
+            Some(16)
+        );
+        assert_eq!(
+            info(r#"{"result": {"server_version": "8.0"}}"#).major(),
+            Some(8)
+        );
+        assert_eq!(
+            info(r#"{"result": {"server_version": "master"}}"#).major(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_compatibility_warnings() {
+        assert!(compatibility_warnings(17, "zip").is_empty());
+        assert!(compatibility_warnings(9, "dump").is_empty());
+        assert_eq!(compatibility_warnings(8, "zip").len(), 1);
+        assert_eq!(compatibility_warnings(7, "dump").len(), 1);
+        assert_eq!(compatibility_warnings(7, "zip").len(), 2);
+    }
+
+    #[test]
+    fn test_parse_csrf_token() {
+        let html =
+            r#"<form><input type="hidden" name="csrf_token" value="abc123o1700000000"/></form>"#;
+        assert_eq!(parse_csrf_token(html).as_deref(), Some("abc123o1700000000"));
+        assert_eq!(parse_csrf_token("<form></form>"), None);
+        assert!(backup_needs_csrf_token(Some(17)));
+        assert!(backup_needs_csrf_token(None));
+        assert!(!backup_needs_csrf_token(Some(16)));
+    }
+
+    #[test]
+    fn test_backup_response() {
+        let ok = BackupResponse::parse("200 application/octet-stream").unwrap();
+        assert!(!ok.is_error());
+        let page = BackupResponse::parse("200 text/html; charset=utf-8").unwrap();
+        assert!(page.is_error());
+        assert!(BackupResponse::parse("500 ").unwrap().is_error());
+        assert_eq!(BackupResponse::parse("000 "), None);
+        assert_eq!(BackupResponse::parse(""), None);
+    }
+
+    #[test]
+    fn test_backup_error() {
+        let page = BackupResponse::parse("200 text/html").unwrap();
+        let html = r#"<html><head><title>Odoo</title></head><body>
+            <div class="alert alert-danger">Database backup error: <b>Access Denied</b></div></body></html>"#;
+        assert!(matches!(
+            backup_error(&page, html),
+            BackupError::AuthFailed(message) if message == "Database backup error: Access Denied"
+        ));
+
+        let json = BackupResponse::parse("200 application/json").unwrap();
+        let body = r#"{"jsonrpc": "2.0", "id": null, "error": {"code": 200, "message": "Odoo Server Error",
+            "data": {"name": "odoo.exceptions.UserError", "message": "Database not found"}}}"#;
+        assert_eq!(
+            backup_error(&json, body).to_string(),
+            "Odoo API error: Database not found"
+        );
+
+        let gateway = BackupResponse::parse("502 text/html").unwrap();
+        assert_eq!(
+            backup_error(&gateway, "<html><title>502 Bad Gateway</title></html>").to_string(),
+            "Odoo API error: Odoo sent an error instead of a backup: 502 Bad Gateway"
+        );
+        assert_eq!(
+            backup_error(&gateway, "").to_string(),
+            "Odoo API error: Odoo sent an error instead of a backup: HTTP 502 (text/html)"
+        );
+    }
+
+    #[test]
+    fn test_parse_jsonrpc_error() {
+        let body = r#"{"jsonrpc": "2.0", "id": null, "error": {
+            "code": 200,
+            "message": "Odoo Server Error",
+            "data": {"name": "odoo.exceptions.UserError", "message": "Database not found"}
+        }}"#;
+        let result = parse_version_info(body);
+        match result.unwrap_err() {
+            BackupError::OdooApi(msg) => assert_eq!(msg, "Database not found"),
+            _ => panic!("Expected OdooApi error variant"),
+        }
+
+        let denied = r#"{"jsonrpc": "2.0", "id": null, "error": {
+            "code": 200,
+            "message": "Odoo Server Error",
+            "data": {"name": "odoo.exceptions.AccessDenied", "message": "Access Denied"}
+        }}"#;
+        match parse_version_info(denied).unwrap_err() {
+            BackupError::AuthFailed(msg) => assert_eq!(msg, "Access Denied"),
+            _ => panic!("Expected AuthFailed error variant"),
+        }
+    }
+
+    #[test]
+    fn test_parse_health_probe() {
+        let probe = parse_health_probe("200 0.042317\n").unwrap();
+        assert_eq!(
+            probe,
+            HealthProbe {
+                status: 200,
+                latency_ms: 42
+            }
+        );
+        assert!(probe.is_healthy());
+        assert!(parse_health_probe("303 0.01").unwrap().is_healthy());
+        assert!(!parse_health_probe("502 0.01").unwrap().is_healthy());
+
+        assert!(matches!(
+            parse_health_probe("000 5.001").unwrap_err(),
+            BackupError::Network(_)
+        ));
+        assert!(matches!(
+            parse_health_probe("").unwrap_err(),
+            BackupError::OdooApi(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_non_json_response() {
+        let result = parse_version_info("<html>502 Bad Gateway</html>");
+        assert!(matches!(result.unwrap_err(), BackupError::OdooApi(_)));
+    }
+}
@@ -0,0 +1,261 @@
+use crate::error::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::IsTerminal;
+
+/// How commands print their results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned columns under a header line
+    #[default]
+    Table,
+    /// Pretty-printed JSON
+    Json,
+    /// Tab-separated columns without a header, for scripts
+    Plain,
+}
+
+impl OutputFormat {
+    /// `Json` when a command's own `--json` flag is set, this format
+    /// otherwise.
+    pub fn or_json(self, json: bool) -> Self {
+        if json {
+            OutputFormat::Json
+        } else {
+            self
+        }
+    }
+}
+
+/// When to color output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ColorChoice {
+    /// Only when stdout is a terminal and NO_COLOR is not set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Auto => {
+                std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+            }
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+
+    /// The matching `RUST_LOG_STYLE` value for log lines.
+    pub fn log_style(self) -> &'static str {
+        match self {
+            ColorChoice::Auto if std::env::var_os("NO_COLOR").is_some() => "never",
+            ColorChoice::Auto => "auto",
+            ColorChoice::Always => "always",
+            ColorChoice::Never => "never",
+        }
+    }
+}
+
+/// Colors for OK, WARN and FAIL states.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Green,
+    Yellow,
+    Red,
+}
+
+impl Color {
+    /// `text` wrapped in the ANSI escape codes of this color.
+    pub fn paint(self, text: &str) -> String {
+        let code = match self {
+            Color::Green => 32,
+            Color::Yellow => 33,
+            Color::Red => 31,
+        };
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    }
+
+    /// `text` in this color when `enabled`, unchanged otherwise.
+    pub fn paint_if(self, enabled: bool, text: &str) -> String {
+        if enabled {
+            self.paint(text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Rows of text cells under column headers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    headers: Vec<&'static str>,
+    right_aligned: Vec<usize>,
+    rows: Vec<Vec<String>>,
+    /// Cell colors by row and column, used only when `color` is set
+    colors: HashMap<(usize, usize), Color>,
+    color: bool,
+}
+
+impl Table {
+    pub fn new(headers: &[&'static str]) -> Self {
+        Self {
+            headers: headers.to_vec(),
+            right_aligned: Vec::new(),
+            rows: Vec::new(),
+            colors: HashMap::new(),
+            color: false,
+        }
+    }
+
+    /// Colors painted cells in table output when `enabled`.
+    pub fn colored(mut self, enabled: bool) -> Self {
+        self.color = enabled;
+        self
+    }
+
+    /// Right-aligns the columns at `columns`, e.g. sizes.
+    pub fn align_right(mut self, columns: &[usize]) -> Self {
+        self.right_aligned = columns.to_vec();
+        self
+    }
+
+    pub fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells);
+    }
+
+    /// Colors the cell at `column` of the last row.
+    pub fn paint(&mut self, column: usize, color: Color) {
+        if let Some(row) = self.rows.len().checked_sub(1) {
+            self.colors.insert((row, column), color);
+        }
+    }
+
+    /// The table as aligned columns under a header line, or for `Plain` as
+    /// tab-separated cells without one. The last column is never padded.
+    /// Colors are added after padding, so they do not shift the columns.
+    pub fn render(&self, format: OutputFormat) -> String {
+        if format == OutputFormat::Plain {
+            return self
+                .rows
+                .iter()
+                .map(|row| format!("{}\n", row.join("\t")))
+                .collect();
+        }
+
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.len()).collect();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let line = |row: Option<usize>, cells: &[&str]| {
+            let last = cells.len().saturating_sub(1);
+            let cells: Vec<_> = cells
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| {
+                    let cell = match (i == last, self.right_aligned.contains(&i)) {
+                        (_, true) => format!("{:>1$}", cell, widths[i]),
+                        (true, false) => cell.to_string(),
+                        (false, false) => format!("{:<1$}", cell, widths[i]),
+                    };
+                    match row.and_then(|row| self.colors.get(&(row, i))) {
+                        Some(color) if self.color => {
+                            let start = cell.len() - cell.trim_start().len();
+                            let text = cell.trim();
+                            format!(
+                                "{}{}{}",
+                                &cell[..start],
+                                color.paint(text),
+                                &cell[start + text.len()..]
+                            )
+                        }
+                        _ => cell,
+                    }
+                })
+                .collect();
+            format!("{}\n", cells.join("  ").trim_end())
+        };
+        let mut text = line(None, &self.headers);
+        for (i, row) in self.rows.iter().enumerate() {
+            text.push_str(&line(
+                Some(i),
+                &row.iter().map(String::as_str).collect::<Vec<_>>(),
+            ));
+        }
+        text
+    }
+}
+
+/// `value` as JSON for `Json`, otherwise the table `table` builds from it.
+pub fn render<T: Serialize + ?Sized>(
+    format: OutputFormat,
+    value: &T,
+    table: impl FnOnce() -> Table,
+) -> Result<String> {
+    match format {
+        OutputFormat::Json => Ok(format!("{}\n", serde_json::to_string_pretty(value)?)),
+        _ => Ok(table().render(format)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table {
+        let mut table = Table::new(&["NAME", "SIZE", "LABEL"]).align_right(&[1]);
+        table.row(vec![
+            "a.zip".to_string(),
+            "2.0 KB".to_string(),
+            String::new(),
+        ]);
+        table.row(vec![
+            "backup.zip".to_string(),
+            "512 B".to_string(),
+            "pre migration".to_string(),
+        ]);
+        table.paint(1, Color::Red);
+        table
+    }
+
+    #[test]
+    fn test_render_table() {
+        assert_eq!(
+            table().render(OutputFormat::Table),
+            "NAME          SIZE  LABEL\n\
+             a.zip       2.0 KB\n\
+             backup.zip   512 B  pre migration\n"
+        );
+        assert_eq!(
+            table().render(OutputFormat::Plain),
+            "a.zip\t2.0 KB\t\nbackup.zip\t512 B\tpre migration\n"
+        );
+        assert_eq!(
+            table().colored(true).render(OutputFormat::Table),
+            "NAME          SIZE  LABEL\n\
+             a.zip       2.0 KB\n\
+             backup.zip   \x1b[31m512 B\x1b[0m  pre migration\n"
+        );
+        assert!(!table()
+            .colored(true)
+            .render(OutputFormat::Plain)
+            .contains('\x1b'));
+        assert_eq!(ColorChoice::Always.log_style(), "always");
+        assert!(!ColorChoice::Never.enabled());
+    }
+
+    #[test]
+    fn test_render_json() {
+        let json = render(OutputFormat::Json, &["a.zip"], table).unwrap();
+        assert_eq!(json, "[\n  \"a.zip\"\n]\n");
+        let text = render(OutputFormat::Plain, &["a.zip"], table).unwrap();
+        assert!(text.starts_with("a.zip\t"));
+        assert_eq!(OutputFormat::Plain.or_json(true), OutputFormat::Json);
+        assert_eq!(OutputFormat::Plain.or_json(false), OutputFormat::Plain);
+    }
+}
@@ -0,0 +1,370 @@
+use crate::error::{BackupError, Result, ResultExt};
+use crate::size;
+use chrono::Duration;
+use std::fs::{self, File};
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// `backup_format` and file extension of `pg_basebackup` backups.
+pub const FORMAT: &str = "basebackup";
+
+/// `backup_format` and file extension of `pg_dump` backups.
+pub const DUMP_FORMAT: &str = "pgdump";
+
+/// Entry of the backup holding the cluster, as `pg_basebackup -Ft -z`
+/// writes it, with the WAL needed to start it.
+const BASE_ENTRY: &str = "base.tar.gz";
+
+/// Entry of a `pg_dump` backup holding the dump directory, a plain tar as
+/// `pg_dump` compresses the files in it already.
+const DUMP_ENTRY: &str = "dump.tar";
+
+/// Entry of the backup holding the database's filestore directory.
+const FILESTORE_ENTRY: &str = "filestore.tar.gz";
+
+/// What a standby reports about itself: whether it is in recovery, the
+/// state and slot of its WAL receiver, and its replay lag in seconds. A
+/// standby that replayed all it received has no lag, however long ago the
+/// primary last wrote.
+pub const REPLICA_STATUS_SQL: &str = "SELECT pg_is_in_recovery(), \
+    coalesce((SELECT status FROM pg_stat_wal_receiver), ''), \
+    coalesce((SELECT slot_name FROM pg_stat_wal_receiver), ''), \
+    CASE WHEN pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0 \
+    ELSE extract(epoch FROM now() - pg_last_xact_replay_timestamp()) END";
+
+/// The replication state of a PostgreSQL server backups are taken from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplicaStatus {
+    pub in_recovery: bool,
+    /// State of the WAL receiver, `streaming` while it follows the primary
+    pub receiver: String,
+    /// Replication slot the standby streams through
+    pub slot: Option<String>,
+    /// How far behind the primary the standby is, when it can tell
+    pub lag_seconds: Option<f64>,
+}
+
+impl ReplicaStatus {
+    /// Parses the `psql -tA` output of `REPLICA_STATUS_SQL`.
+    pub fn parse(output: &str) -> Option<Self> {
+        let mut fields = output.trim().split('|');
+        let in_recovery = fields.next()? == "t";
+        let receiver = fields.next()?.to_string();
+        let slot = fields.next()?;
+        let lag = fields.next()?;
+        Some(Self {
+            in_recovery,
+            receiver,
+            slot: (!slot.is_empty()).then(|| slot.to_string()),
+            lag_seconds: lag.parse().ok(),
+        })
+    }
+
+    /// Whether a backup may be taken from `server` now, as a standby at
+    /// most `max_lag` behind.
+    pub fn check(
+        &self,
+        server: &str,
+        max_lag: Option<Duration>,
+    ) -> std::result::Result<(), String> {
+        if !self.in_recovery {
+            return Err(format!(
+                "{} is not a standby, refusing to back up a primary",
+                server
+            ));
+        }
+        if self.receiver != "streaming" {
+            return Err(format!(
+                "{} is not streaming from its primary{}",
+                server,
+                match self.receiver.as_str() {
+                    "" => String::new(),
+                    state => format!(" ({})", state),
+                }
+            ));
+        }
+        if let Some(max_lag) = max_lag {
+            match self.lag_seconds {
+                Some(lag) if lag <= max_lag.num_seconds() as f64 => {}
+                Some(lag) => {
+                    return Err(format!(
+                        "{} is {} behind its primary, more than {}",
+                        server,
+                        size::format_age(lag as i64),
+                        size::format_age(max_lag.num_seconds())
+                    ))
+                }
+                None => return Err(format!("{} cannot tell its replication lag", server)),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `file_name` is a backup taken with PostgreSQL's tools rather
+/// than Odoo's, which only `unpack` reads.
+pub fn is_physical(file_name: &str) -> bool {
+    is_dump(file_name) || file_name.ends_with(&format!(".{}", FORMAT))
+}
+
+pub fn is_dump(file_name: &str) -> bool {
+    file_name.ends_with(&format!(".{}", DUMP_FORMAT))
+}
+
+/// The entry holding the database in the backup at `path`, and the
+/// directory `unpack` extracts it into.
+fn database_entry(path: &Path) -> (&'static str, &'static str) {
+    if is_dump(&path.to_string_lossy()) {
+        (DUMP_ENTRY, "dump")
+    } else {
+        (BASE_ENTRY, "pgdata")
+    }
+}
+
+/// Where `pg_basebackup` writes the base archive of the backup at
+/// `container_backup_path`, inside the PostgreSQL container.
+pub fn base_path(container_backup_path: &str) -> String {
+    let file_name = Path::new(container_backup_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!("/tmp/{}.base.tar.gz", file_name)
+}
+
+/// Where `pg_dump` writes the dump archive of the backup at
+/// `container_backup_path`, inside the PostgreSQL container.
+pub fn dump_path(container_backup_path: &str) -> String {
+    base_path(container_backup_path).replace(".base.tar.gz", ".dump.tar")
+}
+
+/// Where the filestore archive of the backup at `container_backup_path` is
+/// written, inside the Odoo container.
+pub fn filestore_path(container_backup_path: &str) -> String {
+    format!("{}.filestore.tar.gz", container_backup_path)
+}
+
+/// Joins the base or dump archive and the filestore archive into the
+/// backup at `output`, uncompressed as both are compressed already.
+pub fn pack(database: &Path, filestore: &Path, output: &Path) -> Result<()> {
+    let file = File::create(output)
+        .map_err(|e| BackupError::from_fs(format!("Failed to create {}", output.display()), e))?;
+    let mut writer = ZipWriter::new(file);
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(true);
+    let (database_entry, _) = database_entry(output);
+    for (name, path) in [(database_entry, database), (FILESTORE_ENTRY, filestore)] {
+        let mut part = File::open(path)
+            .map_err(|e| BackupError::from_fs(format!("Failed to open {}", path.display()), e))?;
+        writer
+            .start_file(name, options)
+            .map_err(|e| zip_error(output, e))?;
+        io::copy(&mut part, &mut writer).map_err(|e| {
+            BackupError::from_fs(format!("Failed to write {}", output.display()), e)
+        })?;
+    }
+    writer
+        .finish()
+        .map_err(|e| zip_error(output, e))?
+        .sync_all()?;
+    Ok(())
+}
+
+/// Unpacks the backup at `backup` into `dir`, which must be empty or not
+/// exist: the PostgreSQL data directory into `pgdata`, or the dump
+/// directory into `dump`, and the filestore into `filestore`. Uses the
+/// host's `tar`.
+pub fn unpack(backup: &Path, dir: &Path) -> Result<()> {
+    if dir.exists()
+        && fs::read_dir(dir)
+            .map_err(|e| BackupError::from_fs(format!("Failed to read {}", dir.display()), e))?
+            .next()
+            .is_some()
+    {
+        return Err(BackupError::Config(format!(
+            "{} is not empty",
+            dir.display()
+        )));
+    }
+    let file = File::open(backup)
+        .map_err(|e| BackupError::from_fs(format!("Failed to open {}", backup.display()), e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| zip_error(backup, e))?;
+    let (database_entry, database_dir) = database_entry(backup);
+    for (entry, target) in [
+        (database_entry, database_dir),
+        (FILESTORE_ENTRY, "filestore"),
+    ] {
+        let target = dir.join(target);
+        fs::create_dir_all(&target).map_err(|e| {
+            BackupError::from_fs(format!("Failed to create {}", target.display()), e)
+        })?;
+        let part = dir.join(entry);
+        let mut source = archive.by_name(entry).map_err(|e| zip_error(backup, e))?;
+        let mut file = File::create(&part)
+            .map_err(|e| BackupError::from_fs(format!("Failed to create {}", part.display()), e))?;
+        io::copy(&mut source, &mut file)
+            .map_err(|e| BackupError::from_fs(format!("Failed to write {}", part.display()), e))?;
+        drop(file);
+        let extracted = extract_tar(&part, &target);
+        let _ = fs::remove_file(&part);
+        extracted?;
+    }
+    if database_dir == "pgdata" {
+        // PostgreSQL refuses to start on a data directory others can read
+        let pgdata = dir.join("pgdata");
+        fs::set_permissions(&pgdata, fs::Permissions::from_mode(0o700)).map_err(|e| {
+            BackupError::from_fs(format!("Failed to restrict {}", pgdata.display()), e)
+        })?;
+    }
+    Ok(())
+}
+
+/// Extracts with the host's `tar`, which tells gzipped archives from plain
+/// ones itself.
+fn extract_tar(archive: &Path, target: &Path) -> Result<()> {
+    let output = Command::new("tar")
+        .arg("-xf")
+        .arg(archive)
+        .arg("-C")
+        .arg(target)
+        .output()
+        .context("tar is needed to unpack a physical backup")?;
+    if !output.status.success() {
+        return Err(BackupError::FileSystem(format!(
+            "Failed to extract {}: {}",
+            archive.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+fn zip_error(path: &Path, e: impl std::fmt::Display) -> BackupError {
+    BackupError::FileSystem(format!("Invalid physical backup {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// A gzipped tar of `files`, made with the host's `tar`.
+    fn tar_gz(dir: &Path, name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+        let source = dir.join(format!("{}-source", name));
+        for (path, content) in files {
+            let path = source.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let archive = dir.join(name);
+        let status = Command::new("tar")
+            .arg("-czf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(&source)
+            .arg(".")
+            .status()
+            .unwrap();
+        assert!(status.success());
+        archive
+    }
+
+    #[test]
+    fn test_paths() {
+        let container_path = "/var/lib/odoo/backups/backup_acme_20240601_020000.basebackup";
+        assert!(is_physical("backup_acme_20240601_020000.basebackup"));
+        assert!(is_physical("backup_acme_20240601_020000.pgdump"));
+        assert!(!is_physical("backup_acme_20240601_020000.zip"));
+        assert_eq!(
+            base_path(container_path),
+            "/tmp/backup_acme_20240601_020000.basebackup.base.tar.gz"
+        );
+        assert_eq!(
+            dump_path("/var/lib/odoo/backups/backup_acme_20240601_020000.pgdump"),
+            "/tmp/backup_acme_20240601_020000.pgdump.dump.tar"
+        );
+        assert_eq!(
+            filestore_path(container_path),
+            format!("{}.filestore.tar.gz", container_path)
+        );
+    }
+
+    #[test]
+    fn test_replica_status() {
+        let status = ReplicaStatus::parse("t|streaming|odoo_standby|42.5\n").unwrap();
+        assert_eq!(status.slot.as_deref(), Some("odoo_standby"));
+        assert!(status.check("odoo-db-replica", None).is_ok());
+        assert!(status
+            .check("odoo-db-replica", Some(Duration::minutes(5)))
+            .is_ok());
+        assert_eq!(
+            status
+                .check("odoo-db-replica", Some(Duration::seconds(30)))
+                .unwrap_err(),
+            "odoo-db-replica is 42s behind its primary, more than 30s"
+        );
+
+        let primary = ReplicaStatus::parse("f|||").unwrap();
+        assert_eq!(primary.lag_seconds, None);
+        assert!(primary
+            .check("odoo-db", None)
+            .unwrap_err()
+            .contains("not a standby"));
+        let stalled = ReplicaStatus::parse("t|waiting||").unwrap();
+        assert_eq!(
+            stalled.check("odoo-db-replica", None).unwrap_err(),
+            "odoo-db-replica is not streaming from its primary (waiting)"
+        );
+        assert_eq!(ReplicaStatus::parse(""), None);
+    }
+
+    #[test]
+    fn test_pack_and_unpack() {
+        let temp_dir = tempdir().unwrap();
+        let base = tar_gz(temp_dir.path(), "base.tar.gz", &[("PG_VERSION", "16\n")]);
+        let filestore = tar_gz(
+            temp_dir.path(),
+            "filestore.tar.gz",
+            &[("acme/ab/ab12", "attachment")],
+        );
+        let backup = temp_dir.path().join("backup_acme.basebackup");
+        pack(&base, &filestore, &backup).unwrap();
+
+        let restored = temp_dir.path().join("restored");
+        unpack(&backup, &restored).unwrap();
+        assert_eq!(
+            fs::read_to_string(restored.join("pgdata/PG_VERSION")).unwrap(),
+            "16\n"
+        );
+        assert_eq!(
+            fs::read_to_string(restored.join("filestore/acme/ab/ab12")).unwrap(),
+            "attachment"
+        );
+        assert!(!restored.join(BASE_ENTRY).exists());
+
+        // Never unpacked over an existing directory
+        assert!(unpack(&backup, &restored).is_err());
+    }
+
+    #[test]
+    fn test_pack_and_unpack_dump() {
+        let temp_dir = tempdir().unwrap();
+        let dump = tar_gz(temp_dir.path(), "dump.tar", &[("toc.dat", "toc")]);
+        let filestore = tar_gz(temp_dir.path(), "filestore.tar.gz", &[("acme/ab/ab12", "")]);
+        let backup = temp_dir.path().join("backup_acme.pgdump");
+        pack(&dump, &filestore, &backup).unwrap();
+
+        let restored = temp_dir.path().join("restored");
+        unpack(&backup, &restored).unwrap();
+        assert_eq!(
+            fs::read_to_string(restored.join("dump/toc.dat")).unwrap(),
+            "toc"
+        );
+        assert!(restored.join("filestore/acme/ab/ab12").exists());
+        assert!(!restored.join("pgdata").exists());
+    }
+}
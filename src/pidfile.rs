@@ -0,0 +1,148 @@
+use crate::error::{BackupError, Result};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// A PID file held for as long as the value lives. Creating it fails while
+/// another live process holds the file; a file left behind by a process that
+/// is gone is taken over.
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+    pid: u32,
+}
+
+impl PidFile {
+    pub fn acquire(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let pid = std::process::id();
+        // Two attempts: the second one after removing a stale file
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    writeln!(file, "{}", pid).map_err(|e| {
+                        BackupError::from_fs(format!("Failed to write {}", path.display()), e)
+                    })?;
+                    return Ok(Self { path, pid });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    if let Some(holder) = read_pid(&path) {
+                        if holder != pid && process_alive(holder) {
+                            return Err(BackupError::AlreadyRunning { pid: holder });
+                        }
+                    }
+                    log::warn!("Removing stale PID file {}", path.display());
+                    remove_if_exists(&path)?;
+                }
+                Err(e) => {
+                    return Err(BackupError::from_fs(
+                        format!("Failed to create {}", path.display()),
+                        e,
+                    ))
+                }
+            }
+        }
+        Err(BackupError::FileSystem(format!(
+            "{} keeps reappearing, another instance may be starting",
+            path.display()
+        )))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        // Leave the file alone if another process has taken it over
+        if read_pid(&self.path) == Some(self.pid) {
+            if let Err(e) = fs::remove_file(&self.path) {
+                log::warn!("Failed to remove {}: {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+fn read_pid(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(BackupError::from_fs(
+            format!("Failed to remove {}", path.display()),
+            e,
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Whether a process with `pid` exists.
+fn process_alive(pid: u32) -> bool {
+    let proc_dir = Path::new("/proc");
+    if proc_dir.is_dir() {
+        return proc_dir.join(pid.to_string()).exists();
+    }
+    // Without procfs, signal 0 checks for the process without touching it
+    std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_pid_file_lifecycle() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("odoo-backup.pid");
+
+        let pid_file = PidFile::acquire(&path).unwrap();
+        assert_eq!(read_pid(&path), Some(std::process::id()));
+        assert_eq!(pid_file.path(), path);
+        drop(pid_file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_pid_file_held_by_live_process() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("odoo-backup.pid");
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        fs::write(&path, format!("{}\n", child.id())).unwrap();
+
+        let result = PidFile::acquire(&path);
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(
+            matches!(result.unwrap_err(), BackupError::AlreadyRunning { pid } if pid == child.id())
+        );
+        // The running instance's file is left alone
+        assert_eq!(read_pid(&path), Some(child.id()));
+    }
+
+    #[test]
+    fn test_pid_file_takes_over_stale_file() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("odoo-backup.pid");
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        child.wait().unwrap();
+        fs::write(&path, format!("{}\n", child.id())).unwrap();
+
+        let pid_file = PidFile::acquire(&path).unwrap();
+        assert_eq!(read_pid(&path), Some(std::process::id()));
+
+        // Garbage is treated as stale too
+        drop(pid_file);
+        fs::write(&path, "not a pid").unwrap();
+        assert!(PidFile::acquire(&path).is_ok());
+    }
+}
@@ -0,0 +1,186 @@
+use crate::backup::{BackupManager, VerifyStatus};
+use crate::config::DatabaseConfig;
+use crate::output::Color;
+use serde::Serialize;
+use std::path::Path;
+
+/// A step of the pre-upgrade checks and how it went.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Step {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Whether a client is ready for an Odoo or module upgrade: a fresh
+/// backup was taken, verified and, when asked, restored.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PreUpgradeReport {
+    pub client: String,
+    /// File name of the fresh backup
+    pub backup: Option<String>,
+    pub steps: Vec<Step>,
+    pub go: bool,
+}
+
+impl PreUpgradeReport {
+    /// The steps and the verdict as text, colored when `color` is set.
+    pub fn render(&self, color: bool) -> String {
+        let mut out = String::new();
+        for step in &self.steps {
+            let status = if step.passed {
+                Color::Green.paint_if(color, "ok")
+            } else {
+                Color::Red.paint_if(color, "FAILED")
+            };
+            out.push_str(&format!("  {:<13} {} {}\n", step.name, status, step.detail));
+        }
+        let verdict = if self.go {
+            Color::Green.paint_if(color, "GO")
+        } else {
+            Color::Red.paint_if(color, "NO-GO")
+        };
+        let reason = match self.steps.iter().find(|step| !step.passed) {
+            Some(step) => format!("{} failed, do not upgrade yet", step.name),
+            None => format!(
+                "{} can be upgraded, {} is the backup to return to",
+                self.client,
+                self.backup.as_deref().unwrap_or("-")
+            ),
+        };
+        out.push_str(&format!("{}: {}\n", verdict, reason));
+        out
+    }
+}
+
+/// Takes a fresh backup of `config` with the label `manager` was given,
+/// verifies it and, with `restore_test`, restores it into a scratch
+/// database. Stops at the first step that fails.
+pub async fn run(
+    manager: &BackupManager,
+    config: &DatabaseConfig,
+    restore_test: bool,
+) -> PreUpgradeReport {
+    let mut report = PreUpgradeReport {
+        client: config.name.clone(),
+        backup: None,
+        steps: Vec::new(),
+        go: false,
+    };
+    let file_name = match manager.backup_database(config).await {
+        Ok(path) => {
+            let file_name = Path::new(&path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or(path);
+            report.steps.push(Step {
+                name: "backup",
+                passed: true,
+                detail: file_name.clone(),
+            });
+            report.backup = Some(file_name.clone());
+            file_name
+        }
+        Err(e) => {
+            report.steps.push(Step {
+                name: "backup",
+                passed: false,
+                detail: e.to_string(),
+            });
+            return report;
+        }
+    };
+
+    let verified = match manager.verify_backups(Some(&file_name), None, None) {
+        Ok(results) => match results.first().map(|result| &result.status) {
+            Some(VerifyStatus::Ok) => Ok("checksum matches".to_string()),
+            Some(VerifyStatus::Missing) => Err("the backup file is missing".to_string()),
+            Some(VerifyStatus::Corrupt { reason }) => Err(reason.clone()),
+            None => Err("the backup has no checksum to verify".to_string()),
+        },
+        Err(e) => Err(e.to_string()),
+    };
+    let passed = verified.is_ok();
+    report.steps.push(Step {
+        name: "verify",
+        passed,
+        detail: verified.unwrap_or_else(|e| e),
+    });
+    if !passed {
+        return report;
+    }
+
+    if restore_test {
+        let restored = manager.test_restore(config).await;
+        let passed = restored.is_ok();
+        report.steps.push(Step {
+            name: "restore test",
+            passed,
+            detail: match restored {
+                Ok(_) => "restored into a scratch database and dropped again".to_string(),
+                Err(e) => e.to_string(),
+            },
+        });
+        if !passed {
+            return report;
+        }
+    }
+    report.go = true;
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{ExecOutput, MockRuntime};
+    use tempfile::tempdir;
+
+    fn create_test_database_config() -> DatabaseConfig {
+        serde_json::from_value(serde_json::json!({
+            "name": "Acme",
+            "database_name": "acme",
+            "url": "http://localhost:8069",
+            "container_name": "odoo",
+            "master_password": "admin",
+            "backup_format": "dump",
+            "output_path": "/tmp/backups",
+            "retention_days": 30,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pre_upgrade() {
+        let temp_dir = tempdir().unwrap();
+        let backup_dir = temp_dir.path().to_str().unwrap();
+        let config = create_test_database_config();
+        let manager = BackupManager::new(backup_dir.to_string())
+            .with_runtime(Box::new(
+                MockRuntime::new()
+                    .with_container("odoo")
+                    .respond("stat -c %s", ExecOutput::ok("4\n"))
+                    .respond("dd if=", ExecOutput::ok("data")),
+            ))
+            .with_label("pre-upgrade".to_string());
+
+        let report = run(&manager, &config, false).await;
+        assert!(report.go, "{:?}", report);
+        assert_eq!(report.steps.len(), 2);
+        let backup = report.backup.clone().unwrap();
+        assert!(backup.ends_with(".dump"));
+        assert!(report.render(false).contains(&format!(
+            "GO: Acme can be upgraded, {} is the backup to return to",
+            backup
+        )));
+
+        let manager = BackupManager::new(backup_dir.to_string()).with_runtime(Box::new(
+            MockRuntime::new().respond("/web/database/backup", ExecOutput::failed("refused")),
+        ));
+        let report = run(&manager, &config, true).await;
+        assert!(!report.go);
+        assert_eq!(report.steps.len(), 1);
+        assert!(report
+            .render(false)
+            .ends_with("NO-GO: backup failed, do not upgrade yet\n"));
+    }
+}
@@ -0,0 +1,176 @@
+use crate::events::BackupEvent;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Mutex;
+
+/// How backup progress is reported besides the log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ProgressMode {
+    /// No progress reporting
+    #[default]
+    None,
+    /// One JSON event per line on stderr
+    Json,
+}
+
+/// A step of a backup, in the order they happen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// Odoo writes the backup inside the container
+    Request,
+    /// The backup is copied from the container to the host
+    Copy,
+    /// The backup is checksummed, repacked, deduplicated and uploaded
+    Store,
+}
+
+/// A progress event for wrappers, e.g.
+/// `{"event":"phase","db":"Acme","phase":"copy","pct":42}`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    Phase {
+        db: String,
+        phase: Phase,
+        /// Percentage of the phase done, when it is known
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pct: Option<u8>,
+    },
+    Uploaded {
+        db: String,
+        location: String,
+    },
+    Finished {
+        db: String,
+        path: String,
+    },
+    Skipped {
+        db: String,
+        path: String,
+    },
+    Failed {
+        db: String,
+        error: String,
+    },
+}
+
+impl From<&BackupEvent> for ProgressEvent {
+    fn from(event: &BackupEvent) -> Self {
+        let phase = |db: &str, phase, pct| ProgressEvent::Phase {
+            db: db.to_string(),
+            phase,
+            pct,
+        };
+        match event {
+            BackupEvent::Started { client } => phase(client, Phase::Request, None),
+            BackupEvent::ContainerDone { client, .. } => phase(client, Phase::Copy, Some(0)),
+            BackupEvent::Progress {
+                client,
+                bytes,
+                total,
+            } => {
+                let pct = match *total {
+                    0 => 100,
+                    total => (bytes.min(&total) * 100 / total) as u8,
+                };
+                phase(client, Phase::Copy, Some(pct))
+            }
+            BackupEvent::Copied { client, .. } => phase(client, Phase::Store, None),
+            BackupEvent::Uploaded { client, location } => ProgressEvent::Uploaded {
+                db: client.clone(),
+                location: location.clone(),
+            },
+            BackupEvent::Finished { client, path } => ProgressEvent::Finished {
+                db: client.clone(),
+                path: path.clone(),
+            },
+            BackupEvent::Skipped { client, path } => ProgressEvent::Skipped {
+                db: client.clone(),
+                path: path.clone(),
+            },
+            BackupEvent::Failed { client, error } => ProgressEvent::Failed {
+                db: client.clone(),
+                error: error.clone(),
+            },
+        }
+    }
+}
+
+/// Writes backup events as line-delimited JSON on stderr.
+#[derive(Debug, Default)]
+pub struct JsonProgress {
+    /// Last copy percentage reported per database
+    copied: Mutex<HashMap<String, u8>>,
+}
+
+impl JsonProgress {
+    /// The line for `event`, or `None` when it would only repeat the copy
+    /// percentage already reported.
+    pub fn line(&self, event: &BackupEvent) -> Option<String> {
+        let event = ProgressEvent::from(event);
+        if let ProgressEvent::Phase {
+            db,
+            phase: Phase::Copy,
+            pct: Some(pct),
+        } = &event
+        {
+            let mut copied = self.copied.lock().unwrap();
+            if copied.insert(db.clone(), *pct) == Some(*pct) {
+                return None;
+            }
+        }
+        serde_json::to_string(&event).ok()
+    }
+
+    pub fn report(&self, event: &BackupEvent) {
+        if let Some(line) = self.line(event) {
+            // One write per line, so lines of parallel backups never interleave
+            let _ = std::io::stderr().write_all(format!("{}\n", line).as_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(bytes: u64) -> BackupEvent {
+        BackupEvent::Progress {
+            client: "Acme".to_string(),
+            bytes,
+            total: 200,
+        }
+    }
+
+    #[test]
+    fn test_json_progress_lines() {
+        let reporter = JsonProgress::default();
+        assert_eq!(
+            reporter
+                .line(&BackupEvent::Started {
+                    client: "Acme".to_string()
+                })
+                .unwrap(),
+            r#"{"event":"phase","db":"Acme","phase":"request"}"#
+        );
+        assert_eq!(
+            reporter.line(&progress(84)).unwrap(),
+            r#"{"event":"phase","db":"Acme","phase":"copy","pct":42}"#
+        );
+        // Still 42%, so nothing new to report
+        assert_eq!(reporter.line(&progress(85)), None);
+        assert!(reporter.line(&progress(200)).unwrap().contains("100"));
+        assert_eq!(
+            reporter
+                .line(&BackupEvent::Failed {
+                    client: "Acme".to_string(),
+                    error: "boom".to_string()
+                })
+                .unwrap(),
+            r#"{"event":"failed","db":"Acme","error":"boom"}"#
+        );
+    }
+}
@@ -0,0 +1,118 @@
+/// Hosts that are always reached directly, so a proxy set for the host
+/// never catches requests to Odoo inside its own container.
+const LOCAL_HOSTS: &str = "localhost,127.0.0.1,::1";
+
+/// Schemes a proxy URL may use.
+const SCHEMES: [&str; 4] = ["http://", "https://", "socks5://", "socks5h://"];
+
+/// Checks that `url` can be used as a proxy.
+pub fn validate(url: &str) -> std::result::Result<(), String> {
+    if SCHEMES.iter().any(|scheme| url.starts_with(scheme)) {
+        Ok(())
+    } else {
+        Err(format!(
+            "'{}' must start with http://, https://, socks5:// or socks5h://",
+            url
+        ))
+    }
+}
+
+/// The outbound proxy for requests to one URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proxy {
+    pub url: String,
+    /// Comma-separated hosts reached without the proxy
+    pub no_proxy: String,
+}
+
+impl Proxy {
+    /// A proxy at `url`, bypassed for the hosts in `NO_PROXY`.
+    pub fn new(url: &str) -> Self {
+        Self::resolve(url, Some(url), None).expect("a configured proxy always resolves")
+    }
+
+    /// The proxy for requests to `target`: `configured` if set, otherwise
+    /// `HTTPS_PROXY` or `HTTP_PROXY` from the environment by the scheme of
+    /// `target`. Hosts in `no_proxy`, or else `NO_PROXY`, bypass it.
+    pub fn resolve(target: &str, configured: Option<&str>, no_proxy: Option<&str>) -> Option<Self> {
+        Self::resolve_with(target, configured, no_proxy, |name| {
+            std::env::var(name).ok()
+        })
+    }
+
+    fn resolve_with(
+        target: &str,
+        configured: Option<&str>,
+        no_proxy: Option<&str>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Option<Self> {
+        // Either case is common; curl and reqwest read both
+        let var = |name: &str| {
+            env(name)
+                .or_else(|| env(&name.to_lowercase()))
+                .filter(|value| !value.is_empty())
+        };
+        let url = match configured {
+            Some(url) => url.to_string(),
+            None if target.starts_with("https://") => var("HTTPS_PROXY")?,
+            None => var("HTTP_PROXY")?,
+        };
+        let no_proxy = match no_proxy.map(str::to_string).or_else(|| var("NO_PROXY")) {
+            Some(hosts) => format!("{},{}", LOCAL_HOSTS, hosts),
+            None => LOCAL_HOSTS.to_string(),
+        };
+        Some(Self { url, no_proxy })
+    }
+
+    /// Environment variables that make a child process, such as the `aws`
+    /// CLI, use this proxy.
+    pub fn env(&self) -> [(&'static str, &str); 3] {
+        [
+            ("HTTPS_PROXY", &self.url),
+            ("HTTP_PROXY", &self.url),
+            ("NO_PROXY", &self.no_proxy),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "https_proxy" => Some("http://proxy.corp:3128".to_string()),
+            "NO_PROXY" => Some(".internal".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_proxy() {
+        let proxy = Proxy::resolve_with("https://erp.acme.com", None, None, env).unwrap();
+        assert_eq!(proxy.url, "http://proxy.corp:3128");
+        assert_eq!(proxy.no_proxy, "localhost,127.0.0.1,::1,.internal");
+        // No HTTP_PROXY for plain http
+        assert_eq!(
+            Proxy::resolve_with("http://localhost:8069", None, None, env),
+            None
+        );
+
+        let proxy = Proxy::resolve_with(
+            "http://localhost:8069",
+            Some("socks5h://gw:1080"),
+            Some("odoo"),
+            env,
+        )
+        .unwrap();
+        assert_eq!(proxy.url, "socks5h://gw:1080");
+        assert_eq!(proxy.no_proxy, "localhost,127.0.0.1,::1,odoo");
+    }
+
+    #[test]
+    fn test_validate_proxy() {
+        assert!(validate("http://proxy.corp:3128").is_ok());
+        assert!(validate("socks5://gw:1080").is_ok());
+        assert!(validate("proxy.corp:3128").is_err());
+    }
+}
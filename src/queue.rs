@@ -0,0 +1,197 @@
+use crate::config::DatabaseConfig;
+use crate::error::{BackupError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Queue state file kept in the host backup directory while backups run.
+pub const QUEUE_FILE: &str = ".odoo-backup-queue.json";
+
+/// A backup waiting for or holding a slot.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedJob {
+    pub client: String,
+    pub container: String,
+    pub priority: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+/// What the queue looks like at one moment, as shown by `status`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueueSnapshot {
+    pub updated_at: Option<DateTime<Utc>>,
+    pub running: Vec<QueuedJob>,
+    /// In the order they will start, as far as container availability allows
+    pub pending: Vec<QueuedJob>,
+}
+
+impl QueueSnapshot {
+    pub fn is_empty(&self) -> bool {
+        self.running.is_empty() && self.pending.is_empty()
+    }
+
+    /// The queue state last written to `backup_dir`, if backups are running.
+    pub fn load(backup_dir: &str) -> Result<Option<Self>> {
+        let path = Path::new(backup_dir).join(QUEUE_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| BackupError::from_fs(format!("Failed to read {}", path.display()), e))?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// Publishes the snapshot in `backup_dir`; an empty queue removes the file.
+    pub fn save(&self, backup_dir: &str) -> Result<()> {
+        let path = Path::new(backup_dir).join(QUEUE_FILE);
+        if self.is_empty() {
+            return match fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(BackupError::from_fs(
+                    format!("Failed to remove {}", path.display()),
+                    e,
+                )),
+                _ => Ok(()),
+            };
+        }
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(self)?).map_err(|e| {
+            BackupError::from_fs(format!("Failed to write {}", tmp_path.display()), e)
+        })?;
+        fs::rename(&tmp_path, &path)
+            .map_err(|e| BackupError::from_fs(format!("Failed to replace {}", path.display()), e))
+    }
+}
+
+/// Backups waiting to run. The highest priority job whose container is not
+/// busy goes first; equal priorities keep their queueing order.
+#[derive(Debug, Default)]
+pub struct JobQueue {
+    pending: Vec<(QueuedJob, usize)>,
+    running: Vec<(QueuedJob, usize)>,
+}
+
+impl JobQueue {
+    /// Queues `configs`; the returned indexes identify them in `start_next`.
+    pub fn new(configs: &[DatabaseConfig]) -> Self {
+        let mut pending: Vec<_> = configs
+            .iter()
+            .enumerate()
+            .map(|(i, db)| {
+                (
+                    QueuedJob {
+                        client: db.name.clone(),
+                        container: db.container_name.clone(),
+                        priority: db.priority,
+                        started_at: None,
+                    },
+                    i,
+                )
+            })
+            .collect();
+        // Stable, so equal priorities stay in configuration order
+        pending.sort_by_key(|(job, _)| std::cmp::Reverse(job.priority));
+        Self {
+            pending,
+            running: Vec::new(),
+        }
+    }
+
+    /// Starts the next runnable job and returns its index, or `None` when
+    /// every pending job waits for a busy container.
+    pub fn start_next(&mut self) -> Option<usize> {
+        let position = self.pending.iter().position(|(job, _)| {
+            !self
+                .running
+                .iter()
+                .any(|(r, _)| r.container == job.container)
+        })?;
+        let (mut job, index) = self.pending.remove(position);
+        job.started_at = Some(Utc::now());
+        self.running.push((job, index));
+        Some(index)
+    }
+
+    /// Marks the running job `index` as done.
+    pub fn finish(&mut self, index: usize) {
+        self.running.retain(|(_, i)| *i != index);
+    }
+
+    /// Drops every job that has not started yet.
+    pub fn clear_pending(&mut self) {
+        self.pending.clear();
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub fn snapshot(&self) -> QueueSnapshot {
+        QueueSnapshot {
+            updated_at: Some(Utc::now()),
+            running: self.running.iter().map(|(job, _)| job.clone()).collect(),
+            pending: self.pending.iter().map(|(job, _)| job.clone()).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn database(name: &str, container: &str, priority: i32) -> DatabaseConfig {
+        DatabaseConfig {
+            name: name.to_string(),
+            database_name: name.to_lowercase(),
+            container_name: container.to_string(),
+            master_password: "admin".to_string(),
+            retention_days: 7,
+            priority,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_job_queue_order() {
+        let configs = vec![
+            database("staging", "odoo-a", -5),
+            database("prod-a", "odoo-a", 10),
+            database("prod-b", "odoo-b", 10),
+            database("internal", "odoo-c", 0),
+        ];
+        let mut queue = JobQueue::new(&configs);
+
+        assert_eq!(queue.start_next(), Some(1));
+        assert_eq!(queue.start_next(), Some(2));
+        // staging waits for odoo-a, so internal goes ahead
+        assert_eq!(queue.start_next(), Some(3));
+        assert_eq!(queue.start_next(), None);
+        assert!(queue.has_pending());
+
+        let snapshot = queue.snapshot();
+        assert_eq!(snapshot.running.len(), 3);
+        assert_eq!(snapshot.pending[0].client, "staging");
+
+        queue.finish(1);
+        assert_eq!(queue.start_next(), Some(0));
+        assert!(!queue.has_pending());
+    }
+
+    #[test]
+    fn test_queue_snapshot_save_and_load() {
+        let temp_dir = tempdir().unwrap();
+        let backup_dir = temp_dir.path().to_str().unwrap();
+        assert_eq!(QueueSnapshot::load(backup_dir).unwrap(), None);
+
+        let mut queue = JobQueue::new(&[database("prod", "odoo", 1), database("dev", "odoo", 0)]);
+        queue.start_next();
+        let snapshot = queue.snapshot();
+        snapshot.save(backup_dir).unwrap();
+        assert_eq!(QueueSnapshot::load(backup_dir).unwrap(), Some(snapshot));
+
+        QueueSnapshot::default().save(backup_dir).unwrap();
+        assert!(!temp_dir.path().join(QUEUE_FILE).exists());
+    }
+}
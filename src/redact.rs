@@ -0,0 +1,94 @@
+use std::sync::RwLock;
+
+/// What a secret is replaced with.
+pub const MASK: &str = "*****";
+
+/// Form field Odoo's database manager takes the master password in.
+const PASSWORD_FIELD: &str = "master_pwd=";
+
+/// Secrets known to this process, e.g. resolved master passwords.
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Masks `secret` wherever `redact` is applied from now on, such as in log
+/// lines.
+pub fn register(secret: &str) {
+    if secret.is_empty() {
+        return;
+    }
+    let mut secrets = SECRETS.write().unwrap();
+    if !secrets.iter().any(|s| s == secret) {
+        secrets.push(secret.to_string());
+    }
+}
+
+/// `text` with every registered secret and `master_pwd=` form value masked.
+pub fn redact(text: &str) -> String {
+    let secrets = SECRETS.read().unwrap();
+    redact_with(text, secrets.iter().map(String::as_str))
+}
+
+/// `text` with `secrets` and `master_pwd=` form values masked.
+pub fn redact_with<'a>(text: &str, secrets: impl IntoIterator<Item = &'a str>) -> String {
+    let mut text = mask_password_fields(text);
+    for secret in secrets {
+        if !secret.is_empty() && secret != MASK {
+            text = text.replace(secret, MASK);
+        }
+    }
+    text
+}
+
+/// Masks the value after each `master_pwd=`, up to the quote, whitespace or
+/// `&` that ends it.
+fn mask_password_fields(text: &str) -> String {
+    let mut masked = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(PASSWORD_FIELD) {
+        let (head, tail) = rest.split_at(start + PASSWORD_FIELD.len());
+        masked.push_str(head);
+        let end = tail
+            .find(|c: char| c == '\'' || c == '"' || c == '&' || c.is_whitespace())
+            .unwrap_or(tail.len());
+        if end > 0 {
+            masked.push_str(MASK);
+        }
+        rest = &tail[end..];
+    }
+    masked.push_str(rest);
+    masked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_password_fields() {
+        let command =
+            "curl -s -f -X POST -F 'master_pwd=s3cr3t!' -F 'name=acme' http://localhost:8069";
+        assert_eq!(
+            redact_with(command, []),
+            "curl -s -f -X POST -F 'master_pwd=*****' -F 'name=acme' http://localhost:8069"
+        );
+        assert_eq!(
+            redact_with("master_pwd=a&name=b master_pwd=", []),
+            "master_pwd=*****&name=b master_pwd="
+        );
+    }
+
+    #[test]
+    fn test_redact_registered_secrets() {
+        register("hunter2-registered");
+        let line = r#"{"params": {"args": ["hunter2-registered", "odoo_backup_check"]}}"#;
+        let redacted = redact(line);
+        assert!(!redacted.contains("hunter2-registered"));
+        assert_eq!(
+            redacted,
+            r#"{"params": {"args": ["*****", "odoo_backup_check"]}}"#
+        );
+        assert_eq!(
+            redact_with("no secrets here", ["", MASK]),
+            "no secrets here"
+        );
+    }
+}
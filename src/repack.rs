@@ -0,0 +1,133 @@
+use crate::error::{BackupError, Result};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Highest deflate level. Level 0 stores entries uncompressed.
+pub const MAX_LEVEL: u32 = 9;
+
+/// Rewrites the zip backup at `path` with every entry compressed at `level`.
+/// The backup is replaced only once the new archive is complete.
+pub fn repack_zip(path: &Path, level: u32) -> Result<()> {
+    let mut repacked = path.as_os_str().to_owned();
+    repacked.push(".repack");
+    let repacked = PathBuf::from(repacked);
+
+    let result = write_repacked(path, &repacked, level).and_then(|()| {
+        fs::rename(&repacked, path)
+            .map_err(|e| BackupError::from_fs(format!("Failed to replace {}", path.display()), e))
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&repacked);
+    }
+    result
+}
+
+fn write_repacked(source: &Path, target: &Path, level: u32) -> Result<()> {
+    let file = File::open(source)
+        .map_err(|e| BackupError::from_fs(format!("Failed to open {}", source.display()), e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| zip_error(source, e))?;
+    let file = File::create(target)
+        .map_err(|e| BackupError::from_fs(format!("Failed to create {}", target.display()), e))?;
+    let mut writer = ZipWriter::new(file);
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| zip_error(source, e))?;
+        let name = entry.name().to_string();
+        let mut options = entry_options(level).large_file(entry.size() >= u32::MAX as u64);
+        if let Some(modified) = entry.last_modified() {
+            options = options.last_modified_time(modified);
+        }
+        if let Some(mode) = entry.unix_mode() {
+            options = options.unix_permissions(mode);
+        }
+        if entry.is_dir() {
+            writer
+                .add_directory(name, options)
+                .map_err(|e| zip_error(target, e))?;
+            continue;
+        }
+        writer
+            .start_file(name, options)
+            .map_err(|e| zip_error(target, e))?;
+        io::copy(&mut entry, &mut writer)?;
+    }
+    writer
+        .finish()
+        .map_err(|e| zip_error(target, e))?
+        .sync_all()?;
+    Ok(())
+}
+
+fn entry_options(level: u32) -> SimpleFileOptions {
+    if level == 0 {
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored)
+    } else {
+        SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .compression_level(Some(level.min(MAX_LEVEL) as i64))
+    }
+}
+
+fn zip_error(path: &Path, e: impl std::fmt::Display) -> BackupError {
+    BackupError::FileSystem(format!(
+        "Failed to repack zip backup {}: {}",
+        path.display(),
+        e
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use tempfile::tempdir;
+
+    fn write_zip(path: &Path) {
+        let mut writer = ZipWriter::new(File::create(path).unwrap());
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        writer.start_file("dump.sql", options).unwrap();
+        writer
+            .write_all(&b"INSERT INTO res_partner;\n".repeat(1000))
+            .unwrap();
+        writer.add_directory("filestore/", options).unwrap();
+        writer.start_file("filestore/ab/abcdef", options).unwrap();
+        writer.write_all(b"attachment").unwrap();
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_repack_to_store() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("backup.zip");
+        write_zip(&path);
+        let compressed = fs::metadata(&path).unwrap().len();
+
+        repack_zip(&path, 0).unwrap();
+
+        assert!(fs::metadata(&path).unwrap().len() > compressed);
+        assert!(!temp_dir.path().join("backup.zip.repack").exists());
+        let mut archive = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(archive.len(), 3);
+        let mut dump = archive.by_name("dump.sql").unwrap();
+        assert_eq!(dump.compression(), CompressionMethod::Stored);
+        let mut content = String::new();
+        dump.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "INSERT INTO res_partner;\n".repeat(1000));
+        drop(dump);
+        assert!(archive.by_name("filestore/").unwrap().is_dir());
+    }
+
+    #[test]
+    fn test_repack_keeps_invalid_backup() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("backup.zip");
+        fs::write(&path, b"not a zip").unwrap();
+
+        assert!(repack_zip(&path, 1).is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"not a zip");
+        assert!(!temp_dir.path().join("backup.zip.repack").exists());
+    }
+}
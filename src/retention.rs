@@ -0,0 +1,197 @@
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How often a backup is kept for the long term. Each run belongs to one
+/// tier, and backups of a tier are kept for a number of its periods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionTier {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl fmt::Display for RetentionTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RetentionTier::Daily => "daily",
+            RetentionTier::Weekly => "weekly",
+            RetentionTier::Monthly => "monthly",
+            RetentionTier::Yearly => "yearly",
+        };
+        f.write_str(name)
+    }
+}
+
+impl RetentionTier {
+    /// The calendar day, ISO week, month or year `date` falls in.
+    fn period(self, date: NaiveDate) -> (i32, u32) {
+        match self {
+            RetentionTier::Daily => (date.year(), date.ordinal()),
+            RetentionTier::Weekly => {
+                let week = date.iso_week();
+                (week.year(), week.week())
+            }
+            RetentionTier::Monthly => (date.year(), date.month()),
+            RetentionTier::Yearly => (date.year(), 0),
+        }
+    }
+
+    /// Whether `a` and `b` fall in the same period of this tier.
+    pub fn same_period(self, a: NaiveDate, b: NaiveDate) -> bool {
+        self.period(a) == self.period(b)
+    }
+
+    /// When a backup of this tier taken at `taken` and kept for `keep`
+    /// periods expires.
+    pub fn expiry(self, taken: DateTime<Utc>, keep: u32) -> DateTime<Utc> {
+        let months = match self {
+            RetentionTier::Daily => return taken + Duration::days(keep as i64),
+            RetentionTier::Weekly => return taken + Duration::weeks(keep as i64),
+            RetentionTier::Monthly => keep,
+            RetentionTier::Yearly => keep.saturating_mul(12),
+        };
+        taken
+            .checked_add_months(Months::new(months))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+}
+
+/// Retention of one tier: a number of periods, e.g. `14`, or
+/// `{"keep": 52, "backup_format": "zip"}` to also take the tier's backups
+/// in another format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TierRule {
+    Keep(u32),
+    Detailed {
+        keep: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        backup_format: Option<String>,
+    },
+}
+
+impl TierRule {
+    /// Number of periods backups of the tier are kept for.
+    pub fn keep(&self) -> u32 {
+        match self {
+            TierRule::Keep(keep) | TierRule::Detailed { keep, .. } => *keep,
+        }
+    }
+
+    pub fn backup_format(&self) -> Option<&str> {
+        match self {
+            TierRule::Keep(_) => None,
+            TierRule::Detailed { backup_format, .. } => backup_format.as_deref(),
+        }
+    }
+}
+
+/// The tier of a backup taken on `today`: the longest of `tiers` whose
+/// current period has no backup yet, counting backups of longer tiers as
+/// well. Falls back to the shortest tier, so a second run on the same day
+/// is a daily one. `taken` holds the tier and local date of earlier
+/// successful backups.
+pub fn tier_for_run(
+    tiers: impl IntoIterator<Item = RetentionTier>,
+    taken: &[(RetentionTier, NaiveDate)],
+    today: NaiveDate,
+) -> Option<RetentionTier> {
+    let mut tiers: Vec<_> = tiers.into_iter().collect();
+    tiers.sort();
+    let shortest = *tiers.first()?;
+    tiers
+        .into_iter()
+        .rev()
+        .find(|tier| {
+            !taken
+                .iter()
+                .any(|(t, date)| t >= tier && tier.same_period(*date, today))
+        })
+        .or(Some(shortest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_tier_rule_parsing() {
+        let rules: std::collections::BTreeMap<RetentionTier, TierRule> = serde_json::from_str(
+            r#"{"daily": 14, "weekly": {"keep": 52, "backup_format": "zip"}}"#,
+        )
+        .unwrap();
+        assert_eq!(rules[&RetentionTier::Daily], TierRule::Keep(14));
+        assert_eq!(rules[&RetentionTier::Weekly].keep(), 52);
+        assert_eq!(rules[&RetentionTier::Weekly].backup_format(), Some("zip"));
+        assert!(serde_json::from_str::<RetentionTier>(r#""hourly""#).is_err());
+    }
+
+    #[test]
+    fn test_tier_for_run() {
+        let tiers = [RetentionTier::Daily, RetentionTier::Weekly];
+        assert_eq!(tier_for_run([], &[], date("2024-01-15")), None);
+
+        // The first backup of the week is the weekly one
+        assert_eq!(
+            tier_for_run(tiers, &[], date("2024-01-15")),
+            Some(RetentionTier::Weekly)
+        );
+        let taken = [(RetentionTier::Weekly, date("2024-01-15"))];
+        assert_eq!(
+            tier_for_run(tiers, &taken, date("2024-01-16")),
+            Some(RetentionTier::Daily)
+        );
+        assert_eq!(
+            tier_for_run(tiers, &taken, date("2024-01-15")),
+            Some(RetentionTier::Daily)
+        );
+        assert_eq!(
+            tier_for_run(tiers, &taken, date("2024-01-22")),
+            Some(RetentionTier::Weekly)
+        );
+
+        // A monthly backup covers its week too
+        let tiers = [
+            RetentionTier::Daily,
+            RetentionTier::Weekly,
+            RetentionTier::Monthly,
+        ];
+        let taken = [(RetentionTier::Monthly, date("2024-01-01"))];
+        assert_eq!(
+            tier_for_run(tiers, &taken, date("2024-01-02")),
+            Some(RetentionTier::Daily)
+        );
+        assert_eq!(
+            tier_for_run(tiers, &taken, date("2024-02-01")),
+            Some(RetentionTier::Monthly)
+        );
+    }
+
+    #[test]
+    fn test_expiry() {
+        let taken: DateTime<Utc> = "2024-01-31T02:00:00Z".parse().unwrap();
+        assert_eq!(
+            RetentionTier::Daily.expiry(taken, 14),
+            "2024-02-14T02:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(
+            RetentionTier::Weekly.expiry(taken, 52),
+            "2025-01-29T02:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(
+            RetentionTier::Monthly.expiry(taken, 1),
+            "2024-02-29T02:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(
+            RetentionTier::Yearly.expiry(taken, 2),
+            "2026-01-31T02:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+}
@@ -0,0 +1,385 @@
+use crate::error::{BackupError, Result, ResultExt};
+use serde::Deserialize;
+use std::process::Command;
+
+/// Output of a command run inside a container.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExecOutput {
+    pub success: bool,
+    pub stdout: Vec<u8>,
+    pub stderr: String,
+}
+
+impl ExecOutput {
+    /// A successful run that printed `stdout`.
+    pub fn ok(stdout: impl Into<Vec<u8>>) -> Self {
+        Self {
+            success: true,
+            stdout: stdout.into(),
+            stderr: String::new(),
+        }
+    }
+
+    /// A failed run that printed `stderr`.
+    pub fn failed(stderr: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            stdout: Vec::new(),
+            stderr: stderr.into(),
+        }
+    }
+
+    pub fn stdout_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.stdout).to_string()
+    }
+}
+
+/// A host directory or volume mounted into a container.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Mount {
+    /// Path on the host
+    #[serde(rename = "Source")]
+    pub source: String,
+    /// Path inside the container
+    #[serde(rename = "Destination")]
+    pub destination: String,
+}
+
+/// The container operations backups are built from. `DockerRuntime` drives
+/// the `docker` CLI; other implementations can stand in for it, e.g. in
+/// tests. An `Err` means the runtime itself could not be reached, while a
+/// command that ran and failed is reported through `ExecOutput::success`.
+pub trait ContainerRuntime: Send + Sync {
+    /// Runs `args` inside `container`.
+    fn exec(&self, container: &str, args: &[&str]) -> Result<ExecOutput>;
+
+    /// Copies a host file into `container` at `container_path`.
+    fn copy_to_container(
+        &self,
+        host_path: &str,
+        container: &str,
+        container_path: &str,
+    ) -> Result<()>;
+
+    /// Names of the running containers.
+    fn running_containers(&self) -> Result<Vec<String>>;
+
+    /// Stops `container`, e.g. a cron worker during a backup.
+    fn stop_container(&self, container: &str) -> Result<()>;
+
+    /// Starts `container` again.
+    fn start_container(&self, container: &str) -> Result<()>;
+
+    /// Mounts of `container`. Runtimes that cannot tell report none, and
+    /// backups are then always copied out of the container.
+    fn mounts(&self, _container: &str) -> Result<Vec<Mount>> {
+        Ok(Vec::new())
+    }
+}
+
+impl<T: ContainerRuntime + ?Sized> ContainerRuntime for std::sync::Arc<T> {
+    fn exec(&self, container: &str, args: &[&str]) -> Result<ExecOutput> {
+        (**self).exec(container, args)
+    }
+
+    fn copy_to_container(
+        &self,
+        host_path: &str,
+        container: &str,
+        container_path: &str,
+    ) -> Result<()> {
+        (**self).copy_to_container(host_path, container, container_path)
+    }
+
+    fn running_containers(&self) -> Result<Vec<String>> {
+        (**self).running_containers()
+    }
+
+    fn stop_container(&self, container: &str) -> Result<()> {
+        (**self).stop_container(container)
+    }
+
+    fn start_container(&self, container: &str) -> Result<()> {
+        (**self).start_container(container)
+    }
+
+    fn mounts(&self, container: &str) -> Result<Vec<Mount>> {
+        (**self).mounts(container)
+    }
+}
+
+/// Runs container operations through the `docker` CLI.
+#[derive(Debug, Default)]
+pub struct DockerRuntime;
+
+impl DockerRuntime {
+    fn docker(args: &[&str]) -> Result<ExecOutput> {
+        let output = Command::new("docker")
+            .args(args)
+            .output()
+            .with_context(|| format!("Failed to run docker {}", args[0]))?;
+        Ok(ExecOutput {
+            success: output.status.success(),
+            stdout: output.stdout,
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
+}
+
+impl ContainerRuntime for DockerRuntime {
+    fn exec(&self, container: &str, args: &[&str]) -> Result<ExecOutput> {
+        let mut docker_args = vec!["exec", container];
+        docker_args.extend_from_slice(args);
+        Self::docker(&docker_args)
+    }
+
+    fn copy_to_container(
+        &self,
+        host_path: &str,
+        container: &str,
+        container_path: &str,
+    ) -> Result<()> {
+        let output = Self::docker(&[
+            "cp",
+            host_path,
+            &format!("{}:{}", container, container_path),
+        ])?;
+        if !output.success {
+            return Err(BackupError::Docker(format!(
+                "Copy to container failed: {}",
+                output.stderr
+            )));
+        }
+        Ok(())
+    }
+
+    fn running_containers(&self) -> Result<Vec<String>> {
+        let output = Self::docker(&["ps", "--format", "{{.Names}}"])?;
+        if !output.success {
+            return Err(BackupError::Docker(format!(
+                "Failed to list containers: {}",
+                output.stderr
+            )));
+        }
+        Ok(output
+            .stdout_lossy()
+            .lines()
+            .map(|s| s.to_string())
+            .filter(|s| !s.is_empty())
+            .collect())
+    }
+
+    fn stop_container(&self, container: &str) -> Result<()> {
+        let output = Self::docker(&["stop", container])?;
+        if !output.success {
+            return Err(BackupError::from_docker_output(
+                container,
+                "Failed to stop container",
+                &output.stderr,
+            ));
+        }
+        Ok(())
+    }
+
+    fn start_container(&self, container: &str) -> Result<()> {
+        let output = Self::docker(&["start", container])?;
+        if !output.success {
+            return Err(BackupError::from_docker_output(
+                container,
+                "Failed to start container",
+                &output.stderr,
+            ));
+        }
+        Ok(())
+    }
+
+    fn mounts(&self, container: &str) -> Result<Vec<Mount>> {
+        let output = Self::docker(&["inspect", "--format", "{{json .Mounts}}", container])?;
+        if !output.success {
+            return Err(BackupError::from_docker_output(
+                container,
+                "Failed to inspect container",
+                &output.stderr,
+            ));
+        }
+        serde_json::from_slice(&output.stdout)
+            .map_err(|e| BackupError::Docker(format!("Unexpected mounts from inspect: {}", e)))
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+pub use mock::MockRuntime;
+
+#[cfg(any(test, feature = "test-util"))]
+mod mock {
+    use super::{ContainerRuntime, ExecOutput, Mount};
+    use crate::error::Result;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    /// A scripted `ContainerRuntime` for tests. Commands are answered by the
+    /// first registered response whose pattern occurs in the command line,
+    /// and succeed with no output otherwise. Every call is recorded.
+    #[derive(Default)]
+    pub struct MockRuntime {
+        running: Vec<String>,
+        mounts: Vec<Mount>,
+        responses: Vec<(String, ExecOutput)>,
+        delays: Vec<(String, Duration)>,
+        calls: Mutex<Vec<String>>,
+        spans: Mutex<Vec<(String, Instant, Instant)>>,
+    }
+
+    impl MockRuntime {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Reports `container` as running.
+        pub fn with_container(mut self, container: &str) -> Self {
+            self.running.push(container.to_string());
+            self
+        }
+
+        /// Reports host directory `source` as mounted at `destination` in
+        /// every container.
+        pub fn with_mount(mut self, source: &str, destination: &str) -> Self {
+            self.mounts.push(Mount {
+                source: source.to_string(),
+                destination: destination.to_string(),
+            });
+            self
+        }
+
+        /// Answers commands containing `pattern` with `output`.
+        pub fn respond(mut self, pattern: &str, output: ExecOutput) -> Self {
+            self.responses.push((pattern.to_string(), output));
+            self
+        }
+
+        /// Holds commands containing `pattern` for `delay` before answering,
+        /// like a slow `docker exec`.
+        pub fn delay(mut self, pattern: &str, delay: Duration) -> Self {
+            self.delays.push((pattern.to_string(), delay));
+            self
+        }
+
+        /// Delayed command lines with when they started and finished.
+        pub fn spans(&self) -> Vec<(String, Instant, Instant)> {
+            self.spans.lock().unwrap().clone()
+        }
+
+        /// Command lines seen so far, as `exec <container> <args>`,
+        /// `cp <host> <container>:<path>`, `ps`, `inspect <container>`,
+        /// `stop <container>` or `start <container>`.
+        pub fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+
+        fn record(&self, call: String) -> String {
+            self.calls.lock().unwrap().push(call.clone());
+            call
+        }
+    }
+
+    impl ContainerRuntime for MockRuntime {
+        fn exec(&self, container: &str, args: &[&str]) -> Result<ExecOutput> {
+            let call = self.record(format!("exec {} {}", container, args.join(" ")));
+            if let Some((_, delay)) = self
+                .delays
+                .iter()
+                .find(|(pattern, _)| call.contains(pattern.as_str()))
+            {
+                let started = Instant::now();
+                std::thread::sleep(*delay);
+                self.spans
+                    .lock()
+                    .unwrap()
+                    .push((call.clone(), started, Instant::now()));
+            }
+            Ok(self
+                .responses
+                .iter()
+                .find(|(pattern, _)| call.contains(pattern.as_str()))
+                .map(|(_, output)| output.clone())
+                .unwrap_or_else(|| ExecOutput::ok(Vec::new())))
+        }
+
+        fn copy_to_container(
+            &self,
+            host_path: &str,
+            container: &str,
+            container_path: &str,
+        ) -> Result<()> {
+            self.record(format!("cp {} {}:{}", host_path, container, container_path));
+            Ok(())
+        }
+
+        fn running_containers(&self) -> Result<Vec<String>> {
+            self.record("ps".to_string());
+            Ok(self.running.clone())
+        }
+
+        fn stop_container(&self, container: &str) -> Result<()> {
+            self.record(format!("stop {}", container));
+            Ok(())
+        }
+
+        fn start_container(&self, container: &str) -> Result<()> {
+            self.record(format!("start {}", container));
+            Ok(())
+        }
+
+        fn mounts(&self, container: &str) -> Result<Vec<Mount>> {
+            self.record(format!("inspect {}", container));
+            Ok(self.mounts.clone())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mounts() {
+        let mounts: Vec<Mount> = serde_json::from_str(
+            r#"[{"Type":"bind","Source":"/srv/odoo/backups","Destination":"/tmp/backups","Mode":"","RW":true}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            mounts,
+            vec![Mount {
+                source: "/srv/odoo/backups".to_string(),
+                destination: "/tmp/backups".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_mock_runtime_responses() {
+        let runtime = MockRuntime::new()
+            .with_container("odoo")
+            .respond("stat -c", ExecOutput::ok("42\n"))
+            .respond("rm -f", ExecOutput::failed("read-only file system"));
+
+        assert_eq!(runtime.running_containers().unwrap(), vec!["odoo"]);
+        let stat = runtime
+            .exec("odoo", &["stat", "-c", "%s", "/tmp/b.zip"])
+            .unwrap();
+        assert_eq!(stat.stdout_lossy(), "42\n");
+        let rm = runtime.exec("odoo", &["rm", "-f", "/tmp/b.zip"]).unwrap();
+        assert!(!rm.success);
+        assert!(runtime.exec("odoo", &["true"]).unwrap().success);
+
+        assert_eq!(
+            runtime.calls(),
+            vec![
+                "ps",
+                "exec odoo stat -c %s /tmp/b.zip",
+                "exec odoo rm -f /tmp/b.zip",
+                "exec odoo true"
+            ]
+        );
+    }
+}
@@ -0,0 +1,222 @@
+use crate::error::{BackupError, Result};
+use std::fs;
+use std::path::PathBuf;
+
+/// Masks personal and bank data in the well-known Odoo tables of a restored
+/// database: partner, user, employee and lead names, emails and phone
+/// numbers, and bank account numbers. Employee names are also masked on
+/// their `resource_resource` rows, which hold them since Odoo 13. Columns
+/// missing in some modules or versions are skipped. The partners of companies, the superuser and the
+/// admin keep their names and logins so the copy stays usable.
+pub const DEFAULT_SQL: &str = "\
+BEGIN;
+CREATE FUNCTION pg_temp.scrub(tbl text, col text, expr text, cond text DEFAULT 'true')
+RETURNS void AS $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM information_schema.columns
+               WHERE table_schema = current_schema() AND table_name = tbl AND column_name = col) THEN
+        EXECUTE format('UPDATE %I SET %I = %s WHERE %I IS NOT NULL AND (%s)', tbl, col, expr, col, cond);
+    END IF;
+END
+$$ LANGUAGE plpgsql;
+SELECT pg_temp.scrub('res_partner', 'name', $s$'Partner ' || id$s$,
+    'id NOT IN (SELECT partner_id FROM res_company)');
+SELECT pg_temp.scrub('res_partner', 'display_name', $s$'Partner ' || id$s$,
+    'id NOT IN (SELECT partner_id FROM res_company)');
+SELECT pg_temp.scrub('res_partner', 'complete_name', $s$'Partner ' || id$s$,
+    'id NOT IN (SELECT partner_id FROM res_company)');
+SELECT pg_temp.scrub('res_partner', 'email', $s$'partner' || id || '@example.invalid'$s$);
+SELECT pg_temp.scrub('res_partner', 'email_normalized', $s$'partner' || id || '@example.invalid'$s$);
+SELECT pg_temp.scrub('res_partner', 'phone', 'NULL');
+SELECT pg_temp.scrub('res_partner', 'mobile', 'NULL');
+SELECT pg_temp.scrub('res_partner', 'phone_sanitized', 'NULL');
+SELECT pg_temp.scrub('res_users', 'login', $s$'user' || id || '@example.invalid'$s$, 'id > 2');
+SELECT pg_temp.scrub('res_partner_bank', 'acc_number', $s$'MASKED' || id$s$);
+SELECT pg_temp.scrub('res_partner_bank', 'sanitized_acc_number', $s$'MASKED' || id$s$);
+SELECT pg_temp.scrub('res_partner_bank', 'acc_holder_name', 'NULL');
+SELECT pg_temp.scrub('hr_employee', 'name', $s$'Employee ' || id$s$);
+SELECT pg_temp.scrub('hr_employee', 'work_email', $s$'employee' || id || '@example.invalid'$s$);
+SELECT pg_temp.scrub('hr_employee', 'private_email', 'NULL');
+SELECT pg_temp.scrub('hr_employee', 'work_phone', 'NULL');
+SELECT pg_temp.scrub('hr_employee', 'mobile_phone', 'NULL');
+SELECT pg_temp.scrub('hr_employee', 'private_phone', 'NULL');
+SELECT pg_temp.scrub('hr_employee', 'identification_id', 'NULL');
+SELECT pg_temp.scrub('hr_employee', 'passport_id', 'NULL');
+DO $$
+BEGIN
+    IF to_regclass('hr_employee') IS NOT NULL AND to_regclass('resource_resource') IS NOT NULL THEN
+        UPDATE resource_resource r SET name = 'Employee ' || e.id
+            FROM hr_employee e WHERE e.resource_id = r.id;
+    END IF;
+END
+$$;
+SELECT pg_temp.scrub('crm_lead', 'contact_name', $s$'Contact ' || id$s$);
+SELECT pg_temp.scrub('crm_lead', 'email_from', $s$'lead' || id || '@example.invalid'$s$);
+SELECT pg_temp.scrub('crm_lead', 'phone', 'NULL');
+SELECT pg_temp.scrub('crm_lead', 'mobile', 'NULL');
+SELECT pg_temp.scrub('mail_message', 'email_from', $s$'masked@example.invalid'$s$);
+SELECT pg_temp.scrub('mail_mail', 'email_to', $s$'masked@example.invalid'$s$);
+SELECT pg_temp.scrub('mail_mail', 'email_cc', 'NULL');
+INSERT INTO ir_config_parameter (key, value) VALUES ('odoo_backup_service.scrubbed', 'True')
+    ON CONFLICT (key) DO UPDATE SET value = 'True';
+COMMIT;
+";
+
+/// A scrub step `restore --scrub` runs on the copy: the built-in masking or
+/// an SQL script on the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scrub {
+    Default,
+    Script(PathBuf),
+}
+
+impl Scrub {
+    /// Parses `default` or the path of an SQL script.
+    pub fn parse(input: &str) -> std::result::Result<Self, String> {
+        match input {
+            "default" => Ok(Scrub::Default),
+            "" => Err("expected 'default' or the path of an SQL script".to_string()),
+            path => Ok(Scrub::Script(PathBuf::from(path))),
+        }
+    }
+
+    /// The SQL to run, ending with a newline.
+    pub fn sql(&self) -> Result<String> {
+        match self {
+            Scrub::Default => Ok(DEFAULT_SQL.to_string()),
+            Scrub::Script(path) => {
+                let mut sql = fs::read_to_string(path).map_err(|e| {
+                    BackupError::from_fs(format!("Failed to read {}", path.display()), e)
+                })?;
+                if !sql.ends_with('\n') {
+                    sql.push('\n');
+                }
+                Ok(sql)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Scrub {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scrub::Default => write!(f, "default"),
+            Scrub::Script(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_default_sql_masks_personal_data() {
+        for (table, column) in [
+            ("res_partner", "email"),
+            ("res_partner", "phone"),
+            ("res_partner", "name"),
+            ("res_users", "login"),
+            ("res_partner_bank", "acc_number"),
+            ("hr_employee", "private_email"),
+        ] {
+            assert!(
+                DEFAULT_SQL.contains(&format!("'{}', '{}'", table, column)),
+                "{}.{} is not scrubbed",
+                table,
+                column
+            );
+        }
+        assert!(DEFAULT_SQL.trim_end().ends_with("COMMIT;"));
+    }
+
+    /// Runs `sql` with `psql` against `database` on the server the `PG*`
+    /// variables point to, and returns the rows it prints.
+    fn psql(database: &str, sql: &str) -> String {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new("psql")
+            .args(["-X", "-q", "-tA", "-v", "ON_ERROR_STOP=1", "-d", database])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(sql.as_bytes())
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success(), "psql failed on:\n{}", sql);
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL server, reached through the PG* variables"]
+    fn test_default_sql_on_fixture() {
+        let database = format!("scrub_test_{}", std::process::id());
+        psql("postgres", &format!("CREATE DATABASE {};", database));
+        psql(
+            &database,
+            "CREATE TABLE res_partner (id int PRIMARY KEY, name text, email text, phone text);
+             CREATE TABLE res_company (id int PRIMARY KEY, partner_id int);
+             CREATE TABLE res_users (id int PRIMARY KEY, login text);
+             CREATE TABLE resource_resource (id int PRIMARY KEY, name text);
+             CREATE TABLE hr_employee (id int PRIMARY KEY, name text, resource_id int, work_email text);
+             CREATE TABLE ir_config_parameter (key text UNIQUE, value text);
+             INSERT INTO res_partner VALUES
+                 (1, 'Acme', 'info@acme.example', '+1 555 0100'),
+                 (7, 'Jane Doe', 'jane@doe.example', '+1 555 0199');
+             INSERT INTO res_company VALUES (1, 1);
+             INSERT INTO res_users VALUES (2, 'admin'), (5, 'jane@doe.example');
+             INSERT INTO resource_resource VALUES (3, 'Jane Doe'), (4, 'Meeting Room');
+             INSERT INTO hr_employee VALUES (9, 'Jane Doe', 3, 'jane@acme.example');",
+        );
+
+        psql(&database, DEFAULT_SQL);
+        let rows = psql(
+            &database,
+            "SELECT id, name, email, phone FROM res_partner ORDER BY id;
+             SELECT id, login FROM res_users ORDER BY id;
+             SELECT id, name FROM resource_resource ORDER BY id;
+             SELECT id, name, work_email FROM hr_employee;
+             SELECT value FROM ir_config_parameter WHERE key = 'odoo_backup_service.scrubbed';",
+        );
+        psql("postgres", &format!("DROP DATABASE {};", database));
+
+        assert_eq!(
+            rows.lines().collect::<Vec<_>>(),
+            vec![
+                "1|Acme|partner1@example.invalid|",
+                "7|Partner 7|partner7@example.invalid|",
+                "2|admin",
+                "5|user5@example.invalid",
+                "3|Employee 9",
+                "4|Meeting Room",
+                "9|Employee 9|employee9@example.invalid",
+                "True",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_scrub() {
+        let temp_dir = tempdir().unwrap();
+        let script = temp_dir.path().join("scrub.sql");
+        fs::write(&script, "UPDATE sale_order SET note = NULL;").unwrap();
+
+        assert_eq!(Scrub::parse("default").unwrap(), Scrub::Default);
+        let custom = Scrub::parse(script.to_str().unwrap()).unwrap();
+        assert_eq!(
+            custom.sql().unwrap(),
+            "UPDATE sale_order SET note = NULL;\n"
+        );
+        assert!(Scrub::parse("").is_err());
+        assert!(Scrub::Script(temp_dir.path().join("missing.sql"))
+            .sql()
+            .is_err());
+    }
+}
@@ -0,0 +1,273 @@
+use crate::error::{BackupError, Result, ResultExt};
+use std::env;
+use std::io::{self, BufRead, IsTerminal};
+use std::process::Command;
+
+/// Value that asks for the secret to be entered when the command runs.
+pub const PROMPT_MARKER: &str = "-";
+
+const ENV_PREFIX: &str = "env:";
+const AWS_SECRETS_MANAGER_PREFIX: &str = "aws-sm:";
+const AWS_PARAMETER_STORE_PREFIX: &str = "ssm:";
+
+/// Where a configured secret value comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretSource {
+    /// The value is stored in the configuration file as-is
+    Literal(String),
+    /// `env:NAME` reads the value from an environment variable
+    Env(String),
+    /// `aws-sm:ID` reads a secret string from AWS Secrets Manager. A `#key`
+    /// suffix picks one field out of a JSON secret.
+    AwsSecretsManager {
+        secret_id: String,
+        key: Option<String>,
+    },
+    /// `ssm:NAME` reads a (decrypted) parameter from AWS SSM Parameter Store
+    AwsParameterStore(String),
+    /// `-` prompts on the terminal, or reads a line from stdin when piped
+    Prompt,
+}
+
+impl SecretSource {
+    pub fn parse(value: &str) -> Self {
+        if value == PROMPT_MARKER {
+            SecretSource::Prompt
+        } else if let Some(name) = value.strip_prefix(ENV_PREFIX) {
+            SecretSource::Env(name.to_string())
+        } else if let Some(reference) = value.strip_prefix(AWS_SECRETS_MANAGER_PREFIX) {
+            match reference.split_once('#') {
+                Some((secret_id, key)) => SecretSource::AwsSecretsManager {
+                    secret_id: secret_id.to_string(),
+                    key: Some(key.to_string()),
+                },
+                None => SecretSource::AwsSecretsManager {
+                    secret_id: reference.to_string(),
+                    key: None,
+                },
+            }
+        } else if let Some(name) = value.strip_prefix(AWS_PARAMETER_STORE_PREFIX) {
+            SecretSource::AwsParameterStore(name.to_string())
+        } else {
+            SecretSource::Literal(value.to_string())
+        }
+    }
+
+    /// Resolves sources that need no user interaction. Prompt sources are
+    /// returned untouched so they can be asked for right before they are used.
+    pub fn resolve_non_interactive(&self) -> Result<Option<String>> {
+        match self {
+            SecretSource::Literal(value) => Ok(Some(value.clone())),
+            SecretSource::Env(name) => env::var(name).map(Some).map_err(|_| {
+                BackupError::Config(format!("Environment variable {} is not set", name))
+            }),
+            SecretSource::AwsSecretsManager { secret_id, key } => {
+                let value = self.fetch_from_aws(secret_id)?;
+                match key {
+                    Some(key) => extract_json_field(&value, key).map(Some),
+                    None => Ok(Some(value)),
+                }
+            }
+            SecretSource::AwsParameterStore(name) => self.fetch_from_aws(name).map(Some),
+            SecretSource::Prompt => Ok(None),
+        }
+    }
+
+    /// Arguments for the `aws` CLI call that fetches this secret, which picks
+    /// up credentials through the standard AWS credential chain.
+    fn aws_cli_args(&self) -> Option<Vec<&str>> {
+        match self {
+            SecretSource::AwsSecretsManager { secret_id, .. } => Some(vec![
+                "secretsmanager",
+                "get-secret-value",
+                "--secret-id",
+                secret_id,
+                "--query",
+                "SecretString",
+                "--output",
+                "text",
+            ]),
+            SecretSource::AwsParameterStore(name) => Some(vec![
+                "ssm",
+                "get-parameter",
+                "--name",
+                name,
+                "--with-decryption",
+                "--query",
+                "Parameter.Value",
+                "--output",
+                "text",
+            ]),
+            _ => None,
+        }
+    }
+
+    fn fetch_from_aws(&self, reference: &str) -> Result<String> {
+        let args = self.aws_cli_args().unwrap_or_default();
+        let output = Command::new("aws")
+            .args(&args)
+            .output()
+            .with_context(|| format!("Failed to run aws CLI for {}", reference))?;
+
+        if !output.status.success() {
+            return Err(BackupError::Config(format!(
+                "Failed to fetch secret {} from AWS: {}",
+                reference,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        let value = String::from_utf8_lossy(&output.stdout)
+            .trim_end_matches(['\r', '\n'])
+            .to_string();
+        if value.is_empty() {
+            return Err(BackupError::Config(format!(
+                "Secret {} from AWS is empty",
+                reference
+            )));
+        }
+        Ok(value)
+    }
+}
+
+fn extract_json_field(secret: &str, key: &str) -> Result<String> {
+    let value: serde_json::Value = serde_json::from_str(secret)
+        .map_err(|e| BackupError::Config(format!("Secret is not a JSON object: {}", e)))?;
+    value
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_string())
+        .ok_or_else(|| BackupError::Config(format!("Secret has no string field '{}'", key)))
+}
+
+/// Asks for a secret, hiding the input when attached to a terminal.
+pub fn prompt_secret(label: &str) -> Result<String> {
+    let secret = if io::stdin().is_terminal() {
+        rpassword::prompt_password(format!("{}: ", label))?
+    } else {
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line)?;
+        line.trim_end_matches(['\r', '\n']).to_string()
+    };
+
+    if secret.is_empty() {
+        return Err(BackupError::Config(format!("{}: no value provided", label)));
+    }
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_literal() {
+        assert_eq!(
+            SecretSource::parse("admin"),
+            SecretSource::Literal("admin".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_prompt_marker() {
+        assert_eq!(SecretSource::parse("-"), SecretSource::Prompt);
+        assert_eq!(
+            SecretSource::parse("--"),
+            SecretSource::Literal("--".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_env_reference() {
+        assert_eq!(
+            SecretSource::parse("env:ODOO_MASTER"),
+            SecretSource::Env("ODOO_MASTER".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_aws_secrets_manager_reference() {
+        assert_eq!(
+            SecretSource::parse("aws-sm:odoo/clientA/master"),
+            SecretSource::AwsSecretsManager {
+                secret_id: "odoo/clientA/master".to_string(),
+                key: None,
+            }
+        );
+        assert_eq!(
+            SecretSource::parse("aws-sm:odoo/clientA#master_password"),
+            SecretSource::AwsSecretsManager {
+                secret_id: "odoo/clientA".to_string(),
+                key: Some("master_password".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_ssm_reference() {
+        assert_eq!(
+            SecretSource::parse("ssm:/odoo/clientA/master"),
+            SecretSource::AwsParameterStore("/odoo/clientA/master".to_string())
+        );
+    }
+
+    #[test]
+    fn test_aws_cli_args() {
+        let sm = SecretSource::parse("aws-sm:odoo/clientA/master");
+        let args = sm.aws_cli_args().unwrap();
+        assert_eq!(
+            &args[..4],
+            [
+                "secretsmanager",
+                "get-secret-value",
+                "--secret-id",
+                "odoo/clientA/master"
+            ]
+        );
+
+        let ssm = SecretSource::parse("ssm:/odoo/clientA/master");
+        let args = ssm.aws_cli_args().unwrap();
+        assert_eq!(
+            &args[..4],
+            ["ssm", "get-parameter", "--name", "/odoo/clientA/master"]
+        );
+        assert!(args.contains(&"--with-decryption"));
+
+        assert!(SecretSource::parse("admin").aws_cli_args().is_none());
+    }
+
+    #[test]
+    fn test_extract_json_field() {
+        let secret = r#"{"master_password": "s3cret", "port": 8069}"#;
+        assert_eq!(
+            extract_json_field(secret, "master_password").unwrap(),
+            "s3cret"
+        );
+        assert!(extract_json_field(secret, "port").is_err());
+        assert!(extract_json_field(secret, "missing").is_err());
+        assert!(extract_json_field("plain text", "master_password").is_err());
+    }
+
+    #[test]
+    fn test_resolve_env_reference() {
+        env::set_var("ODOO_BACKUP_TEST_SECRET", "s3cret");
+        let source = SecretSource::parse("env:ODOO_BACKUP_TEST_SECRET");
+        assert_eq!(
+            source.resolve_non_interactive().unwrap(),
+            Some("s3cret".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_missing_env_reference() {
+        let source = SecretSource::parse("env:ODOO_BACKUP_TEST_SECRET_MISSING");
+        let result = source.resolve_non_interactive();
+        assert!(matches!(result.unwrap_err(), BackupError::Config(_)));
+    }
+
+    #[test]
+    fn test_resolve_prompt_is_deferred() {
+        let source = SecretSource::parse("-");
+        assert_eq!(source.resolve_non_interactive().unwrap(), None);
+    }
+}
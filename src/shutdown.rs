@@ -0,0 +1,110 @@
+use crate::error::{BackupError, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// Exit code used when the process stopped because it was asked to.
+pub const EXIT_ABORTED: i32 = 130;
+
+/// Cooperative shutdown flag shared by everything doing long-running work.
+/// Work checks it between steps and stops with `BackupError::Cancelled`,
+/// cleaning up after itself on the way out.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    triggered: AtomicBool,
+    notify: Notify,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn trigger(&self) {
+        self.inner.triggered.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.inner.triggered.load(Ordering::SeqCst)
+    }
+
+    /// Fails with `BackupError::Cancelled` once shutdown was requested.
+    pub fn check(&self) -> Result<()> {
+        if self.is_triggered() {
+            return Err(BackupError::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Resolves once shutdown was requested.
+    pub async fn wait(&self) {
+        let notified = self.inner.notify.notified();
+        if self.is_triggered() {
+            return;
+        }
+        notified.await;
+    }
+
+    /// Triggers shutdown on SIGINT or SIGTERM. A second signal exits right
+    /// away, for when cleanup itself hangs.
+    pub fn listen_for_signals(&self) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            wait_for_signal().await;
+            log::warn!("Shutdown requested, finishing up (signal again to exit now)");
+            shutdown.trigger();
+            wait_for_signal().await;
+            std::process::exit(EXIT_ABORTED);
+        });
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+        }
+        Err(e) => {
+            log::warn!("Cannot listen for SIGTERM: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shutdown_trigger() {
+        let shutdown = Shutdown::new();
+        assert!(shutdown.check().is_ok());
+
+        let waiter = shutdown.clone();
+        let handle = tokio::spawn(async move { waiter.wait().await });
+        shutdown.trigger();
+        handle.await.unwrap();
+
+        assert!(shutdown.is_triggered());
+        assert!(matches!(shutdown.check(), Err(BackupError::Cancelled)));
+        // Waiting after the fact returns immediately
+        shutdown.wait().await;
+    }
+}
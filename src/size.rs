@@ -0,0 +1,138 @@
+const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+/// Formats a byte count for display, e.g. `1.5 GB`.
+pub fn format_size(bytes: u64) -> String {
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Formats a change in size with its sign, e.g. `+1.5 MB` or `-200 B`.
+pub fn format_size_delta(delta: i64) -> String {
+    let sign = if delta < 0 { '-' } else { '+' };
+    format!("{}{}", sign, format_size(delta.unsigned_abs()))
+}
+
+/// Parses a human size such as `500MB`, `1.5 GB` or `1024` (bytes). Units
+/// are binary multiples, matching `format_size`.
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("Invalid size '{}'", input))?;
+
+    let unit = unit.trim().to_ascii_uppercase();
+    let unit = unit
+        .strip_suffix("IB")
+        .map(|u| format!("{}B", u))
+        .unwrap_or(unit);
+    let exponent = match unit.as_str() {
+        "" | "B" => 0,
+        "K" | "KB" => 1,
+        "M" | "MB" => 2,
+        "G" | "GB" => 3,
+        "T" | "TB" => 4,
+        _ => return Err(format!("Unknown size unit in '{}'", input)),
+    };
+    Ok((number * 1024f64.powi(exponent)) as u64)
+}
+
+/// Formats an age in seconds as the two most significant units, e.g. `3d 4h`.
+pub fn format_age(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    let (days, hours, minutes) = (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Parses a duration such as `30d`, `12h`, `2w` or `90m`. A bare number is
+/// taken as seconds.
+pub fn parse_duration(input: &str) -> Result<chrono::Duration, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: i64 = number
+        .parse()
+        .map_err(|_| format!("Invalid duration '{}'", input))?;
+
+    let duration = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "s" => chrono::Duration::seconds(number),
+        "m" => chrono::Duration::minutes(number),
+        "h" => chrono::Duration::hours(number),
+        "d" => chrono::Duration::days(number),
+        "w" => chrono::Duration::weeks(number),
+        _ => return Err(format!("Unknown duration unit in '{}'", input)),
+    };
+    Ok(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GB");
+    }
+
+    #[test]
+    fn test_format_size_delta() {
+        assert_eq!(format_size_delta(1536), "+1.5 KB");
+        assert_eq!(format_size_delta(-200), "-200 B");
+        assert_eq!(format_size_delta(0), "+0 B");
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("500MB"), Ok(500 * 1024 * 1024));
+        assert_eq!(parse_size("1.5 GB"), Ok(1536 * 1024 * 1024));
+        assert_eq!(parse_size("2gib"), Ok(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size("10k"), Ok(10 * 1024));
+        assert!(parse_size("GB").is_err());
+        assert!(parse_size("10 parsecs").is_err());
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(42), "42s");
+        assert_eq!(format_age(600), "10m");
+        assert_eq!(format_age(3 * 3600 + 120), "3h 2m");
+        assert_eq!(format_age(2 * 86400 + 5 * 3600), "2d 5h");
+        assert_eq!(format_age(-5), "0s");
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30d"), Ok(chrono::Duration::days(30)));
+        assert_eq!(parse_duration("12h"), Ok(chrono::Duration::hours(12)));
+        assert_eq!(parse_duration("2W"), Ok(chrono::Duration::weeks(2)));
+        assert_eq!(parse_duration("90"), Ok(chrono::Duration::seconds(90)));
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("3 fortnights").is_err());
+    }
+}
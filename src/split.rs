@@ -0,0 +1,297 @@
+use crate::checksum;
+use crate::error::{BackupError, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Suffix of the manifest written next to the parts of a split backup.
+pub const PARTS_MANIFEST_SUFFIX: &str = ".parts.json";
+
+/// Describes how a backup was split so it can be put back together.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartsManifest {
+    /// Name of the original backup file
+    pub file_name: String,
+    pub size: u64,
+    pub sha256: String,
+    pub parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Part {
+    pub name: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+fn fs_error(action: &str, path: &Path, e: std::io::Error) -> BackupError {
+    BackupError::from_fs(format!("Failed to {} {}", action, path.display()), e)
+}
+
+pub fn is_manifest(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(PARTS_MANIFEST_SUFFIX)
+}
+
+/// Whether a file name looks like `<backup>.partNN`.
+pub fn is_part(file_name: &str) -> bool {
+    file_name
+        .rsplit_once(".part")
+        .is_some_and(|(_, n)| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Hashes everything written through it.
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Splits `path` into `<name>.partNN` files of at most `part_size` bytes
+/// plus a `<name>.parts.json` manifest, then removes the original. Files
+/// that already fit are left alone and `None` is returned.
+pub fn split_file(path: &Path, part_size: u64) -> Result<Option<PathBuf>> {
+    let size = fs::metadata(path)
+        .map_err(|e| fs_error("stat", path, e))?
+        .len();
+    if size <= part_size {
+        return Ok(None);
+    }
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| BackupError::FileSystem(format!("Invalid backup path {}", path.display())))?
+        .to_string();
+    let dir = path.parent().unwrap_or(Path::new("."));
+
+    let mut reader = BufReader::new(File::open(path).map_err(|e| fs_error("open", path, e))?);
+    let mut file_hasher = Sha256::new();
+    let mut parts = Vec::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    let mut remaining = size;
+    while remaining > 0 {
+        let name = format!("{}.part{:02}", file_name, parts.len());
+        let part_path = dir.join(&name);
+        let mut part_file =
+            File::create(&part_path).map_err(|e| fs_error("create", &part_path, e))?;
+        let mut part_hasher = Sha256::new();
+        let mut part_len = 0u64;
+        while part_len < part_size {
+            let want = buffer.len().min((part_size - part_len) as usize);
+            let read = reader
+                .read(&mut buffer[..want])
+                .map_err(|e| fs_error("read", path, e))?;
+            if read == 0 {
+                break;
+            }
+            part_file
+                .write_all(&buffer[..read])
+                .map_err(|e| fs_error("write", &part_path, e))?;
+            part_hasher.update(&buffer[..read]);
+            file_hasher.update(&buffer[..read]);
+            part_len += read as u64;
+        }
+        if part_len == 0 {
+            let _ = fs::remove_file(&part_path);
+            return Err(BackupError::FileSystem(format!(
+                "{} shrank while it was being split",
+                path.display()
+            )));
+        }
+
+        parts.push(Part {
+            name,
+            size: part_len,
+            sha256: checksum::to_hex(&part_hasher.finalize()),
+        });
+        remaining = remaining.saturating_sub(part_len);
+    }
+
+    let manifest = PartsManifest {
+        file_name: file_name.clone(),
+        size,
+        sha256: checksum::to_hex(&file_hasher.finalize()),
+        parts,
+    };
+    let manifest_path = dir.join(format!("{}{}", file_name, PARTS_MANIFEST_SUFFIX));
+    fs::write(&manifest_path, serde_json::to_vec_pretty(&manifest)?)
+        .map_err(|e| fs_error("write", &manifest_path, e))?;
+    fs::remove_file(path).map_err(|e| fs_error("delete", path, e))?;
+    Ok(Some(manifest_path))
+}
+
+pub fn read_manifest(manifest_path: &Path) -> Result<PartsManifest> {
+    let content =
+        fs::read_to_string(manifest_path).map_err(|e| fs_error("read", manifest_path, e))?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+fn part_path(manifest_path: &Path, part: &Part) -> PathBuf {
+    manifest_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(&part.name)
+}
+
+/// The manifest and all part files of a split backup.
+pub fn files_of(manifest_path: &Path) -> Result<Vec<PathBuf>> {
+    let manifest = read_manifest(manifest_path)?;
+    let mut files: Vec<_> = manifest
+        .parts
+        .iter()
+        .map(|part| part_path(manifest_path, part))
+        .collect();
+    files.push(manifest_path.to_path_buf());
+    Ok(files)
+}
+
+/// SHA-256 of the reassembled backup, computed without writing it out.
+pub fn sha256_of_parts(manifest_path: &Path) -> Result<String> {
+    let manifest = read_manifest(manifest_path)?;
+    let mut sink = HashingWriter {
+        inner: io::sink(),
+        hasher: Sha256::new(),
+    };
+    for part in &manifest.parts {
+        let path = part_path(manifest_path, part);
+        let mut file = File::open(&path).map_err(|e| fs_error("open", &path, e))?;
+        io::copy(&mut file, &mut sink).map_err(|e| fs_error("read", &path, e))?;
+    }
+    Ok(checksum::to_hex(&sink.hasher.finalize()))
+}
+
+/// Reassembles a split backup at `output`, verifying every part and the
+/// whole file against the manifest.
+pub fn join_parts(manifest_path: &Path, output: &Path) -> Result<()> {
+    let manifest = read_manifest(manifest_path)?;
+    let tmp_output = output.with_extension("partial");
+    let out_file = File::create(&tmp_output).map_err(|e| fs_error("create", &tmp_output, e))?;
+    let mut writer = HashingWriter {
+        inner: out_file,
+        hasher: Sha256::new(),
+    };
+
+    let mut buffer = vec![0u8; 1024 * 1024];
+    for part in &manifest.parts {
+        let path = part_path(manifest_path, part);
+        let mut file = File::open(&path).map_err(|e| fs_error("open", &path, e))?;
+        let mut part_hasher = Sha256::new();
+        loop {
+            let read = file
+                .read(&mut buffer)
+                .map_err(|e| fs_error("read", &path, e))?;
+            if read == 0 {
+                break;
+            }
+            part_hasher.update(&buffer[..read]);
+            writer
+                .write_all(&buffer[..read])
+                .map_err(|e| fs_error("write", &tmp_output, e))?;
+        }
+        if checksum::to_hex(&part_hasher.finalize()) != part.sha256 {
+            let _ = fs::remove_file(&tmp_output);
+            return Err(BackupError::FileSystem(format!(
+                "Part {} does not match its recorded checksum",
+                part.name
+            )));
+        }
+    }
+    writer.flush()?;
+
+    if checksum::to_hex(&writer.hasher.finalize()) != manifest.sha256 {
+        let _ = fs::remove_file(&tmp_output);
+        return Err(BackupError::FileSystem(format!(
+            "Reassembled {} does not match its recorded checksum",
+            manifest.file_name
+        )));
+    }
+    fs::rename(&tmp_output, output).map_err(|e| fs_error("write", output, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write_backup(dir: &Path, len: usize) -> (PathBuf, Vec<u8>) {
+        let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+        let path = dir.join("backup_db_20240101_120000.zip");
+        fs::write(&path, &data).unwrap();
+        (path, data)
+    }
+
+    #[test]
+    fn test_split_and_join() {
+        let temp_dir = tempdir().unwrap();
+        let (path, data) = write_backup(temp_dir.path(), 2500);
+
+        let manifest_path = split_file(&path, 1000).unwrap().unwrap();
+        assert!(!path.exists());
+        assert!(is_manifest(&manifest_path));
+
+        let manifest = read_manifest(&manifest_path).unwrap();
+        assert_eq!(manifest.file_name, "backup_db_20240101_120000.zip");
+        assert_eq!(manifest.size, 2500);
+        let sizes: Vec<_> = manifest.parts.iter().map(|p| p.size).collect();
+        assert_eq!(sizes, vec![1000, 1000, 500]);
+        assert_eq!(
+            manifest.parts[2].name,
+            "backup_db_20240101_120000.zip.part02"
+        );
+        assert_eq!(manifest.sha256, checksum::sha256_bytes(&data));
+        assert_eq!(sha256_of_parts(&manifest_path).unwrap(), manifest.sha256);
+        assert_eq!(files_of(&manifest_path).unwrap().len(), 4);
+
+        let output = temp_dir.path().join("restored.zip");
+        join_parts(&manifest_path, &output).unwrap();
+        assert_eq!(fs::read(&output).unwrap(), data);
+    }
+
+    #[test]
+    fn test_split_leaves_small_files_alone() {
+        let temp_dir = tempdir().unwrap();
+        let (path, _) = write_backup(temp_dir.path(), 1000);
+        assert!(split_file(&path, 1000).unwrap().is_none());
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn test_join_detects_corrupt_part() {
+        let temp_dir = tempdir().unwrap();
+        let (path, _) = write_backup(temp_dir.path(), 2500);
+        let manifest_path = split_file(&path, 1000).unwrap().unwrap();
+        fs::write(
+            temp_dir.path().join("backup_db_20240101_120000.zip.part01"),
+            b"garbage",
+        )
+        .unwrap();
+
+        let output = temp_dir.path().join("restored.zip");
+        assert!(join_parts(&manifest_path, &output).is_err());
+        assert!(!output.exists());
+        assert_ne!(
+            sha256_of_parts(&manifest_path).unwrap(),
+            read_manifest(&manifest_path).unwrap().sha256
+        );
+    }
+
+    #[test]
+    fn test_is_part() {
+        assert!(is_part("backup_db_20240101_120000.zip.part00"));
+        assert!(is_part("backup_db_20240101_120000.zip.part123"));
+        assert!(!is_part("backup_db_20240101_120000.zip"));
+        assert!(!is_part("backup_db_20240101_120000.zip.parts.json"));
+    }
+}
@@ -0,0 +1,80 @@
+use crate::config::StagingProfile;
+
+/// The SQL applying `profile` to a restored database, in one transaction
+/// and ending with a newline. Tables that only exist with some modules or
+/// versions are skipped.
+pub fn sql(profile: &StagingProfile) -> String {
+    let mut sql = String::from("BEGIN;\n");
+    if let Some(url) = &profile.base_url {
+        sql.push_str(&format!(
+            "INSERT INTO ir_config_parameter (key, value) VALUES ('web.base.url', {})
+    ON CONFLICT (key) DO UPDATE SET value = EXCLUDED.value;
+INSERT INTO ir_config_parameter (key, value) VALUES ('web.base.url.freeze', 'True')
+    ON CONFLICT (key) DO UPDATE SET value = 'True';
+",
+            literal(url)
+        ));
+    }
+    if profile.disable_mail_servers {
+        sql.push_str("UPDATE ir_mail_server SET active = false;\n");
+    }
+    if profile.disable_payment_providers || profile.disable_shipping_providers {
+        sql.push_str("DO $$\nBEGIN\n");
+        if profile.disable_payment_providers {
+            sql.push_str(
+                "    IF to_regclass('payment_acquirer') IS NOT NULL THEN
+        UPDATE payment_acquirer SET state = 'disabled';
+    END IF;
+    IF to_regclass('payment_provider') IS NOT NULL THEN
+        UPDATE payment_provider SET state = 'disabled';
+    END IF;
+",
+            );
+        }
+        if profile.disable_shipping_providers {
+            sql.push_str(
+                "    IF to_regclass('delivery_carrier') IS NOT NULL THEN
+        UPDATE delivery_carrier SET active = false, prod_environment = false
+            WHERE delivery_type NOT IN ('fixed', 'base_on_rule');
+    END IF;
+",
+            );
+        }
+        sql.push_str("END\n$$;\n");
+    }
+    sql.push_str("COMMIT;\n");
+    sql
+}
+
+/// `value` as an SQL string literal.
+fn literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sql_rewrites_base_url_and_disables_integrations() {
+        let profile = StagingProfile {
+            base_url: Some("https://staging.example.com/?a='b'".to_string()),
+            ..Default::default()
+        };
+        let sql = sql(&profile);
+        assert!(sql.contains("('web.base.url', 'https://staging.example.com/?a=''b''')"));
+        assert!(sql.contains("('web.base.url.freeze', 'True')"));
+        for table in ["ir_mail_server", "payment_provider", "delivery_carrier"] {
+            assert!(sql.contains(table), "{} is not disabled", table);
+        }
+        assert!(sql.starts_with("BEGIN;\n") && sql.ends_with("COMMIT;\n"));
+
+        let profile = StagingProfile {
+            disable_mail_servers: false,
+            disable_payment_providers: false,
+            disable_shipping_providers: false,
+            ..Default::default()
+        };
+        assert_eq!(super::sql(&profile), "BEGIN;\nCOMMIT;\n");
+    }
+}
@@ -0,0 +1,209 @@
+use crate::catalog::{BackupStatus, CatalogEntry};
+use serde::{Deserialize, Serialize};
+use tokio::net::UdpSocket;
+
+/// Global `statsd` setting: where per-run metrics are sent over UDP.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatsdConfig {
+    /// `host:port` of the StatsD server or Datadog agent
+    #[serde(default = "default_address")]
+    pub address: String,
+    /// Prefix of every metric name
+    #[serde(default = "default_prefix")]
+    pub prefix: String,
+    /// DogStatsD tags added to every metric, e.g. "env:prod"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+fn default_address() -> String {
+    "127.0.0.1:8125".to_string()
+}
+
+fn default_prefix() -> String {
+    "odoo_backup".to_string()
+}
+
+impl StatsdConfig {
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        match self.address.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {}
+            _ => return Err(format!("address '{}' is not host:port", self.address)),
+        }
+        if self.prefix.is_empty() {
+            return Err("prefix cannot be empty".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn build(&self) -> StatsdClient {
+        StatsdClient {
+            config: self.clone(),
+        }
+    }
+}
+
+/// Sends the outcome of each backup run as StatsD metrics with DogStatsD
+/// tags.
+#[derive(Debug, Clone)]
+pub struct StatsdClient {
+    config: StatsdConfig,
+}
+
+impl StatsdClient {
+    /// The metric lines for a finished run: its duration as a timer, its
+    /// size as a gauge when it succeeded, and a `success`, `failure` or
+    /// `skipped` counter. Aborted runs only report their duration.
+    pub fn run_metrics(&self, entry: &CatalogEntry) -> Vec<String> {
+        let mut tags = vec![
+            format!("client:{}", tag_value(&entry.client)),
+            format!("database:{}", tag_value(&entry.database_name)),
+        ];
+        tags.extend(self.config.tags.iter().cloned());
+        let tags = tags.join(",");
+        let prefix = &self.config.prefix;
+
+        let mut lines = Vec::new();
+        if let Some(finished) = entry.finished_at {
+            let millis = (finished - entry.started_at).num_milliseconds().max(0);
+            lines.push(format!("{}.duration:{}|ms|#{}", prefix, millis, tags));
+        }
+        if entry.status == BackupStatus::Success {
+            if let Some(size) = entry.size {
+                lines.push(format!("{}.size:{}|g|#{}", prefix, size, tags));
+            }
+        }
+        let counter = match entry.status {
+            BackupStatus::Success => Some("success"),
+            BackupStatus::Failed => Some("failure"),
+            BackupStatus::Skipped => Some("skipped"),
+            BackupStatus::Aborted | BackupStatus::Missing => None,
+        };
+        if let Some(counter) = counter {
+            lines.push(format!("{}.{}:1|c|#{}", prefix, counter, tags));
+        }
+        lines
+    }
+
+    /// Sends the metrics of `entry` in one datagram. Metrics are best
+    /// effort, so a failure is only logged.
+    pub async fn send_run(&self, entry: &CatalogEntry) {
+        let payload = self.run_metrics(entry).join("\n");
+        if let Err(e) = self.send(&payload).await {
+            log::warn!(
+                "Failed to send metrics to StatsD at {}: {}",
+                self.config.address,
+                e
+            );
+        }
+    }
+
+    async fn send(&self, payload: &str) -> std::io::Result<()> {
+        let target = tokio::net::lookup_host(&self.config.address)
+            .await?
+            .next()
+            .ok_or_else(|| std::io::Error::other("address did not resolve"))?;
+        let local = if target.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(local).await?;
+        socket.send_to(payload.as_bytes(), target).await?;
+        Ok(())
+    }
+}
+
+/// `value` as a DogStatsD tag value: lowercase, with the characters that
+/// separate tags and fields replaced.
+fn tag_value(value: &str) -> String {
+    value
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            ',' | '|' | '#' | '@' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::BackupError;
+    use chrono::Duration;
+
+    fn config(address: &str) -> StatsdConfig {
+        StatsdConfig {
+            address: address.to_string(),
+            prefix: default_prefix(),
+            tags: vec!["env:prod".to_string()],
+        }
+    }
+
+    fn entry(status: BackupStatus) -> CatalogEntry {
+        let mut entry = serde_json::from_value::<CatalogEntry>(serde_json::json!({
+            "id": "run-1",
+            "client": "Acme Inc",
+            "database_name": "acme",
+            "backup_format": "zip",
+            "started_at": "2024-01-15T02:00:00Z",
+            "status": "success",
+        }))
+        .unwrap();
+        entry.status = status;
+        entry.finished_at = Some(entry.started_at + Duration::milliseconds(1500));
+        entry.size = Some(2048);
+        entry
+    }
+
+    #[test]
+    fn test_statsd_config_validate() {
+        assert!(config("127.0.0.1:8125").validate().is_ok());
+        assert!(config("datadog-agent:8125").validate().is_ok());
+        assert!(config("127.0.0.1").validate().is_err());
+        assert!(config(":8125").validate().is_err());
+
+        let parsed: StatsdConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(parsed.address, "127.0.0.1:8125");
+        assert_eq!(parsed.prefix, "odoo_backup");
+    }
+
+    #[test]
+    fn test_run_metrics() {
+        let client = config("127.0.0.1:8125").build();
+        let tags = "#client:acme_inc,database:acme,env:prod";
+        assert_eq!(
+            client.run_metrics(&entry(BackupStatus::Success)),
+            vec![
+                format!("odoo_backup.duration:1500|ms|{}", tags),
+                format!("odoo_backup.size:2048|g|{}", tags),
+                format!("odoo_backup.success:1|c|{}", tags),
+            ]
+        );
+        assert_eq!(
+            client.run_metrics(&entry(BackupStatus::Failed)),
+            vec![
+                format!("odoo_backup.duration:1500|ms|{}", tags),
+                format!("odoo_backup.failure:1|c|{}", tags),
+            ]
+        );
+        assert_eq!(client.run_metrics(&entry(BackupStatus::Aborted)).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_run() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let client = config(&server.local_addr().unwrap().to_string()).build();
+
+        let mut failed = entry(BackupStatus::Success);
+        failed.fail(&BackupError::Config("boom".to_string()));
+        client.send_run(&failed).await;
+
+        let mut buffer = [0; 1024];
+        let len = server.recv(&mut buffer).await.unwrap();
+        let payload = std::str::from_utf8(&buffer[..len]).unwrap();
+        assert!(payload.contains("\nodoo_backup.failure:1|c|#client:acme_inc"));
+    }
+}
@@ -0,0 +1,272 @@
+use crate::catalog::{BackupStatus, Catalog};
+use crate::config::DatabaseConfig;
+use crate::daemon::ScheduleSnapshot;
+use crate::error::Result;
+use crate::freshness::Freshness;
+use crate::odoo_api::HealthProbe;
+use crate::queue::QueueSnapshot;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// How long `status` waits for Odoo to answer its health check.
+pub const HTTP_CHECK_TIMEOUT_SECS: u64 = 5;
+
+/// Outcome of the HTTP health check of a database's Odoo instance.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HttpHealth {
+    pub status: Option<u16>,
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+impl HttpHealth {
+    pub fn from_probe(probe: Result<HealthProbe>) -> Self {
+        match probe {
+            Ok(probe) => Self {
+                status: Some(probe.status),
+                latency_ms: Some(probe.latency_ms),
+                error: None,
+            },
+            Err(e) => Self {
+                status: None,
+                latency_ms: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.status.is_some_and(|status| {
+            HealthProbe {
+                status,
+                latency_ms: 0,
+            }
+            .is_healthy()
+        })
+    }
+
+    /// e.g. `HTTP 200 in 42 ms`.
+    pub fn describe(&self) -> String {
+        match (self.status, self.latency_ms, &self.error) {
+            (Some(status), Some(latency_ms), _) => {
+                format!("HTTP {} in {} ms", status, latency_ms)
+            }
+            (_, _, Some(error)) => format!("HTTP check failed: {}", error),
+            _ => "HTTP check failed".to_string(),
+        }
+    }
+}
+
+/// What `status` reports about one configured database.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DatabaseStatus {
+    pub client: String,
+    pub container: String,
+    pub enabled: bool,
+    pub running: bool,
+    /// Health check of the Odoo instance; only checked while the container
+    /// runs
+    pub http: Option<HttpHealth>,
+    /// Start of the last successful backup
+    pub last_backup: Option<DateTime<Utc>>,
+    pub last_backup_size: Option<u64>,
+    /// Outcome of the newest backup attempt
+    pub last_attempt: Option<BackupStatus>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// When the running daemon backs the database up next
+    pub next_run: Option<DateTime<Utc>>,
+    /// Whether the last good backup is older than `max_age_hours`, when set
+    pub stale: Option<bool>,
+}
+
+impl DatabaseStatus {
+    pub fn new(
+        db: &DatabaseConfig,
+        running: bool,
+        catalog: &Catalog,
+        schedule: Option<&ScheduleSnapshot>,
+        freshness: &[Freshness],
+    ) -> Self {
+        let last_success = catalog.last_success(&db.name);
+        let last_attempt = catalog.last_attempt(&db.name);
+        Self {
+            client: db.name.clone(),
+            container: db.container_name.clone(),
+            enabled: db.enabled,
+            running,
+            http: None,
+            last_backup: last_success.map(|e| e.started_at),
+            last_backup_size: last_success.and_then(|e| e.size),
+            last_attempt: last_attempt.map(|e| e.status),
+            last_attempt_at: last_attempt.map(|e| e.started_at),
+            last_error: last_attempt.and_then(|e| e.error.clone()),
+            next_run: schedule.and_then(|s| s.next_backup.get(&db.name).copied()),
+            stale: freshness
+                .iter()
+                .find(|f| f.client == db.name)
+                .map(|f| f.stale),
+        }
+    }
+
+    /// Why monitoring should flag the database, if it should. Disabled
+    /// databases are expected to be down and are never flagged.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.enabled {
+            return problems;
+        }
+        if !self.running {
+            problems.push(format!(
+                "{}: container {} is down",
+                self.client, self.container
+            ));
+        }
+        if let Some(http) = self.http.as_ref().filter(|http| !http.is_healthy()) {
+            problems.push(format!(
+                "{}: Odoo is not serving ({})",
+                self.client,
+                http.describe()
+            ));
+        }
+        if self.stale == Some(true) {
+            problems.push(format!("{}: backup is stale", self.client));
+        }
+        problems
+    }
+}
+
+/// Everything `status` shows, in the shape `status --json` prints.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatusReport {
+    pub databases: Vec<DatabaseStatus>,
+    pub running_containers: Vec<String>,
+    pub queue: Option<QueueSnapshot>,
+}
+
+impl StatusReport {
+    /// Problems across all databases, for `status --exit-code`.
+    pub fn problems(&self) -> Vec<String> {
+        self.databases.iter().flat_map(|db| db.problems()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::CatalogEntry;
+    use crate::error::BackupError;
+    use std::collections::BTreeMap;
+    use tempfile::tempdir;
+
+    fn database(name: &str) -> DatabaseConfig {
+        DatabaseConfig {
+            name: name.to_string(),
+            database_name: name.to_lowercase(),
+            container_name: format!("odoo-{}", name.to_lowercase()),
+            master_password: "admin".to_string(),
+            retention_days: 7,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_database_status() {
+        let temp_dir = tempdir().unwrap();
+        let backup_dir = temp_dir.path().to_str().unwrap();
+        let db = database("Acme");
+        let now = Utc::now();
+
+        let mut success = CatalogEntry::start(&db);
+        success.started_at = now - chrono::Duration::hours(2);
+        success.status = BackupStatus::Success;
+        success.size = Some(1024);
+        Catalog::record(backup_dir, success).unwrap();
+        let mut failure = CatalogEntry::start(&db);
+        failure.fail(&BackupError::Timeout("slow".to_string()));
+        Catalog::record(backup_dir, failure).unwrap();
+
+        let catalog = Catalog::load(backup_dir).unwrap();
+        let schedule = ScheduleSnapshot {
+            updated_at: Some(now),
+            next_backup: BTreeMap::from([("Acme".to_string(), now)]),
+        };
+        let freshness = [Freshness {
+            client: "Acme".to_string(),
+            last_backup: None,
+            max_age_hours: 1,
+            stale: true,
+        }];
+        let status = DatabaseStatus::new(&db, false, &catalog, Some(&schedule), &freshness);
+
+        assert_eq!(status.last_backup, Some(now - chrono::Duration::hours(2)));
+        assert_eq!(status.last_backup_size, Some(1024));
+        assert_eq!(status.last_attempt, Some(BackupStatus::Failed));
+        assert_eq!(status.last_error.as_deref(), Some("Timed out: slow"));
+        assert_eq!(status.next_run, Some(now));
+        assert_eq!(
+            status.problems(),
+            vec![
+                "Acme: container odoo-acme is down".to_string(),
+                "Acme: backup is stale".to_string()
+            ]
+        );
+
+        let empty = DatabaseStatus::new(&database("New"), true, &catalog, None, &[]);
+        assert_eq!(empty.last_backup, None);
+        assert_eq!(empty.last_attempt, None);
+        assert_eq!(empty.stale, None);
+        assert!(empty.problems().is_empty());
+    }
+
+    #[test]
+    fn test_http_health() {
+        let temp_dir = tempdir().unwrap();
+        let catalog = Catalog::load(temp_dir.path().to_str().unwrap()).unwrap();
+        let mut status = DatabaseStatus::new(&database("Acme"), true, &catalog, None, &[]);
+
+        status.http = Some(HttpHealth::from_probe(Ok(HealthProbe {
+            status: 200,
+            latency_ms: 42,
+        })));
+        assert_eq!(
+            status.http.as_ref().unwrap().describe(),
+            "HTTP 200 in 42 ms"
+        );
+        assert!(status.problems().is_empty());
+
+        status.http = Some(HttpHealth::from_probe(Ok(HealthProbe {
+            status: 502,
+            latency_ms: 3,
+        })));
+        assert_eq!(
+            status.problems(),
+            vec!["Acme: Odoo is not serving (HTTP 502 in 3 ms)".to_string()]
+        );
+
+        status.http = Some(HttpHealth::from_probe(Err(BackupError::Network(
+            "no response".to_string(),
+        ))));
+        assert_eq!(
+            status.problems(),
+            vec![
+                "Acme: Odoo is not serving (HTTP check failed: Network error: no response)"
+                    .to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_disabled_database_is_not_a_problem() {
+        let temp_dir = tempdir().unwrap();
+        let catalog = Catalog::load(temp_dir.path().to_str().unwrap()).unwrap();
+        let mut db = database("Archived");
+        db.enabled = false;
+        let report = StatusReport {
+            databases: vec![DatabaseStatus::new(&db, false, &catalog, None, &[])],
+            running_containers: Vec::new(),
+            queue: None,
+        };
+        assert!(report.problems().is_empty());
+    }
+}
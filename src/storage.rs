@@ -0,0 +1,566 @@
+use crate::error::{BackupError, Result, ResultExt};
+use crate::proxy::{self, Proxy};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::Stdio;
+use tokio::io::AsyncRead;
+
+/// Where backups are kept once they leave the local backup directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum StorageConfig {
+    /// A directory, typically a mounted NAS or cold-storage volume
+    Local { path: String },
+    /// An S3 bucket, written through the `aws` CLI
+    S3 {
+        bucket: String,
+        #[serde(default)]
+        prefix: Option<String>,
+        /// S3 storage class for uploads, e.g. "GLACIER" or "DEEP_ARCHIVE"
+        #[serde(default)]
+        storage_class: Option<String>,
+        /// Outbound proxy for the `aws` CLI. Without one it uses
+        /// `HTTPS_PROXY` of the host.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        proxy: Option<String>,
+    },
+}
+
+impl StorageConfig {
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        match self {
+            StorageConfig::Local { path } if path.is_empty() => {
+                Err("path cannot be empty".to_string())
+            }
+            StorageConfig::S3 { bucket, .. } if bucket.is_empty() => {
+                Err("bucket cannot be empty".to_string())
+            }
+            StorageConfig::S3 {
+                proxy: Some(proxy), ..
+            } => proxy::validate(proxy).map_err(|e| format!("proxy: {}", e)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn build(&self) -> Box<dyn StorageBackend> {
+        match self {
+            StorageConfig::Local { path } => Box::new(LocalStorage::new(path)),
+            StorageConfig::S3 {
+                bucket,
+                prefix,
+                storage_class,
+                proxy,
+            } => Box::new(S3Storage {
+                bucket: bucket.clone(),
+                prefix: prefix.clone().unwrap_or_default(),
+                storage_class: storage_class.clone(),
+                proxy: proxy.as_deref().map(Proxy::new),
+            }),
+        }
+    }
+}
+
+/// A stream of object data.
+pub type ByteStream = Pin<Box<dyn AsyncRead + Send>>;
+
+/// An object held by a storage backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectInfo {
+    pub key: String,
+    pub size: u64,
+}
+
+/// A place backups can be copied to. Keys are `/`-separated paths relative
+/// to the backend's root. Implement this to archive to targets the crate does
+/// not ship, and register the backend with `BackupManager::with_archive`.
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// Stores everything read from `data` under `key` and returns the stored
+    /// object's location.
+    async fn put(&self, key: &str, data: ByteStream) -> Result<String>;
+
+    /// Streams the object stored under `key`.
+    async fn get(&self, key: &str) -> Result<ByteStream>;
+
+    /// Objects whose key starts with `prefix`, sorted by key.
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>>;
+
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    async fn exists(&self, key: &str) -> Result<bool>;
+
+    /// Copies a local file to `key`. Backends with a faster path for whole
+    /// files can override this.
+    async fn put_file(&self, local_path: &Path, key: &str) -> Result<String> {
+        let file = tokio::fs::File::open(local_path).await.map_err(|e| {
+            BackupError::from_fs(format!("Failed to open {}", local_path.display()), e)
+        })?;
+        self.put(key, Box::pin(file)).await
+    }
+}
+
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+fn fs_error(action: &str, path: &Path, e: std::io::Error) -> BackupError {
+    BackupError::from_fs(format!("Failed to {} {}", action, path.display()), e)
+}
+
+/// Files under `dir`, recursively, as keys relative to `root`.
+fn collect_objects(root: &Path, dir: &Path, objects: &mut Vec<ObjectInfo>) -> Result<()> {
+    for entry in fs::read_dir(dir).map_err(|e| fs_error("read", dir, e))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_objects(root, &path, objects)?;
+        } else if path.extension().is_none_or(|ext| ext != "partial") {
+            let key = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let size = fs::metadata(&path)
+                .map_err(|e| fs_error("stat", &path, e))?
+                .len();
+            objects.push(ObjectInfo { key, size });
+        }
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl StorageBackend for LocalStorage {
+    async fn put(&self, key: &str, mut data: ByteStream) -> Result<String> {
+        let destination = self.root.join(key);
+        if let Some(parent) = destination.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| fs_error("create directory", parent, e))?;
+        }
+
+        // Write under a temporary name so a partial copy is never mistaken
+        // for a complete one
+        let tmp_destination = destination.with_extension("partial");
+        let mut file = tokio::fs::File::create(&tmp_destination)
+            .await
+            .map_err(|e| fs_error("create", &tmp_destination, e))?;
+        tokio::io::copy(&mut data, &mut file)
+            .await
+            .map_err(|e| fs_error("write", &tmp_destination, e))?;
+        file.sync_all().await?;
+        tokio::fs::rename(&tmp_destination, &destination)
+            .await
+            .map_err(|e| fs_error("move into place", &destination, e))?;
+        Ok(destination.to_string_lossy().to_string())
+    }
+
+    async fn get(&self, key: &str) -> Result<ByteStream> {
+        let path = self.root.join(key);
+        let file = tokio::fs::File::open(&path)
+            .await
+            .map_err(|e| fs_error("open", &path, e))?;
+        Ok(Box::pin(file))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        let mut objects = Vec::new();
+        if self.root.is_dir() {
+            collect_objects(&self.root, &self.root, &mut objects)?;
+        }
+        objects.retain(|o| o.key.starts_with(prefix));
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let path = self.root.join(key);
+        tokio::fs::remove_file(&path)
+            .await
+            .map_err(|e| fs_error("delete", &path, e))
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        Ok(self.root.join(key).is_file())
+    }
+}
+
+pub struct S3Storage {
+    bucket: String,
+    prefix: String,
+    storage_class: Option<String>,
+    proxy: Option<Proxy>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct S3Listing {
+    #[serde(default)]
+    contents: Vec<S3Object>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct S3Object {
+    key: String,
+    size: u64,
+}
+
+impl S3Storage {
+    /// The `aws` CLI, going through the configured proxy if any.
+    fn command(&self) -> tokio::process::Command {
+        let mut command = tokio::process::Command::new("aws");
+        if let Some(proxy) = &self.proxy {
+            command.envs(proxy.env());
+        }
+        command
+    }
+
+    fn object_key(&self, key: &str) -> String {
+        let prefix = self.prefix.trim_matches('/');
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}/{}", prefix, key)
+        }
+    }
+
+    fn uri(&self, key: &str) -> String {
+        format!("s3://{}/{}", self.bucket, self.object_key(key))
+    }
+
+    /// `aws s3 cp` arguments uploading `source`, a path or `-` for stdin.
+    fn put_args(&self, source: &str, key: &str) -> Vec<String> {
+        let mut args = vec![
+            "s3".to_string(),
+            "cp".to_string(),
+            "--only-show-errors".to_string(),
+            source.to_string(),
+            self.uri(key),
+        ];
+        if let Some(storage_class) = &self.storage_class {
+            args.push("--storage-class".to_string());
+            args.push(storage_class.clone());
+        }
+        args
+    }
+
+    async fn aws(&self, args: &[String], key: &str) -> Result<Vec<u8>> {
+        let output = self
+            .command()
+            .args(args)
+            .output()
+            .await
+            .context("Failed to run aws CLI")?;
+        if !output.status.success() {
+            return Err(BackupError::Network(format!(
+                "{} {} failed: {}",
+                args[..2].join(" "),
+                self.uri(key),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(output.stdout)
+    }
+}
+
+fn strings(args: &[&str]) -> Vec<String> {
+    args.iter().map(|a| a.to_string()).collect()
+}
+
+#[async_trait]
+impl StorageBackend for S3Storage {
+    async fn put(&self, key: &str, mut data: ByteStream) -> Result<String> {
+        let mut child = self
+            .command()
+            .args(self.put_args("-", key))
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to run aws CLI")?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let copied = tokio::io::copy(&mut data, &mut stdin).await;
+        drop(stdin);
+
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            return Err(BackupError::Network(format!(
+                "Upload to {} failed: {}",
+                self.uri(key),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        copied.with_context(|| format!("Upload to {} failed", self.uri(key)))?;
+        Ok(self.uri(key))
+    }
+
+    async fn put_file(&self, local_path: &Path, key: &str) -> Result<String> {
+        self.aws(&self.put_args(&local_path.to_string_lossy(), key), key)
+            .await?;
+        Ok(self.uri(key))
+    }
+
+    async fn get(&self, key: &str) -> Result<ByteStream> {
+        // Download to a temporary file first, so a failed download is
+        // reported instead of showing up as a truncated stream
+        let tmp_path = std::env::temp_dir().join(format!("odoo-backup-{}", uuid::Uuid::new_v4()));
+        let tmp = tmp_path.to_string_lossy().to_string();
+        let result = self
+            .aws(
+                &strings(&["s3", "cp", "--only-show-errors", &self.uri(key), &tmp]),
+                key,
+            )
+            .await;
+        let file = match result {
+            Ok(_) => tokio::fs::File::open(&tmp_path)
+                .await
+                .map_err(BackupError::from),
+            Err(e) => Err(e),
+        };
+        // The open handle keeps the data readable after the unlink
+        let _ = fs::remove_file(&tmp_path);
+        Ok(Box::pin(file?))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        let full_prefix = self.object_key(prefix);
+        let body = self
+            .aws(
+                &strings(&[
+                    "s3api",
+                    "list-objects-v2",
+                    "--bucket",
+                    &self.bucket,
+                    "--prefix",
+                    &full_prefix,
+                    "--output",
+                    "json",
+                ]),
+                prefix,
+            )
+            .await?;
+        parse_s3_listing(&body, &self.object_key(""))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.aws(
+            &strings(&["s3", "rm", "--only-show-errors", &self.uri(key)]),
+            key,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        let status = self
+            .command()
+            .args([
+                "s3api",
+                "head-object",
+                "--bucket",
+                &self.bucket,
+                "--key",
+                &self.object_key(key),
+            ])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await
+            .context("Failed to run aws CLI")?;
+        Ok(status.success())
+    }
+}
+
+/// Parses `list-objects-v2` output, making keys relative to `root`. An
+/// empty listing prints nothing at all.
+fn parse_s3_listing(body: &[u8], root: &str) -> Result<Vec<ObjectInfo>> {
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(Vec::new());
+    }
+    let listing: S3Listing = serde_json::from_slice(body)?;
+    let mut objects: Vec<_> = listing
+        .contents
+        .into_iter()
+        .map(|o| ObjectInfo {
+            key: o.key.strip_prefix(root).unwrap_or(&o.key).to_string(),
+            size: o.size,
+        })
+        .collect();
+    objects.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(objects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn test_storage_config_parsing() {
+        let local: StorageConfig =
+            serde_json::from_str(r#"{"type": "local", "path": "/mnt/cold"}"#).unwrap();
+        assert_eq!(
+            local,
+            StorageConfig::Local {
+                path: "/mnt/cold".to_string()
+            }
+        );
+
+        let s3: StorageConfig = serde_json::from_str(
+            r#"{"type": "s3", "bucket": "backups", "storage_class": "GLACIER"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            s3,
+            StorageConfig::S3 {
+                bucket: "backups".to_string(),
+                prefix: None,
+                storage_class: Some("GLACIER".to_string()),
+                proxy: None,
+            }
+        );
+
+        assert!(serde_json::from_str::<StorageConfig>(r#"{"type": "ftp"}"#).is_err());
+    }
+
+    #[test]
+    fn test_storage_config_validation() {
+        let empty = StorageConfig::S3 {
+            bucket: String::new(),
+            prefix: None,
+            storage_class: None,
+            proxy: None,
+        };
+        assert!(empty.validate().is_err());
+        let proxied = StorageConfig::S3 {
+            bucket: "backups".to_string(),
+            prefix: None,
+            storage_class: None,
+            proxy: Some("proxy.corp:3128".to_string()),
+        };
+        assert!(proxied.validate().unwrap_err().starts_with("proxy: "));
+        let local = StorageConfig::Local {
+            path: "/mnt/cold".to_string(),
+        };
+        assert!(local.validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_local_storage_put() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("backup.zip");
+        fs::write(&source, b"backup data").unwrap();
+
+        let storage = LocalStorage::new(temp_dir.path().join("archive"));
+        let location = storage
+            .put_file(&source, "test_database/backup.zip")
+            .await
+            .unwrap();
+
+        let stored = temp_dir.path().join("archive/test_database/backup.zip");
+        assert_eq!(location, stored.to_string_lossy());
+        assert_eq!(fs::read(&stored).unwrap(), b"backup data");
+        assert!(!stored.with_extension("partial").exists());
+    }
+
+    #[tokio::test]
+    async fn test_local_storage_get_list_delete() {
+        let temp_dir = tempdir().unwrap();
+        let storage = LocalStorage::new(temp_dir.path());
+        storage
+            .put("db/b.zip", Box::pin(&b"second"[..]))
+            .await
+            .unwrap();
+        storage
+            .put("db/a.zip", Box::pin(&b"first"[..]))
+            .await
+            .unwrap();
+        storage
+            .put("other/c.zip", Box::pin(&b"x"[..]))
+            .await
+            .unwrap();
+
+        let mut data = Vec::new();
+        storage
+            .get("db/a.zip")
+            .await
+            .unwrap()
+            .read_to_end(&mut data)
+            .await
+            .unwrap();
+        assert_eq!(data, b"first");
+
+        let listed = storage.list("db/").await.unwrap();
+        assert_eq!(
+            listed,
+            vec![
+                ObjectInfo {
+                    key: "db/a.zip".to_string(),
+                    size: 5
+                },
+                ObjectInfo {
+                    key: "db/b.zip".to_string(),
+                    size: 6
+                }
+            ]
+        );
+
+        assert!(storage.exists("db/a.zip").await.unwrap());
+        storage.delete("db/a.zip").await.unwrap();
+        assert!(!storage.exists("db/a.zip").await.unwrap());
+        assert!(storage.get("db/a.zip").await.is_err());
+    }
+
+    #[test]
+    fn test_parse_s3_listing() {
+        let body = br#"{"Contents": [
+            {"Key": "odoo/db/b.zip", "Size": 20, "StorageClass": "GLACIER"},
+            {"Key": "odoo/db/a.zip", "Size": 10}
+        ]}"#;
+        let objects = parse_s3_listing(body, "odoo/").unwrap();
+        assert_eq!(objects[0].key, "db/a.zip");
+        assert_eq!(objects[1].size, 20);
+        assert!(parse_s3_listing(b"", "odoo/").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_s3_put_args() {
+        let storage = S3Storage {
+            bucket: "backups".to_string(),
+            prefix: "/odoo/".to_string(),
+            storage_class: Some("DEEP_ARCHIVE".to_string()),
+            proxy: None,
+        };
+        assert_eq!(
+            storage.put_args("/var/backups/odoo/b.zip", "db/b.zip"),
+            vec![
+                "s3",
+                "cp",
+                "--only-show-errors",
+                "/var/backups/odoo/b.zip",
+                "s3://backups/odoo/db/b.zip",
+                "--storage-class",
+                "DEEP_ARCHIVE"
+            ]
+        );
+
+        let storage = S3Storage {
+            bucket: "backups".to_string(),
+            prefix: String::new(),
+            storage_class: None,
+            proxy: Some(Proxy::new("http://proxy.corp:3128")),
+        };
+        assert_eq!(storage.uri("db/b.zip"), "s3://backups/db/b.zip");
+    }
+}
@@ -0,0 +1,201 @@
+use crate::catalog::{BackupStatus, Catalog, CatalogEntry};
+use crate::config::DatabaseConfig;
+use crate::error::{BackupError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Summary of the latest backup run, kept in the host backup directory for
+/// external tooling.
+pub const LAST_RUN_FILE: &str = "last-run.json";
+
+/// Every database a backup run attempted and how it went.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub databases: Vec<DatabaseRun>,
+}
+
+/// Outcome of one database in a backup run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatabaseRun {
+    pub client: String,
+    pub database_name: String,
+    /// `None` when the run ended before the backup started
+    pub status: Option<BackupStatus>,
+    /// The backup taken, or for a skipped database the unchanged one
+    pub path: Option<String>,
+    pub size: Option<u64>,
+    pub duration_secs: Option<i64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deduplicated: bool,
+    pub error: Option<String>,
+}
+
+impl RunSummary {
+    /// The run over `databases` that started at `started_at`, as recorded
+    /// in `catalog`.
+    pub fn from_catalog(
+        catalog: &Catalog,
+        backup_dir: &str,
+        databases: &[DatabaseConfig],
+        started_at: DateTime<Utc>,
+    ) -> Self {
+        let databases: Vec<_> = databases
+            .iter()
+            .map(|db| {
+                let entry = catalog
+                    .last_attempt(&db.name)
+                    .filter(|entry| entry.started_at >= started_at);
+                DatabaseRun::new(catalog, backup_dir, db, entry)
+            })
+            .collect();
+        let count = |statuses: &[BackupStatus]| {
+            databases
+                .iter()
+                .filter(|run| run.status.is_some_and(|s| statuses.contains(&s)))
+                .count()
+        };
+        Self {
+            started_at,
+            finished_at: Utc::now(),
+            succeeded: count(&[BackupStatus::Success, BackupStatus::Skipped]),
+            failed: count(&[BackupStatus::Failed]),
+            databases,
+        }
+    }
+}
+
+impl DatabaseRun {
+    fn new(
+        catalog: &Catalog,
+        backup_dir: &str,
+        db: &DatabaseConfig,
+        entry: Option<&CatalogEntry>,
+    ) -> Self {
+        let Some(entry) = entry else {
+            return Self {
+                client: db.name.clone(),
+                database_name: db.database_name.clone(),
+                status: None,
+                path: None,
+                size: None,
+                duration_secs: None,
+                deduplicated: false,
+                error: None,
+            };
+        };
+        let backup = match entry.status {
+            BackupStatus::Skipped => catalog.last_success(&entry.client),
+            _ => Some(entry),
+        };
+        Self {
+            client: entry.client.clone(),
+            database_name: entry.database_name.clone(),
+            status: Some(entry.status),
+            path: backup.and_then(|b| b.file_name.as_deref()).map(|name| {
+                Path::new(backup_dir)
+                    .join(name)
+                    .to_string_lossy()
+                    .to_string()
+            }),
+            size: backup.and_then(|b| b.size),
+            duration_secs: entry.duration_secs(),
+            deduplicated: backup.is_some_and(|b| b.deduplicated),
+            error: entry.error.clone(),
+        }
+    }
+}
+
+/// Writes the summary of the run over `databases` that started at
+/// `started_at` to `last-run.json`, replacing the previous one. A failure
+/// is only logged.
+pub fn write_last_run(backup_dir: &str, databases: &[DatabaseConfig], started_at: DateTime<Utc>) {
+    let result = Catalog::load(backup_dir).and_then(|catalog| {
+        let summary = RunSummary::from_catalog(&catalog, backup_dir, databases, started_at);
+        save(backup_dir, &summary)
+    });
+    if let Err(e) = result {
+        log::warn!("Failed to write {}: {}", LAST_RUN_FILE, e);
+    }
+}
+
+/// Saves `summary` through a temporary file, so readers never see a
+/// partial summary.
+fn save(backup_dir: &str, summary: &RunSummary) -> Result<()> {
+    let path = Path::new(backup_dir).join(LAST_RUN_FILE);
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(summary)?)
+        .map_err(|e| BackupError::from_fs(format!("Failed to write {}", tmp.display()), e))?;
+    fs::rename(&tmp, &path)
+        .map_err(|e| BackupError::from_fs(format!("Failed to replace {}", path.display()), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use tempfile::tempdir;
+
+    fn database(name: &str) -> DatabaseConfig {
+        DatabaseConfig {
+            name: name.to_string(),
+            database_name: name.to_lowercase(),
+            container_name: "odoo".to_string(),
+            master_password: "admin".to_string(),
+            retention_days: 7,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_write_last_run() {
+        let dir = tempdir().unwrap();
+        let backup_dir = dir.path().to_str().unwrap();
+        let databases = [database("Acme"), database("Globex"), database("Initech")];
+        let before = Utc::now() - Duration::days(1);
+
+        let mut previous = CatalogEntry::start(&databases[0]);
+        previous.started_at = before;
+        previous.succeed("/tmp/backups/backup_acme_old.zip");
+        previous.size = Some(1024);
+        Catalog::record(backup_dir, previous).unwrap();
+        let started_at = Utc::now();
+
+        let mut skipped = CatalogEntry::start(&databases[0]);
+        skipped.skip();
+        Catalog::record(backup_dir, skipped).unwrap();
+        let mut failed = CatalogEntry::start(&databases[1]);
+        failed.fail(&BackupError::Config("disk full".to_string()));
+        Catalog::record(backup_dir, failed).unwrap();
+
+        write_last_run(backup_dir, &databases, started_at);
+        let content = fs::read_to_string(dir.path().join(LAST_RUN_FILE)).unwrap();
+        let summary: RunSummary = serde_json::from_str(&content).unwrap();
+
+        assert_eq!(summary.started_at, started_at);
+        assert_eq!((summary.succeeded, summary.failed), (1, 1));
+        let acme = &summary.databases[0];
+        assert_eq!(acme.status, Some(BackupStatus::Skipped));
+        assert_eq!(
+            acme.path,
+            Some(
+                dir.path()
+                    .join("backup_acme_old.zip")
+                    .to_string_lossy()
+                    .to_string()
+            )
+        );
+        assert_eq!(acme.size, Some(1024));
+        let globex = &summary.databases[1];
+        assert_eq!(globex.status, Some(BackupStatus::Failed));
+        assert_eq!(globex.path, None);
+        assert!(globex.error.as_deref().unwrap().contains("disk full"));
+        // Initech never started
+        assert_eq!(summary.databases[2].status, None);
+    }
+}
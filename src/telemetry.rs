@@ -0,0 +1,79 @@
+use crate::error::Result;
+
+/// Name backup runs are reported under.
+pub const SERVICE_NAME: &str = "odoo-backup-service";
+
+/// Keeps the export of backup run spans to an OpenTelemetry collector, such
+/// as Jaeger or Tempo, running. Spans still buffered are sent when it is
+/// dropped.
+#[derive(Default)]
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+/// Sends spans to the OTLP/HTTP collector at `endpoint`, e.g.
+/// `http://localhost:4318`. Without an endpoint spans are discarded.
+#[cfg(feature = "otel")]
+pub fn init(endpoint: Option<&str>) -> Result<Telemetry> {
+    use crate::error::BackupError;
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_otlp::WithExportConfig;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let Some(endpoint) = endpoint else {
+        return Ok(Telemetry::default());
+    };
+    let traces = format!("{}/v1/traces", endpoint.trim_end_matches('/'));
+    // The blocking HTTP client may not be created on a runtime thread
+    let exporter = std::thread::spawn(move || {
+        opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(traces)
+            .build()
+    })
+    .join()
+    .expect("exporter setup does not panic")
+    .map_err(|e| BackupError::Config(format!("otlp_endpoint: {}", e)))?;
+
+    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            opentelemetry_sdk::Resource::builder()
+                .with_service_name(SERVICE_NAME)
+                .build(),
+        )
+        .build();
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME));
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer))
+        .map_err(|e| BackupError::Config(format!("otlp_endpoint: {}", e)))?;
+    log::info!("Exporting traces to {}", endpoint);
+    Ok(Telemetry {
+        provider: Some(provider),
+    })
+}
+
+/// Without the `otel` feature spans are never exported.
+#[cfg(not(feature = "otel"))]
+pub fn init(endpoint: Option<&str>) -> Result<Telemetry> {
+    if let Some(endpoint) = endpoint {
+        log::warn!(
+            "Not exporting traces to {}: built without the otel feature",
+            endpoint
+        );
+    }
+    Ok(Telemetry::default())
+}
+
+#[cfg(feature = "otel")]
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            // Shutting down waits on the exporter, which blocks
+            let result = std::thread::spawn(move || provider.shutdown()).join();
+            if let Ok(Err(e)) = result {
+                log::warn!("Failed to flush traces: {}", e);
+            }
+        }
+    }
+}
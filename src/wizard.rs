@@ -0,0 +1,400 @@
+use crate::config::{self, DatabaseConfig};
+use crate::docker::DockerManager;
+use crate::error::{BackupError, Result};
+use crate::secrets::{self, SecretSource};
+use std::io::{BufRead, Write};
+
+/// Asks questions on `output` and reads the answers from `input`.
+pub struct Prompter<R, W> {
+    input: R,
+    output: W,
+    /// Read secrets without echoing them, from the terminal
+    hide_secrets: bool,
+}
+
+impl<R: BufRead, W: Write> Prompter<R, W> {
+    pub fn new(input: R, output: W, hide_secrets: bool) -> Self {
+        Self {
+            input,
+            output,
+            hide_secrets,
+        }
+    }
+
+    pub fn say(&mut self, message: &str) -> Result<()> {
+        writeln!(self.output, "{}", message)?;
+        Ok(())
+    }
+
+    /// Asks until `parse` accepts the answer. An empty answer stands for
+    /// `default` when there is one.
+    pub fn ask<T>(
+        &mut self,
+        question: &str,
+        default: Option<&str>,
+        parse: impl Fn(&str) -> std::result::Result<T, String>,
+    ) -> Result<T> {
+        loop {
+            match default {
+                Some(default) => write!(self.output, "{} [{}]: ", question, default)?,
+                None => write!(self.output, "{}: ", question)?,
+            }
+            self.output.flush()?;
+
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Err(BackupError::Config(format!(
+                    "{}: no answer given",
+                    question
+                )));
+            }
+            let answer = match line.trim() {
+                "" => default.unwrap_or(""),
+                answer => answer,
+            };
+            match parse(answer) {
+                Ok(value) => return Ok(value),
+                Err(e) => self.say(&format!("  {}", e))?,
+            }
+        }
+    }
+
+    /// Asks a yes or no question.
+    pub fn confirm(&mut self, question: &str, default: bool) -> Result<bool> {
+        self.ask(
+            question,
+            Some(if default { "y" } else { "n" }),
+            |answer| match answer.to_lowercase().as_str() {
+                "y" | "yes" => Ok(true),
+                "n" | "no" => Ok(false),
+                _ => Err("Answer y or n".to_string()),
+            },
+        )
+    }
+
+    fn ask_secret(&mut self, question: &str) -> Result<String> {
+        if self.hide_secrets {
+            return secrets::prompt_secret(question);
+        }
+        self.ask(question, None, not_empty)
+    }
+}
+
+fn not_empty(answer: &str) -> std::result::Result<String, String> {
+    if answer.is_empty() {
+        Err("A value is required".to_string())
+    } else {
+        Ok(answer.to_string())
+    }
+}
+
+/// Walks through the fields of a new database, checking the container,
+/// URL and master password against the running containers as it goes.
+/// `existing` are the names already in the configuration.
+pub async fn ask_database<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    docker: &DockerManager,
+    existing: &[String],
+) -> Result<DatabaseConfig> {
+    let name = prompter.ask("Client name", None, |answer| {
+        let name = not_empty(answer)?;
+        if existing.contains(&name) {
+            return Err(format!("{} is already configured", name));
+        }
+        Ok(name)
+    })?;
+
+    let containers = docker.list_containers().await.unwrap_or_default();
+    if containers.is_empty() {
+        prompter.say("No running containers found")?;
+    } else {
+        prompter.say("Running containers:")?;
+        for (i, container) in containers.iter().enumerate() {
+            prompter.say(&format!("  {}) {}", i + 1, container))?;
+        }
+    }
+    let container_name = prompter.ask("Container (number or name)", None, |answer| match answer
+        .parse::<usize>(
+    ) {
+        Ok(n) if (1..=containers.len()).contains(&n) => Ok(containers[n - 1].clone()),
+        _ => not_empty(answer),
+    })?;
+    if !containers.contains(&container_name) {
+        prompter.say(&format!(
+            "  Warning: {} is not running, it cannot be checked now",
+            container_name
+        ))?;
+    }
+
+    let database_name = prompter.ask(
+        "Odoo database name",
+        Some(&config::default_database_name(&name)),
+        not_empty,
+    )?;
+
+    let mut config = new_database(name, database_name, container_name, String::new());
+    let running = containers.contains(&config.container_name);
+
+    loop {
+        config.url = prompter.ask(
+            "Odoo URL inside the container",
+            Some("http://localhost:8069"),
+            |answer| {
+                if answer.starts_with("http://") || answer.starts_with("https://") {
+                    Ok(answer.trim_end_matches('/').to_string())
+                } else {
+                    Err("The URL must start with http:// or https://".to_string())
+                }
+            },
+        )?;
+        if !running {
+            break;
+        }
+        match docker
+            .probe_http(&config, crate::status::HTTP_CHECK_TIMEOUT_SECS)
+            .await
+        {
+            Ok(probe) if probe.is_healthy() => {
+                prompter.say(&format!("  Odoo answered in {} ms", probe.latency_ms))?;
+                break;
+            }
+            Ok(probe) => prompter.say(&format!("  Odoo answered HTTP {}", probe.status))?,
+            Err(e) => prompter.say(&format!("  {}", e))?,
+        }
+        if prompter.confirm("Use this URL anyway?", false)? {
+            break;
+        }
+    }
+
+    ask_master_password(prompter, docker, &mut config, running).await?;
+
+    config.backup_format =
+        prompter.ask(
+            "Backup format (zip or dump)",
+            Some("zip"),
+            |answer| match answer {
+                "zip" | "dump" => Ok(answer.to_string()),
+                _ => Err("Choose zip or dump".to_string()),
+            },
+        )?;
+    config.retention_days = prompter.ask("Days to keep backups", Some("30"), |answer| {
+        answer
+            .parse()
+            .map_err(|_| format!("'{}' is not a number of days", answer))
+    })?;
+    config.output_path = prompter.ask(
+        "Directory for temporary backups inside the container",
+        Some("/tmp/backups"),
+        not_empty,
+    )?;
+    config.tags = prompter.ask("Tags, comma separated", Some(""), |answer| {
+        Ok(answer
+            .split(',')
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect())
+    })?;
+    Ok(config)
+}
+
+/// A database with the defaults of the wizard and of `discover`.
+fn new_database(
+    name: String,
+    database_name: String,
+    container_name: String,
+    url: String,
+) -> DatabaseConfig {
+    DatabaseConfig {
+        name,
+        database_name,
+        url,
+        container_name,
+        ..Default::default()
+    }
+}
+
+/// Asks for the master password of `config`, checking literal passwords
+/// against Odoo when its container is `running`.
+pub async fn ask_master_password<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    docker: &DockerManager,
+    config: &mut DatabaseConfig,
+    running: bool,
+) -> Result<()> {
+    prompter.say("The master password may also be env:NAME, aws-sm:ID, ssm:NAME or - to prompt")?;
+    loop {
+        config.master_password = prompter.ask_secret("Master password")?;
+        // References are resolved when the configuration loads, not here
+        if !running
+            || !matches!(
+                SecretSource::parse(&config.master_password),
+                SecretSource::Literal(_)
+            )
+        {
+            break;
+        }
+        match docker.check_master_password(config).await {
+            Ok(()) => {
+                prompter.say("  Master password accepted")?;
+                break;
+            }
+            Err(e) if matches!(e.root(), BackupError::AuthFailed(_)) => {
+                prompter.say("  Odoo rejected the master password, try again")?;
+            }
+            Err(e) => {
+                prompter.say(&format!("  Could not check the master password: {}", e))?;
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Config entries with the wizard's defaults for the databases served at
+/// `url` in `container` that are not in `existing`, by name or by database
+/// and container. Their master password is left to `ask_master_password`.
+pub async fn discover_databases(
+    docker: &DockerManager,
+    container: &str,
+    url: &str,
+    existing: &[DatabaseConfig],
+) -> Result<Vec<DatabaseConfig>> {
+    let url = url.trim_end_matches('/').to_string();
+    let server = new_database(String::new(), String::new(), container.to_string(), url);
+    let names = docker.list_databases(&server).await?;
+    Ok(names
+        .into_iter()
+        .filter(|name| {
+            !existing.iter().any(|db| {
+                &db.name == name
+                    || (&db.database_name == name && db.container_name == server.container_name)
+            })
+        })
+        .map(|name| DatabaseConfig {
+            name: name.clone(),
+            database_name: name,
+            ..server.clone()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{ExecOutput, MockRuntime};
+    use std::io::Cursor;
+
+    async fn answer(
+        answers: &str,
+        docker: &DockerManager,
+        existing: &[String],
+    ) -> (Result<DatabaseConfig>, String) {
+        let mut output = Vec::new();
+        let mut prompter = Prompter::new(Cursor::new(answers.to_string()), &mut output, false);
+        let result = ask_database(&mut prompter, docker, existing).await;
+        (result, String::from_utf8(output).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_ask_database() {
+        let docker = DockerManager::with_runtime(Box::new(
+            MockRuntime::new()
+                .with_container("odoo-acme")
+                .with_container("postgres")
+                .respond("/web/login", ExecOutput::ok("200 0.020"))
+                .respond(
+                    "/jsonrpc",
+                    ExecOutput::ok(r#"{"jsonrpc": "2.0", "id": null, "result": false}"#),
+                ),
+        ));
+        // Taken name, then defaults for everything but the container,
+        // password, retention and tags
+        let answers = "Existing\nAcme Corp\n1\n\n\nsecret\n\n14\n\nprod, eu\n";
+        let (result, output) = answer(answers, &docker, &["Existing".to_string()]).await;
+
+        let config = result.unwrap();
+        assert_eq!(config.name, "Acme Corp");
+        assert_eq!(config.container_name, "odoo-acme");
+        assert_eq!(config.database_name, "acme_corp");
+        assert_eq!(config.url, "http://localhost:8069");
+        assert_eq!(config.master_password, "secret");
+        assert_eq!(config.backup_format, "zip");
+        assert_eq!(config.retention_days, 14);
+        assert_eq!(config.tags, vec!["prod".to_string(), "eu".to_string()]);
+        assert!(output.contains("Existing is already configured"));
+        assert!(output.contains("  1) odoo-acme"));
+        assert!(output.contains("Master password accepted"));
+    }
+
+    #[tokio::test]
+    async fn test_ask_database_retries_rejected_password() {
+        let docker = DockerManager::with_runtime(Box::new(
+            MockRuntime::new()
+                .with_container("odoo-acme")
+                .respond("/web/login", ExecOutput::ok("200 0.020"))
+                .respond(
+                    "\"wrong\"",
+                    ExecOutput::ok(
+                        r#"{"jsonrpc": "2.0", "id": null, "error": {"code": 200, "message": "Odoo Server Error",
+                        "data": {"name": "odoo.exceptions.AccessDenied", "message": "Access Denied"}}}"#,
+                    ),
+                )
+                .respond(
+                    "/jsonrpc",
+                    ExecOutput::ok(r#"{"jsonrpc": "2.0", "id": null, "result": false}"#),
+                ),
+        ));
+        let answers = "Acme\nodoo-acme\n\n\nwrong\nright\ndump\n\n\n\n";
+        let (result, output) = answer(answers, &docker, &[]).await;
+
+        let config = result.unwrap();
+        assert_eq!(config.master_password, "right");
+        assert_eq!(config.backup_format, "dump");
+        assert!(output.contains("Odoo rejected the master password"));
+    }
+
+    #[tokio::test]
+    async fn test_discover_databases() {
+        let docker = DockerManager::with_runtime(Box::new(MockRuntime::new().respond(
+            "/web/database/list",
+            ExecOutput::ok(
+                r#"{"jsonrpc": "2.0", "id": null, "result": ["acme", "globex", "initech"]}"#,
+            ),
+        )));
+        let mut configured = new_database(
+            "Acme Corp".to_string(),
+            "acme".to_string(),
+            "odoo".to_string(),
+            "http://localhost:8069".to_string(),
+        );
+        configured.master_password = "secret".to_string();
+        let existing = vec![
+            configured,
+            new_database(
+                "initech".to_string(),
+                "initech_prod".to_string(),
+                "other".to_string(),
+                String::new(),
+            ),
+        ];
+
+        let databases = discover_databases(&docker, "odoo", "http://localhost:8069/", &existing)
+            .await
+            .unwrap();
+        assert_eq!(databases.len(), 1);
+        assert_eq!(databases[0].name, "globex");
+        assert_eq!(databases[0].database_name, "globex");
+        assert_eq!(databases[0].container_name, "odoo");
+        assert_eq!(databases[0].url, "http://localhost:8069");
+        assert_eq!(databases[0].backup_format, "zip");
+        assert!(databases[0].master_password.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ask_database_without_answers() {
+        let docker = DockerManager::with_runtime(Box::new(MockRuntime::new()));
+        let (result, _) = answer("Acme\n", &docker, &[]).await;
+        assert!(matches!(result.unwrap_err(), BackupError::Config(_)));
+    }
+}